@@ -0,0 +1,127 @@
+use retina_core::config::parse_config;
+use retina_core::filter::explain::{Explanation, Stage};
+use retina_core::filter::SpannedFilterError;
+
+use std::path::PathBuf;
+use std::process;
+
+use anyhow::Result;
+use clap::{Parser, Subcommand};
+
+/// Retina development tools.
+#[derive(Parser, Debug)]
+#[clap(name = "retina")]
+struct Args {
+    #[clap(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Validates a configuration file and prints the resulting DPDK EAL parameters.
+    CheckConfig {
+        #[clap(parse(from_os_str), value_name = "FILE")]
+        config: PathBuf,
+    },
+    /// Prints each step of the compilation of a filter, and optionally counts the packets of a
+    /// trace that match it.
+    Explain {
+        /// Filter expression.
+        filter: String,
+        /// Packet capture to evaluate the filter against. Can be repeated.
+        #[clap(long, value_name = "FILE")]
+        pcap: Vec<String>,
+    },
+}
+
+fn main() -> Result<()> {
+    env_logger::init();
+    let args = Args::parse();
+    match args.command {
+        Command::CheckConfig { config } => check_config(config),
+        Command::Explain { filter, pcap } => explain(&filter, &pcap),
+    }
+}
+
+fn check_config(path: PathBuf) -> Result<()> {
+    let config = parse_config(&path)?;
+    let diagnostics = config.validate();
+    for diagnostic in diagnostics.iter() {
+        println!("{}", diagnostic);
+    }
+    let nb_errors = diagnostics.iter().filter(|d| d.is_error()).count();
+    let nb_warnings = diagnostics.len() - nb_errors;
+    println!(
+        "{}: {} error(s), {} warning(s)",
+        path.display(),
+        nb_errors,
+        nb_warnings
+    );
+    println!("EAL parameters: {}", config.get_eal_params().join(" "));
+    if nb_errors > 0 {
+        process::exit(1);
+    }
+    Ok(())
+}
+
+fn explain(filter: &str, pcap: &[String]) -> Result<()> {
+    let explanation = match Explanation::new(filter) {
+        Ok(explanation) => explanation,
+        Err(error) => match error.downcast_ref::<SpannedFilterError>() {
+            Some(error) => {
+                eprintln!("{}", error.render(filter));
+                process::exit(1);
+            }
+            None => return Err(error),
+        },
+    };
+    println!("Filter: {}\n", explanation.filter_str);
+    println!("Syntax tree:\n{}", explanation.ast);
+    println!("Patterns:");
+    for (i, pattern) in explanation.patterns.iter().enumerate() {
+        println!("  [{}] {}", i, pattern);
+    }
+    println!("\nPredicate tree:\n{}", explanation.ptree);
+    println!("Predicates:");
+    for info in explanation.predicates.iter() {
+        println!(
+            "  {:<40} {:<12} {}",
+            info.predicate.to_string(),
+            info.stage.to_string(),
+            if info.hardware {
+                "hardware"
+            } else {
+                "software"
+            }
+        );
+    }
+    println!(
+        "\nHardware filterable: {}",
+        if explanation.is_hardware_filterable() {
+            "yes"
+        } else {
+            "no"
+        }
+    );
+    println!("Parsers: [{}]", explanation.parsers.join(", "));
+
+    if !pcap.is_empty() {
+        let counts = explanation.count_matches(pcap)?;
+        println!("\nFrames: {}", counts.frames);
+        for (i, count) in counts.patterns.iter().enumerate() {
+            match count {
+                Some(count) => println!("  [{}] {}", i, count),
+                None => println!("  [{}] n/a", i),
+            }
+        }
+        println!("Matched: {}", counts.matched);
+        if explanation
+            .predicates
+            .iter()
+            .any(|info| info.stage != Stage::Packet)
+        {
+            println!("Note: connection and session predicates are not evaluated.");
+        }
+    }
+    Ok(())
+}
@@ -6,32 +6,57 @@
 //! file itself will typically be a command line argument passed to the application.
 //!
 //!  Retina can run in either "online" mode (reading packets from a live network interface) or
-//! "offline" mode (reading packets from a capture file). See
+//! "offline" mode (reading packets from a capture file). Interfaces that cannot be bound to DPDK
+//! (e.g., virtual NICs) can be read in "af_packet" mode. See
 //! [configs](https://github.com/stanford-esrg/retina/tree/main/configs) for examples.
 
 use crate::lcore::{CoreId, SocketId};
 
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fmt;
 use std::fs;
+use std::io;
+use std::net::SocketAddr;
 use std::path::Path;
 
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
+use thiserror::Error;
 
 /// Loads a configuration file from `path`.
+///
+/// Panics if the file cannot be read or parsed, or if the configuration is invalid (see
+/// [RuntimeConfig::validate](RuntimeConfig::validate)). Warnings are logged.
 pub fn load_config<P: AsRef<Path>>(path: P) -> RuntimeConfig {
-    let config_str = fs::read_to_string(path).expect("ERROR: File read failed");
-    let config: RuntimeConfig = toml::from_str(&config_str).expect("Invalid config file");
+    let config = match parse_config(path) {
+        Ok(config) => config,
+        Err(err) => {
+            log::error!("{}", err);
+            panic!();
+        }
+    };
 
     // error check config
-    if config.online.is_some() == config.offline.is_some() {
-        log::error!(
-            "Configure either live ports or offline analysis: {:#?}",
-            config
-        );
+    let diagnostics = config.validate();
+    for diagnostic in diagnostics.iter() {
+        match diagnostic.severity {
+            Severity::Warning => log::warn!("{}", diagnostic),
+            Severity::Error => log::error!("{}", diagnostic),
+        }
+    }
+    if diagnostics.iter().any(Diagnostic::is_error) {
+        log::error!("Invalid configuration: {:#?}", config);
         panic!();
     }
     config
 }
 
+/// Reads and parses a configuration file from `path`, without validating it.
+pub fn parse_config<P: AsRef<Path>>(path: P) -> Result<RuntimeConfig, ConfigError> {
+    let config_str = fs::read_to_string(path)?;
+    Ok(toml::from_str(&config_str)?)
+}
+
 /// Loads a default configuration file.
 ///
 /// For demonstration purposes only, not configured for performance. The default configuration
@@ -85,9 +110,22 @@ pub struct RuntimeConfig {
     #[serde(default = "default_offline")]
     pub offline: Option<OfflineConfig>,
 
+    /// AF_PACKET capture settings. May be specified instead of `online` or `offline`.
+    #[serde(default = "default_af_packet")]
+    pub af_packet: Option<AfPacketConfig>,
+
     /// Connection tracking settings.
     pub conntrack: ConnTrackConfig,
 
+    /// Passive fingerprinting settings. Defaults to `None` (fingerprinting disabled).
+    #[serde(default = "default_fingerprint")]
+    pub fingerprint: Option<FingerprintConfig>,
+
+    /// Callback offload settings. Defaults to `None` (callbacks run on the packet processing
+    /// cores).
+    #[serde(default = "default_offload")]
+    pub offload: Option<OffloadConfig>,
+
     #[doc(hidden)]
     /// Runtime filter for testing purposes.
     #[serde(default = "default_filter")]
@@ -106,6 +144,12 @@ impl RuntimeConfig {
                 }
             }
         }
+        if let Some(offline) = &self.offline {
+            cores.extend(offline.cores.iter().map(|c| CoreId(*c)));
+        }
+        if let Some(af_packet) = &self.af_packet {
+            cores.extend(af_packet.cores.iter().map(|c| CoreId(*c)));
+        }
         cores.sort();
         cores.dedup();
         cores
@@ -122,9 +166,9 @@ impl RuntimeConfig {
         sockets
     }
 
-    /// Returns DPDK EAL parameters.
+    /// Returns the DPDK EAL parameters used to initialize the runtime.
     #[allow(clippy::vec_init_then_push)]
-    pub(crate) fn get_eal_params(&self) -> Vec<String> {
+    pub fn get_eal_params(&self) -> Vec<String> {
         let mut eal_params = vec![];
 
         eal_params.push("--main-lcore".to_owned());
@@ -148,6 +192,11 @@ impl RuntimeConfig {
             }
         }
 
+        if self.af_packet.is_some() {
+            // Packets are read from kernel sockets, PCI devices are not used
+            eal_params.push("--no-pci".to_owned());
+        }
+
         eal_params.push("-n".to_owned());
         eal_params.push(self.nb_memory_channels.to_string());
 
@@ -176,6 +225,18 @@ fn default_offline() -> Option<OfflineConfig> {
     None
 }
 
+fn default_af_packet() -> Option<AfPacketConfig> {
+    None
+}
+
+fn default_fingerprint() -> Option<FingerprintConfig> {
+    None
+}
+
+fn default_offload() -> Option<OffloadConfig> {
+    None
+}
+
 fn default_filter() -> Option<String> {
     None
 }
@@ -194,20 +255,28 @@ impl Default for RuntimeConfig {
             offline: Some(OfflineConfig {
                 mtu: 9702,
                 // assumes Retina is being run from crate root
-                pcap: "./traces/small_flows.pcap".to_string(),
+                pcap: vec!["./traces/small_flows.pcap".to_string()],
+                cores: Vec::new(),
+                replay_speed: None,
             }),
+            af_packet: None,
             conntrack: ConnTrackConfig {
                 max_connections: 100_000,
+                eviction: EvictionPolicy::None,
                 max_out_of_order: 100,
                 timeout_resolution: 100,
                 udp_inactivity_timeout: 60_000,
                 tcp_inactivity_timeout: 300_000,
+                sctp_inactivity_timeout: 300_000,
                 tcp_establish_timeout: 5000,
+                active_timeout: None,
                 init_synack: false,
                 init_fin: false,
                 init_rst: false,
                 init_data: false,
             },
+            fingerprint: None,
+            offload: None,
             filter: None,
         }
     }
@@ -438,6 +507,9 @@ fn default_sink() -> Option<SinkConfig> {
 /// [online.monitor.log]
 ///     directory = "./log"
 ///     interval = 1000
+///
+/// [online.monitor.prometheus]
+///     address = "0.0.0.0:9898"
 /// ```
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct MonitorConfig {
@@ -448,6 +520,10 @@ pub struct MonitorConfig {
     /// Logging configuration. Defaults to `None` (no logs).
     #[serde(default = "default_log")]
     pub log: Option<LogConfig>,
+
+    /// Prometheus metrics endpoint configuration. Defaults to `None` (no endpoint).
+    #[serde(default = "default_prometheus")]
+    pub prometheus: Option<PrometheusConfig>,
 }
 
 fn default_display() -> Option<DisplayConfig> {
@@ -458,6 +534,10 @@ fn default_log() -> Option<LogConfig> {
     None
 }
 
+fn default_prometheus() -> Option<PrometheusConfig> {
+    None
+}
+
 /* --------------------------------------------------------------------------------- */
 
 /// Live statistics display options.
@@ -469,6 +549,7 @@ fn default_log() -> Option<LogConfig> {
 /// [online.monitor.display]
 ///     throughput = true
 ///     mempool_usage = true
+///     conntrack = true
 /// ```
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct DisplayConfig {
@@ -480,6 +561,12 @@ pub struct DisplayConfig {
     #[serde(default = "default_display_mempool_usage")]
     pub mempool_usage: bool,
 
+    /// Display live per-core connection tracking counters (table size, connections created,
+    /// expired, terminated, rejected while the table is full, evicted, removed due to out-of-order
+    /// buffer overflow, and dropped after filtering or parsing). Defaults to `false`.
+    #[serde(default = "default_display_conntrack")]
+    pub conntrack: bool,
+
     /// List of live port statistics to display.
     ///
     /// ## Remarks
@@ -500,6 +587,10 @@ fn default_display_mempool_usage() -> bool {
     true
 }
 
+fn default_display_conntrack() -> bool {
+    false
+}
+
 fn default_display_port_stats() -> Vec<String> {
     vec![]
 }
@@ -508,6 +599,9 @@ fn default_display_port_stats() -> Vec<String> {
 
 /// Logging options.
 ///
+/// Port statistics are logged to one CSV file per port, and per-core connection tracking counters
+/// are logged to `conntrack.csv`.
+///
 /// ## Example
 /// ```toml
 /// [online.monitor.log]
@@ -550,28 +644,199 @@ fn default_log_port_stats() -> Vec<String> {
 
 /* --------------------------------------------------------------------------------- */
 
+/// Prometheus metrics endpoint options.
+///
+/// If enabled, Retina serves metrics in [OpenMetrics](https://openmetrics.io/) text format at
+/// `http://<address>/metrics`. Exposed metrics include port extended statistics, mempool usage,
+/// per-core packet and byte counts, per-core connection tracking counters and table sizes, and
+/// per-subscription callback counts.
+///
+/// ## Example
+/// ```toml
+/// [online.monitor.prometheus]
+///     address = "0.0.0.0:9898"
+///     interval = 1000
+/// ```
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct PrometheusConfig {
+    /// Socket address to listen on. Defaults to `"127.0.0.1:9898"`.
+    #[serde(default = "default_prometheus_address")]
+    pub address: String,
+
+    /// How often to refresh metrics (in milliseconds). Defaults to `1000`.
+    #[serde(default = "default_prometheus_interval")]
+    pub interval: u64,
+}
+
+fn default_prometheus_address() -> String {
+    "127.0.0.1:9898".to_string()
+}
+
+fn default_prometheus_interval() -> u64 {
+    1000
+}
+
+/* --------------------------------------------------------------------------------- */
+
 /// Offline traffic analysis options.
 ///
-/// Offline mode runs using a single core and performs offline analysis of already captured pcap
-/// files. Either [OnlineConfig](OnlineConfig) or [OfflineConfig](OfflineConfig) must be specified,
-/// but not both. This mode is primarily intended for functional testing.
+/// Offline mode performs offline analysis of already captured pcap files. Either
+/// [OnlineConfig](OnlineConfig) or [OfflineConfig](OfflineConfig) must be specified, but not both.
+/// This mode is primarily intended for functional testing.
+///
+/// By default, the main core reads and processes the whole capture. If worker `cores` are
+/// specified, the main core only reads the capture and distributes packets to the workers using
+/// the same symmetric RSS hash and redirection table as online mode, so that each connection is
+/// processed by a single worker with its own connection table.
+///
+/// Multiple capture files (e.g., rotating captures) are read as one continuous trace in timestamp
+/// order, keeping connection state across file boundaries. Both pcap and pcapng files are
+/// supported. pcapng interface blocks are honored, including per-interface link types and
+/// timestamp resolutions.
+///
+/// Connection tracking follows the capture timestamps of the trace: inactive connections expire,
+/// and connection durations are measured, in trace time regardless of how fast the trace is read.
+/// By default, the trace is read as fast as possible. Set `replay_speed` to instead pace packets
+/// according to their capture timestamps, e.g., for testing callbacks that depend on timing.
+///
+/// Frames without an Ethernet header are converted to Ethernet before processing. Supported
+/// link-layer header types are raw IP, BSD loopback, Linux cooked capture (SLL and SLL2, e.g.,
+/// `tcpdump -i any`), and unencrypted 802.11 data frames with or without a radiotap header. Frames
+/// with other link-layer header types are skipped.
 ///
 /// ## Example
 /// ```toml
 /// [offline]
 ///     pcap = "sample_pcaps/smallFlows.pcap"
 ///     mtu = 9702
+///     cores = [1,2,3,4]
+/// ```
+///
+/// ```toml
+/// [offline]
+///     pcap = ["/data/captures/", "/data/more/*.pcapng", "extra.pcap"]
+///     replay_speed = 10.0
 /// ```
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct OfflineConfig {
-    /// Path to packet capture (pcap) file.
-    pub pcap: String,
+    /// Packet capture (pcap or pcapng) files to read. Either a single path or a list of paths,
+    /// each of which may be a file, a directory (all files in it are read), or a glob pattern.
+    #[serde(deserialize_with = "deserialize_paths")]
+    pub pcap: Vec<String>,
 
     /// Maximum frame size, equivalent to MTU on a live interface. Defaults to `1500`.
     ///
     /// To include jumbo frames, set this value higher (e.g., `9702`).
     #[serde(default = "default_mtu")]
     pub mtu: usize,
+
+    /// List of worker cores that process packets. Defaults to empty (packets are processed on the
+    /// main core).
+    ///
+    /// ## Remarks
+    /// Packets queued to the workers hold mbufs, so the mempool `capacity` should be large
+    /// enough to hold `8192` packets per worker in addition to the connection tracking buffers.
+    #[serde(default = "default_offline_cores")]
+    pub cores: Vec<u32>,
+
+    /// Replays the trace in real time sped up by this factor (e.g., `1.0` for the original speed,
+    /// `10.0` for ten times faster), pacing packets according to their capture timestamps.
+    /// Defaults to `None` (packets are read as fast as possible).
+    #[serde(default = "default_replay_speed")]
+    pub replay_speed: Option<f64>,
+}
+
+fn default_offline_cores() -> Vec<u32> {
+    Vec::new()
+}
+
+fn default_replay_speed() -> Option<f64> {
+    None
+}
+
+/// Deserializes a single path or a list of paths.
+fn deserialize_paths<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Paths {
+        One(String),
+        Many(Vec<String>),
+    }
+
+    Ok(match Paths::deserialize(deserializer)? {
+        Paths::One(path) => vec![path],
+        Paths::Many(paths) => paths,
+    })
+}
+
+/* --------------------------------------------------------------------------------- */
+
+/// AF_PACKET live capture options.
+///
+/// AF_PACKET mode captures packets from a regular Linux network interface, without binding it to
+/// DPDK. This is useful when the NIC cannot be bound (e.g., on virtual machines or containers), at
+/// the cost of lower throughput than online mode. Each core opens its own socket and joins a
+/// common fanout group, so that the kernel distributes packets of the same flow to the same core.
+/// Packets are copied from a memory-mapped TPACKET_V3 ring into DPDK mbufs.
+/// Exactly one of [OnlineConfig](OnlineConfig), [OfflineConfig](OfflineConfig), or
+/// [AfPacketConfig](AfPacketConfig) must be specified.
+///
+/// Requires the `CAP_NET_RAW` capability (and `CAP_NET_ADMIN` for promiscuous mode).
+///
+/// ## Example
+/// ```toml
+/// [af_packet]
+///     interface = "veth0"
+///     cores = [1,2,3,4]
+///     duration = 30
+///     promiscuous = true
+///     mtu = 1500
+/// ```
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct AfPacketConfig {
+    /// Name of the network interface to capture from (e.g., `"eth0"`).
+    pub interface: String,
+
+    /// List of cores that read from the interface.
+    pub cores: Vec<u32>,
+
+    /// If set, the applicaton will stop after `duration` seconds. Defaults to `None`.
+    #[serde(default = "default_duration")]
+    pub duration: Option<u64>,
+
+    /// Whether promiscuous mode is enabled on the interface. Defaults to `true`.
+    #[serde(default = "default_promiscuous")]
+    pub promiscuous: bool,
+
+    /// Maximum transmission unit (in bytes) of captured frames. Defaults to `1500`.
+    ///
+    /// Frames larger than the MTU plus the Ethernet header and up to two VLAN tags are discarded.
+    /// To capture jumbo frames, set this value higher (e.g., `9702`).
+    #[serde(default = "default_mtu")]
+    pub mtu: usize,
+
+    /// Packet fanout group identifier. Defaults to the lower 16 bits of the process ID.
+    ///
+    /// Must be unique among the processes capturing from the same interface.
+    #[serde(default = "default_fanout_group")]
+    pub fanout_group: Option<u16>,
+
+    /// Size (in bytes) of the memory-mapped receive ring of each core. Defaults to `67108864`.
+    ///
+    /// Larger rings are more tolerant of processing delays at the cost of higher memory usage.
+    #[serde(default = "default_ring_size")]
+    pub ring_size: usize,
+}
+
+fn default_fanout_group() -> Option<u16> {
+    None
+}
+
+fn default_ring_size() -> usize {
+    64 * 1024 * 1024
 }
 
 /* --------------------------------------------------------------------------------- */
@@ -589,7 +854,10 @@ pub struct OfflineConfig {
 ///     timeout_resolution = 100
 ///     udp_inactivity_timeout = 60_000
 ///     tcp_inactivity_timeout = 300_000
+///     sctp_inactivity_timeout = 300_000
 ///     tcp_establish_timeout = 5000
+///     active_timeout = 1_800_000
+///     eviction = "oldest_idle"
 /// ```
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct ConnTrackConfig {
@@ -598,8 +866,13 @@ pub struct ConnTrackConfig {
     #[serde(default = "default_max_connections")]
     pub max_connections: usize,
 
-    /// Maximum number of out-of-order packets allowed per TCP connection before it is force
-    /// expired. Defaults to `100`.
+    /// How to make room for a new connection when the table is full. Defaults to `"none"` (new
+    /// connections are not tracked until existing connections expire).
+    #[serde(default = "default_eviction")]
+    pub eviction: EvictionPolicy,
+
+    /// Maximum number of out-of-order packets allowed per TCP connection (or out-of-order DATA
+    /// chunks per SCTP association) before it is force expired. Defaults to `100`.
     #[serde(default = "default_max_out_of_order")]
     pub max_out_of_order: usize,
 
@@ -617,6 +890,14 @@ pub struct ConnTrackConfig {
     #[serde(default = "default_tcp_inactivity_timeout")]
     pub tcp_inactivity_timeout: usize,
 
+    /// An SCTP association can be inactive for up to this amount of time (in milliseconds) before
+    /// it is force expired. Defaults to `300_000` (5 minutes).
+    ///
+    /// SCTP endpoints send heartbeats on idle paths (every 30 seconds by default), so this should
+    /// be set well above the heartbeat interval.
+    #[serde(default = "default_sctp_inactivity_timeout")]
+    pub sctp_inactivity_timeout: usize,
+
     /// Inactivity time between the first and second packet of a TCP connection before it is force
     /// expired (in milliseconds).
     ///
@@ -626,6 +907,18 @@ pub struct ConnTrackConfig {
     #[serde(default = "default_tcp_establish_timeout")]
     pub tcp_establish_timeout: usize,
 
+    /// Interval (in milliseconds) at which interim records of long-lived connections are
+    /// delivered while the connection is active. Defaults to `None` (records are only delivered
+    /// when the connection terminates).
+    ///
+    /// ## Remarks
+    /// Only applies to subscriptions that report connection records, such as
+    /// [Connection](crate::subscription::Connection). Interim records are checked for at the
+    /// `timeout_resolution`, and are skipped for connections with no packets since the previous
+    /// record.
+    #[serde(default = "default_active_timeout")]
+    pub active_timeout: Option<usize>,
+
     #[doc(hidden)]
     /// Whether to track TCP connections where the first observed packet is a SYN/ACK. Defaults to
     /// `false`.
@@ -655,6 +948,10 @@ fn default_max_connections() -> usize {
     10_000_000
 }
 
+fn default_eviction() -> EvictionPolicy {
+    EvictionPolicy::None
+}
+
 fn default_max_out_of_order() -> usize {
     100
 }
@@ -671,10 +968,18 @@ fn default_tcp_inactivity_timeout() -> usize {
     300_000
 }
 
+fn default_sctp_inactivity_timeout() -> usize {
+    300_000
+}
+
 fn default_tcp_establish_timeout() -> usize {
     5000
 }
 
+fn default_active_timeout() -> Option<usize> {
+    None
+}
+
 fn default_init_synack() -> bool {
     false
 }
@@ -690,3 +995,740 @@ fn default_init_rst() -> bool {
 fn default_init_data() -> bool {
     false
 }
+
+/* --------------------------------------------------------------------------------- */
+
+/// Passive fingerprinting options.
+///
+/// When a signature database is configured, the runtime loads it at startup and
+/// [Connection](crate::subscription::connection::Connection) records are annotated with the
+/// operating systems of the originator and responder, inferred from their TCP handshake packets.
+///
+/// ## Example
+/// ```toml
+/// [fingerprint]
+///     p0f_db = "/etc/p0f/p0f.fp"
+/// ```
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct FingerprintConfig {
+    /// Path to a TCP signature database in p0f v3 format.
+    pub p0f_db: String,
+}
+
+/// Connection table eviction policy.
+///
+/// Under table pressure (e.g., during a SYN flood), evicting existing connections prevents new
+/// connections from being starved. Evicted connections are terminated as if they had expired.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum EvictionPolicy {
+    /// Do not evict. New connections are not tracked while the table is full.
+    None,
+    /// Evict the connection that has been idle the longest.
+    OldestIdle,
+    /// Evict the longest-idle connection that has not yet matched the filter (still probing for
+    /// an application-layer protocol, or waiting to age out after failing to match). Falls back to
+    /// the connection that has been idle the longest.
+    ProbingFirst,
+}
+
+/* --------------------------------------------------------------------------------- */
+
+/// Callback offload options.
+///
+/// By default, callbacks run inline on the packet processing cores, so callbacks that do any
+/// substantial work reduce throughput. When offloaded, subscribable objects are instead pushed
+/// onto a bounded queue per packet processing core and delivered to the callback by a pool of
+/// worker threads. Objects from the same core are delivered in order by the same worker thread.
+///
+/// Offloading requires the runtime to be created with
+/// [Runtime::new_offloaded](crate::Runtime::new_offloaded), which requires the callback to be
+/// thread-safe. The core exit callbacks run after the objects queued by the core have been
+/// delivered, and the shutdown callback runs after all queued objects have been delivered.
+///
+/// ## Example
+/// ```toml
+/// [offload]
+///     nb_workers = 4
+///     queue_size = 8192
+///     backpressure = "drop_oldest"
+/// ```
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct OffloadConfig {
+    /// Number of worker threads that invoke the callback. Defaults to `1`.
+    ///
+    /// ## Remarks
+    /// Each packet processing core's queue is served by a single worker, so at most one worker
+    /// per packet processing core is used.
+    #[serde(default = "default_offload_workers")]
+    pub nb_workers: usize,
+
+    /// Maximum number of subscribable objects waiting in each core's queue. Defaults to `4096`.
+    #[serde(default = "default_offload_queue_size")]
+    pub queue_size: usize,
+
+    /// What to do when a core's queue is full. Defaults to `block`.
+    #[serde(default = "default_backpressure")]
+    pub backpressure: Backpressure,
+}
+
+fn default_offload_workers() -> usize {
+    1
+}
+
+fn default_offload_queue_size() -> usize {
+    4096
+}
+
+fn default_backpressure() -> Backpressure {
+    Backpressure::Block
+}
+
+/// Callback offload backpressure policy.
+///
+/// Objects dropped under backpressure are counted by the monitor.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Backpressure {
+    /// Wait for space in the queue. Stalls packet processing on the core while the workers
+    /// catch up, which may cause packets to be dropped by the NIC instead.
+    Block,
+    /// Drop the new object.
+    DropNewest,
+    /// Drop the oldest object in the queue to make room for the new object.
+    DropOldest,
+}
+
+/* --------------------------------------------------------------------------------- */
+
+/// Largest per-core object cache supported by DPDK mempools.
+const MEMPOOL_CACHE_MAX_SIZE: usize = 512;
+
+/// Number of buckets in the RSS redirection table.
+const RSS_RETA_SIZE: usize = 512;
+
+/// Severity of a configuration [Diagnostic](Diagnostic).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// The runtime will start, but the option is likely a mistake or will hurt performance.
+    Warning,
+    /// The runtime will fail to start.
+    Error,
+}
+
+/// A problem found while validating a [RuntimeConfig](RuntimeConfig).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// Severity of the problem.
+    pub severity: Severity,
+    /// Path of the offending option (e.g., `online.ports[0].cores`), or empty if the problem is
+    /// not specific to one option.
+    pub field: String,
+    /// Description of the problem.
+    pub message: String,
+}
+
+impl Diagnostic {
+    fn error(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Diagnostic {
+            severity: Severity::Error,
+            field: field.into(),
+            message: message.into(),
+        }
+    }
+
+    fn warning(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Diagnostic {
+            severity: Severity::Warning,
+            field: field.into(),
+            message: message.into(),
+        }
+    }
+
+    /// Returns `true` if the problem prevents the runtime from starting.
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        if self.field.is_empty() {
+            write!(f, "{}: {}", severity, self.message)
+        } else {
+            write!(f, "{}: {}: {}", severity, self.field, self.message)
+        }
+    }
+}
+
+/// Errors reading a configuration file.
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("Failed to read config file: {0}")]
+    Read(#[from] io::Error),
+
+    #[error("Invalid config file: {0}")]
+    Parse(#[from] toml::de::Error),
+}
+
+impl RuntimeConfig {
+    /// Checks the configuration for invalid or inconsistent options without initializing the
+    /// runtime.
+    ///
+    /// Returns the list of problems found, empty if none. Errors indicate that the runtime would
+    /// fail to start (e.g., a `timeout_resolution` larger than all inactivity timeouts). Warnings
+    /// indicate options that are likely mistakes or that will hurt performance (e.g., processing
+    /// cores that are not on the NUMA node of the NIC they poll).
+    ///
+    /// ## Remarks
+    /// Cores, PCI devices, interfaces, and their NUMA nodes are looked up in sysfs, so these
+    /// checks should be run on the machine that will run Retina. They are skipped if sysfs is not
+    /// available.
+    pub fn validate(&self) -> Vec<Diagnostic> {
+        let mut diagnostics = vec![];
+
+        let nb_modes = [
+            self.online.is_some(),
+            self.offline.is_some(),
+            self.af_packet.is_some(),
+        ]
+        .iter()
+        .filter(|m| **m)
+        .count();
+        if nb_modes != 1 {
+            diagnostics.push(Diagnostic::error(
+                "",
+                "Configure exactly one of live ports (online), AF_PACKET capture (af_packet), or \
+                 offline analysis (offline)",
+            ));
+        }
+
+        if self.nb_memory_channels == 0 {
+            diagnostics.push(Diagnostic::error(
+                "nb_memory_channels",
+                "Must be at least 1",
+            ));
+        }
+        validate_cores("main_core", &[self.main_core], &mut diagnostics);
+
+        self.validate_mempool(&mut diagnostics);
+        self.validate_conntrack(&mut diagnostics);
+        if let Some(online) = &self.online {
+            self.validate_online(online, &mut diagnostics);
+        }
+        if let Some(offline) = &self.offline {
+            self.validate_offline(offline, &mut diagnostics);
+        }
+        if let Some(af_packet) = &self.af_packet {
+            self.validate_af_packet(af_packet, &mut diagnostics);
+        }
+
+        if let Some(fingerprint) = &self.fingerprint {
+            if !Path::new(&fingerprint.p0f_db).is_file() {
+                diagnostics.push(Diagnostic::error(
+                    "fingerprint.p0f_db",
+                    format!("No such file: {}", fingerprint.p0f_db),
+                ));
+            }
+        }
+
+        if let Some(offload) = &self.offload {
+            if offload.nb_workers == 0 {
+                diagnostics.push(Diagnostic::error(
+                    "offload.nb_workers",
+                    "Must be at least 1",
+                ));
+            }
+            if offload.queue_size == 0 {
+                diagnostics.push(Diagnostic::error(
+                    "offload.queue_size",
+                    "Must be at least 1",
+                ));
+            }
+            let nb_cores = self.processing_cores().len();
+            if offload.nb_workers > nb_cores {
+                diagnostics.push(Diagnostic::warning(
+                    "offload.nb_workers",
+                    format!(
+                        "More workers ({}) than packet processing cores ({}), only {} will be used",
+                        offload.nb_workers, nb_cores, nb_cores
+                    ),
+                ));
+            }
+        }
+
+        diagnostics.sort_by_key(|diagnostic| Reverse(diagnostic.severity));
+        diagnostics
+    }
+
+    /// Returns the IDs of the cores that process packets (and invoke the callback) in the
+    /// configured runtime mode.
+    pub(crate) fn processing_cores(&self) -> Vec<u32> {
+        let mut cores = if let Some(online) = &self.online {
+            online
+                .ports
+                .iter()
+                .flat_map(|port| port.cores.iter().copied())
+                .collect()
+        } else if let Some(offline) = &self.offline {
+            if offline.cores.is_empty() {
+                vec![self.main_core]
+            } else {
+                offline.cores.clone()
+            }
+        } else if let Some(af_packet) = &self.af_packet {
+            af_packet.cores.clone()
+        } else {
+            vec![]
+        };
+        cores.sort_unstable();
+        cores.dedup();
+        cores
+    }
+
+    fn validate_mempool(&self, diagnostics: &mut Vec<Diagnostic>) {
+        let mempool = &self.mempool;
+        if mempool.capacity == 0 {
+            diagnostics.push(Diagnostic::error("mempool.capacity", "Must be at least 1"));
+        }
+        if mempool.cache_size > MEMPOOL_CACHE_MAX_SIZE {
+            diagnostics.push(Diagnostic::error(
+                "mempool.cache_size",
+                format!(
+                    "Cache size ({}) exceeds the maximum ({})",
+                    mempool.cache_size, MEMPOOL_CACHE_MAX_SIZE
+                ),
+            ));
+        } else if mempool.cache_size * 3 > mempool.capacity * 2 {
+            diagnostics.push(Diagnostic::error(
+                "mempool.cache_size",
+                format!(
+                    "Cache size ({}) must be at most 2/3 of the capacity ({})",
+                    mempool.cache_size, mempool.capacity
+                ),
+            ));
+        } else if mempool.cache_size > 0 && mempool.capacity % mempool.cache_size > 0 {
+            diagnostics.push(Diagnostic::warning(
+                "mempool.cache_size",
+                format!(
+                    "Cache size ({}) does not evenly divide the capacity ({})",
+                    mempool.cache_size, mempool.capacity
+                ),
+            ));
+        }
+    }
+
+    fn validate_conntrack(&self, diagnostics: &mut Vec<Diagnostic>) {
+        let conntrack = &self.conntrack;
+        if conntrack.max_connections == 0 {
+            diagnostics.push(Diagnostic::error(
+                "conntrack.max_connections",
+                "Must be at least 1",
+            ));
+        }
+
+        let resolution = conntrack.timeout_resolution;
+        if resolution == 0 {
+            diagnostics.push(Diagnostic::error(
+                "conntrack.timeout_resolution",
+                "Must be at least 1",
+            ));
+            return;
+        }
+        let timeouts = [
+            ("udp_inactivity_timeout", conntrack.udp_inactivity_timeout),
+            ("tcp_inactivity_timeout", conntrack.tcp_inactivity_timeout),
+            ("sctp_inactivity_timeout", conntrack.sctp_inactivity_timeout),
+            ("tcp_establish_timeout", conntrack.tcp_establish_timeout),
+        ];
+        let max_timeout = timeouts[..3]
+            .iter()
+            .map(|(_, timeout)| *timeout)
+            .max()
+            .unwrap_or_default();
+        if resolution > max_timeout {
+            diagnostics.push(Diagnostic::error(
+                "conntrack.timeout_resolution",
+                format!(
+                    "Timeout check period ({} ms) must be smaller than the maximum inactivity \
+                     timeout ({} ms)",
+                    resolution, max_timeout
+                ),
+            ));
+        } else {
+            for (name, timeout) in timeouts {
+                if timeout < resolution {
+                    diagnostics.push(Diagnostic::warning(
+                        format!("conntrack.{}", name),
+                        format!(
+                            "Timeout ({} ms) is shorter than the timeout check period ({} ms), \
+                             connections will expire late",
+                            timeout, resolution
+                        ),
+                    ));
+                }
+            }
+        }
+        match conntrack.active_timeout {
+            Some(0) => diagnostics.push(Diagnostic::error(
+                "conntrack.active_timeout",
+                "Must be at least 1",
+            )),
+            Some(active_timeout) if resolution > active_timeout => {
+                diagnostics.push(Diagnostic::error(
+                    "conntrack.active_timeout",
+                    format!(
+                        "Active timeout ({} ms) must be larger than the timeout check period ({} \
+                         ms)",
+                        active_timeout, resolution
+                    ),
+                ))
+            }
+            _ => (),
+        }
+    }
+
+    fn validate_online(&self, online: &OnlineConfig, diagnostics: &mut Vec<Diagnostic>) {
+        if online.ports.is_empty() {
+            diagnostics.push(Diagnostic::error("online.ports", "No ports configured"));
+        }
+        let mut devices = HashMap::new();
+        for (i, port) in online.ports.iter().enumerate() {
+            let field = format!("online.ports[{}]", i);
+            if let Some(prev) = devices.insert(port.device.as_str(), i) {
+                diagnostics.push(Diagnostic::error(
+                    format!("{}.device", field),
+                    format!(
+                        "Device {} is also configured by online.ports[{}]",
+                        port.device, prev
+                    ),
+                ));
+            }
+            if !pci_device_exists(&port.device) {
+                diagnostics.push(Diagnostic::warning(
+                    format!("{}.device", field),
+                    format!("PCI device {} not found", port.device),
+                ));
+            }
+
+            validate_cores(&format!("{}.cores", field), &port.cores, diagnostics);
+            let mut cores = port.cores.clone();
+            cores.sort_unstable();
+            cores.dedup();
+            if cores.is_empty() {
+                diagnostics.push(Diagnostic::error(
+                    format!("{}.cores", field),
+                    "No packet processing cores configured",
+                ));
+            } else if cores.len() < port.cores.len() {
+                diagnostics.push(Diagnostic::warning(
+                    format!("{}.cores", field),
+                    "Duplicate cores are ignored",
+                ));
+            }
+            if cores.contains(&self.main_core) {
+                diagnostics.push(Diagnostic::error(
+                    format!("{}.cores", field),
+                    format!(
+                        "Main core {} cannot be a packet processing core",
+                        self.main_core
+                    ),
+                ));
+            }
+
+            if let Some(sink) = &port.sink {
+                let field = format!("{}.sink", field);
+                validate_cores(&format!("{}.core", field), &[sink.core], diagnostics);
+                if sink.core == self.main_core {
+                    diagnostics.push(Diagnostic::error(
+                        format!("{}.core", field),
+                        format!("Main core {} cannot be a sink core", self.main_core),
+                    ));
+                } else if cores.contains(&sink.core) {
+                    diagnostics.push(Diagnostic::warning(
+                        format!("{}.core", field),
+                        format!("Sink core {} also processes packets", sink.core),
+                    ));
+                }
+                if !cores.is_empty() {
+                    if sink.nb_buckets < cores.len() || sink.nb_buckets > RSS_RETA_SIZE {
+                        diagnostics.push(Diagnostic::error(
+                            format!("{}.nb_buckets", field),
+                            format!(
+                                "Number of buckets ({}) must range from the number of cores \
+                                 ({}) to {}",
+                                sink.nb_buckets,
+                                cores.len(),
+                                RSS_RETA_SIZE
+                            ),
+                        ));
+                    } else if sink.nb_buckets % cores.len() > 0 {
+                        diagnostics.push(Diagnostic::warning(
+                            format!("{}.nb_buckets", field),
+                            format!(
+                                "Number of buckets ({}) is not a multiple of the number of cores \
+                                 ({}), may result in poor load balancing",
+                                sink.nb_buckets,
+                                cores.len()
+                            ),
+                        ));
+                    }
+                }
+            }
+
+            if let Some(node) = pci_device_numa_node(&port.device) {
+                let remote: Vec<String> = cores
+                    .iter()
+                    .filter(|core| core_numa_node(**core).is_some_and(|n| n != node))
+                    .map(|core| core.to_string())
+                    .collect();
+                if !remote.is_empty() {
+                    diagnostics.push(Diagnostic::warning(
+                        format!("{}.cores", field),
+                        format!(
+                            "Cores {} are not on the NUMA node of device {} (node {})",
+                            remote.join(","),
+                            port.device,
+                            node
+                        ),
+                    ));
+                }
+            }
+        }
+
+        if let Some(prometheus) = online.monitor.as_ref().and_then(|m| m.prometheus.as_ref()) {
+            if prometheus.address.parse::<SocketAddr>().is_err() {
+                diagnostics.push(Diagnostic::error(
+                    "online.monitor.prometheus.address",
+                    format!("Invalid socket address: {}", prometheus.address),
+                ));
+            }
+        }
+    }
+
+    fn validate_offline(&self, offline: &OfflineConfig, diagnostics: &mut Vec<Diagnostic>) {
+        if offline.pcap.is_empty() {
+            diagnostics.push(Diagnostic::error(
+                "offline.pcap",
+                "No capture files configured",
+            ));
+        }
+        for path in offline.pcap.iter() {
+            let is_pattern = path.contains(['*', '?', '[']);
+            if !is_pattern && !Path::new(path).exists() {
+                diagnostics.push(Diagnostic::warning(
+                    "offline.pcap",
+                    format!("No such file or directory: {}", path),
+                ));
+            }
+        }
+        validate_cores("offline.cores", &offline.cores, diagnostics);
+        if offline.cores.contains(&self.main_core) {
+            diagnostics.push(Diagnostic::error(
+                "offline.cores",
+                format!("Main core {} cannot be a worker core", self.main_core),
+            ));
+        }
+        if let Some(speed) = offline.replay_speed {
+            if !speed.is_finite() || speed <= 0.0 {
+                diagnostics.push(Diagnostic::error(
+                    "offline.replay_speed",
+                    format!("Offline replay speed must be positive: {}", speed),
+                ));
+            }
+        }
+    }
+
+    fn validate_af_packet(&self, af_packet: &AfPacketConfig, diagnostics: &mut Vec<Diagnostic>) {
+        if af_packet.interface.is_empty() {
+            diagnostics.push(Diagnostic::error(
+                "af_packet.interface",
+                "No interface configured",
+            ));
+        } else if !interface_exists(&af_packet.interface) {
+            diagnostics.push(Diagnostic::warning(
+                "af_packet.interface",
+                format!("Interface {} not found", af_packet.interface),
+            ));
+        }
+        validate_cores("af_packet.cores", &af_packet.cores, diagnostics);
+        if af_packet.cores.is_empty() {
+            diagnostics.push(Diagnostic::error("af_packet.cores", "No cores configured"));
+        }
+        if af_packet.cores.contains(&self.main_core) {
+            diagnostics.push(Diagnostic::error(
+                "af_packet.cores",
+                format!("Main core {} cannot be a capture core", self.main_core),
+            ));
+        }
+        if af_packet.ring_size == 0 {
+            diagnostics.push(Diagnostic::error(
+                "af_packet.ring_size",
+                "Must be at least 1",
+            ));
+        }
+    }
+}
+
+const SYSFS_CPU: &str = "/sys/devices/system/cpu";
+const SYSFS_PCI: &str = "/sys/bus/pci/devices";
+const SYSFS_NET: &str = "/sys/class/net";
+
+/// Checks that `cores` exist on this machine.
+fn validate_cores(field: &str, cores: &[u32], diagnostics: &mut Vec<Diagnostic>) {
+    let missing: Vec<String> = cores
+        .iter()
+        .filter(|core| !core_exists(**core))
+        .map(|core| core.to_string())
+        .collect();
+    match missing.len() {
+        0 => (),
+        1 => diagnostics.push(Diagnostic::error(
+            field,
+            format!("Core {} does not exist on this machine", missing[0]),
+        )),
+        _ => diagnostics.push(Diagnostic::error(
+            field,
+            format!("Cores {} do not exist on this machine", missing.join(",")),
+        )),
+    }
+}
+
+/// Returns `false` if sysfs lists the CPUs of this machine and `core` is not one of them.
+fn core_exists(core: u32) -> bool {
+    !Path::new(SYSFS_CPU).is_dir() || Path::new(&format!("{}/cpu{}", SYSFS_CPU, core)).exists()
+}
+
+/// Returns the NUMA node of `core`, if known.
+fn core_numa_node(core: u32) -> Option<u32> {
+    fs::read_dir(format!("{}/cpu{}", SYSFS_CPU, core))
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .find_map(|entry| {
+            entry
+                .file_name()
+                .to_str()?
+                .strip_prefix("node")?
+                .parse()
+                .ok()
+        })
+}
+
+/// Returns the sysfs name of a PCI device (e.g., `0000:3b:00.0` for `3b:00.0`), or `None` if
+/// `device` is not a PCI address.
+fn pci_device_name(device: &str) -> Option<String> {
+    let is_pci = device
+        .chars()
+        .all(|c| c.is_ascii_hexdigit() || c == ':' || c == '.');
+    match device.matches(':').count() {
+        1 if is_pci => Some(format!("0000:{}", device.to_lowercase())),
+        2 if is_pci => Some(device.to_lowercase()),
+        _ => None,
+    }
+}
+
+/// Returns `false` if sysfs lists the PCI devices of this machine and `device` is a PCI address
+/// that is not one of them. Virtual devices are assumed to exist.
+fn pci_device_exists(device: &str) -> bool {
+    match pci_device_name(device) {
+        Some(name) => {
+            !Path::new(SYSFS_PCI).is_dir() || Path::new(&format!("{}/{}", SYSFS_PCI, name)).exists()
+        }
+        None => true,
+    }
+}
+
+/// Returns the NUMA node of PCI device `device`, if known.
+fn pci_device_numa_node(device: &str) -> Option<u32> {
+    let name = pci_device_name(device)?;
+    fs::read_to_string(format!("{}/{}/numa_node", SYSFS_PCI, name))
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+}
+
+/// Returns `false` if sysfs lists the network interfaces of this machine and `interface` is not
+/// one of them.
+fn interface_exists(interface: &str) -> bool {
+    !Path::new(SYSFS_NET).is_dir() || Path::new(&format!("{}/{}", SYSFS_NET, interface)).exists()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn errors(config: &RuntimeConfig) -> Vec<String> {
+        config
+            .validate()
+            .into_iter()
+            .filter(Diagnostic::is_error)
+            .map(|diagnostic| diagnostic.field)
+            .collect()
+    }
+
+    #[test]
+    fn core_config_validate() {
+        let mut config = default_config();
+        assert!(errors(&config).is_empty());
+
+        config.conntrack.timeout_resolution = 400_000;
+        config.conntrack.active_timeout = Some(1000);
+        config.mempool.cache_size = 8000;
+        config.offline.as_mut().unwrap().cores = vec![0];
+        assert_eq!(
+            errors(&config),
+            [
+                "mempool.cache_size",
+                "conntrack.timeout_resolution",
+                "conntrack.active_timeout",
+                "offline.cores",
+            ]
+        );
+
+        let mut config = default_config();
+        config.offline = None;
+        config.online = Some(OnlineConfig {
+            duration: None,
+            promiscuous: true,
+            nb_rxd: 4096,
+            mtu: 1500,
+            hardware_assist: true,
+            dpdk_supl_args: vec![],
+            monitor: None,
+            ports: vec![
+                PortMap {
+                    device: "0000:3b:00.0".to_string(),
+                    cores: vec![1, 2, 3],
+                    sink: Some(SinkConfig {
+                        core: 0,
+                        nb_buckets: 2,
+                    }),
+                },
+                PortMap {
+                    device: "0000:3b:00.0".to_string(),
+                    cores: vec![],
+                    sink: None,
+                },
+            ],
+        });
+        // Cores that do not exist on the test machine are also reported
+        let errors = errors(&config);
+        for field in [
+            "online.ports[0].sink.core",
+            "online.ports[0].sink.nb_buckets",
+            "online.ports[1].device",
+            "online.ports[1].cores",
+        ] {
+            assert!(errors.iter().any(|error| error == field), "{}", field);
+        }
+        assert_eq!(
+            config.get_eal_params()[..4],
+            ["--main-lcore", "0", "-l", "0,1,2,3"]
+        );
+    }
+}
@@ -0,0 +1,130 @@
+//! Packet clock.
+//!
+//! Connection tracking measures time with the packet clock of the current core. In online mode,
+//! the packet clock is the system clock. In offline mode, it follows the capture timestamps of
+//! the packets processed by the core instead, so that inactivity timeouts and connection
+//! durations are measured in trace time regardless of how fast the trace is read. When the trace
+//! is replayed in real time, the packet clock also advances between packets at the replay speed.
+
+use std::cell::Cell;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Instant and capture timestamp (in nanoseconds) of the first packet in the trace, shared by all
+/// cores so that their trace times are comparable.
+static TRACE_EPOCH: OnceLock<(Instant, u64)> = OnceLock::new();
+
+/// Instant and system time at which the packet clock was first converted to system time, if not
+/// following a trace.
+static SYSTEM_EPOCH: OnceLock<(Instant, SystemTime)> = OnceLock::new();
+
+/// Speed factor of real-time replay, if the trace is replayed in real time.
+static REPLAY_SPEED: OnceLock<f64> = OnceLock::new();
+
+thread_local! {
+    /// Trace time of the last packet processed by this core, if following a trace.
+    static TRACE_TIME: Cell<Option<Instant>> = const { Cell::new(None) };
+}
+
+/// Returns the current time of this core's packet clock.
+#[inline]
+pub(crate) fn now() -> Instant {
+    let time = TRACE_TIME.with(|time| time.get());
+    match (time, REPLAY_SPEED.get(), TRACE_EPOCH.get()) {
+        (time, Some(&speed), Some(&(origin, _))) => {
+            let replay_time = origin
+                + Instant::now()
+                    .saturating_duration_since(origin)
+                    .mul_f64(speed);
+            time.map_or(replay_time, |time| time.max(replay_time))
+        }
+        (Some(time), _, _) => time,
+        (None, _, _) => Instant::now(),
+    }
+}
+
+/// Replays the trace in real time, sped up by a factor of `speed`.
+pub(crate) fn set_replay_speed(speed: f64) {
+    REPLAY_SPEED.get_or_init(|| speed);
+}
+
+/// Returns the (system clock) instant at which the packet captured at `ts` (in nanoseconds since
+/// the UNIX epoch) is due, if the trace is replayed in real time.
+pub(crate) fn replay_due(ts: u64) -> Option<Instant> {
+    let speed = *REPLAY_SPEED.get()?;
+    let (origin, first_ts) = epoch(ts);
+    Some(origin + Duration::from_nanos(ts.saturating_sub(first_ts)).div_f64(speed))
+}
+
+/// Advances this core's packet clock to capture timestamp `ts` (in nanoseconds since the UNIX
+/// epoch). The clock never moves backwards, so out-of-order timestamps are ignored.
+#[inline]
+pub(crate) fn set_trace_time(ts: u64) {
+    let (origin, first_ts) = epoch(ts);
+    let time = origin + Duration::from_nanos(ts.saturating_sub(first_ts));
+    TRACE_TIME.with(|current| {
+        let is_later = match current.get() {
+            Some(current) => current < time,
+            None => true,
+        };
+        if is_later {
+            current.set(Some(time));
+        }
+    });
+}
+
+/// Converts `instant` of the packet clock to system time. When following a trace, this is the
+/// capture time of the trace. Conversions of the same instant always return the same time.
+pub(crate) fn system_time(instant: Instant) -> SystemTime {
+    let (origin, origin_time) = match TRACE_EPOCH.get() {
+        Some(&(origin, first_ts)) => (origin, UNIX_EPOCH + Duration::from_nanos(first_ts)),
+        None => *SYSTEM_EPOCH.get_or_init(|| (Instant::now(), SystemTime::now())),
+    };
+    if instant >= origin {
+        origin_time + (instant - origin)
+    } else {
+        origin_time - (origin - instant)
+    }
+}
+
+/// Returns the trace epoch, starting it at the packet captured at `ts` if needed.
+fn epoch(ts: u64) -> (Instant, u64) {
+    *TRACE_EPOCH.get_or_init(|| (Instant::now(), ts))
+}
+
+/// Returns the capture timestamp of the trace epoch, starting it at `ts` if needed. The epoch is
+/// shared by all tests in the process, so tests measure trace times relative to it.
+#[cfg(test)]
+pub(crate) fn epoch_ts(ts: u64) -> u64 {
+    epoch(ts).1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn core_clock_trace_time() {
+        const MS: u64 = 1_000_000;
+        let first_ts = epoch_ts(1_000 * MS);
+        set_trace_time(first_ts);
+        let start = now();
+        set_trace_time(first_ts + 500 * MS);
+        assert_eq!(now() - start, Duration::from_millis(500));
+        set_trace_time(first_ts + 200 * MS);
+        assert_eq!(now() - start, Duration::from_millis(500));
+
+        // Other cores share the same epoch
+        let other = std::thread::spawn(move || {
+            set_trace_time(first_ts + 1_000 * MS);
+            now()
+        })
+        .join()
+        .unwrap();
+        assert_eq!(other - start, Duration::from_secs(1));
+        assert_eq!(
+            system_time(other),
+            UNIX_EPOCH + Duration::from_nanos(first_ts + 1_000 * MS)
+        );
+    }
+}
@@ -1,6 +1,7 @@
 use crate::conntrack::conn_id::FiveTuple;
 use crate::conntrack::pdu::L4Pdu;
 use crate::filter::FilterResult;
+use crate::protocols::packet::sctp::SCTP_PROTOCOL;
 use crate::protocols::packet::udp::UDP_PROTOCOL;
 use crate::protocols::stream::{
     ConnData, ParseResult, ParserRegistry, ProbeRegistryResult, Session,
@@ -14,6 +15,8 @@ where
 {
     /// State of Conn
     pub(crate) state: ConnState,
+    /// Whether the connection has matched the filter and been delivered to the subscription
+    pub(crate) matched: bool,
     /// Connection data (for filtering)
     pub(crate) cdata: ConnData,
     /// Subscription data (for delivering)
@@ -27,6 +30,7 @@ where
     pub(super) fn new(five_tuple: FiveTuple, pkt_term_node: usize) -> Self {
         ConnInfo {
             state: ConnState::Probing,
+            matched: false,
             cdata: ConnData::new(five_tuple, pkt_term_node),
             sdata: T::new(five_tuple),
         }
@@ -80,6 +84,7 @@ where
                 match subscription.filter_conn(&self.cdata) {
                     FilterResult::MatchTerminal(_idx) => {
                         self.sdata.on_match(Session::default(), subscription);
+                        self.matched = true;
                         self.state = self.get_match_state(0);
                     }
                     FilterResult::MatchNonTerminal(_idx) => {
@@ -103,6 +108,7 @@ where
                 if let Some(session) = self.cdata.conn_parser.remove_session(id) {
                     if subscription.filter_session(&session, self.cdata.conn_term_node) {
                         self.sdata.on_match(session, subscription);
+                        self.matched = true;
                         self.state = self.get_match_state(id);
                     } else {
                         self.state = self.get_nomatch_state(id);
@@ -142,7 +148,11 @@ where
     }
 
     fn get_drop_state(&self) -> ConnState {
-        if self.cdata.five_tuple.proto == UDP_PROTOCOL {
+        // SCTP associations can be picked up mid-stream, so they must also age out to avoid being
+        // re-inserted
+        if self.cdata.five_tuple.proto == UDP_PROTOCOL
+            || self.cdata.five_tuple.proto == SCTP_PROTOCOL
+        {
             return ConnState::Dropped;
         }
         ConnState::Remove
@@ -159,7 +169,7 @@ pub enum ConnState {
     Tracking,
     /// Connection will be removed
     Remove,
-    /// Unmatched UDP connection; waiting to be aged out by timerwheel.
-    /// Prevents dropped UDP conns from being re-inserted in table
+    /// Unmatched UDP connection or SCTP association; waiting to be aged out by timerwheel.
+    /// Prevents dropped UDP and SCTP conns from being re-inserted in table
     Dropped,
 }
@@ -1,18 +1,22 @@
 //! State management for connections.
 //!
-//! Tracks a TCP or UDP connection, performs stream reassembly, and manages protocol parser state
-//! throughout the duration of the connection.
+//! Tracks a TCP, UDP, or SCTP connection, performs stream reassembly, and manages protocol parser
+//! state throughout the duration of the connection.
 
 pub(crate) mod conn_info;
+pub(crate) mod sctp_conn;
 pub(crate) mod tcp_conn;
 pub(crate) mod udp_conn;
 
 use self::conn_info::{ConnInfo, ConnState};
+use self::sctp_conn::SctpConn;
 use self::tcp_conn::TcpConn;
 use self::udp_conn::UdpConn;
+use crate::conntrack::clock;
 use crate::conntrack::conn_id::FiveTuple;
 use crate::conntrack::pdu::{L4Context, L4Pdu};
 use crate::filter::FilterResult;
+use crate::protocols::packet::sctp::{ABORT, SHUTDOWN_COMPLETE};
 use crate::protocols::packet::tcp::{ACK, RST, SYN};
 use crate::protocols::stream::ParserRegistry;
 use crate::subscription::{Subscription, Trackable};
@@ -20,12 +24,14 @@ use crate::subscription::{Subscription, Trackable};
 use anyhow::{bail, Result};
 use std::time::Instant;
 
-/// Tracks either a TCP or a UDP connection.
+/// Tracks a TCP connection, a UDP connection, or an SCTP association.
 ///
-/// Performs light-weight stream reassembly for TCP connections and tracks UDP connections.
+/// Performs light-weight stream reassembly for TCP connections, per-stream DATA chunk ordering for
+/// SCTP associations, and tracks UDP connections.
 pub(crate) enum L4Conn {
     Tcp(TcpConn),
     Udp(UdpConn),
+    Sctp(SctpConn),
 }
 
 /// Connection state.
@@ -41,6 +47,8 @@ where
     pub(crate) l4conn: L4Conn,
     /// Connection information for filtering and parsing.
     pub(crate) info: ConnInfo<T>,
+    /// Generation of the interim record timer of the connection, if scheduled.
+    pub(crate) report_gen: Option<u64>,
 }
 
 impl<T> Conn<T>
@@ -59,10 +67,11 @@ where
             bail!("Not SYN")
         };
         Ok(Conn {
-            last_seen_ts: Instant::now(),
+            last_seen_ts: clock::now(),
             inactivity_window: initial_timeout,
             l4conn: L4Conn::Tcp(tcp_conn),
             info: ConnInfo::new(five_tuple, ctxt.idx),
+            report_gen: None,
         })
     }
 
@@ -73,10 +82,37 @@ where
         let five_tuple = FiveTuple::from_ctxt(ctxt);
         let udp_conn = UdpConn;
         Ok(Conn {
-            last_seen_ts: Instant::now(),
+            last_seen_ts: clock::now(),
             inactivity_window: initial_timeout,
             l4conn: L4Conn::Udp(udp_conn),
             info: ConnInfo::new(five_tuple, ctxt.idx),
+            report_gen: None,
+        })
+    }
+
+    /// Creates a new SCTP association from `ctxt` with an initial inactivity window of
+    /// `initial_timeout` and a maximum out-of-order tolerance of `max_ooo` DATA chunks.
+    ///
+    /// Unlike TCP, associations may be picked up mid-stream. Packets that would immediately end the
+    /// association (ABORT or SHUTDOWN COMPLETE) do not create one.
+    pub(super) fn new_sctp(
+        ctxt: L4Context,
+        initial_timeout: usize,
+        max_ooo: usize,
+    ) -> Result<Self> {
+        let five_tuple = FiveTuple::from_ctxt(ctxt);
+        let sctp_conn = match ctxt.sctp {
+            Some(sctp) if !sctp.has_chunk(ABORT) && !sctp.has_chunk(SHUTDOWN_COMPLETE) => {
+                SctpConn::new(ctxt, max_ooo)
+            }
+            _ => bail!("Not a new association"),
+        };
+        Ok(Conn {
+            last_seen_ts: clock::now(),
+            inactivity_window: initial_timeout,
+            l4conn: L4Conn::Sctp(sctp_conn),
+            info: ConnInfo::new(five_tuple, ctxt.idx),
+            report_gen: None,
         })
     }
 
@@ -103,6 +139,20 @@ where
                 }
             }
             L4Conn::Udp(_udp_conn) => self.info.consume_pdu(pdu, subscription, registry),
+            L4Conn::Sctp(sctp_conn) => {
+                if self.info.state == ConnState::Tracking {
+                    if sctp_conn.ctos.nb_buffered() != 0 {
+                        sctp_conn.ctos.clear();
+                    }
+                    if sctp_conn.stoc.nb_buffered() != 0 {
+                        sctp_conn.stoc.clear();
+                    }
+                    sctp_conn.update_term_condition(pdu.ctxt.sctp, pdu.dir);
+                    self.info.sdata.post_match(pdu, subscription);
+                } else {
+                    sctp_conn.reassemble(pdu, &mut self.info, subscription, registry);
+                }
+            }
         }
     }
 
@@ -116,11 +166,26 @@ where
         self.info.state
     }
 
+    /// Returns `true` if the connection has matched the filter.
+    pub(super) fn matched(&self) -> bool {
+        self.info.matched
+    }
+
     /// Returns `true` if the connection has been naturally terminated.
     pub(super) fn terminated(&self) -> bool {
         match &self.l4conn {
             L4Conn::Tcp(tcp_conn) => tcp_conn.is_terminated(),
             L4Conn::Udp(_udp_conn) => false,
+            L4Conn::Sctp(sctp_conn) => sctp_conn.is_terminated(),
+        }
+    }
+
+    /// Returns `true` if the connection overflowed its out-of-order buffer.
+    pub(super) fn ooo_overflow(&self) -> bool {
+        match &self.l4conn {
+            L4Conn::Tcp(tcp_conn) => tcp_conn.ooo_overflow(),
+            L4Conn::Udp(_udp_conn) => false,
+            L4Conn::Sctp(sctp_conn) => sctp_conn.ooo_overflow(),
         }
     }
 
@@ -130,11 +195,20 @@ where
         self.five_tuple().orig == ctxt.src
     }
 
+    /// Invokes the active timeout tasks of a connection that is still alive (e.g., delivering an
+    /// interim record). Only applies to connections that have matched the filter.
+    pub(crate) fn report(&mut self, subscription: &Subscription<T::Subscribed>) {
+        if self.info.state == ConnState::Tracking {
+            self.info.sdata.on_active_timeout(subscription);
+        }
+    }
+
     /// Invokes connection termination tasks that are triggered when any of the following conditions
     /// occur:
     /// - the connection naturally terminates (e.g., FIN/RST)
     /// - the connection expires due to inactivity
-    /// - the connection is drained at the end of the run
+    /// - the connection is evicted or drained at the end of the run (see
+    ///   [force_terminate](Conn::force_terminate))
     pub(crate) fn terminate(&mut self, subscription: &Subscription<T::Subscribed>) {
         match self.info.state {
             ConnState::Probing => {
@@ -165,4 +239,11 @@ where
             }
         }
     }
+
+    /// Invokes connection termination tasks for a connection that is still active, when it is
+    /// evicted from a full table or drained at the end of the run.
+    pub(crate) fn force_terminate(&mut self, subscription: &Subscription<T::Subscribed>) {
+        self.info.sdata.on_force_end();
+        self.terminate(subscription);
+    }
 }
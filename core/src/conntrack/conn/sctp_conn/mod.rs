@@ -0,0 +1,299 @@
+use crate::conntrack::conn::conn_info::{ConnInfo, ConnState};
+use crate::conntrack::pdu::{L4Context, L4Pdu, SctpContext};
+use crate::protocols::packet::sctp::{ABORT, DATA, INIT, SHUTDOWN_COMPLETE};
+use crate::protocols::stream::ParserRegistry;
+use crate::subscription::{Subscription, Trackable};
+
+use std::collections::{HashMap, VecDeque};
+
+/// Tracks an SCTP association.
+///
+/// Associations may be picked up mid-stream, in which case the verification tag of each direction
+/// is learned from the first packet observed in that direction.
+pub(crate) struct SctpConn {
+    pub(crate) ctos: SctpFlow,
+    pub(crate) stoc: SctpFlow,
+}
+
+impl SctpConn {
+    pub(crate) fn new(ctxt: L4Context, max_ooo: usize) -> Self {
+        let mut sctp_conn = SctpConn {
+            ctos: SctpFlow::new(max_ooo),
+            stoc: SctpFlow::new(max_ooo),
+        };
+        if let Some(sctp) = ctxt.sctp {
+            sctp_conn.learn_tags(&sctp, true);
+        }
+        sctp_conn
+    }
+
+    /// Insert DATA chunks in stream order into ctos or stoc flow. Packets that bundle several
+    /// DATA chunks are ordered chunk by chunk.
+    #[inline]
+    pub(crate) fn reassemble<T: Trackable>(
+        &mut self,
+        pdu: L4Pdu,
+        info: &mut ConnInfo<T>,
+        subscription: &Subscription<T::Subscribed>,
+        registry: &ParserRegistry,
+    ) {
+        let dir = pdu.dir;
+        if let Some(sctp) = pdu.ctxt.sctp {
+            self.learn_tags(&sctp, dir);
+        }
+        let flow = if dir { &mut self.ctos } else { &mut self.stoc };
+        match pdu.ctxt.sctp {
+            Some(sctp) if sctp.nb_data > 1 => {
+                for pdu in pdu.split_sctp_data() {
+                    flow.insert_chunk(pdu, info, subscription, registry);
+                }
+            }
+            _ => flow.insert_chunk(pdu, info, subscription, registry),
+        }
+    }
+
+    /// Returns `true` if either flow's out-of-order buffer is at capacity
+    #[inline]
+    pub(crate) fn ooo_overflow(&self) -> bool {
+        self.ctos.nb_buffered() >= self.ctos.capacity
+            || self.stoc.nb_buffered() >= self.stoc.capacity
+    }
+
+    /// Returns `true` if the association should be terminated
+    #[inline]
+    pub(crate) fn is_terminated(&self) -> bool {
+        // An ABORT or SHUTDOWN COMPLETE has been sent
+        let term = (1 << ABORT) | (1 << SHUTDOWN_COMPLETE);
+        (self.ctos.consumed_chunks | self.stoc.consumed_chunks) & term != 0
+    }
+
+    /// Updates association termination chunks
+    #[inline]
+    pub(super) fn update_term_condition(&mut self, sctp: Option<SctpContext>, dir: bool) {
+        let chunks = sctp.map_or(0, |sctp| sctp.chunks);
+        if dir {
+            self.ctos.consumed_chunks |= chunks;
+        } else {
+            self.stoc.consumed_chunks |= chunks;
+        }
+    }
+
+    /// Records the Initiate Tag of an INIT or INIT-ACK as the verification tag expected from the
+    /// peer.
+    fn learn_tags(&mut self, sctp: &SctpContext, dir: bool) {
+        if let Some(tag) = sctp.initiate_tag {
+            if dir {
+                self.stoc.verification_tag = Some(tag);
+            } else {
+                self.ctos.verification_tag = Some(tag);
+            }
+        }
+    }
+}
+
+/// Represents a uni-directional SCTP flow
+pub(crate) struct SctpFlow {
+    /// Verification tag expected on packets in this direction
+    pub(super) verification_tag: Option<u32>,
+    /// Chunk types consumed in this direction.
+    /// Bit `n` is set for chunk type `n`.
+    pub(super) consumed_chunks: u32,
+    /// Per-stream ordering state, keyed by stream identifier
+    pub(crate) streams: HashMap<u16, SctpStream>,
+    /// Maximum number of DATA chunks buffered across all streams
+    capacity: usize,
+}
+
+impl SctpFlow {
+    /// Creates a new SCTP flow with out-of-order capacity
+    #[inline]
+    fn new(capacity: usize) -> Self {
+        SctpFlow {
+            verification_tag: None,
+            consumed_chunks: 0,
+            streams: HashMap::new(),
+            capacity,
+        }
+    }
+
+    /// Returns the number of DATA chunks buffered out of order across all streams
+    pub(crate) fn nb_buffered(&self) -> usize {
+        self.streams.values().map(|s| s.ooo_buf.len()).sum()
+    }
+
+    /// Clears all buffered DATA chunks
+    pub(crate) fn clear(&mut self) {
+        for stream in self.streams.values_mut() {
+            stream.ooo_buf.clear();
+        }
+    }
+
+    /// Attempt to insert incoming packet into flow.
+    /// Control chunks and unordered DATA are consumed immediately. Ordered DATA is consumed in
+    /// stream sequence order: future chunks are buffered and old chunks are dropped.
+    /// Shunts association if the incoming chunk causes out-of-order buffer overflow
+    #[inline]
+    fn insert_chunk<T: Trackable>(
+        &mut self,
+        pdu: L4Pdu,
+        info: &mut ConnInfo<T>,
+        subscription: &Subscription<T::Subscribed>,
+        registry: &ParserRegistry,
+    ) {
+        let sctp = pdu.ctxt.sctp.unwrap_or_default();
+
+        match self.verification_tag {
+            Some(tag) if tag != sctp.verification_tag => {
+                // INIT is sent with a zero tag, ABORT and SHUTDOWN COMPLETE may reflect the
+                // peer's tag
+                if !(sctp.has_chunk(INIT)
+                    || sctp.has_chunk(ABORT)
+                    || sctp.has_chunk(SHUTDOWN_COMPLETE))
+                {
+                    log::debug!(
+                        "Dropping packet with unexpected verification tag. cur: {} expect: {}",
+                        sctp.verification_tag,
+                        tag
+                    );
+                    return;
+                }
+            }
+            Some(_) => (),
+            None => {
+                if !sctp.has_chunk(INIT) {
+                    self.verification_tag = Some(sctp.verification_tag);
+                }
+            }
+        }
+        self.consumed_chunks |= sctp.chunks;
+
+        if !sctp.has_chunk(DATA) || sctp.unordered {
+            info.consume_pdu(pdu, subscription, registry);
+            return;
+        }
+
+        let nb_buffered = self.nb_buffered();
+        let stream = self
+            .streams
+            .entry(sctp.stream_id)
+            .or_insert_with(SctpStream::new);
+        match stream.next_ssn {
+            Some(next_ssn) if next_ssn == sctp.stream_seq => {
+                // Chunk is the next expected chunk in the stream
+                info.consume_pdu(pdu, subscription, registry);
+                stream.flush_ooo_buffer::<T>(
+                    next_ssn.wrapping_add(1),
+                    info,
+                    subscription,
+                    registry,
+                );
+            }
+            Some(next_ssn) if ssn_lt(next_ssn, sctp.stream_seq) => {
+                // Chunk comes after the next expected chunk in the stream
+                if nb_buffered >= self.capacity {
+                    log::warn!("Out-of-order buffer overflow");
+                    info.state = ConnState::Remove;
+                    return;
+                }
+                stream.ooo_buf.push_back(pdu);
+            }
+            Some(next_ssn) => {
+                // Chunk is a retransmission
+                log::debug!(
+                    "Dropping old DATA chunk. tsn: {} ssn: {} expect: {}",
+                    sctp.tsn,
+                    sctp.stream_seq,
+                    next_ssn
+                );
+                drop(pdu);
+            }
+            None => {
+                // First DATA chunk observed in the stream
+                info.consume_pdu(pdu, subscription, registry);
+                stream.flush_ooo_buffer::<T>(
+                    sctp.stream_seq.wrapping_add(1),
+                    info,
+                    subscription,
+                    registry,
+                );
+            }
+        }
+    }
+}
+
+/// Ordering state of a single SCTP stream
+pub(crate) struct SctpStream {
+    /// Expected stream sequence number of the next ordered DATA chunk
+    next_ssn: Option<u16>,
+    /// Out-of-order buffer
+    pub(crate) ooo_buf: VecDeque<L4Pdu>,
+}
+
+impl SctpStream {
+    fn new() -> Self {
+        SctpStream {
+            next_ssn: None,
+            ooo_buf: VecDeque::new(),
+        }
+    }
+
+    /// Consumes buffered chunks in stream sequence order starting from `expected_ssn`, and drops
+    /// buffered chunks that are older than the new next expected SSN.
+    fn flush_ooo_buffer<T: Trackable>(
+        &mut self,
+        expected_ssn: u16,
+        info: &mut ConnInfo<T>,
+        subscription: &Subscription<T::Subscribed>,
+        registry: &ParserRegistry,
+    ) {
+        let mut next_ssn = expected_ssn;
+        while info.state != ConnState::Remove {
+            let pos = self
+                .ooo_buf
+                .iter()
+                .position(|pdu| stream_seq(pdu) == next_ssn);
+            match pos {
+                Some(pos) => {
+                    // unwrap ok because pos < len
+                    let pdu = self.ooo_buf.remove(pos).unwrap();
+                    info.consume_pdu(pdu, subscription, registry);
+                    next_ssn = next_ssn.wrapping_add(1);
+                }
+                None => break,
+            }
+        }
+        self.ooo_buf.retain(|pdu| ssn_lt(next_ssn, stream_seq(pdu)));
+        self.next_ssn = Some(next_ssn);
+    }
+}
+
+#[inline]
+fn stream_seq(pdu: &L4Pdu) -> u16 {
+    pdu.ctxt.sctp.map_or(0, |sctp| sctp.stream_seq)
+}
+
+/// Serial number comparison of 16-bit stream sequence numbers (RFC 1982).
+#[inline]
+fn ssn_lt(lhs: u16, rhs: u16) -> bool {
+    lhs != rhs && rhs.wrapping_sub(lhs) < (1 << 15)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn core_sctp_ssn_lt() {
+        assert!(ssn_lt(0, 1));
+        assert!(!ssn_lt(1, 0));
+        assert!(!ssn_lt(7, 7));
+        // Wraparound
+        assert!(ssn_lt(u16::MAX, 0));
+        assert!(ssn_lt(65530, 5));
+        assert!(!ssn_lt(5, 65530));
+        // Half of the sequence space apart
+        assert!(ssn_lt(0, (1 << 15) - 1));
+        assert!(!ssn_lt(0, 1 << 15));
+        assert!(ssn_lt((1 << 15) + 1, 0));
+    }
+}
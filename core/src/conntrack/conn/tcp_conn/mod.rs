@@ -40,6 +40,12 @@ impl TcpConn {
         }
     }
 
+    /// Returns `true` if either out-of-order buffer is at capacity
+    #[inline]
+    pub(crate) fn ooo_overflow(&self) -> bool {
+        self.ctos.ooo_buf.is_full() || self.stoc.ooo_buf.is_full()
+    }
+
     /// Returns `true` if the connection should be terminated
     #[inline]
     pub(crate) fn is_terminated(&self) -> bool {
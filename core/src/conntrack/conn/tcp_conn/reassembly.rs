@@ -156,6 +156,11 @@ impl OutOfOrderBuffer {
         self.buf.len()
     }
 
+    /// Returns `true` if the buffer is at capacity
+    pub(crate) fn is_full(&self) -> bool {
+        self.len() >= self.capacity
+    }
+
     /// Inserts segment at the end of the buffer.
     fn insert_back(&mut self, segment: L4Pdu) -> Result<()> {
         log::debug!("insert with seq : {:#?}", segment.seq_no());
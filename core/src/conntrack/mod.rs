@@ -4,30 +4,38 @@
 //! directly managed by users. However, it publicly exposes some useful connection identifiers for
 //! convenience.
 
+pub(crate) mod clock;
 pub(crate) mod conn;
 pub mod conn_id;
 pub(crate) mod pdu;
+pub(crate) mod stats;
 mod timerwheel;
 
 use self::conn::conn_info::ConnState;
 use self::conn::{Conn, L4Conn};
 use self::conn_id::ConnId;
 use self::pdu::{L4Context, L4Pdu};
+use self::stats::ConnTrackStats;
 use self::timerwheel::TimerWheel;
-use crate::config::ConnTrackConfig;
+use crate::config::{ConnTrackConfig, EvictionPolicy};
 use crate::memory::mbuf::Mbuf;
+use crate::protocols::packet::sctp::SCTP_PROTOCOL;
 use crate::protocols::packet::tcp::TCP_PROTOCOL;
 use crate::protocols::packet::udp::UDP_PROTOCOL;
 use crate::protocols::stream::ParserRegistry;
 use crate::subscription::{Subscription, Trackable};
 
 use std::cmp;
-use std::time::Instant;
+use std::sync::Arc;
 
 use anyhow::anyhow;
 use hashlink::linked_hash_map::{LinkedHashMap, RawEntryMut};
 
-/// Manages state for all TCP and UDP connections.
+/// Maximum number of entries scanned for an unmatched connection under the `ProbingFirst` eviction
+/// policy.
+const EVICTION_SCAN_LIMIT: usize = 64;
+
+/// Manages state for all TCP and UDP connections and SCTP associations.
 ///
 /// One `ConnTracker` is maintained per core. `ConnTracker` is not meant to be directly managed by
 /// users, but can be configured at runtime with a maximum capacity, out-of-order tolerance,
@@ -45,24 +53,35 @@ where
     table: LinkedHashMap<ConnId, Conn<T>>,
     /// Manages connection timeouts.
     timerwheel: TimerWheel,
+    /// Connection tracking counters, shared with the monitor.
+    stats: Arc<ConnTrackStats>,
 }
 
 impl<T> ConnTracker<T>
 where
     T: Trackable,
 {
-    /// Creates a new `ConnTracker`.
-    pub(crate) fn new(config: TrackerConfig, registry: ParserRegistry) -> Self {
+    /// Creates a new `ConnTracker` that records its counters in `stats`.
+    pub(crate) fn new(
+        config: TrackerConfig,
+        registry: ParserRegistry,
+        stats: Arc<ConnTrackStats>,
+    ) -> Self {
         let table = LinkedHashMap::with_capacity(config.max_connections);
         let timerwheel = TimerWheel::new(
-            cmp::max(config.tcp_inactivity_timeout, config.udp_inactivity_timeout),
+            cmp::max(
+                config.sctp_inactivity_timeout,
+                cmp::max(config.tcp_inactivity_timeout, config.udp_inactivity_timeout),
+            ),
             config.timeout_resolution,
+            config.active_timeout,
         );
         ConnTracker {
             config,
             registry,
             table,
             timerwheel,
+            stats,
         }
     }
 
@@ -82,8 +101,12 @@ where
         let conn_id = ConnId::new(ctxt.src, ctxt.dst, ctxt.proto);
         match self.table.raw_entry_mut().from_key(&conn_id) {
             RawEntryMut::Occupied(mut occupied) => {
+                if self.config.eviction != EvictionPolicy::None {
+                    // Keep the table ordered by last activity
+                    occupied.to_back();
+                }
                 let conn = occupied.get_mut();
-                conn.last_seen_ts = Instant::now();
+                conn.last_seen_ts = clock::now();
                 if conn.state() == ConnState::Dropped {
                     // Allow connection to age out.
                     // last_seen_ts is updated to avoid aging out long-lived UDP
@@ -94,23 +117,36 @@ where
                 conn.inactivity_window = match &conn.l4conn {
                     L4Conn::Tcp(_) => self.config.tcp_inactivity_timeout,
                     L4Conn::Udp(_) => self.config.udp_inactivity_timeout,
+                    L4Conn::Sctp(_) => self.config.sctp_inactivity_timeout,
                 };
                 if conn.state() == ConnState::Remove {
                     log::error!("Conn in Remove state when occupied in table");
                 }
                 let pdu = L4Pdu::new(mbuf, ctxt, dir);
                 conn.update(pdu, subscription, &self.registry);
-                if conn.state() == ConnState::Remove {
-                    occupied.remove();
-                    return;
+                match conn.state() {
+                    ConnState::Remove => {
+                        self.stats
+                            .record_removal(conn.ooo_overflow(), conn.matched());
+                        occupied.remove();
+                        return;
+                    }
+                    ConnState::Dropped => self.stats.incr_parser_dropped(),
+                    _ => (),
                 }
 
                 if conn.terminated() {
                     conn.terminate(subscription);
                     occupied.remove();
+                    self.stats.incr_terminated();
+                } else {
+                    self.timerwheel.insert_active(&conn_id, conn);
                 }
             }
             RawEntryMut::Vacant(_) => {
+                if self.size() >= self.config.max_connections {
+                    self.evict(subscription);
+                }
                 if self.size() < self.config.max_connections {
                     let conn = match ctxt.proto {
                         TCP_PROTOCOL => Conn::new_tcp(
@@ -119,39 +155,102 @@ where
                             self.config.max_out_of_order,
                         ),
                         UDP_PROTOCOL => Conn::new_udp(ctxt, self.config.udp_inactivity_timeout),
+                        SCTP_PROTOCOL => Conn::new_sctp(
+                            ctxt,
+                            self.config.sctp_inactivity_timeout,
+                            self.config.max_out_of_order,
+                        ),
                         _ => Err(anyhow!("Invalid L4 Protocol")),
                     };
                     if let Ok(mut conn) = conn {
                         let pdu = L4Pdu::new(mbuf, ctxt, true);
                         conn.info.consume_pdu(pdu, subscription, &self.registry);
-                        if conn.state() != ConnState::Remove {
-                            self.timerwheel.insert(
-                                &conn_id,
-                                conn.last_seen_ts,
-                                conn.inactivity_window,
-                            );
-                            self.table.insert(conn_id, conn);
+                        match conn.state() {
+                            ConnState::Remove => self
+                                .stats
+                                .record_removal(conn.ooo_overflow(), conn.matched()),
+                            state => {
+                                if state == ConnState::Dropped {
+                                    self.stats.incr_parser_dropped();
+                                }
+                                self.timerwheel.insert(
+                                    &conn_id,
+                                    conn.last_seen_ts,
+                                    conn.inactivity_window,
+                                );
+                                self.timerwheel.insert_active(&conn_id, &mut conn);
+                                self.table.insert(conn_id, conn);
+                                self.stats.incr_created();
+                            }
                         }
                     }
                 } else {
-                    log::error!("Table full. Dropping packet.");
+                    log::debug!("Table full. Dropping packet.");
+                    self.stats.incr_rejected_full();
                 }
             }
         }
+        self.stats.set_table_size(self.size());
+    }
+
+    /// Evicts a connection according to the configured eviction policy to make room for a new
+    /// connection. Evicted connections are terminated as if the run had ended.
+    fn evict(&mut self, subscription: &Subscription<T::Subscribed>) {
+        let conn_id = eviction_candidate(
+            self.config.eviction,
+            self.table
+                .iter()
+                .map(|(conn_id, conn)| (conn_id, conn.state())),
+        )
+        .cloned();
+        if let Some(mut conn) = conn_id.and_then(|conn_id| self.table.remove(&conn_id)) {
+            conn.force_terminate(subscription);
+            self.stats.incr_evicted();
+        }
     }
 
     /// Drains any remaining connections that satisfy the filter on runtime termination.
     pub(crate) fn drain(&mut self, subscription: &Subscription<T::Subscribed>) {
         log::info!("Draining Connection table");
         for (_, mut conn) in self.table.drain() {
-            conn.terminate(subscription);
+            conn.force_terminate(subscription);
         }
+        self.stats.set_table_size(0);
     }
 
     /// Checks for and removes inactive connections.
     pub(crate) fn check_inactive(&mut self, subscription: &Subscription<T::Subscribed>) {
-        self.timerwheel
+        let nb_expired = self
+            .timerwheel
             .check_inactive(&mut self.table, subscription);
+        if nb_expired > 0 {
+            self.stats.add_expired(nb_expired);
+            self.stats.set_table_size(self.size());
+        }
+    }
+}
+
+/// Returns the key of the connection to evict under `policy`, given the table `entries` ordered
+/// from least to most recently active.
+fn eviction_candidate<'a, K>(
+    policy: EvictionPolicy,
+    mut entries: impl Iterator<Item = (&'a K, ConnState)>,
+) -> Option<&'a K> {
+    match policy {
+        EvictionPolicy::None => None,
+        EvictionPolicy::OldestIdle => entries.next().map(|(key, _)| key),
+        EvictionPolicy::ProbingFirst => {
+            let unmatched =
+                |state: ConnState| matches!(state, ConnState::Probing | ConnState::Dropped);
+            let (oldest, state) = entries.next()?;
+            if unmatched(state) {
+                return Some(oldest);
+            }
+            let candidate = entries
+                .take(EVICTION_SCAN_LIMIT - 1)
+                .find(|(_, state)| unmatched(*state));
+            Some(candidate.map_or(oldest, |(key, _)| key))
+        }
     }
 }
 
@@ -160,14 +259,20 @@ where
 pub(crate) struct TrackerConfig {
     /// Maximum number of connections that can be tracked per-core.
     pub(super) max_connections: usize,
+    /// How to make room for new connections when the table is full.
+    pub(super) eviction: EvictionPolicy,
     /// Maximum number of out-of-order packets allowed per TCP connection.
     pub(super) max_out_of_order: usize,
     /// Time to expire inactive UDP connections (in milliseconds).
     pub(super) udp_inactivity_timeout: usize,
     /// Time to expire inactive TCP connections (in milliseconds).
     pub(super) tcp_inactivity_timeout: usize,
+    /// Time to expire inactive SCTP associations (in milliseconds).
+    pub(super) sctp_inactivity_timeout: usize,
     /// Time to expire unestablished TCP connections (in milliseconds).
     pub(super) tcp_establish_timeout: usize,
+    /// Interval between interim records of active connections (in milliseconds).
+    pub(super) active_timeout: Option<usize>,
     /// Frequency to check for inactive streams (in milliseconds).
     pub(super) timeout_resolution: usize,
 }
@@ -176,11 +281,69 @@ impl From<&ConnTrackConfig> for TrackerConfig {
     fn from(config: &ConnTrackConfig) -> Self {
         TrackerConfig {
             max_connections: config.max_connections,
+            eviction: config.eviction,
             max_out_of_order: config.max_out_of_order,
             udp_inactivity_timeout: config.udp_inactivity_timeout,
             tcp_inactivity_timeout: config.tcp_inactivity_timeout,
+            sctp_inactivity_timeout: config.sctp_inactivity_timeout,
             tcp_establish_timeout: config.tcp_establish_timeout,
+            active_timeout: config.active_timeout,
             timeout_resolution: config.timeout_resolution,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn core_eviction_candidate() {
+        let entries = [
+            (1, ConnState::Tracking),
+            (2, ConnState::Parsing),
+            (3, ConnState::Dropped),
+            (4, ConnState::Probing),
+        ];
+        let iter = || entries.iter().map(|(key, state)| (key, *state));
+
+        assert_eq!(eviction_candidate(EvictionPolicy::None, iter()), None);
+        assert_eq!(
+            eviction_candidate(EvictionPolicy::OldestIdle, iter()),
+            Some(&1)
+        );
+        assert_eq!(
+            eviction_candidate(EvictionPolicy::ProbingFirst, iter()),
+            Some(&3)
+        );
+
+        // Falls back to the oldest connection if all connections have matched
+        let iter = || entries[..2].iter().map(|(key, state)| (key, *state));
+        assert_eq!(
+            eviction_candidate(EvictionPolicy::ProbingFirst, iter()),
+            Some(&1)
+        );
+
+        // Only scans the oldest connections
+        let mut entries = vec![(0, ConnState::Tracking); EVICTION_SCAN_LIMIT];
+        entries.push((1, ConnState::Probing));
+        let iter = || entries.iter().map(|(key, state)| (key, *state));
+        assert_eq!(
+            eviction_candidate(EvictionPolicy::ProbingFirst, iter()),
+            Some(&0)
+        );
+        entries[EVICTION_SCAN_LIMIT - 1] = (2, ConnState::Probing);
+        let iter = || entries.iter().map(|(key, state)| (key, *state));
+        assert_eq!(
+            eviction_candidate(EvictionPolicy::ProbingFirst, iter()),
+            Some(&2)
+        );
+
+        let iter = std::iter::empty::<(&usize, ConnState)>;
+        assert_eq!(eviction_candidate(EvictionPolicy::OldestIdle, iter()), None);
+        assert_eq!(
+            eviction_candidate(EvictionPolicy::ProbingFirst, iter()),
+            None
+        );
+    }
+}
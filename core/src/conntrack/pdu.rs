@@ -2,7 +2,8 @@ use crate::memory::mbuf::Mbuf;
 use crate::protocols::packet::ethernet::Ethernet;
 use crate::protocols::packet::ipv4::Ipv4;
 use crate::protocols::packet::ipv6::Ipv6;
-use crate::protocols::packet::tcp::{Tcp, TCP_PROTOCOL};
+use crate::protocols::packet::sctp::{Sctp, SctpDataChunk, SCTP_PROTOCOL};
+use crate::protocols::packet::tcp::{Tcp, TcpOptions, TCP_PROTOCOL};
 use crate::protocols::packet::udp::{Udp, UDP_PROTOCOL};
 use crate::protocols::packet::Packet;
 
@@ -51,10 +52,69 @@ impl L4Pdu {
         self.ctxt.seq_no
     }
 
+    #[inline]
+    pub(crate) fn ack_no(&self) -> u32 {
+        self.ctxt.ack_no
+    }
+
+    #[inline]
+    pub(crate) fn window(&self) -> u16 {
+        self.ctxt.window
+    }
+
     #[inline]
     pub(crate) fn flags(&self) -> u8 {
         self.ctxt.flags
     }
+
+    #[inline]
+    pub(crate) fn timestamps(&self) -> Option<(u32, u32)> {
+        self.ctxt.timestamps
+    }
+
+    /// Splits an SCTP packet that bundles several DATA chunks into one PDU per DATA chunk, in
+    /// packet order. The PDUs share the packet buffer. Other packets are returned unchanged.
+    pub(crate) fn split_sctp_data(self) -> Vec<L4Pdu> {
+        let sctp = match self.ctxt.sctp {
+            Some(sctp) if sctp.nb_data > 1 => sctp,
+            _ => return vec![self],
+        };
+        let data_chunks = sctp_data_chunks(&self.mbuf);
+        if data_chunks.is_empty() {
+            return vec![self];
+        }
+        data_chunks
+            .iter()
+            .enumerate()
+            .map(|(i, data)| {
+                let ctxt = L4Context {
+                    offset: data.offset,
+                    length: data.length,
+                    sctp: Some(SctpContext {
+                        trailing: i > 0,
+                        ..sctp.with_data(data)
+                    }),
+                    ..self.ctxt
+                };
+                L4Pdu::new(self.mbuf.share(), ctxt, self.dir)
+            })
+            .collect()
+    }
+
+    /// Parses the TCP options of the segment. Returns `None` if the segment is not TCP.
+    pub(crate) fn tcp_options(&self) -> Option<TcpOptions> {
+        if self.ctxt.proto != TCP_PROTOCOL {
+            return None;
+        }
+        let eth = self.mbuf.parse_to::<Ethernet>().ok()?;
+        if let Ok(ipv4) = eth.parse_to::<Ipv4>() {
+            ipv4.parse_to::<Tcp>().ok().map(|tcp| tcp.options())
+        } else if let Ok(ipv6) = eth.parse_to::<Ipv6>() {
+            ipv6.parse_to::<Tcp>().ok().map(|tcp| tcp.options())
+        } else {
+            None
+        }
+    }
 }
 
 /// Parsed transport-layer context from the packet used for connection tracking.
@@ -74,11 +134,26 @@ pub struct L4Context {
     pub(crate) length: usize,
     /// Raw sequence number of segment.
     pub(crate) seq_no: u32,
+    /// Raw acknowledgment number of segment.
+    pub(crate) ack_no: u32,
+    /// Raw (unscaled) TCP receive window.
+    pub(crate) window: u16,
     /// TCP flags.
     pub(crate) flags: u8,
+    /// TSval and TSecr of the TCP timestamps option. `None` if the option is absent.
+    pub(crate) timestamps: Option<(u32, u32)>,
+    /// SCTP chunk summary. `None` for TCP and UDP.
+    pub(crate) sctp: Option<SctpContext>,
 }
 
 impl L4Context {
+    /// Returns the number of payload bytes in the packet. For SCTP, this is the user data of all
+    /// DATA chunks in the packet, even if the context describes a single chunk of a split packet.
+    #[inline]
+    pub(crate) fn packet_len(&self) -> usize {
+        self.sctp.map_or(self.length, |sctp| sctp.data_len)
+    }
+
     pub(crate) fn new(mbuf: &Mbuf, idx: usize) -> Result<Self> {
         if let Ok(eth) = mbuf.parse_to::<Ethernet>() {
             if let Ok(ipv4) = eth.parse_to::<Ipv4>() {
@@ -94,7 +169,11 @@ impl L4Context {
                             offset: tcp.next_header_offset(),
                             length: payload_size,
                             seq_no: tcp.seq_no(),
+                            ack_no: tcp.ack_no(),
+                            window: tcp.window(),
                             flags: tcp.flags(),
+                            timestamps: tcp.timestamps(),
+                            sctp: None,
                         })
                     } else {
                         bail!("Malformed Packet");
@@ -111,13 +190,33 @@ impl L4Context {
                             offset: udp.next_header_offset(),
                             length: payload_size,
                             seq_no: 0,
+                            ack_no: 0,
+                            window: 0,
                             flags: 0,
+                            timestamps: None,
+                            sctp: None,
                         })
                     } else {
                         bail!("Malformed Packet");
                     }
+                } else if let Ok(sctp) = ipv4.parse_to::<Sctp>() {
+                    let (sctp_ctxt, offset, length) = SctpContext::new(&sctp);
+                    Ok(L4Context {
+                        src: SocketAddr::new(IpAddr::V4(ipv4.src_addr()), sctp.src_port()),
+                        dst: SocketAddr::new(IpAddr::V4(ipv4.dst_addr()), sctp.dst_port()),
+                        proto: SCTP_PROTOCOL,
+                        idx,
+                        offset,
+                        length,
+                        seq_no: 0,
+                        ack_no: 0,
+                        window: 0,
+                        flags: 0,
+                        timestamps: None,
+                        sctp: Some(sctp_ctxt),
+                    })
                 } else {
-                    bail!("Not TCP or UDP");
+                    bail!("Not TCP, UDP, or SCTP");
                 }
             } else if let Ok(ipv6) = eth.parse_to::<Ipv6>() {
                 if let Ok(tcp) = ipv6.parse_to::<Tcp>() {
@@ -132,7 +231,11 @@ impl L4Context {
                             offset: tcp.next_header_offset(),
                             length: payload_size,
                             seq_no: tcp.seq_no(),
+                            ack_no: tcp.ack_no(),
+                            window: tcp.window(),
                             flags: tcp.flags(),
+                            timestamps: tcp.timestamps(),
+                            sctp: None,
                         })
                     } else {
                         bail!("Malformed Packet");
@@ -149,13 +252,33 @@ impl L4Context {
                             offset: udp.next_header_offset(),
                             length: payload_size,
                             seq_no: 0,
+                            ack_no: 0,
+                            window: 0,
                             flags: 0,
+                            timestamps: None,
+                            sctp: None,
                         })
                     } else {
                         bail!("Malformed Packet");
                     }
+                } else if let Ok(sctp) = ipv6.parse_to::<Sctp>() {
+                    let (sctp_ctxt, offset, length) = SctpContext::new(&sctp);
+                    Ok(L4Context {
+                        src: SocketAddr::new(IpAddr::V6(ipv6.src_addr()), sctp.src_port()),
+                        dst: SocketAddr::new(IpAddr::V6(ipv6.dst_addr()), sctp.dst_port()),
+                        proto: SCTP_PROTOCOL,
+                        idx,
+                        offset,
+                        length,
+                        seq_no: 0,
+                        ack_no: 0,
+                        window: 0,
+                        flags: 0,
+                        timestamps: None,
+                        sctp: Some(sctp_ctxt),
+                    })
                 } else {
-                    bail!("Not TCP or UDP");
+                    bail!("Not TCP, UDP, or SCTP");
                 }
             } else {
                 bail!("Not IP");
@@ -165,3 +288,99 @@ impl L4Context {
         }
     }
 }
+
+/// Returns the DATA chunks of the SCTP packet in `mbuf`.
+fn sctp_data_chunks(mbuf: &Mbuf) -> Vec<SctpDataChunk> {
+    let eth = match mbuf.parse_to::<Ethernet>() {
+        Ok(eth) => eth,
+        Err(_) => return vec![],
+    };
+    if let Ok(ipv4) = eth.parse_to::<Ipv4>() {
+        if let Ok(sctp) = ipv4.parse_to::<Sctp>() {
+            return sctp.data_chunks().collect();
+        }
+    } else if let Ok(ipv6) = eth.parse_to::<Ipv6>() {
+        if let Ok(sctp) = ipv6.parse_to::<Sctp>() {
+            return sctp.data_chunks().collect();
+        }
+    }
+    vec![]
+}
+
+/// Summary of the chunks in an SCTP packet used for association tracking.
+///
+/// DATA chunk fields describe the first DATA chunk in the packet, whose user data is referenced by
+/// the `offset` and `length` of the enclosing `L4Context`. Packets that bundle several DATA chunks
+/// are split into one PDU per chunk before reassembly.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct SctpContext {
+    /// Verification tag from the common header.
+    pub(crate) verification_tag: u32,
+    /// Bitmask of chunk types present in the packet. Bit `n` is set if a chunk of type `n < 32`
+    /// is present.
+    pub(crate) chunks: u32,
+    /// Initiate Tag of an INIT or INIT-ACK chunk.
+    pub(crate) initiate_tag: Option<u32>,
+    /// Transmission sequence number of the first DATA chunk.
+    pub(crate) tsn: u32,
+    /// Stream identifier of the first DATA chunk.
+    pub(crate) stream_id: u16,
+    /// Stream sequence number of the first DATA chunk.
+    pub(crate) stream_seq: u16,
+    /// `true` if the first DATA chunk is unordered.
+    pub(crate) unordered: bool,
+    /// Number of DATA chunks in the packet.
+    pub(crate) nb_data: u16,
+    /// Total length of the user data of all DATA chunks in the packet.
+    pub(crate) data_len: usize,
+    /// `true` for the PDUs of a split packet after the first. The packet is accounted for with its
+    /// first DATA chunk.
+    pub(crate) trailing: bool,
+}
+
+impl SctpContext {
+    /// Summarizes the chunks in `sctp`. Returns the context along with the offset and length of the
+    /// first DATA chunk's user data, or an empty payload after the common header if the packet has
+    /// no user data.
+    fn new(sctp: &Sctp) -> (Self, usize, usize) {
+        let mut ctxt = SctpContext {
+            verification_tag: sctp.verification_tag(),
+            ..Default::default()
+        };
+        let mut payload = (sctp.next_header_offset(), 0);
+        for chunk in sctp.chunks() {
+            if chunk.chunk_type < 32 {
+                ctxt.chunks |= 1 << chunk.chunk_type;
+            }
+            if ctxt.initiate_tag.is_none() {
+                ctxt.initiate_tag = chunk.initiate_tag();
+            }
+            if let Some(data) = chunk.as_data() {
+                if ctxt.nb_data == 0 {
+                    ctxt = ctxt.with_data(&data);
+                    payload = (data.offset, data.length);
+                }
+                ctxt.nb_data = ctxt.nb_data.saturating_add(1);
+                ctxt.data_len += data.length;
+            }
+        }
+        (ctxt, payload.0, payload.1)
+    }
+
+    /// Returns the context of the packet with DATA chunk fields describing `data`.
+    fn with_data(self, data: &SctpDataChunk) -> Self {
+        SctpContext {
+            tsn: data.tsn,
+            stream_id: data.stream_id,
+            stream_seq: data.stream_seq,
+            unordered: data.unordered,
+            ..self
+        }
+    }
+
+    /// Returns `true` if the packet contains a chunk of type `chunk_type`.
+    #[inline]
+    pub(crate) fn has_chunk(&self, chunk_type: u8) -> bool {
+        chunk_type < 32 && self.chunks & (1 << chunk_type) != 0
+    }
+}
@@ -0,0 +1,187 @@
+//! Connection tracking counters.
+
+use std::fmt;
+use std::ops::AddAssign;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use serde::Serialize;
+
+/// Live connection tracking counters for a single core.
+///
+/// Counters are updated by the owning core and may be read concurrently by the monitor.
+#[derive(Debug, Default)]
+pub(crate) struct ConnTrackStats {
+    created: AtomicU64,
+    expired: AtomicU64,
+    terminated: AtomicU64,
+    rejected_full: AtomicU64,
+    evicted: AtomicU64,
+    ooo_overflow: AtomicU64,
+    parser_dropped: AtomicU64,
+    table_size: AtomicU64,
+}
+
+impl ConnTrackStats {
+    #[inline]
+    pub(super) fn incr_created(&self) {
+        self.created.fetch_add(1, Ordering::Relaxed);
+    }
+
+    #[inline]
+    pub(super) fn add_expired(&self, nb_expired: usize) {
+        self.expired.fetch_add(nb_expired as u64, Ordering::Relaxed);
+    }
+
+    #[inline]
+    pub(super) fn incr_terminated(&self) {
+        self.terminated.fetch_add(1, Ordering::Relaxed);
+    }
+
+    #[inline]
+    pub(super) fn incr_rejected_full(&self) {
+        self.rejected_full.fetch_add(1, Ordering::Relaxed);
+    }
+
+    #[inline]
+    pub(super) fn incr_evicted(&self) {
+        self.evicted.fetch_add(1, Ordering::Relaxed);
+    }
+
+    #[inline]
+    pub(super) fn incr_ooo_overflow(&self) {
+        self.ooo_overflow.fetch_add(1, Ordering::Relaxed);
+    }
+
+    #[inline]
+    pub(super) fn incr_parser_dropped(&self) {
+        self.parser_dropped.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Records a connection that was removed from the table before it terminated or expired.
+    /// Connections that are removed because they no longer need to be tracked after matching
+    /// (e.g., once a TLS handshake has been delivered) are not counted as dropped.
+    #[inline]
+    pub(super) fn record_removal(&self, ooo_overflow: bool, matched: bool) {
+        if ooo_overflow {
+            self.incr_ooo_overflow();
+        } else if !matched {
+            self.incr_parser_dropped();
+        }
+    }
+
+    #[inline]
+    pub(super) fn set_table_size(&self, size: usize) {
+        self.table_size.store(size as u64, Ordering::Relaxed);
+    }
+
+    /// Returns a point-in-time copy of the counters.
+    pub(crate) fn snapshot(&self) -> ConnTrackCounters {
+        ConnTrackCounters {
+            created: self.created.load(Ordering::Relaxed),
+            expired: self.expired.load(Ordering::Relaxed),
+            terminated: self.terminated.load(Ordering::Relaxed),
+            rejected_full: self.rejected_full.load(Ordering::Relaxed),
+            evicted: self.evicted.load(Ordering::Relaxed),
+            ooo_overflow: self.ooo_overflow.load(Ordering::Relaxed),
+            parser_dropped: self.parser_dropped.load(Ordering::Relaxed),
+            table_size: self.table_size.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Snapshot of connection tracking counters.
+#[derive(Debug, Default, Clone, Copy, Serialize)]
+pub(crate) struct ConnTrackCounters {
+    /// Connections inserted into the table.
+    pub(crate) created: u64,
+    /// Connections removed after an inactivity timeout.
+    pub(crate) expired: u64,
+    /// Connections removed after natural termination (e.g., FIN/RST).
+    pub(crate) terminated: u64,
+    /// New connections that were not tracked because the table was full.
+    pub(crate) rejected_full: u64,
+    /// Connections evicted to make room for new connections.
+    pub(crate) evicted: u64,
+    /// Connections removed due to out-of-order buffer overflow.
+    pub(crate) ooo_overflow: u64,
+    /// Connections discarded because they did not match the filter or could not be parsed.
+    pub(crate) parser_dropped: u64,
+    /// Number of connections currently in the table.
+    pub(crate) table_size: u64,
+}
+
+impl AddAssign for ConnTrackCounters {
+    fn add_assign(&mut self, other: Self) {
+        self.created += other.created;
+        self.expired += other.expired;
+        self.terminated += other.terminated;
+        self.rejected_full += other.rejected_full;
+        self.evicted += other.evicted;
+        self.ooo_overflow += other.ooo_overflow;
+        self.parser_dropped += other.parser_dropped;
+        self.table_size += other.table_size;
+    }
+}
+
+impl fmt::Display for ConnTrackCounters {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "table: {}, created: {}, expired: {}, terminated: {}, rejected (full): {}, evicted: {}, ooo overflow: {}, dropped: {}",
+            self.table_size,
+            self.created,
+            self.expired,
+            self.terminated,
+            self.rejected_full,
+            self.evicted,
+            self.ooo_overflow,
+            self.parser_dropped,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn core_conntrack_stats() {
+        let stats = ConnTrackStats::default();
+        stats.incr_created();
+        stats.incr_created();
+        stats.add_expired(3);
+        stats.incr_terminated();
+        stats.incr_rejected_full();
+        stats.incr_evicted();
+        stats.set_table_size(5);
+
+        // Removed after the filter did not match
+        stats.record_removal(false, false);
+        // Removed after matching (e.g., TLS handshake delivered)
+        stats.record_removal(false, true);
+        // Out-of-order buffer overflow, whether or not the connection matched
+        stats.record_removal(true, false);
+        stats.record_removal(true, true);
+
+        let counters = stats.snapshot();
+        assert_eq!(counters.created, 2);
+        assert_eq!(counters.expired, 3);
+        assert_eq!(counters.terminated, 1);
+        assert_eq!(counters.rejected_full, 1);
+        assert_eq!(counters.evicted, 1);
+        assert_eq!(counters.ooo_overflow, 2);
+        assert_eq!(counters.parser_dropped, 1);
+        assert_eq!(counters.table_size, 5);
+
+        let mut total = ConnTrackCounters::default();
+        total += counters;
+        total += counters;
+        assert_eq!(total.created, 4);
+        assert_eq!(total.parser_dropped, 2);
+        assert_eq!(total.table_size, 10);
+        assert_eq!(
+            total.to_string(),
+            "table: 10, created: 4, expired: 6, terminated: 2, rejected (full): 2, evicted: 2, ooo overflow: 4, dropped: 2"
+        );
+    }
+}
@@ -1,40 +1,63 @@
+use crate::conntrack::clock;
+use crate::conntrack::conn::conn_info::ConnState;
 use crate::conntrack::{Conn, ConnId};
 use crate::subscription::{Subscription, Trackable};
 
-use crossbeam_channel::{tick, Receiver};
 use hashlink::linked_hash_map::LinkedHashMap;
 use hashlink::linked_hash_map::RawEntryMut;
+use std::cmp;
 use std::collections::VecDeque;
-use std::time::{Duration, Instant};
+use std::time::Instant;
 
-/// Tracks inactive connection expiration.
+/// Tracks inactive connection expiration and interim records of active connections.
 pub(super) struct TimerWheel {
     /// Period to check for inactive connections (in milliseconds).
     period: usize,
+    /// Interval between interim records of active connections (in milliseconds), if enabled.
+    active_timeout: Option<usize>,
     /// Start time of the `TimerWheel`.
     start_ts: Instant,
-    /// Timeout ticker, fires every `period` milliseconds.
-    ticker: Receiver<Instant>,
     /// Index of the next bucket to expire.
     next_bucket: usize,
+    /// Generation of the next interim record timer.
+    next_gen: u64,
     /// List of timers.
-    timers: Vec<VecDeque<ConnId>>,
+    timers: Vec<VecDeque<Timer>>,
+}
+
+/// A connection timer.
+#[derive(Debug, Clone)]
+enum Timer {
+    /// Expires the connection if it has been inactive for its inactivity window.
+    Inactivity(ConnId),
+    /// Delivers an interim record of the connection with the given timer generation at the given
+    /// time (in milliseconds since the start of the `TimerWheel`). Dropped if the connection ID
+    /// now belongs to a different connection.
+    Active(ConnId, u64, usize),
 }
 
 impl TimerWheel {
-    /// Creates a new `TimerWheel` with a maximum timeout of `max_timeout` and a timeout check
-    /// period of `timeout_resolution`.
-    pub(super) fn new(max_timeout: usize, timeout_resolution: usize) -> Self {
+    /// Creates a new `TimerWheel` with a maximum timeout of `max_timeout`, a timeout check period
+    /// of `timeout_resolution`, and an optional active timeout.
+    pub(super) fn new(
+        max_timeout: usize,
+        timeout_resolution: usize,
+        active_timeout: Option<usize>,
+    ) -> Self {
         if timeout_resolution > max_timeout {
             panic!("Timeout check period must be smaller than maximum inactivity timeout")
         }
-        let start_ts = Instant::now();
-        let ticker = tick(Duration::from_millis(timeout_resolution as u64));
+        if active_timeout.is_some_and(|active_timeout| timeout_resolution > active_timeout) {
+            panic!("Timeout check period must be smaller than active timeout")
+        }
+        let max_timeout = cmp::max(max_timeout, active_timeout.unwrap_or_default());
+        let start_ts = clock::now();
         TimerWheel {
             period: timeout_resolution,
+            active_timeout,
             start_ts,
-            ticker,
             next_bucket: 0,
+            next_gen: 0,
             timers: vec![VecDeque::new(); max_timeout / timeout_resolution],
         }
     }
@@ -47,21 +70,47 @@ impl TimerWheel {
         last_seen_ts: Instant,
         inactivity_window: usize,
     ) {
-        let current_time = (last_seen_ts - self.start_ts).as_millis() as usize;
+        let current_time = elapsed(self.start_ts, last_seen_ts);
         let timer_index = ((current_time + inactivity_window) / self.period) % self.timers.len();
         log::debug!("Inserting into index: {}, {:?}", timer_index, current_time);
-        self.timers[timer_index].push_back(conn_id.to_owned());
+        self.timers[timer_index].push_back(Timer::Inactivity(conn_id.to_owned()));
+    }
+
+    /// Schedules interim records of connection `conn` once it is tracked, if enabled and delivered
+    /// by its subscription.
+    #[inline]
+    pub(super) fn insert_active<T: Trackable>(&mut self, conn_id: &ConnId, conn: &mut Conn<T>) {
+        let Some(active_timeout) = self.active_timeout else {
+            return;
+        };
+        if !T::INTERIM_RECORDS || conn.report_gen.is_some() || conn.state() != ConnState::Tracking {
+            return;
+        }
+        let generation = self.next_gen;
+        self.next_gen += 1;
+        conn.report_gen = Some(generation);
+        let report_time = elapsed(self.start_ts, conn.last_seen_ts) + active_timeout;
+        let timer_index = (report_time / self.period) % self.timers.len();
+        self.timers[timer_index].push_back(Timer::Active(
+            conn_id.to_owned(),
+            generation,
+            report_time,
+        ));
     }
 
-    /// Checks for and remove inactive connections.
+    /// Checks for and remove inactive connections, and delivers interim records of active
+    /// connections, once every `period` milliseconds of packet clock time.
+    ///
+    /// Returns the number of connections removed.
     #[inline]
     pub(super) fn check_inactive<T: Trackable>(
         &mut self,
         table: &mut LinkedHashMap<ConnId, Conn<T>>,
         subscription: &Subscription<T::Subscribed>,
-    ) {
+    ) -> usize {
         let table_len = table.len();
-        if let Ok(now) = self.ticker.try_recv() {
+        let now = clock::now();
+        if elapsed(self.start_ts, now) / self.period > self.next_bucket {
             let nb_removed = self.remove_inactive(now, table, subscription);
             log::debug!(
                 "expired: {} ({})",
@@ -69,11 +118,13 @@ impl TimerWheel {
                 nb_removed as f64 / table_len as f64
             );
             log::debug!("new table size: {}", table.len());
+            return nb_removed;
         }
+        0
     }
 
     /// Removes connections that have been inactive for at least their inactivity window time
-    /// period.
+    /// period, and delivers interim records of the remaining connections that are due.
     ///
     /// Returns the number of connections removed.
     #[inline]
@@ -84,9 +135,11 @@ impl TimerWheel {
         subscription: &Subscription<T::Subscribed>,
     ) -> usize {
         let period = self.period;
+        let start_ts = self.start_ts;
         let nb_buckets = self.timers.len();
-        let mut not_expired: Vec<(usize, ConnId)> = vec![];
-        let check_time = (now - self.start_ts).as_millis() as usize / period * period;
+        let active_timeout = self.active_timeout.unwrap_or(usize::MAX);
+        let mut not_expired: Vec<(usize, Timer)> = vec![];
+        let check_time = elapsed(start_ts, now) / period * period;
 
         let mut cnt_exp = 0;
         let last_expire_bucket = check_time / period;
@@ -105,29 +158,197 @@ impl TimerWheel {
             );
             let list = &mut self.timers[expire_bucket % nb_buckets];
 
-            for conn_id in list.drain(..) {
-                if let RawEntryMut::Occupied(mut occupied) =
-                    table.raw_entry_mut().from_key(&conn_id)
-                {
-                    let conn = occupied.get_mut();
-                    let last_seen_time = (conn.last_seen_ts - self.start_ts).as_millis() as usize;
-                    log::debug!("Last seen time: {}", last_seen_time);
-                    let expire_time = last_seen_time + conn.inactivity_window;
-                    if expire_time < check_time {
-                        cnt_exp += 1;
-                        conn.terminate(subscription);
-                        occupied.remove();
-                    } else {
-                        let timer_index = (expire_time / period) % nb_buckets;
-                        not_expired.push((timer_index, conn_id));
+            for timer in list.drain(..) {
+                match timer {
+                    Timer::Inactivity(conn_id) => {
+                        if let RawEntryMut::Occupied(mut occupied) =
+                            table.raw_entry_mut().from_key(&conn_id)
+                        {
+                            let conn = occupied.get_mut();
+                            let last_seen_time = elapsed(start_ts, conn.last_seen_ts);
+                            log::debug!("Last seen time: {}", last_seen_time);
+                            let expire_time = last_seen_time + conn.inactivity_window;
+                            if expire_time < check_time {
+                                cnt_exp += 1;
+                                conn.terminate(subscription);
+                                occupied.remove();
+                            } else {
+                                let timer_index = (expire_time / period) % nb_buckets;
+                                not_expired.push((timer_index, Timer::Inactivity(conn_id)));
+                            }
+                        }
+                    }
+                    Timer::Active(conn_id, generation, mut report_time) => {
+                        if let Some(conn) = table
+                            .get_mut(&conn_id)
+                            .filter(|conn| conn.report_gen == Some(generation))
+                        {
+                            if report_time < check_time {
+                                conn.report(subscription);
+                                // Skip the intervals missed since the last check
+                                let nb_missed = (check_time - report_time) / active_timeout;
+                                report_time += (nb_missed + 1) * active_timeout;
+                            }
+                            let timer_index = (report_time / period) % nb_buckets;
+                            not_expired.push((
+                                timer_index,
+                                Timer::Active(conn_id, generation, report_time),
+                            ));
+                        }
                     }
                 }
             }
-            for (timer_index, conn_id) in not_expired.drain(..) {
-                self.timers[timer_index].push_back(conn_id);
+            for (timer_index, timer) in not_expired.drain(..) {
+                self.timers[timer_index].push_back(timer);
             }
         }
         self.next_bucket = last_expire_bucket;
         cnt_exp
     }
 }
+
+/// Returns the time elapsed between `start_ts` and `ts` (in milliseconds).
+#[inline]
+fn elapsed(start_ts: Instant, ts: Instant) -> usize {
+    ts.saturating_duration_since(start_ts).as_millis() as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conntrack::conn_id::FiveTuple;
+    use crate::conntrack::pdu::{L4Context, L4Pdu};
+    use crate::conntrack::ConnTracker;
+    use crate::filter::{FilterFactory, FilterResult};
+    use crate::memory::mbuf::Mbuf;
+    use crate::protocols::packet::udp::UDP_PROTOCOL;
+    use crate::protocols::stream::{ConnParser, Session};
+    use crate::subscription::{CallbackStats, Level, Subscribable};
+
+    use std::cell::RefCell;
+    use std::sync::Arc;
+
+    struct Interim;
+
+    struct TrackedInterim;
+
+    impl Subscribable for Interim {
+        type Tracked = TrackedInterim;
+
+        fn level() -> Level {
+            Level::Connection
+        }
+
+        fn parsers() -> Vec<ConnParser> {
+            vec![]
+        }
+
+        fn process_packet(
+            _mbuf: Mbuf,
+            _subscription: &Subscription<Self>,
+            _conn_tracker: &mut ConnTracker<Self::Tracked>,
+        ) {
+            unreachable!()
+        }
+    }
+
+    impl Trackable for TrackedInterim {
+        type Subscribed = Interim;
+        const INTERIM_RECORDS: bool = true;
+
+        fn new(_five_tuple: FiveTuple) -> Self {
+            TrackedInterim
+        }
+
+        fn pre_match(&mut self, _pdu: L4Pdu, _session_id: Option<usize>) {}
+
+        fn on_match(&mut self, _session: Session, _subscription: &Subscription<Self::Subscribed>) {}
+
+        fn post_match(&mut self, _pdu: L4Pdu, _subscription: &Subscription<Self::Subscribed>) {}
+
+        fn on_terminate(&mut self, _subscription: &Subscription<Self::Subscribed>) {}
+
+        fn on_active_timeout(&mut self, subscription: &Subscription<Self::Subscribed>) {
+            subscription.invoke(Interim);
+        }
+    }
+
+    fn tracked_conn(ctxt: L4Context) -> Conn<TrackedInterim> {
+        let mut conn = Conn::new_udp(ctxt, 5000).unwrap();
+        conn.info.state = ConnState::Tracking;
+        conn
+    }
+
+    #[test]
+    fn core_timerwheel_reused_conn_id() {
+        // Trace times (in nanoseconds) relative to the epoch shared with other tests
+        const MS: u64 = 1_000_000;
+        let start = clock::epoch_ts(0);
+        clock::set_trace_time(start);
+
+        let reports = RefCell::new(vec![]);
+        let factory = FilterFactory::new(
+            "",
+            |_| FilterResult::MatchTerminal(0),
+            |_| FilterResult::MatchTerminal(0),
+            |_, _| true,
+        );
+        let subscription = Subscription::new(
+            factory,
+            |_: Interim| reports.borrow_mut().push(clock::now()),
+            Arc::new(CallbackStats::new::<Interim>()),
+        );
+        let mut wheel = TimerWheel::new(10_000, 100, Some(1000));
+        let mut table = LinkedHashMap::new();
+
+        let ctxt = L4Context {
+            src: "10.0.0.1:1234".parse().unwrap(),
+            dst: "10.0.0.2:53".parse().unwrap(),
+            proto: UDP_PROTOCOL,
+            idx: 0,
+            offset: 0,
+            length: 0,
+            seq_no: 0,
+            ack_no: 0,
+            window: 0,
+            flags: 0,
+            timestamps: None,
+            sctp: None,
+        };
+        let conn_id = ConnId::new(ctxt.src, ctxt.dst, ctxt.proto);
+        let mut conn = tracked_conn(ctxt);
+        wheel.insert(&conn_id, conn.last_seen_ts, conn.inactivity_window);
+        wheel.insert_active(&conn_id, &mut conn);
+        table.insert(conn_id.clone(), conn);
+
+        // The connection terminates and its five-tuple is reused by a new connection
+        clock::set_trace_time(start + 500 * MS);
+        wheel.check_inactive(&mut table, &subscription);
+        table.remove(&conn_id);
+        let mut conn = tracked_conn(ctxt);
+        wheel.insert(&conn_id, conn.last_seen_ts, conn.inactivity_window);
+        wheel.insert_active(&conn_id, &mut conn);
+        wheel.insert_active(&conn_id, &mut conn);
+        table.insert(conn_id.clone(), conn);
+        let reused = clock::now();
+
+        for ms in (600..=3600).step_by(100) {
+            clock::set_trace_time(start + ms * MS);
+            wheel.check_inactive(&mut table, &subscription);
+        }
+        // Exactly one interim record per period of the new connection
+        let reports: Vec<_> = reports
+            .borrow()
+            .iter()
+            .map(|ts| (*ts - reused).as_millis())
+            .collect();
+        assert_eq!(reports, [1100, 2100, 3100]);
+        let nb_active = wheel
+            .timers
+            .iter()
+            .flatten()
+            .filter(|timer| matches!(timer, Timer::Active(..)))
+            .count();
+        assert_eq!(nb_active, 1);
+    }
+}
@@ -16,10 +16,15 @@ lazy_static! {
     pub(crate) static ref LAYERS: Graph::<ProtocolName, ()> = {
         let mut g = Graph::<ProtocolName, ()>::new();
         let ethernet = g.add_node(protocol!("ethernet"));
+        let arp      = g.add_node(protocol!("arp"));
+        let lldp     = g.add_node(protocol!("lldp"));
+        let cdp      = g.add_node(protocol!("cdp"));
+        let eapol    = g.add_node(protocol!("eapol"));
         let ipv4     = g.add_node(protocol!("ipv4"));
         let ipv6     = g.add_node(protocol!("ipv6"));
         let tcp      = g.add_node(protocol!("tcp"));
         let udp      = g.add_node(protocol!("udp"));
+        let sctp     = g.add_node(protocol!("sctp"));
         let tls      = g.add_node(protocol!("tls"));
         let http     = g.add_node(protocol!("http"));
         let ssh      = g.add_node(protocol!("ssh"));
@@ -27,10 +32,15 @@ lazy_static! {
         let quic     = g.add_node(protocol!("quic"));
         // define valid outer layers for each protocol header
         g.extend_with_edges([
+            (arp, ethernet),
+            (lldp, ethernet),
+            (cdp, ethernet),
+            (eapol, ethernet),
             (ipv4, ethernet),
             (ipv6, ethernet),
             (tcp, ipv4), (tcp, ipv6),
             (udp, ipv4), (udp, ipv6),
+            (sctp, ipv4), (sctp, ipv6),
             (tls, tcp),
             (http, tcp),
             (ssh, tcp),
@@ -120,10 +130,11 @@ impl Predicate {
     }
 
     /// Returns `true` if the predicate's protocol requires connection tracking
-    /// i.e., is an application-layer protocol that runs on top of TCP or UDP.
+    /// i.e., is an application-layer protocol that runs on top of TCP, UDP, or SCTP.
     fn needs_conntrack(&self) -> bool {
         has_path(self.get_protocol(), &protocol!("tcp"))
             || has_path(self.get_protocol(), &protocol!("udp"))
+            || has_path(self.get_protocol(), &protocol!("sctp"))
     }
 
     /// Returns `true` if predicate can be pushed down to hardware port.
@@ -146,6 +157,55 @@ impl fmt::Display for Predicate {
     }
 }
 
+/// A node of the abstract syntax tree of a filter expression
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Node {
+    /// A single predicate
+    Predicate(Predicate),
+    /// Logical OR of conjuncts
+    Disjunct(Vec<Node>),
+    /// Logical AND of predicates and parenthesized disjuncts
+    Conjunct(Vec<Node>),
+}
+
+impl Node {
+    fn pprint(&self) -> String {
+        fn pprint(s: &mut String, node: &Node, prefix: String, last: bool) {
+            let prefix_current = if last { "`- " } else { "|- " };
+            let children = match node {
+                Node::Predicate(predicate) => {
+                    s.push_str(&format!("{}{}{}\n", prefix, prefix_current, predicate));
+                    return;
+                }
+                Node::Disjunct(children) => {
+                    s.push_str(&format!("{}{}or\n", prefix, prefix_current));
+                    children
+                }
+                Node::Conjunct(children) => {
+                    s.push_str(&format!("{}{}and\n", prefix, prefix_current));
+                    children
+                }
+            };
+
+            let prefix_child = if last { "   " } else { "|  " };
+            let prefix = prefix + prefix_child;
+            for (i, child) in children.iter().enumerate() {
+                pprint(s, child, prefix.to_string(), i == children.len() - 1);
+            }
+        }
+
+        let mut s = String::new();
+        pprint(&mut s, self, "".to_string(), true);
+        s
+    }
+}
+
+impl fmt::Display for Node {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        write!(f, "{}", self.pprint())
+    }
+}
+
 /// Name of the protocol used in filter syntax
 /// By convention, this should be the all-lowercase version of the protocol struct identifier
 #[derive(Default, Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -254,6 +314,11 @@ mod tests {
         assert!(!has_path(&protocol!("tls"), &protocol!("ssh")));
         assert!(has_path(&protocol!("quic"), &protocol!("udp")));
         assert!(!has_path(&protocol!("quic"), &protocol!("dns")));
+        assert!(has_path(&protocol!("arp"), &protocol!("ethernet")));
+        assert!(!has_path(&protocol!("arp"), &protocol!("ipv4")));
+        assert!(!has_path(&protocol!("tcp"), &protocol!("lldp")));
+        assert!(has_path(&protocol!("sctp"), &protocol!("ipv6")));
+        assert!(!has_path(&protocol!("sctp"), &protocol!("udp")));
     }
 
     #[test]
@@ -283,6 +348,27 @@ mod tests {
             value: Value::Int(80),
         };
         assert!(tcp_binary.on_packet());
+
+        let arp_binary = Predicate::Binary {
+            protocol: protocol!("arp"),
+            field: field!("op"),
+            op: BinOp::Eq,
+            value: Value::Int(2),
+        };
+        assert!(arp_binary.on_packet());
+
+        let sctp_binary = Predicate::Binary {
+            protocol: protocol!("sctp"),
+            field: field!("port"),
+            op: BinOp::Eq,
+            value: Value::Int(3868),
+        };
+        assert!(sctp_binary.on_packet());
+
+        let eapol_unary = Predicate::Unary {
+            protocol: protocol!("eapol"),
+        };
+        assert!(eapol_unary.on_packet());
     }
 
     #[test]
@@ -0,0 +1,274 @@
+//! Interpreted evaluation of packet-level predicates.
+//!
+//! Filters are normally compiled into sub-filters by the `filter` macro. To explain a filter
+//! without compiling it, packet-level predicates can instead be evaluated on raw Ethernet frames,
+//! following the semantics of the generated packet filter. Only fields declared with a header
+//! location in the [field registry](super::fields) are supported.
+
+use super::ast::*;
+use super::fields::{self, Read};
+
+const ETHERTYPE_IPV4: u16 = 0x0800;
+const ETHERTYPE_IPV6: u16 = 0x86dd;
+const ETHERTYPE_ARP: u16 = 0x0806;
+const ETHERTYPE_LLDP: u16 = 0x88cc;
+const ETHERTYPE_EAPOL: u16 = 0x888e;
+const ETHERTYPE_802_1Q: u16 = 0x8100;
+
+const TCP_PROTOCOL: u8 = 6;
+const UDP_PROTOCOL: u8 = 17;
+const SCTP_PROTOCOL: u8 = 132;
+
+/// Returns the fields read for `protocol.field`. Combined fields read both the source and the
+/// destination field.
+fn reads(protocol: &ProtocolName, field: &FieldName) -> Vec<Read> {
+    let names = match field.name() {
+        "addr" => vec!["src_addr", "dst_addr"],
+        "port" => vec!["src_port", "dst_port"],
+        name => vec![name],
+    };
+    names
+        .iter()
+        .filter_map(|name| fields::field(protocol.name(), name)?.header)
+        .collect()
+}
+
+/// Returns the result of comparing field value `lhs` to `value` with `op`, or `None` if the
+/// comparison is not supported.
+fn compare(op: BinOp, value: &Value, lhs: u128) -> Option<bool> {
+    match value {
+        Value::Int(val) => {
+            let val = *val as u128;
+            match op {
+                BinOp::Eq => Some(lhs == val),
+                BinOp::Ne => Some(lhs != val),
+                BinOp::Ge => Some(lhs >= val),
+                BinOp::Le => Some(lhs <= val),
+                BinOp::Gt => Some(lhs > val),
+                BinOp::Lt => Some(lhs < val),
+                _ => None,
+            }
+        }
+        Value::IntRange { from, to } => match op {
+            BinOp::In => Some(lhs >= *from as u128 && lhs <= *to as u128),
+            _ => None,
+        },
+        Value::Ipv4(net) => {
+            let netmask = u32::from(net.netmask()) as u128;
+            let addr = u32::from(net.addr()) as u128 & netmask;
+            match op {
+                BinOp::Eq | BinOp::In => Some(lhs & netmask == addr),
+                BinOp::Ne => Some(lhs & netmask != addr),
+                _ => None,
+            }
+        }
+        Value::Ipv6(net) => {
+            let netmask = u128::from(net.netmask());
+            let addr = u128::from(net.addr()) & netmask;
+            match op {
+                BinOp::Eq | BinOp::In => Some(lhs & netmask == addr),
+                BinOp::Ne => Some(lhs & netmask != addr),
+                _ => None,
+            }
+        }
+        Value::Text(_) => None,
+    }
+}
+
+/// Returns `true` if `predicate` can be evaluated on raw frames.
+pub(super) fn is_supported(predicate: &Predicate) -> bool {
+    match predicate {
+        Predicate::Unary { protocol } => matches!(
+            protocol.name(),
+            "ethernet" | "ipv4" | "ipv6" | "tcp" | "udp" | "sctp" | "arp" | "lldp" | "eapol"
+        ),
+        Predicate::Binary {
+            protocol,
+            field,
+            op,
+            value,
+        } => {
+            let expected = if field.is_combined() { 2 } else { 1 };
+            reads(protocol, field).len() == expected && compare(*op, value, 0).is_some()
+        }
+    }
+}
+
+/// The packet headers of a frame.
+#[derive(Debug)]
+pub(super) struct Headers<'a>(Vec<(&'static str, &'a [u8])>);
+
+impl<'a> Headers<'a> {
+    /// Splits Ethernet frame `data` into its packet headers, as Retina's packet parsers would.
+    pub(super) fn parse(data: &'a [u8]) -> Self {
+        let mut headers = vec![("ethernet", data)];
+        let Some(mut ether_type) = read_u16(data, 12) else {
+            return Headers(vec![]);
+        };
+        let mut offset = 14;
+        if ether_type == ETHERTYPE_802_1Q {
+            let Some(inner) = read_u16(data, 16) else {
+                return Headers(headers);
+            };
+            ether_type = inner;
+            offset = 18;
+        }
+        let Some(payload) = data.get(offset..) else {
+            return Headers(headers);
+        };
+
+        let (protocol, transport) = match ether_type {
+            ETHERTYPE_IPV4 => {
+                headers.push(("ipv4", payload));
+                let ihl = (payload.first().copied().unwrap_or_default() & 0x0f) as usize * 4;
+                (payload.get(9).copied(), payload.get(ihl..))
+            }
+            ETHERTYPE_IPV6 => {
+                headers.push(("ipv6", payload));
+                (payload.get(6).copied(), payload.get(40..))
+            }
+            ETHERTYPE_ARP => {
+                headers.push(("arp", payload));
+                (None, None)
+            }
+            ETHERTYPE_LLDP => {
+                headers.push(("lldp", payload));
+                (None, None)
+            }
+            ETHERTYPE_EAPOL => {
+                headers.push(("eapol", payload));
+                (None, None)
+            }
+            _ => (None, None),
+        };
+        if let (Some(protocol), Some(transport)) = (protocol, transport) {
+            match protocol {
+                TCP_PROTOCOL => headers.push(("tcp", transport)),
+                UDP_PROTOCOL => headers.push(("udp", transport)),
+                SCTP_PROTOCOL => headers.push(("sctp", transport)),
+                _ => (),
+            }
+        }
+        Headers(headers)
+    }
+
+    fn get(&self, protocol: &ProtocolName) -> Option<&'a [u8]> {
+        self.0
+            .iter()
+            .find(|(name, _)| *name == protocol.name())
+            .map(|(_, header)| *header)
+    }
+
+    /// Returns `true` if the frame satisfies `predicate`. Predicates that are not supported are
+    /// never satisfied.
+    pub(super) fn eval(&self, predicate: &Predicate) -> bool {
+        let Some(header) = self.get(predicate.get_protocol()) else {
+            return false;
+        };
+        match predicate {
+            Predicate::Unary { .. } => true,
+            Predicate::Binary {
+                protocol,
+                field,
+                op,
+                value,
+            } => {
+                let mut results = reads(protocol, field)
+                    .into_iter()
+                    .map(|read| read_field(header, read).and_then(|lhs| compare(*op, value, lhs)));
+                if field.is_combined() && *op != BinOp::Ne {
+                    results.any(|result| result == Some(true))
+                } else {
+                    // Combined fields must differ from both the source and the destination value
+                    let mut results = results.peekable();
+                    results.peek().is_some() && results.all(|result| result == Some(true))
+                }
+            }
+        }
+    }
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    let bytes = data.get(offset..offset + 2)?;
+    Some(u16::from_be_bytes([bytes[0], bytes[1]]))
+}
+
+fn read_field(header: &[u8], read: Read) -> Option<u128> {
+    match read {
+        Read::Bits {
+            offset,
+            len,
+            mask,
+            shift,
+        } => {
+            let bytes = header.get(offset..offset + len)?;
+            let value = bytes
+                .iter()
+                .fold(0u128, |value, byte| value << 8 | *byte as u128);
+            Some((value & mask) >> shift)
+        }
+        Read::Flag { offset, mask } => Some((header.get(offset)? & mask != 0) as u128),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ipnet::Ipv4Net;
+
+    #[test]
+    fn core_eval_packet_predicates() {
+        let mut frame = vec![0u8; 12];
+        frame.extend_from_slice(&[0x08, 0x00]);
+        // IPv4 header: 10.0.0.1 -> 10.0.0.2, TCP
+        frame.extend_from_slice(&[0x45, 0, 0, 40, 0, 0, 0x40, 0, 64, 6, 0, 0]);
+        frame.extend_from_slice(&[10, 0, 0, 1, 10, 0, 0, 2]);
+        // TCP header: 51000 -> 443, SYN
+        frame.extend_from_slice(&[0xc7, 0x38, 0x01, 0xbb, 0, 0, 0, 1, 0, 0, 0, 0]);
+        frame.extend_from_slice(&[0x50, 0x02, 0xff, 0xff, 0, 0, 0, 0]);
+        let headers = Headers::parse(&frame);
+
+        let binary = |protocol: &str, field: &str, op: BinOp, value: Value| Predicate::Binary {
+            protocol: protocol!(protocol),
+            field: field!(field),
+            op,
+            value,
+        };
+        let subnet = Value::Ipv4("10.0.0.0/24".parse::<Ipv4Net>().unwrap());
+        assert!(headers.eval(&Predicate::Unary {
+            protocol: protocol!("tcp")
+        }));
+        assert!(!headers.eval(&Predicate::Unary {
+            protocol: protocol!("udp")
+        }));
+        assert!(headers.eval(&binary("ipv4", "src_addr", BinOp::In, subnet.clone())));
+        assert!(!headers.eval(&binary("ipv4", "addr", BinOp::Ne, subnet)));
+        assert!(headers.eval(&binary("ipv4", "flags", BinOp::Eq, Value::Int(2))));
+        assert!(headers.eval(&binary("tcp", "port", BinOp::Eq, Value::Int(443))));
+        assert!(!headers.eval(&binary("tcp", "port", BinOp::Ne, Value::Int(443))));
+        assert!(headers.eval(&binary("tcp", "syn", BinOp::Eq, Value::Int(1))));
+        assert!(headers.eval(&binary(
+            "tcp",
+            "src_port",
+            BinOp::In,
+            Value::IntRange {
+                from: 50000,
+                to: 60000
+            }
+        )));
+
+        assert!(!is_supported(&binary(
+            "tcp",
+            "mss",
+            BinOp::Eq,
+            Value::Int(1460)
+        )));
+        assert!(!is_supported(&binary(
+            "tcp",
+            "port",
+            BinOp::Re,
+            Value::Text("443".to_string())
+        )));
+    }
+}
@@ -0,0 +1,208 @@
+//! Filter explanations.
+//!
+//! The [filter](../../retina_filtergen/attr.filter.html) macro compiles a filter expression into
+//! packet, connection, and session sub-filters. An [Explanation](Explanation) exposes each step
+//! of this compilation without generating code, which is useful for debugging filters that match
+//! more or less traffic than expected.
+//!
+//! ## Example
+//! ```
+//! let explanation = Explanation::new("tcp.port = 443 and tls.sni ~ 'netflix'").unwrap();
+//! println!("{}", explanation.ptree);
+//! for info in explanation.predicates.iter() {
+//!     println!("{} ({})", info.predicate, info.stage);
+//! }
+//! ```
+
+use super::ast::{Node, Predicate};
+use super::eval::{self, Headers};
+use super::hardware;
+use super::parser::FilterParser;
+use super::pattern::LayeredPattern;
+use super::ptree::{PNode, PTree};
+use super::Filter;
+use crate::protocols::stream::ParserRegistry;
+use crate::runtime::trace::{link, Trace};
+
+use std::fmt;
+
+use anyhow::Result;
+
+/// Filter processing stage at which a predicate is applied.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Stage {
+    /// Applied to each packet, before connection tracking.
+    Packet,
+    /// Applied to each connection, once its application-layer protocol is identified.
+    Connection,
+    /// Applied to each parsed application-layer session.
+    Session,
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Stage::Packet => write!(f, "packet"),
+            Stage::Connection => write!(f, "connection"),
+            Stage::Session => write!(f, "session"),
+        }
+    }
+}
+
+/// How a predicate of the filter is applied.
+#[derive(Debug, Clone)]
+pub struct PredicateInfo {
+    /// The predicate.
+    pub predicate: Predicate,
+    /// The stage at which the predicate is applied.
+    pub stage: Stage,
+    /// Whether the predicate can be expressed as a hardware flow rule. Whether the rule can be
+    /// installed on a port still depends on the NIC.
+    pub hardware: bool,
+}
+
+/// Number of frames of a trace that match each pattern of a filter.
+#[derive(Debug, Clone)]
+pub struct MatchCounts {
+    /// Number of frames read.
+    pub frames: u64,
+    /// Number of frames that satisfy the packet-level predicates of each pattern, in the order of
+    /// [Explanation::patterns](Explanation::patterns). `None` if the pattern contains
+    /// packet-level predicates that cannot be evaluated without the compiled filter.
+    pub patterns: Vec<Option<u64>>,
+    /// Number of frames that satisfy the packet-level predicates of any pattern that can be
+    /// evaluated.
+    pub matched: u64,
+}
+
+/// The intermediate representations of a compiled filter.
+#[derive(Debug)]
+pub struct Explanation {
+    /// The filter expression.
+    pub filter_str: String,
+    /// Abstract syntax tree of the filter expression.
+    pub ast: Node,
+    /// Fully qualified patterns matched by the filter, after removing redundant patterns.
+    pub patterns: Vec<LayeredPattern>,
+    /// Pruned predicate tree from which the sub-filters are generated.
+    pub ptree: PTree,
+    /// Predicates of the filter, in predicate tree order.
+    pub predicates: Vec<PredicateInfo>,
+    /// Application-layer protocols that are parsed to apply the filter. Subscribable types that
+    /// require parsing (e.g., [TlsHandshake](crate::subscription::TlsHandshake)) register their
+    /// own parsers instead.
+    pub parsers: Vec<&'static str>,
+}
+
+impl Explanation {
+    /// Compiles `filter_str` as the filter macro does, and returns the intermediate results.
+    pub fn new(filter_str: &str) -> Result<Self> {
+        let parser = FilterParser {
+            split_combined: false,
+        };
+        let ast = parser.parse_as_ast(filter_str)?;
+
+        let filter = Filter::from_str(filter_str, false)?;
+        let mut ptree = filter.to_ptree();
+        ptree.prune_branches();
+
+        let mut predicates: Vec<PredicateInfo> = vec![];
+        fn visit(node: &PNode, predicates: &mut Vec<PredicateInfo>) {
+            for child in node.children.iter() {
+                if !predicates.iter().any(|info| info.predicate == child.pred) {
+                    let stage = if child.pred.on_packet() {
+                        Stage::Packet
+                    } else if child.pred.on_connection() {
+                        Stage::Connection
+                    } else {
+                        Stage::Session
+                    };
+                    predicates.push(PredicateInfo {
+                        predicate: child.pred.clone(),
+                        stage,
+                        hardware: hardware::offloadable(&child.pred),
+                    });
+                }
+                visit(child, predicates);
+            }
+        }
+        visit(&ptree.root, &mut predicates);
+
+        let mut parsers = ParserRegistry::from_filter(&filter)?.protocols();
+        parsers.sort_unstable();
+        parsers.dedup();
+
+        Ok(Explanation {
+            filter_str: filter_str.to_string(),
+            ast,
+            patterns: filter.get_patterns_layered(),
+            ptree,
+            predicates,
+            parsers,
+        })
+    }
+
+    /// Returns `true` if the packet-level predicates of all patterns can be expressed as hardware
+    /// flow rules.
+    pub fn is_hardware_filterable(&self) -> bool {
+        self.predicates
+            .iter()
+            .filter(|info| info.stage == Stage::Packet)
+            .all(|info| info.hardware)
+    }
+
+    /// Reads the trace made of `paths` and counts the frames that match the packet-level
+    /// predicates of each pattern. Each path may be a file, a directory, or a glob pattern, as in
+    /// [OfflineConfig](crate::config::OfflineConfig).
+    ///
+    /// ## Remarks
+    /// Connection- and session-level predicates require connection tracking and protocol
+    /// parsing, so they are not evaluated: the counts of patterns with such predicates are the
+    /// number of frames that would be passed to connection tracking.
+    pub fn count_matches(&self, paths: &[String]) -> Result<MatchCounts> {
+        let patterns: Vec<Option<Vec<Predicate>>> = self
+            .patterns
+            .iter()
+            .map(|pattern| {
+                let predicates: Vec<Predicate> = pattern
+                    .to_flat_pattern()
+                    .predicates
+                    .into_iter()
+                    .filter(|predicate| predicate.on_packet())
+                    .collect();
+                predicates
+                    .iter()
+                    .all(eval::is_supported)
+                    .then_some(predicates)
+            })
+            .collect();
+
+        let mut counts = MatchCounts {
+            frames: 0,
+            patterns: patterns.iter().map(|p| p.as_ref().map(|_| 0)).collect(),
+            matched: 0,
+        };
+        let mut trace = Trace::open(paths)?;
+        let mut buf = vec![];
+        while let Some(frame) = trace.next_frame() {
+            counts.frames += 1;
+            let Some(data) = link::to_ethernet(frame.link_type, &frame.data, &mut buf) else {
+                continue;
+            };
+            let headers = Headers::parse(data);
+            let mut matched = false;
+            for (pattern, count) in patterns.iter().zip(counts.patterns.iter_mut()) {
+                if let (Some(predicates), Some(count)) = (pattern, count) {
+                    if predicates.iter().all(|predicate| headers.eval(predicate)) {
+                        *count += 1;
+                        matched = true;
+                    }
+                }
+            }
+            if matched || self.patterns.is_empty() {
+                counts.matched += 1;
+            }
+        }
+        Ok(counts)
+    }
+}
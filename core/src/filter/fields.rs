@@ -0,0 +1,538 @@
+//! Filterable protocols and fields.
+//!
+//! Every protocol that can appear in a filter, and every field that can be compared in a binary
+//! predicate, is declared once in [PROTOCOLS](PROTOCOLS). The parser validates filters against
+//! these declarations, the hardware filter uses them to decide which predicates can be offloaded,
+//! and the [filter](../../../retina_filtergen/attr.filter.html) macro uses them to generate the
+//! accessor calls of each sub-filter.
+//!
+//! ## Adding a field
+//! A field is declared with the name used in filter syntax and the type returned by the accessor
+//! method of the protocol struct. By default, the accessor has the same name as the field and the
+//! comparison operators are those supported by the type:
+//! ```
+//! FieldDef::new("src_port", FieldType::U16)
+//!     .hardware()
+//!     .header(int(0, 2))
+//! ```
+//! `hardware` marks fields that can be matched by NIC flow rules (this also requires a mapping to
+//! the corresponding `rte_flow` item), and `header` gives the location of packet header fields so
+//! that filters can be evaluated on raw frames.
+//!
+//! A field whose accessor has a different name, or that supports fewer operators than its type,
+//! declares them with `accessor` and `ops`:
+//! ```
+//! FieldDef::new("ts", FieldType::U8)
+//!     .accessor("has_timestamps")
+//!     .ops(&[BinOp::Eq, BinOp::Ne])
+//! ```
+
+use super::ast::{BinOp, Value};
+
+use std::fmt;
+
+use FieldType::*;
+
+/// The type returned by a field accessor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldType {
+    U8,
+    U16,
+    U32,
+    U64,
+    Ipv4Addr,
+    Ipv6Addr,
+    Str,
+}
+
+impl FieldType {
+    /// Returns `true` if `value` can be compared with a field of this type.
+    pub fn accepts(&self, value: &Value) -> bool {
+        matches!(
+            (self, value),
+            (
+                FieldType::U8 | FieldType::U16 | FieldType::U32 | FieldType::U64,
+                Value::Int(_) | Value::IntRange { .. }
+            ) | (FieldType::Ipv4Addr, Value::Ipv4(_))
+                | (FieldType::Ipv6Addr, Value::Ipv6(_))
+                | (FieldType::Str, Value::Text(_))
+        )
+    }
+
+    /// Returns the largest integer that fits in the type, if it is an integer type.
+    pub fn max_int(&self) -> Option<u64> {
+        match self {
+            FieldType::U8 => Some(u8::MAX.into()),
+            FieldType::U16 => Some(u16::MAX.into()),
+            FieldType::U32 => Some(u32::MAX.into()),
+            FieldType::U64 => Some(u64::MAX),
+            _ => None,
+        }
+    }
+
+    /// Returns the comparison operators supported by the type.
+    const fn ops(&self) -> &'static [BinOp] {
+        match self {
+            FieldType::U8 | FieldType::U16 | FieldType::U32 | FieldType::U64 => &[
+                BinOp::Eq,
+                BinOp::Ne,
+                BinOp::Ge,
+                BinOp::Le,
+                BinOp::Gt,
+                BinOp::Lt,
+                BinOp::In,
+            ],
+            FieldType::Ipv4Addr | FieldType::Ipv6Addr => &[BinOp::Eq, BinOp::Ne, BinOp::In],
+            FieldType::Str => &[BinOp::Eq, BinOp::Re],
+        }
+    }
+}
+
+impl fmt::Display for FieldType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FieldType::U8 => write!(f, "u8"),
+            FieldType::U16 => write!(f, "u16"),
+            FieldType::U32 => write!(f, "u32"),
+            FieldType::U64 => write!(f, "u64"),
+            FieldType::Ipv4Addr => write!(f, "IPv4 address"),
+            FieldType::Ipv6Addr => write!(f, "IPv6 address"),
+            FieldType::Str => write!(f, "string"),
+        }
+    }
+}
+
+/// Returns the comparison operators that can be applied to `value`. Integer ranges can only be
+/// tested with `in`.
+pub(crate) fn value_ops(value: &Value) -> &'static [BinOp] {
+    match value {
+        Value::Int(_) => &[
+            BinOp::Eq,
+            BinOp::Ne,
+            BinOp::Ge,
+            BinOp::Le,
+            BinOp::Gt,
+            BinOp::Lt,
+        ],
+        Value::IntRange { .. } => &[BinOp::In],
+        Value::Ipv4(_) | Value::Ipv6(_) => &[BinOp::Eq, BinOp::Ne, BinOp::In],
+        Value::Text(_) => &[BinOp::Eq, BinOp::Re],
+    }
+}
+
+/// How a field is read from a raw packet header.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Read {
+    /// Big-endian integer of `len` bytes at `offset`, masked and shifted right.
+    Bits {
+        offset: usize,
+        len: usize,
+        mask: u128,
+        shift: u32,
+    },
+    /// `1` if any bit of `mask` is set in the byte at `offset`, `0` otherwise.
+    Flag { offset: usize, mask: u8 },
+}
+
+const fn int(offset: usize, len: usize) -> Read {
+    Read::Bits {
+        offset,
+        len,
+        mask: u128::MAX,
+        shift: 0,
+    }
+}
+
+const fn bits(offset: usize, len: usize, mask: u128, shift: u32) -> Read {
+    Read::Bits {
+        offset,
+        len,
+        mask,
+        shift,
+    }
+}
+
+const fn flag(offset: usize, mask: u8) -> Read {
+    Read::Flag { offset, mask }
+}
+
+/// Comparison operators of fields that are `1` if an option is present and `0` otherwise.
+const PRESENCE_OPS: &[BinOp] = &[BinOp::Eq, BinOp::Ne];
+
+/// A filterable field of a protocol.
+#[derive(Debug)]
+pub struct FieldDef {
+    /// Name of the field in filter syntax.
+    pub name: &'static str,
+    /// Type returned by the accessor.
+    pub ty: FieldType,
+    /// Name of the accessor method of the protocol struct.
+    pub accessor: &'static str,
+    /// Supported comparison operators.
+    pub ops: &'static [BinOp],
+    /// Whether predicates on the field can be expressed as hardware flow rules.
+    pub hardware: bool,
+    /// Location of the field in the packet header, for packet protocols.
+    pub(crate) header: Option<Read>,
+}
+
+impl FieldDef {
+    /// Declares a field with an accessor of the same name, supporting the comparison operators of
+    /// `ty`.
+    const fn new(name: &'static str, ty: FieldType) -> Self {
+        FieldDef {
+            name,
+            ty,
+            accessor: name,
+            ops: ty.ops(),
+            hardware: false,
+            header: None,
+        }
+    }
+
+    /// Sets the accessor method of the field.
+    const fn accessor(self, accessor: &'static str) -> Self {
+        FieldDef { accessor, ..self }
+    }
+
+    /// Restricts the supported comparison operators.
+    const fn ops(self, ops: &'static [BinOp]) -> Self {
+        FieldDef { ops, ..self }
+    }
+
+    /// Marks the field as offloadable to hardware flow rules.
+    const fn hardware(self) -> Self {
+        FieldDef {
+            hardware: true,
+            ..self
+        }
+    }
+
+    /// Sets the location of the field in the packet header.
+    const fn header(self, read: Read) -> Self {
+        FieldDef {
+            header: Some(read),
+            ..self
+        }
+    }
+
+    /// Returns the comparison operators that can be offloaded to hardware flow rules: equality,
+    /// and subnet matches on addresses.
+    pub fn hardware_ops(&self) -> &'static [BinOp] {
+        if !self.hardware {
+            return &[];
+        }
+        match self.ty {
+            FieldType::Ipv4Addr | FieldType::Ipv6Addr => &[BinOp::Eq, BinOp::In],
+            _ => &[BinOp::Eq],
+        }
+    }
+}
+
+/// A filterable protocol.
+#[derive(Debug)]
+pub struct ProtocolDef {
+    /// Name of the protocol in filter syntax.
+    pub name: &'static str,
+    /// Name of the protocol struct, and of its `ConnParser` and `SessionData` variants for
+    /// application-layer protocols.
+    pub ident: &'static str,
+    /// Whether unary predicates on the protocol can be expressed as hardware flow rules.
+    pub hardware: bool,
+    /// Filterable fields.
+    pub fields: &'static [FieldDef],
+}
+
+impl ProtocolDef {
+    const fn new(name: &'static str, ident: &'static str, fields: &'static [FieldDef]) -> Self {
+        ProtocolDef {
+            name,
+            ident,
+            hardware: false,
+            fields,
+        }
+    }
+
+    /// Marks the protocol as offloadable to hardware flow rules.
+    const fn hardware(self) -> Self {
+        ProtocolDef {
+            hardware: true,
+            ..self
+        }
+    }
+
+    /// Returns the field named `name`. The combined fields `addr` and `port` return the
+    /// corresponding source field.
+    pub fn field(&self, name: &str) -> Option<&'static FieldDef> {
+        let name = match name {
+            "addr" if self.has_combined("addr") => "src_addr",
+            "port" if self.has_combined("port") => "src_port",
+            name => name,
+        };
+        self.fields.iter().find(|field| field.name == name)
+    }
+
+    /// Returns `true` if the protocol has both a source and a destination `field`, which can be
+    /// matched with the combined field `field`.
+    fn has_combined(&self, field: &str) -> bool {
+        let src = format!("src_{}", field);
+        let dst = format!("dst_{}", field);
+        self.fields.iter().any(|f| f.name == src) && self.fields.iter().any(|f| f.name == dst)
+    }
+
+    /// Returns the names of the fields, including combined fields.
+    pub fn field_names(&self) -> Vec<&'static str> {
+        let mut names: Vec<&'static str> = self.fields.iter().map(|field| field.name).collect();
+        for combined in ["addr", "port"] {
+            if self.has_combined(combined) {
+                names.push(combined);
+            }
+        }
+        names
+    }
+}
+
+/// Returns the declaration of the protocol named `name`.
+pub fn protocol(name: &str) -> Option<&'static ProtocolDef> {
+    PROTOCOLS.iter().find(|protocol| protocol.name == name)
+}
+
+/// Returns the declaration of `protocol.field`. The combined fields `addr` and `port` return the
+/// corresponding source field.
+pub fn field(protocol: &str, field: &str) -> Option<&'static FieldDef> {
+    self::protocol(protocol)?.field(field)
+}
+
+/// Declarations of all filterable protocols and fields.
+#[rustfmt::skip]
+pub const PROTOCOLS: &[ProtocolDef] = &[
+    ProtocolDef::new("ethernet", "Ethernet", &[]),
+    ProtocolDef::new("arp", "Arp", &[
+        FieldDef::new("hw_type", U16).header(int(0, 2)),
+        FieldDef::new("proto_type", U16).header(int(2, 2)),
+        FieldDef::new("hw_len", U8).header(int(4, 1)),
+        FieldDef::new("proto_len", U8).header(int(5, 1)),
+        FieldDef::new("op", U16).header(int(6, 2)),
+        FieldDef::new("sender_proto_addr", Ipv4Addr).header(int(14, 4)),
+        FieldDef::new("target_proto_addr", Ipv4Addr).header(int(24, 4)),
+    ]),
+    ProtocolDef::new("lldp", "Lldp", &[
+        FieldDef::new("chassis_id_subtype", U8),
+        FieldDef::new("chassis_id", Str),
+        FieldDef::new("port_id_subtype", U8),
+        FieldDef::new("port_id", Str),
+        FieldDef::new("ttl", U16),
+        FieldDef::new("port_description", Str),
+        FieldDef::new("system_name", Str),
+        FieldDef::new("system_description", Str),
+    ]),
+    ProtocolDef::new("cdp", "Cdp", &[
+        FieldDef::new("version", U8),
+        FieldDef::new("ttl", U8),
+        FieldDef::new("checksum", U16),
+        FieldDef::new("device_id", Str),
+        FieldDef::new("port_id", Str),
+        FieldDef::new("software_version", Str),
+        FieldDef::new("platform", Str),
+        FieldDef::new("native_vlan", U16),
+    ]),
+    ProtocolDef::new("eapol", "Eapol", &[
+        FieldDef::new("version", U8).header(int(0, 1)),
+        FieldDef::new("packet_type", U8).header(int(1, 1)),
+        FieldDef::new("body_length", U16).header(int(2, 2)),
+        FieldDef::new("eap_code", U8),
+        FieldDef::new("eap_identifier", U8),
+        FieldDef::new("eap_length", U16),
+        FieldDef::new("eap_type", U8),
+        FieldDef::new("key_descriptor_type", U8),
+    ]),
+    ProtocolDef::new("ipv4", "Ipv4", &[
+        FieldDef::new("version", U8).header(bits(0, 1, 0xf0, 4)),
+        FieldDef::new("ihl", U8).header(bits(0, 1, 0x0f, 0)),
+        FieldDef::new("version_ihl", U8).hardware().header(int(0, 1)),
+        FieldDef::new("dscp", U8).header(bits(1, 1, 0xfc, 2)),
+        FieldDef::new("ecn", U8).header(bits(1, 1, 0x03, 0)),
+        FieldDef::new("dscp_ecn", U8).header(int(1, 1)),
+        FieldDef::new("type_of_service", U8).hardware().header(int(1, 1)),
+        FieldDef::new("total_length", U16).hardware().header(int(2, 2)),
+        FieldDef::new("identification", U16).hardware().header(int(4, 2)),
+        FieldDef::new("flags_to_fragment_offset", U16).hardware().header(int(6, 2)),
+        FieldDef::new("flags", U8).header(bits(6, 1, 0xe0, 5)),
+        FieldDef::new("fragment_offset", U16).header(bits(6, 2, 0x1fff, 0)),
+        FieldDef::new("time_to_live", U8).hardware().header(int(8, 1)),
+        FieldDef::new("protocol", U8).hardware().header(int(9, 1)),
+        FieldDef::new("header_checksum", U16).hardware().header(int(10, 2)),
+        FieldDef::new("src_addr", Ipv4Addr).hardware().header(int(12, 4)),
+        FieldDef::new("dst_addr", Ipv4Addr).hardware().header(int(16, 4)),
+    ]).hardware(),
+    ProtocolDef::new("ipv6", "Ipv6", &[
+        FieldDef::new("version", U8).header(bits(0, 1, 0xf0, 4)),
+        FieldDef::new("dscp", U8).header(bits(0, 2, 0x0fc0, 6)),
+        FieldDef::new("ecn", U8).header(bits(0, 2, 0x0030, 4)),
+        FieldDef::new("traffic_class", U8).header(bits(0, 2, 0x0ff0, 4)),
+        FieldDef::new("flow_label", U32).header(bits(0, 4, 0x000f_ffff, 0)),
+        FieldDef::new("version_to_flow_label", U32).hardware().header(int(0, 4)),
+        FieldDef::new("payload_length", U16).hardware().header(int(4, 2)),
+        FieldDef::new("next_header", U8).hardware().header(int(6, 1)),
+        FieldDef::new("hop_limit", U8).hardware().header(int(7, 1)),
+        FieldDef::new("src_addr", Ipv6Addr).hardware().header(int(8, 16)),
+        FieldDef::new("dst_addr", Ipv6Addr).hardware().header(int(24, 16)),
+    ]).hardware(),
+    ProtocolDef::new("tcp", "Tcp", &[
+        FieldDef::new("src_port", U16).hardware().header(int(0, 2)),
+        FieldDef::new("dst_port", U16).hardware().header(int(2, 2)),
+        FieldDef::new("seq_no", U32).hardware().header(int(4, 4)),
+        FieldDef::new("ack_no", U32).hardware().header(int(8, 4)),
+        FieldDef::new("data_offset", U8).header(bits(12, 1, 0xf0, 4)),
+        FieldDef::new("reserved", U8).header(bits(12, 1, 0x0e, 1)),
+        FieldDef::new("data_offset_to_ns", U8).hardware().header(int(12, 1)),
+        FieldDef::new("flags", U8).hardware().header(int(13, 1)),
+        FieldDef::new("window", U16).hardware().header(int(14, 2)),
+        FieldDef::new("checksum", U16).hardware().header(int(16, 2)),
+        FieldDef::new("urgent_pointer", U16).hardware().header(int(18, 2)),
+        FieldDef::new("ns", U8).header(flag(12, 0x01)),
+        FieldDef::new("cwr", U8).header(flag(13, 0x80)),
+        FieldDef::new("ece", U8).header(flag(13, 0x40)),
+        FieldDef::new("urg", U8).header(flag(13, 0x20)),
+        FieldDef::new("ack", U8).header(flag(13, 0x10)),
+        FieldDef::new("psh", U8).header(flag(13, 0x08)),
+        FieldDef::new("rst", U8).header(flag(13, 0x04)),
+        FieldDef::new("syn", U8).header(flag(13, 0x02)),
+        FieldDef::new("fin", U8).header(flag(13, 0x01)),
+        FieldDef::new("synack", U8).header(flag(13, 0x12)),
+        FieldDef::new("mss", U16),
+        FieldDef::new("wscale", U8),
+        FieldDef::new("sack_permitted", U8).accessor("has_sack_permitted").ops(PRESENCE_OPS),
+        FieldDef::new("ts", U8).accessor("has_timestamps").ops(PRESENCE_OPS),
+        FieldDef::new("tfo", U8).accessor("has_tfo").ops(PRESENCE_OPS),
+    ]).hardware(),
+    ProtocolDef::new("udp", "Udp", &[
+        FieldDef::new("src_port", U16).hardware().header(int(0, 2)),
+        FieldDef::new("dst_port", U16).hardware().header(int(2, 2)),
+        FieldDef::new("length", U16).hardware().header(int(4, 2)),
+        FieldDef::new("checksum", U16).hardware().header(int(6, 2)),
+    ]).hardware(),
+    ProtocolDef::new("sctp", "Sctp", &[
+        FieldDef::new("src_port", U16).header(int(0, 2)),
+        FieldDef::new("dst_port", U16).header(int(2, 2)),
+        FieldDef::new("verification_tag", U32).header(int(4, 4)),
+        FieldDef::new("checksum", U32).header(int(8, 4)),
+        FieldDef::new("chunk_type", U8),
+        FieldDef::new("nb_chunks", U64),
+        FieldDef::new("ppid", U32),
+    ]),
+    ProtocolDef::new("tls", "Tls", &[
+        FieldDef::new("client_version", U16),
+        FieldDef::new("client_random", Str),
+        FieldDef::new("sni", Str),
+        FieldDef::new("server_version", U16),
+        FieldDef::new("server_random", Str),
+        FieldDef::new("cipher", Str),
+        FieldDef::new("compression_alg", U8),
+        FieldDef::new("version", U16),
+    ]),
+    ProtocolDef::new("http", "Http", &[
+        FieldDef::new("uri", Str),
+        FieldDef::new("method", Str),
+        FieldDef::new("request_version", Str),
+        FieldDef::new("user_agent", Str),
+        FieldDef::new("cookie", Str),
+        FieldDef::new("host", Str),
+        FieldDef::new("request_content_length", U64),
+        FieldDef::new("request_content_type", Str),
+        FieldDef::new("request_transfer_encoding", Str),
+        FieldDef::new("response_version", Str),
+        FieldDef::new("status_code", U16),
+        FieldDef::new("status_msg", Str),
+        FieldDef::new("response_content_length", U64),
+        FieldDef::new("response_content_type", Str),
+        FieldDef::new("response_transfer_encoding", Str),
+    ]),
+    ProtocolDef::new("ssh", "Ssh", &[]),
+    ProtocolDef::new("dns", "Dns", &[
+        FieldDef::new("query_domain", Str),
+    ]),
+    ProtocolDef::new("quic", "Quic", &[]),
+];
+
+/// Returns the candidate closest to `name`, if it is close enough to be a likely typo.
+pub(crate) fn suggest<'a>(name: &str, candidates: &[&'a str]) -> Option<&'a str> {
+    let max_distance = std::cmp::max(1, name.chars().count() / 3);
+    candidates
+        .iter()
+        .map(|candidate| (edit_distance(name, candidate), *candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// Edit distance between `a` and `b`, counting insertions, deletions, substitutions, and
+/// transpositions of adjacent characters.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut d = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in d[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            d[i][j] = (d[i - 1][j] + 1)
+                .min(d[i][j - 1] + 1)
+                .min(d[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+    d[a.len()][b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filter::ast::NODE_BIMAP;
+
+    #[test]
+    fn core_fields_registry() {
+        // Every protocol of the layer graph is declared exactly once
+        for protocol in PROTOCOLS.iter() {
+            assert!(NODE_BIMAP.contains_right(&protocol!(protocol.name)));
+            assert_eq!(
+                PROTOCOLS.iter().filter(|p| p.name == protocol.name).count(),
+                1
+            );
+            for field in protocol.fields.iter() {
+                assert!(!field.ops.is_empty());
+                assert!(!field.hardware || protocol.hardware);
+                assert_eq!(
+                    protocol
+                        .fields
+                        .iter()
+                        .filter(|f| f.name == field.name)
+                        .count(),
+                    1
+                );
+            }
+        }
+        assert_eq!(PROTOCOLS.len(), NODE_BIMAP.len());
+
+        let port = field("tcp", "port").unwrap();
+        assert_eq!(port.name, "src_port");
+        assert_eq!(port.hardware_ops(), &[BinOp::Eq]);
+        assert_eq!(
+            field("ipv4", "addr").unwrap().hardware_ops(),
+            &[BinOp::Eq, BinOp::In]
+        );
+        assert!(field("arp", "addr").is_none());
+        assert!(field("tls", "sni").unwrap().hardware_ops().is_empty());
+        let ts = field("tcp", "ts").unwrap();
+        assert_eq!(ts.accessor, "has_timestamps");
+        assert_eq!(ts.ops, &[BinOp::Eq, BinOp::Ne]);
+        assert!(protocol("lldp").unwrap().field_names().contains(&"port_id"));
+    }
+}
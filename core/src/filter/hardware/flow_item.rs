@@ -351,7 +351,7 @@ impl FlowPattern {
                         }
                         _ => bail!(FilterError::InvalidRhsType(value.to_string())),
                     },
-                    "data_offset_to_nw" => match value {
+                    "data_offset_to_ns" => match value {
                         Value::Int(i) => {
                             if let Ok(val) = u8::try_from(*i) {
                                 tcp_spec.hdr.data_off = val;
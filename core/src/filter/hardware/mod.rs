@@ -7,6 +7,7 @@ use self::flow_attr::*;
 use self::flow_item::*;
 
 use super::ast::*;
+use super::fields;
 use super::pattern::*;
 use super::ptree::PTree;
 use super::Filter;
@@ -120,39 +121,7 @@ pub(crate) enum HardwareFilterError {
 }
 
 pub(crate) fn device_supported(pred: &Predicate, port: &Port) -> bool {
-    // Device supported protocols
-    let hw_filterable_protos = hashset! {
-        protocol!("ipv4"),
-        protocol!("ipv6"),
-        protocol!("tcp"),
-        protocol!("udp"),
-    };
-    let proto_supported = hw_filterable_protos.contains(pred.get_protocol());
-    if !proto_supported {
-        info!("Hardware filter does not support protocol for: [{}]", pred);
-        return false;
-    }
-
-    // Only allow equality predicates
-    // MLX5 only supports equality or masked IP address
-    let op_supported = match pred {
-        Predicate::Unary { .. } => true,
-        Predicate::Binary {
-            protocol,
-            field: _,
-            op,
-            value: _,
-        } => {
-            matches!(op, BinOp::Eq)
-                || protocol == &protocol!("ipv4") && matches!(op, BinOp::In)
-                || protocol == &protocol!("ipv6") && matches!(op, BinOp::In)
-        }
-    };
-    if !op_supported {
-        info!(
-            "Hardware filter does not support binary comparison operator for: [{}]",
-            pred
-        );
+    if !offloadable(pred) {
         return false;
     }
 
@@ -170,6 +139,28 @@ pub(crate) fn device_supported(pred: &Predicate, port: &Port) -> bool {
     true
 }
 
+/// Returns `true` if the protocol, field, and comparison operator of `pred` can be expressed as a
+/// flow rule according to the [field registry](super::fields). Whether the rule can be installed
+/// still depends on the device.
+pub(crate) fn offloadable(pred: &Predicate) -> bool {
+    let supported = match pred {
+        Predicate::Unary { protocol } => {
+            fields::protocol(protocol.name()).is_some_and(|protocol| protocol.hardware)
+        }
+        Predicate::Binary {
+            protocol,
+            field,
+            op,
+            value: _,
+        } => fields::field(protocol.name(), field.name())
+            .is_some_and(|field| field.hardware_ops().contains(op)),
+    };
+    if !supported {
+        info!("Hardware filter does not support predicate: [{}]", pred);
+    }
+    supported
+}
+
 fn predicate_supported(predicate: &Predicate, port: &Port, group: u32, priority: u32) -> bool {
     let pattern = FlatPattern {
         predicates: vec![predicate.to_owned()],
@@ -1,6 +1,9 @@
 #[macro_use]
 pub mod macros;
 pub mod ast;
+mod eval;
+pub mod explain;
+pub mod fields;
 mod hardware;
 #[allow(clippy::upper_case_acronyms)]
 mod parser;
@@ -16,6 +19,7 @@ use crate::port::Port;
 use crate::protocols::stream::{ConnData, Session};
 
 use std::fmt;
+use std::ops::Range;
 
 use anyhow::{bail, Result};
 use thiserror::Error;
@@ -146,6 +150,39 @@ pub enum FilterError {
     #[error("Invalid filter format")]
     InvalidFormat,
 
+    #[error("Invalid filter syntax: expected {0}")]
+    InvalidSyntax(String),
+
+    #[error("Unknown protocol `{0}`")]
+    UnknownProtocol(String),
+
+    #[error("Unknown field `{field}` for protocol `{protocol}`")]
+    UnknownField { protocol: String, field: String },
+
+    #[error("Operator `{op}` is not supported for `{field}` with value `{value}`")]
+    UnsupportedOp {
+        field: String,
+        op: String,
+        value: String,
+    },
+
+    #[error("Mismatched types: expected {expected} for `{field}`, found `{value}`")]
+    MismatchedType {
+        field: String,
+        expected: String,
+        value: String,
+    },
+
+    #[error("Value `{value}` out of range for `{field}` ({ty})")]
+    OutOfRange {
+        field: String,
+        ty: String,
+        value: String,
+    },
+
+    #[error("Invalid regular expression: {0}")]
+    InvalidRegex(String),
+
     #[error("Invalid pattern. Contains unsupported layer encapsulation: {0}")]
     InvalidPatternLayers(FlatPattern),
 
@@ -192,9 +229,138 @@ pub enum FilterError {
     },
 }
 
+/// A [FilterError] located in the filter expression.
+#[derive(Error, Debug)]
+#[error("{error}")]
+pub struct SpannedFilterError {
+    /// The error.
+    pub error: FilterError,
+    /// Byte range of the filter expression that caused the error.
+    pub span: Range<usize>,
+    /// Suggested fix, if any.
+    pub help: Option<String>,
+}
+
+impl SpannedFilterError {
+    pub(crate) fn new(error: FilterError, span: Range<usize>) -> Self {
+        SpannedFilterError {
+            error,
+            span,
+            help: None,
+        }
+    }
+
+    pub(crate) fn with_help(mut self, help: String) -> Self {
+        self.help = Some(help);
+        self
+    }
+
+    /// Formats the error with the line of `filter_str` that caused it, e.g.:
+    ///
+    /// ```text
+    /// Unknown field `sin` for protocol `tls`
+    ///  --> 1:24
+    ///   |
+    /// 1 | tcp.port = 443 and tls.sin ~ 'netflix'
+    ///   |                        ^^^
+    ///   = help: did you mean `sni`?
+    /// ```
+    pub fn render(&self, filter_str: &str) -> String {
+        let start = self.span.start.min(filter_str.len());
+        let line_start = filter_str[..start].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = filter_str[start..]
+            .find('\n')
+            .map_or(filter_str.len(), |i| start + i);
+        let line = &filter_str[line_start..line_end];
+        let line_no = filter_str[..start].matches('\n').count() + 1;
+        let column = filter_str[line_start..start].chars().count();
+        let end = self.span.end.clamp(start, line_end);
+        let width = std::cmp::max(1, filter_str[start..end].chars().count());
+
+        let gutter = " ".repeat(line_no.to_string().len());
+        let mut s = format!("{}\n", self.error);
+        s.push_str(&format!("{}--> {}:{}\n", gutter, line_no, column + 1));
+        s.push_str(&format!("{} |\n", gutter));
+        s.push_str(&format!("{} | {}\n", line_no, line));
+        s.push_str(&format!(
+            "{} | {}{}",
+            gutter,
+            " ".repeat(column),
+            "^".repeat(width)
+        ));
+        if let Some(help) = &self.help {
+            s.push_str(&format!("\n{} = help: {}", gutter, help));
+        }
+        s
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    // use super::*;
+    use super::*;
 
-    // TODO: test filter string parsing
+    fn spanned_error(filter_str: &str) -> SpannedFilterError {
+        Filter::from_str(filter_str, false)
+            .unwrap_err()
+            .downcast::<SpannedFilterError>()
+            .unwrap()
+    }
+
+    #[test]
+    fn core_filter_errors() {
+        assert!(Filter::from_str(
+            "(ipv4 and tcp.port >= 100 and tls.sni ~ 'netflix') or http",
+            false
+        )
+        .is_ok());
+        assert!(Filter::from_str(
+            "lldp.port_id = 'eth0' or arp.sender_proto_addr = 10.0.0.1",
+            false
+        )
+        .is_ok());
+
+        let error = spanned_error("tcp.port = 443 and tls.sin ~ 'netflix'");
+        assert!(matches!(error.error, FilterError::UnknownField { .. }));
+        assert_eq!(error.span, 23..26);
+        assert_eq!(error.help.as_deref(), Some("did you mean `tls.sni`?"));
+        assert_eq!(
+            error.render("tcp.port = 443 and tls.sin ~ 'netflix'"),
+            "Unknown field `sin` for protocol `tls`\n \
+             --> 1:24\n  \
+             |\n\
+             1 | tcp.port = 443 and tls.sin ~ 'netflix'\n  \
+             |                        ^^^\n  \
+             = help: did you mean `tls.sni`?"
+        );
+
+        let error = spanned_error("tpc.port = 80");
+        assert!(matches!(error.error, FilterError::UnknownProtocol(_)));
+        assert_eq!(error.span, 0..3);
+        assert_eq!(error.help.as_deref(), Some("did you mean `tcp`?"));
+
+        let error = spanned_error("tcp.port 80");
+        assert!(matches!(error.error, FilterError::InvalidSyntax(_)));
+        assert_eq!(error.span, 9..9);
+        assert_eq!(
+            error.to_string(),
+            "Invalid filter syntax: expected a comparison operator"
+        );
+
+        let error = spanned_error("tcp.port = 'http'");
+        assert!(matches!(error.error, FilterError::MismatchedType { .. }));
+        assert_eq!(error.span, 11..17);
+
+        let error = spanned_error("tcp.port = 1000..2000");
+        assert!(matches!(error.error, FilterError::UnsupportedOp { .. }));
+        assert_eq!(error.help.as_deref(), Some("use `in`"));
+
+        let error = spanned_error("ipv4.time_to_live > 300");
+        assert!(matches!(error.error, FilterError::OutOfRange { .. }));
+
+        let error = spanned_error("ipv4.addr = 1.2.3.400");
+        assert!(matches!(error.error, FilterError::InvalidRhsValue(_)));
+
+        let error = spanned_error("tls.sni ~ 'netflix('");
+        assert!(matches!(error.error, FilterError::InvalidRegex(_)));
+    }
 }
@@ -1,14 +1,17 @@
 use super::ast::*;
-use crate::filter::FilterError;
+use super::fields;
+use crate::filter::{FilterError, SpannedFilterError};
 
 use ipnet::{Ipv4Net, Ipv6Net};
+use pest::error::{Error as PestError, ErrorVariant, InputLocation};
 use pest::iterators::{Pair, Pairs};
 use pest::Parser;
 use std::net::{Ipv4Addr, Ipv6Addr};
-use std::ops::RangeInclusive;
+use std::ops::{Range, RangeInclusive};
 use std::str::FromStr;
 
 use anyhow::{bail, Result};
+use regex::Regex;
 
 #[derive(Parser)]
 #[grammar = "filter/grammar.pest"]
@@ -25,14 +28,15 @@ impl FilterParser {
         Ok(self.flatten_disjunct(ast))
     }
 
-    fn parse_as_ast(&self, filter_raw: &str) -> Result<Node> {
+    /// Parses filter string as an abstract syntax tree
+    pub(crate) fn parse_as_ast(&self, filter_raw: &str) -> Result<Node> {
         let pairs = FilterParser::parse(Rule::filter, filter_raw);
         match pairs {
             Ok(mut pairs) => {
                 let pair = pairs.next().unwrap();
                 self.parse_disjunct(pair)
             }
-            Err(_) => bail!(FilterError::InvalidFormat),
+            Err(error) => bail!(syntax_error(error)),
         }
     }
 
@@ -108,10 +112,12 @@ impl FilterParser {
     fn parse_predicate(&self, pair: Pair<Rule>) -> Result<Node> {
         let mut inner = pair.into_inner();
         let protocol = inner.next().unwrap();
+        self.check_protocol(&protocol)?;
         match inner.next() {
             Some(field) => {
                 let op = inner.next().unwrap();
                 let value = inner.next().unwrap();
+                self.check_binary(&protocol, &field, &op, &value)?;
 
                 match field.as_rule() {
                     Rule::field => Ok(Node::Predicate(Predicate::Binary {
@@ -163,6 +169,139 @@ impl FilterParser {
         }
     }
 
+    /// Returns an error if `protocol` is not a known protocol.
+    fn check_protocol(&self, protocol: &Pair<Rule>) -> Result<()> {
+        let name = protocol.as_str();
+        if fields::protocol(name).is_some() {
+            return Ok(());
+        }
+        let mut error = SpannedFilterError::new(
+            FilterError::UnknownProtocol(name.to_owned()),
+            span(protocol),
+        );
+        let protocols: Vec<&str> = fields::PROTOCOLS.iter().map(|p| p.name).collect();
+        if let Some(suggestion) = fields::suggest(name, &protocols) {
+            error = error.with_help(format!("did you mean `{}`?", suggestion));
+        }
+        bail!(error)
+    }
+
+    /// Returns an error if `protocol.field` is not a known field, or cannot be compared with
+    /// `value` using `op`.
+    fn check_binary(
+        &self,
+        protocol: &Pair<Rule>,
+        field: &Pair<Rule>,
+        op: &Pair<Rule>,
+        value: &Pair<Rule>,
+    ) -> Result<()> {
+        let name = format!("{}.{}", protocol.as_str(), field.as_str());
+        let protocol_def = fields::protocol(protocol.as_str()).expect("protocol is checked");
+        let field_def = match protocol_def.field(field.as_str()) {
+            Some(field_def) => field_def,
+            None => {
+                let mut error = SpannedFilterError::new(
+                    FilterError::UnknownField {
+                        protocol: protocol.as_str().to_owned(),
+                        field: field.as_str().to_owned(),
+                    },
+                    span(field),
+                );
+                let names = protocol_def.field_names();
+                if let Some(suggestion) = fields::suggest(field.as_str(), &names) {
+                    error = error.with_help(format!(
+                        "did you mean `{}.{}`?",
+                        protocol.as_str(),
+                        suggestion
+                    ));
+                } else if names.is_empty() {
+                    error = error.with_help(format!(
+                        "`{}` has no filterable fields, but can be used alone",
+                        protocol.as_str()
+                    ));
+                }
+                bail!(error)
+            }
+        };
+
+        let binop = self.parse_binop(op.clone())?;
+        let rhs = self.parse_value(value.clone()).map_err(|error| {
+            let error = match error.downcast::<FilterError>() {
+                Ok(error) => error,
+                Err(error) => {
+                    FilterError::InvalidRhsValue(format!("`{}` ({})", value.as_str(), error))
+                }
+            };
+            SpannedFilterError::new(error, span(value))
+        })?;
+
+        let ty = field_def.ty;
+        if !ty.accepts(&rhs) {
+            bail!(SpannedFilterError::new(
+                FilterError::MismatchedType {
+                    field: name,
+                    expected: ty.to_string(),
+                    value: value.as_str().to_owned(),
+                },
+                span(value),
+            ));
+        }
+
+        let supported_ops: Vec<BinOp> = fields::value_ops(&rhs)
+            .iter()
+            .copied()
+            .filter(|op| field_def.ops.contains(op))
+            .collect();
+        if !supported_ops.contains(&binop) {
+            let mut error = SpannedFilterError::new(
+                FilterError::UnsupportedOp {
+                    field: name,
+                    op: op.as_str().to_owned(),
+                    value: value.as_str().to_owned(),
+                },
+                span(op),
+            );
+            if !supported_ops.is_empty() {
+                let supported_ops: Vec<String> =
+                    supported_ops.iter().map(|op| format!("`{}`", op)).collect();
+                error = error.with_help(format!("use {}", join_or(&supported_ops)));
+            }
+            bail!(error);
+        }
+
+        if let Some(max) = ty.max_int() {
+            let out_of_range = match rhs {
+                Value::Int(val) => val > max,
+                Value::IntRange { to, .. } => to > max,
+                _ => false,
+            };
+            if out_of_range {
+                bail!(SpannedFilterError::new(
+                    FilterError::OutOfRange {
+                        field: name.clone(),
+                        ty: ty.to_string(),
+                        value: value.as_str().to_owned(),
+                    },
+                    span(value),
+                )
+                .with_help(format!("the maximum value of `{}` is {}", name, max)));
+            }
+        }
+
+        if let (BinOp::Re, Value::Text(text)) = (binop, &rhs) {
+            if let Err(error) = Regex::new(text) {
+                // The last line of a syntax error describes it; the others quote the pattern.
+                let error = error.to_string();
+                let reason = error.lines().last().unwrap_or_default();
+                bail!(SpannedFilterError::new(
+                    FilterError::InvalidRegex(reason.trim_start_matches("error: ").to_owned()),
+                    span(value),
+                ));
+            }
+        }
+        Ok(())
+    }
+
     fn parse_protocol(&self, pair: Pair<Rule>) -> ProtocolName {
         protocol!(pair.as_str())
     }
@@ -266,12 +405,60 @@ impl FilterParser {
     }
 }
 
-// A RawPattern is a conjunct of predicates to satisfy
-pub type RawPattern = Vec<Predicate>;
+/// Returns the byte range of `pair` in the filter string.
+fn span(pair: &Pair<Rule>) -> Range<usize> {
+    pair.as_span().start()..pair.as_span().end()
+}
 
-#[derive(Debug, Clone, PartialEq, Eq)]
-enum Node {
-    Predicate(Predicate),
-    Disjunct(Vec<Node>),
-    Conjunct(Vec<Node>),
+/// Converts a grammar error into a `SpannedFilterError` that lists the expected tokens.
+fn syntax_error(error: PestError<Rule>) -> SpannedFilterError {
+    let span = match error.location {
+        InputLocation::Pos(pos) => pos..pos,
+        InputLocation::Span((start, end)) => start..end,
+    };
+    let expected = match &error.variant {
+        ErrorVariant::ParsingError { positives, .. } => {
+            let mut expected: Vec<String> = vec![];
+            for rule in positives.iter() {
+                let description = match rule {
+                    Rule::expr | Rule::sub_expr | Rule::predicate | Rule::protocol => "a predicate",
+                    Rule::field | Rule::combined_field => "a field",
+                    Rule::bin_op => "a comparison operator",
+                    Rule::and_op => "`and`",
+                    Rule::or_op => "`or`",
+                    Rule::value
+                    | Rule::ipv4_lit
+                    | Rule::ipv6_lit
+                    | Rule::int_lit
+                    | Rule::int_range
+                    | Rule::text => "a value",
+                    Rule::EOI => "end of filter",
+                    _ => continue,
+                };
+                if !expected.iter().any(|e| e == description) {
+                    expected.push(description.to_owned());
+                }
+            }
+            if expected.is_empty() {
+                "a predicate".to_owned()
+            } else {
+                join_or(&expected)
+            }
+        }
+        ErrorVariant::CustomError { message } => message.to_owned(),
+    };
+    SpannedFilterError::new(FilterError::InvalidSyntax(expected), span)
+}
+
+/// Joins `items` as "a", "a or b", or "a, b, or c".
+fn join_or(items: &[String]) -> String {
+    match items {
+        [] => String::new(),
+        [item] => item.to_owned(),
+        [first, second] => format!("{} or {}", first, second),
+        [rest @ .., last] => format!("{}, or {}", rest.join(", "), last),
+    }
 }
+
+// A RawPattern is a conjunct of predicates to satisfy
+pub type RawPattern = Vec<Predicate>;
@@ -0,0 +1,9 @@
+//! Passive fingerprinting.
+//!
+//! Fingerprinting modules infer properties of connection endpoints from traffic that is already
+//! observed during connection tracking, without sending any packets.
+//!
+//! - [`p0f`]: operating system and network stack identification from TCP SYN and SYN/ACK packets,
+//!   using a [p0f v3](https://lcamtuf.coredump.cx/p0f3/) signature database.
+
+pub mod p0f;
@@ -0,0 +1,805 @@
+//! p0f-style passive OS fingerprinting.
+//!
+//! Identifies the operating system (or network stack) of connection endpoints from the TCP SYN
+//! sent by the originator and the SYN/ACK sent by the responder. Observed packets are matched
+//! against a signature database in [p0f v3 format](https://lcamtuf.coredump.cx/p0f3/README), such
+//! as the `p0f.fp` file distributed with p0f. Only the `[tcp:request]` and `[tcp:response]`
+//! sections are used; other sections are ignored.
+//!
+//! The database is loaded once at startup, either by setting `p0f_db` in the
+//! [fingerprint](crate::config::FingerprintConfig) section of the runtime configuration, or by
+//! calling [`load_database`] before the runtime is started. The database cannot be replaced once
+//! loaded. When no database is loaded, fingerprinting is disabled.
+//!
+//! ## Example
+//! ```toml
+//! [fingerprint]
+//!     p0f_db = "/etc/p0f/p0f.fp"
+//! ```
+
+use crate::memory::mbuf::Mbuf;
+use crate::protocols::packet::ethernet::Ethernet;
+use crate::protocols::packet::ipv4::Ipv4;
+use crate::protocols::packet::ipv6::Ipv6;
+use crate::protocols::packet::tcp::*;
+use crate::protocols::packet::Packet;
+
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::{Arc, OnceLock};
+
+use anyhow::{bail, Result};
+use serde::Serialize;
+use thiserror::Error;
+
+/// Maximum hop distance between the initial TTL and the observed TTL.
+const MAX_DIST: u8 = 35;
+
+// Quirks.
+const QUIRK_DF: u32 = 1 << 0;
+const QUIRK_NZ_ID: u32 = 1 << 1;
+const QUIRK_ZERO_ID: u32 = 1 << 2;
+const QUIRK_ECN: u32 = 1 << 3;
+const QUIRK_NZ_MBZ: u32 = 1 << 4;
+const QUIRK_FLOW: u32 = 1 << 5;
+const QUIRK_ZERO_SEQ: u32 = 1 << 6;
+const QUIRK_NZ_ACK: u32 = 1 << 7;
+const QUIRK_ZERO_ACK: u32 = 1 << 8;
+const QUIRK_NZ_URG: u32 = 1 << 9;
+const QUIRK_URG: u32 = 1 << 10;
+const QUIRK_PUSH: u32 = 1 << 11;
+const QUIRK_OPT_ZERO_TS1: u32 = 1 << 12;
+const QUIRK_OPT_NZ_TS2: u32 = 1 << 13;
+const QUIRK_OPT_EOL_NZ: u32 = 1 << 14;
+const QUIRK_OPT_EXWS: u32 = 1 << 15;
+const QUIRK_OPT_BAD: u32 = 1 << 16;
+
+/// Quirks that are tolerated in fuzzy matches, since they are commonly rewritten by middleboxes.
+const FUZZY_QUIRKS: u32 = QUIRK_DF | QUIRK_NZ_ID | QUIRK_ZERO_ID | QUIRK_ECN;
+
+/// Quirk names in p0f signature syntax.
+const QUIRK_NAMES: [(&str, u32); 17] = [
+    ("df", QUIRK_DF),
+    ("id+", QUIRK_NZ_ID),
+    ("id-", QUIRK_ZERO_ID),
+    ("ecn", QUIRK_ECN),
+    ("0+", QUIRK_NZ_MBZ),
+    ("flow", QUIRK_FLOW),
+    ("seq-", QUIRK_ZERO_SEQ),
+    ("ack+", QUIRK_NZ_ACK),
+    ("ack-", QUIRK_ZERO_ACK),
+    ("uptr+", QUIRK_NZ_URG),
+    ("urgf+", QUIRK_URG),
+    ("pushf+", QUIRK_PUSH),
+    ("ts1-", QUIRK_OPT_ZERO_TS1),
+    ("ts2+", QUIRK_OPT_NZ_TS2),
+    ("opt+", QUIRK_OPT_EOL_NZ),
+    ("exws", QUIRK_OPT_EXWS),
+    ("bad", QUIRK_OPT_BAD),
+];
+
+/// Signature database, set once at startup.
+static DATABASE: OnceLock<SignatureDb> = OnceLock::new();
+
+/// Loads the signature database at `path` and enables fingerprinting.
+pub fn load_database<P: AsRef<Path>>(path: P) -> Result<()> {
+    let db = SignatureDb::from_file(path)?;
+    set_database(db)
+}
+
+/// Sets the signature database and enables fingerprinting. Fails if a database is already set.
+pub fn set_database(db: SignatureDb) -> Result<()> {
+    let (nb_requests, nb_responses) = (db.requests.len(), db.responses.len());
+    if DATABASE.set(db).is_err() {
+        bail!("p0f signature database already loaded");
+    }
+    log::info!(
+        "Loaded {} p0f request and {} response signatures",
+        nb_requests,
+        nb_responses
+    );
+    Ok(())
+}
+
+/// Returns the loaded signature database, if any.
+#[inline]
+pub fn database() -> Option<&'static SignatureDb> {
+    DATABASE.get()
+}
+
+/// Returns `true` if a signature database has been loaded.
+#[inline]
+pub(crate) fn enabled() -> bool {
+    DATABASE.get().is_some()
+}
+
+/* --------------------------------------------------------------------------------- */
+
+/// An OS label.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Label {
+    /// `true` for generic signatures, `false` for specific signatures.
+    pub generic: bool,
+    /// OS class (e.g., `unix`, `win`), or `!` for user applications.
+    pub class: String,
+    /// OS or application name (e.g., `Linux`).
+    pub name: String,
+    /// Version or other descriptive information (e.g., `3.11 and newer`).
+    pub flavor: String,
+}
+
+impl FromStr for Label {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let fields: Vec<&str> = s.splitn(4, ':').collect();
+        if fields.len() != 4 {
+            bail!(SignatureError::InvalidLabel(s.to_owned()));
+        }
+        let generic = match fields[0] {
+            "g" => true,
+            "s" => false,
+            _ => bail!(SignatureError::InvalidLabel(s.to_owned())),
+        };
+        Ok(Label {
+            generic,
+            class: fields[1].to_owned(),
+            name: fields[2].to_owned(),
+            flavor: fields[3].to_owned(),
+        })
+    }
+}
+
+impl fmt::Display for Label {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.flavor.is_empty() {
+            write!(f, "{}", self.name)
+        } else {
+            write!(f, "{} {}", self.name, self.flavor)
+        }
+    }
+}
+
+/// Result of matching an observed fingerprint against the signature database.
+#[derive(Debug, Clone, Serialize)]
+pub struct OsMatch {
+    /// Matched OS label.
+    pub label: Label,
+    /// Estimated hop distance between the endpoint and the vantage point.
+    pub distance: u8,
+    /// `true` if the match tolerated TTL or quirk differences.
+    pub fuzzy: bool,
+}
+
+impl fmt::Display for OsMatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.label)?;
+        if self.label.generic {
+            write!(f, " (generic)")?;
+        }
+        if self.fuzzy {
+            write!(f, " (fuzzy)")?;
+        }
+        Ok(())
+    }
+}
+
+/* --------------------------------------------------------------------------------- */
+
+/// An element of the TCP option layout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OptionToken {
+    /// End of options list, followed by `n` bytes of padding.
+    Eol(usize),
+    Nop,
+    Mss,
+    Ws,
+    Sok,
+    Sack,
+    Ts,
+    /// Unrecognized option kind.
+    Unknown(u8),
+}
+
+impl FromStr for OptionToken {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s {
+            "nop" => OptionToken::Nop,
+            "mss" => OptionToken::Mss,
+            "ws" => OptionToken::Ws,
+            "sok" => OptionToken::Sok,
+            "sack" => OptionToken::Sack,
+            "ts" => OptionToken::Ts,
+            _ => {
+                if let Some(pad) = s.strip_prefix("eol+") {
+                    OptionToken::Eol(pad.parse()?)
+                } else if let Some(kind) = s.strip_prefix('?') {
+                    OptionToken::Unknown(kind.parse()?)
+                } else {
+                    bail!(SignatureError::InvalidField("olayout", s.to_owned()))
+                }
+            }
+        })
+    }
+}
+
+impl fmt::Display for OptionToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OptionToken::Eol(pad) => write!(f, "eol+{}", pad),
+            OptionToken::Nop => write!(f, "nop"),
+            OptionToken::Mss => write!(f, "mss"),
+            OptionToken::Ws => write!(f, "ws"),
+            OptionToken::Sok => write!(f, "sok"),
+            OptionToken::Sack => write!(f, "sack"),
+            OptionToken::Ts => write!(f, "ts"),
+            OptionToken::Unknown(kind) => write!(f, "?{}", kind),
+        }
+    }
+}
+
+/// Window size constraint of a signature.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum WindowSize {
+    Any,
+    Value(u16),
+    /// Multiple of the MSS.
+    Mss(u16),
+    /// Multiple of the MTU.
+    Mtu(u16),
+    /// Any multiple of the value.
+    Mod(u16),
+}
+
+/// A TCP signature from the database.
+#[derive(Debug, Clone)]
+pub struct Signature {
+    version: Option<u8>,
+    ittl: u8,
+    olen: u8,
+    mss: Option<u16>,
+    window: WindowSize,
+    wscale: Option<u8>,
+    olayout: Vec<OptionToken>,
+    quirks: u32,
+    payload: Option<bool>,
+}
+
+impl FromStr for Signature {
+    type Err = anyhow::Error;
+
+    /// Parses a signature of the form `ver:ittl:olen:mss:wsize,scale:olayout:quirks:pclass`.
+    fn from_str(s: &str) -> Result<Self> {
+        let fields: Vec<&str> = s.split(':').collect();
+        if fields.len() != 8 {
+            bail!(SignatureError::InvalidSignature(s.to_owned()));
+        }
+        let version = match fields[0] {
+            "*" => None,
+            "4" => Some(4),
+            "6" => Some(6),
+            v => bail!(SignatureError::InvalidField("ver", v.to_owned())),
+        };
+        // Initial TTL may be suffixed with a distance or a '-' for bad TTLs
+        let ittl = fields[1]
+            .split(['+', '-'])
+            .next()
+            .unwrap_or_default()
+            .parse()?;
+        let olen = fields[2].parse()?;
+        let mss = wildcard(fields[3])?;
+        let (wsize, scale) = match fields[4].split_once(',') {
+            Some(split) => split,
+            None => bail!(SignatureError::InvalidField("wsize", fields[4].to_owned())),
+        };
+        let window = if wsize == "*" {
+            WindowSize::Any
+        } else if let Some(n) = wsize.strip_prefix("mss*") {
+            WindowSize::Mss(n.parse()?)
+        } else if let Some(n) = wsize.strip_prefix("mtu*") {
+            WindowSize::Mtu(n.parse()?)
+        } else if let Some(n) = wsize.strip_prefix('%') {
+            WindowSize::Mod(n.parse()?)
+        } else {
+            WindowSize::Value(wsize.parse()?)
+        };
+        let wscale = wildcard(scale)?;
+        let olayout = fields[5]
+            .split(',')
+            .filter(|t| !t.is_empty())
+            .map(OptionToken::from_str)
+            .collect::<Result<Vec<_>>>()?;
+        let mut quirks = 0;
+        for quirk in fields[6].split(',').filter(|q| !q.is_empty()) {
+            match QUIRK_NAMES.iter().find(|(name, _)| *name == quirk) {
+                Some((_, bit)) => quirks |= bit,
+                None => bail!(SignatureError::InvalidField("quirks", quirk.to_owned())),
+            }
+        }
+        let payload = match fields[7] {
+            "*" => None,
+            "0" => Some(false),
+            "+" => Some(true),
+            p => bail!(SignatureError::InvalidField("pclass", p.to_owned())),
+        };
+        Ok(Signature {
+            version,
+            ittl,
+            olen,
+            mss,
+            window,
+            wscale,
+            olayout,
+            quirks,
+            payload,
+        })
+    }
+}
+
+/// Parses `*` as `None`, otherwise a value of type `T`.
+fn wildcard<T: FromStr>(s: &str) -> Result<Option<T>>
+where
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    if s == "*" {
+        Ok(None)
+    } else {
+        Ok(Some(s.parse()?))
+    }
+}
+
+impl Signature {
+    /// Returns `Some(fuzzy)` if `fp` matches the signature.
+    fn matches(&self, fp: &TcpFingerprint) -> Option<bool> {
+        let mut fuzzy = false;
+
+        if self.olayout != fp.olayout || self.olen != fp.olen {
+            return None;
+        }
+        if self.quirks != fp.quirks {
+            if (self.quirks ^ fp.quirks) & !FUZZY_QUIRKS != 0 {
+                return None;
+            }
+            fuzzy = true;
+        }
+        if self.version.is_some_and(|v| v != fp.version)
+            || self.mss.is_some_and(|mss| Some(mss) != fp.mss)
+            || self.wscale.is_some_and(|ws| Some(ws) != fp.wscale)
+            || self.payload.is_some_and(|p| p != fp.payload)
+        {
+            return None;
+        }
+
+        let window_ok = match self.window {
+            WindowSize::Any => true,
+            WindowSize::Value(win) => win == fp.window,
+            WindowSize::Mod(n) => n != 0 && fp.window % n == 0,
+            WindowSize::Mss(n) => fp
+                .mss
+                .is_some_and(|mss| u32::from(mss) * u32::from(n) == u32::from(fp.window)),
+            WindowSize::Mtu(n) => fp
+                .mtu()
+                .is_some_and(|mtu| u32::from(mtu) * u32::from(n) == u32::from(fp.window)),
+        };
+        if !window_ok {
+            return None;
+        }
+
+        if fp.ttl > self.ittl {
+            fuzzy = true;
+        } else if self.ittl - fp.ttl > MAX_DIST {
+            return None;
+        }
+        Some(fuzzy)
+    }
+}
+
+/* --------------------------------------------------------------------------------- */
+
+/// A p0f signature database.
+#[derive(Debug, Default, Clone)]
+pub struct SignatureDb {
+    /// Signatures of TCP SYN packets sent by connection originators.
+    requests: Vec<(Arc<Label>, Signature)>,
+    /// Signatures of TCP SYN/ACK packets sent by connection responders.
+    responses: Vec<(Arc<Label>, Signature)>,
+}
+
+impl SignatureDb {
+    /// Loads a signature database from a p0f-format file.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let contents = fs::read_to_string(path)?;
+        contents.parse()
+    }
+
+    /// Returns the best match for the SYN sent by a connection originator.
+    pub fn match_request(&self, fp: &TcpFingerprint) -> Option<OsMatch> {
+        Self::find_match(&self.requests, fp)
+    }
+
+    /// Returns the best match for the SYN/ACK sent by a connection responder.
+    pub fn match_response(&self, fp: &TcpFingerprint) -> Option<OsMatch> {
+        Self::find_match(&self.responses, fp)
+    }
+
+    /// Prefers exact specific matches, then exact generic matches, then fuzzy matches, in database
+    /// order.
+    fn find_match(sigs: &[(Arc<Label>, Signature)], fp: &TcpFingerprint) -> Option<OsMatch> {
+        let mut generic = None;
+        let mut fuzzy = None;
+        for (label, sig) in sigs.iter() {
+            match sig.matches(fp) {
+                Some(false) if !label.generic => {
+                    return Some(Self::to_match(label, sig, fp, false))
+                }
+                Some(false) => {
+                    generic.get_or_insert((label, sig));
+                }
+                Some(true) => {
+                    fuzzy.get_or_insert((label, sig));
+                }
+                None => (),
+            }
+        }
+        if let Some((label, sig)) = generic {
+            return Some(Self::to_match(label, sig, fp, false));
+        }
+        fuzzy.map(|(label, sig)| Self::to_match(label, sig, fp, true))
+    }
+
+    fn to_match(label: &Label, sig: &Signature, fp: &TcpFingerprint, fuzzy: bool) -> OsMatch {
+        OsMatch {
+            label: label.clone(),
+            distance: sig.ittl.saturating_sub(fp.ttl),
+            fuzzy,
+        }
+    }
+}
+
+impl FromStr for SignatureDb {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        #[derive(PartialEq)]
+        enum Section {
+            Request,
+            Response,
+            Other,
+        }
+
+        let mut db = SignatureDb::default();
+        let mut section = Section::Other;
+        let mut label: Option<Arc<Label>> = None;
+
+        for (lineno, line) in s.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with(';') {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                section = match name {
+                    "tcp:request" => Section::Request,
+                    "tcp:response" => Section::Response,
+                    _ => Section::Other,
+                };
+                label = None;
+                continue;
+            }
+            if section == Section::Other {
+                continue;
+            }
+            let (key, value) = match line.split_once('=') {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None => bail!(SignatureError::InvalidLine(lineno + 1, line.to_owned())),
+            };
+            match key {
+                "label" => label = Some(Arc::new(value.parse()?)),
+                "sig" => {
+                    let sig = match value.parse::<Signature>() {
+                        Ok(sig) => sig,
+                        Err(e) => bail!(SignatureError::InvalidLine(lineno + 1, e.to_string())),
+                    };
+                    let label = match &label {
+                        Some(label) => Arc::clone(label),
+                        None => bail!(SignatureError::InvalidLine(
+                            lineno + 1,
+                            "signature without label".to_owned()
+                        )),
+                    };
+                    match section {
+                        Section::Request => db.requests.push((label, sig)),
+                        Section::Response => db.responses.push((label, sig)),
+                        Section::Other => (),
+                    }
+                }
+                // `sys` and other keys are not used for TCP fingerprinting
+                _ => (),
+            }
+        }
+        Ok(db)
+    }
+}
+
+/* --------------------------------------------------------------------------------- */
+
+/// Fingerprint of an observed TCP SYN or SYN/ACK packet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TcpFingerprint {
+    /// IP version.
+    pub version: u8,
+    /// Observed TTL or hop limit.
+    pub ttl: u8,
+    /// Length of IPv4 options in bytes.
+    pub olen: u8,
+    /// Maximum segment size option.
+    pub mss: Option<u16>,
+    /// Window size.
+    pub window: u16,
+    /// Window scale option.
+    pub wscale: Option<u8>,
+    /// TCP option layout.
+    pub olayout: Vec<OptionToken>,
+    /// Bitmask of quirks.
+    quirks: u32,
+    /// `true` if the packet carries payload.
+    pub payload: bool,
+}
+
+impl TcpFingerprint {
+    /// Computes the fingerprint of the TCP segment in `mbuf`. Returns `None` if the packet is not
+    /// TCP over IPv4 or IPv6.
+    pub(crate) fn from_mbuf(mbuf: &Mbuf) -> Option<Self> {
+        let eth = mbuf.parse_to::<Ethernet>().ok()?;
+        if let Ok(ipv4) = eth.parse_to::<Ipv4>() {
+            let tcp = ipv4.parse_to::<Tcp>().ok()?;
+            let mut quirks = 0;
+            if ipv4.df() {
+                quirks |= QUIRK_DF;
+                if ipv4.identification() != 0 {
+                    quirks |= QUIRK_NZ_ID;
+                }
+            } else if ipv4.identification() == 0 {
+                quirks |= QUIRK_ZERO_ID;
+            }
+            if ipv4.ecn() != 0 {
+                quirks |= QUIRK_ECN;
+            }
+            if ipv4.rf() {
+                quirks |= QUIRK_NZ_MBZ;
+            }
+            // Headers shorter than the fixed IPv4 header (IHL < 5) are malformed
+            let olen = ipv4.header_len().checked_sub(20)?;
+            let payload =
+                (ipv4.total_length() as usize).saturating_sub(ipv4.header_len() + tcp.header_len());
+            Some(Self::new(
+                4,
+                ipv4.time_to_live(),
+                olen as u8,
+                &tcp,
+                quirks,
+                payload > 0,
+            ))
+        } else if let Ok(ipv6) = eth.parse_to::<Ipv6>() {
+            let tcp = ipv6.parse_to::<Tcp>().ok()?;
+            let mut quirks = 0;
+            if ipv6.flow_label() != 0 {
+                quirks |= QUIRK_FLOW;
+            }
+            if ipv6.ecn() != 0 {
+                quirks |= QUIRK_ECN;
+            }
+            let payload = (ipv6.payload_length() as usize).saturating_sub(tcp.header_len());
+            Some(Self::new(6, ipv6.hop_limit(), 0, &tcp, quirks, payload > 0))
+        } else {
+            None
+        }
+    }
+
+    fn new(version: u8, ttl: u8, olen: u8, tcp: &Tcp, ip_quirks: u32, payload: bool) -> Self {
+        let opts = tcp.options();
+        let mut quirks = ip_quirks;
+        let flags = tcp.flags();
+        if flags & (ECE | CWR) != 0 {
+            quirks |= QUIRK_ECN;
+        }
+        if tcp.seq_no() == 0 {
+            quirks |= QUIRK_ZERO_SEQ;
+        }
+        if flags & ACK != 0 {
+            if tcp.ack_no() == 0 {
+                quirks |= QUIRK_ZERO_ACK;
+            }
+        } else if tcp.ack_no() != 0 {
+            quirks |= QUIRK_NZ_ACK;
+        }
+        if flags & URG != 0 {
+            quirks |= QUIRK_URG;
+        } else if tcp.urgent_pointer() != 0 {
+            quirks |= QUIRK_NZ_URG;
+        }
+        if flags & PSH != 0 {
+            quirks |= QUIRK_PUSH;
+        }
+        if let Some((ts1, ts2)) = opts.timestamps {
+            if ts1 == 0 {
+                quirks |= QUIRK_OPT_ZERO_TS1;
+            }
+            if ts2 != 0 && flags & ACK == 0 {
+                quirks |= QUIRK_OPT_NZ_TS2;
+            }
+        }
+        if opts.eol_padding > 0 {
+            let raw = tcp.options_raw();
+            if raw[raw.len() - opts.eol_padding..].iter().any(|b| *b != 0) {
+                quirks |= QUIRK_OPT_EOL_NZ;
+            }
+        }
+        if opts.wscale.is_some_and(|ws| ws > 14) {
+            quirks |= QUIRK_OPT_EXWS;
+        }
+        if opts.malformed {
+            quirks |= QUIRK_OPT_BAD;
+        }
+
+        let olayout = opts
+            .layout
+            .iter()
+            .map(|kind| match *kind {
+                OPT_EOL => OptionToken::Eol(opts.eol_padding),
+                OPT_NOP => OptionToken::Nop,
+                OPT_MSS => OptionToken::Mss,
+                OPT_WSCALE => OptionToken::Ws,
+                OPT_SACK_PERMITTED => OptionToken::Sok,
+                OPT_SACK => OptionToken::Sack,
+                OPT_TIMESTAMPS => OptionToken::Ts,
+                kind => OptionToken::Unknown(kind),
+            })
+            .collect();
+
+        TcpFingerprint {
+            version,
+            ttl,
+            olen,
+            mss: opts.mss,
+            window: tcp.window(),
+            wscale: opts.wscale,
+            olayout,
+            quirks,
+            payload,
+        }
+    }
+
+    /// Estimated path MTU, derived from the MSS and IP version.
+    fn mtu(&self) -> Option<u16> {
+        let overhead = if self.version == 4 { 40 } else { 60 };
+        self.mss.map(|mss| mss.saturating_add(overhead))
+    }
+}
+
+impl fmt::Display for TcpFingerprint {
+    /// Formats the fingerprint as a p0f signature, using the observed TTL and raw window size.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let opt = |v: Option<String>| v.unwrap_or_else(|| "*".to_owned());
+        let olayout: Vec<String> = self.olayout.iter().map(|t| t.to_string()).collect();
+        let quirks: Vec<&str> = QUIRK_NAMES
+            .iter()
+            .filter(|(_, bit)| self.quirks & bit != 0)
+            .map(|(name, _)| *name)
+            .collect();
+        write!(
+            f,
+            "{}:{}:{}:{}:{},{}:{}:{}:{}",
+            self.version,
+            self.ttl,
+            self.olen,
+            opt(self.mss.map(|m| m.to_string())),
+            self.window,
+            opt(self.wscale.map(|w| w.to_string())),
+            olayout.join(","),
+            quirks.join(","),
+            if self.payload { "+" } else { "0" },
+        )
+    }
+}
+
+/* --------------------------------------------------------------------------------- */
+
+#[derive(Error, Debug)]
+pub enum SignatureError {
+    #[error("Invalid label: {0}")]
+    InvalidLabel(String),
+
+    #[error("Invalid signature: {0}")]
+    InvalidSignature(String),
+
+    #[error("Invalid signature field {0}: {1}")]
+    InvalidField(&'static str, String),
+
+    #[error("Invalid signature database at line {0}: {1}")]
+    InvalidLine(usize, String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DB: &str = "
+; comment
+[tcp:request]
+label = s:unix:Linux:3.11 and newer
+sig   = *:64:0:*:mss*20,10:mss,sok,ts,nop,ws:df,id+:0
+sig   = *:64:0:*:mss*20,7:mss,sok,ts,nop,ws:df,id+:0
+
+label = g:win:Windows:7 or 8
+sig   = *:128:0:*:8192,8:mss,nop,ws,nop,nop,sok:df,id+:0
+
+[tcp:response]
+label = s:unix:Linux:3.x
+sig   = *:64:0:*:mss*10,0:mss:df:0
+
+[http:request]
+label = s:!:nginx:1.x
+sys   = Linux
+";
+
+    fn linux_syn() -> TcpFingerprint {
+        TcpFingerprint {
+            version: 4,
+            ttl: 57,
+            olen: 0,
+            mss: Some(1460),
+            window: 29200,
+            wscale: Some(7),
+            olayout: vec![
+                OptionToken::Mss,
+                OptionToken::Sok,
+                OptionToken::Ts,
+                OptionToken::Nop,
+                OptionToken::Ws,
+            ],
+            quirks: QUIRK_DF | QUIRK_NZ_ID,
+            payload: false,
+        }
+    }
+
+    #[test]
+    fn core_p0f_parse_db() {
+        let db: SignatureDb = DB.parse().unwrap();
+        assert_eq!(db.requests.len(), 3);
+        assert_eq!(db.responses.len(), 1);
+        assert!(db.requests[2].0.generic);
+        assert_eq!(db.requests[0].0.to_string(), "Linux 3.11 and newer");
+        assert!("*:64:0:*:mss*20,10:mss,bogus:df:0"
+            .parse::<Signature>()
+            .is_err());
+    }
+
+    #[test]
+    fn core_p0f_match_request() {
+        let db: SignatureDb = DB.parse().unwrap();
+        let os = db.match_request(&linux_syn()).unwrap();
+        assert_eq!(os.label.name, "Linux");
+        assert_eq!(os.distance, 7);
+        assert!(!os.fuzzy);
+
+        let mut scrubbed = linux_syn();
+        scrubbed.quirks = 0;
+        assert!(db.match_request(&scrubbed).unwrap().fuzzy);
+
+        let mut unknown = linux_syn();
+        unknown.window = 1234;
+        assert!(db.match_request(&unknown).is_none());
+    }
+
+    #[test]
+    fn core_p0f_display() {
+        assert_eq!(
+            linux_syn().to_string(),
+            "4:57:0:1460:29200,7:mss,sok,ts,nop,ws:df,id+:0"
+        );
+    }
+}
@@ -0,0 +1,143 @@
+use super::rx_core::CoreStats;
+use super::CoreId;
+use crate::config::{AfPacketConfig, ConnTrackConfig};
+use crate::conntrack::{ConnTracker, TrackerConfig};
+use crate::dpdk;
+use crate::filter::Filter;
+use crate::memory::mbuf::Mbuf;
+use crate::port::af_packet::AfPacketSocket;
+use crate::port::mtu_to_max_frame_len;
+use crate::protocols::stream::ParserRegistry;
+use crate::subscription::*;
+
+use std::ffi::CString;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// How long to wait for packets before checking whether to stop (in milliseconds).
+const POLL_TIMEOUT_MS: i32 = 100;
+
+/// An AfPacketCore reads from its own AF_PACKET socket on a shared fanout group and reduces the
+/// stream of packets into a stream of higher-level network events to be processed by the user.
+pub(crate) struct AfPacketCore<'a, S>
+where
+    S: Subscribable,
+{
+    pub(crate) id: CoreId,
+    pub(crate) options: Arc<AfPacketConfig>,
+    pub(crate) fanout_group: u16,
+    pub(crate) mempool_name: String,
+    pub(crate) filter: Filter,
+    pub(crate) conntrack: ConnTrackConfig,
+    pub(crate) stats: Arc<CoreStats>,
+    pub(crate) subscription: Arc<Subscription<'a, S>>,
+    pub(crate) is_running: Arc<AtomicBool>,
+}
+
+impl<'a, S> AfPacketCore<'a, S>
+where
+    S: Subscribable,
+{
+    pub(crate) fn rx_loop(&self) {
+        log::info!(
+            "Launched AF_PACKET RX on core {}, reading {} (fanout group {})",
+            self.id,
+            self.options.interface,
+            self.fanout_group,
+        );
+
+        let mut socket = AfPacketSocket::open(
+            &self.options.interface,
+            self.fanout_group,
+            self.options.promiscuous,
+            self.options.ring_size,
+        )
+        .expect("Unable to open AF_PACKET socket");
+
+        let cname = CString::new(self.mempool_name.clone()).expect("Invalid CString conversion");
+        let mempool_raw = unsafe { dpdk::rte_mempool_lookup(cname.as_ptr()) };
+
+        let mut nb_pkts = 0;
+        let mut nb_bytes = 0;
+        let mut nb_discarded = 0;
+        let mut buf = Vec::with_capacity(mtu_to_max_frame_len(self.options.mtu as u32) as usize);
+
+        let config = TrackerConfig::from(&self.conntrack);
+        let registry = ParserRegistry::build::<S>(&self.filter).expect("Unable to build registry");
+        log::debug!("{:#?}", registry);
+        let mut conn_table =
+            ConnTracker::<S::Tracked>::new(config, registry, Arc::clone(&self.stats.conntrack));
+
+        while self.is_running.load(Ordering::Relaxed) {
+            let res = socket.recv(POLL_TIMEOUT_MS, |frame| {
+                let data = frame.bytes(&mut buf);
+                if !frame_fits(data.len(), self.options.mtu) {
+                    nb_discarded += 1;
+                    return;
+                }
+                match Mbuf::from_bytes(data, mempool_raw) {
+                    Ok(mbuf) => {
+                        nb_pkts += 1;
+                        nb_bytes += mbuf.data_len() as u64;
+                        S::process_packet(mbuf, &self.subscription, &mut conn_table);
+                    }
+                    Err(error) => {
+                        log::debug!("Core {} discarded packet: {}", self.id, error);
+                        nb_discarded += 1;
+                    }
+                }
+            });
+            if let Err(error) = res {
+                log::error!("Core {} AF_PACKET receive error: {}", self.id, error);
+                break;
+            }
+            self.stats.nb_pkts.store(nb_pkts, Ordering::Relaxed);
+            self.stats.nb_bytes.store(nb_bytes, Ordering::Relaxed);
+            self.stats
+                .nb_discarded
+                .store(nb_discarded, Ordering::Relaxed);
+            conn_table.check_inactive(&self.subscription);
+        }
+
+        // Deliver remaining data in table from unfinished connections
+        conn_table.drain(&self.subscription);
+        self.subscription.invoke_core_exit(self.id.raw());
+
+        let nb_kernel_dropped = socket.stats().map_or(0, |(_, dropped)| dropped);
+        log::info!(
+            "Core {} total recv from {}: {} pkts, {} bytes ({} discarded, {} dropped by kernel)",
+            self.id,
+            self.options.interface,
+            nb_pkts,
+            nb_bytes,
+            nb_discarded,
+            nb_kernel_dropped,
+        );
+        log::info!(
+            "Core {} conntrack: {}",
+            self.id,
+            self.stats.conntrack.snapshot()
+        );
+    }
+}
+
+/// Returns `true` if a frame of `len` bytes fits in an Mbuf sized for `mtu`. Frames include the
+/// Ethernet header and any VLAN tags, but not the CRC.
+fn frame_fits(len: usize, mtu: usize) -> bool {
+    len <= mtu_to_max_frame_len(mtu as u32) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn core_af_packet_frame_fits() {
+        // Full-sized untagged, VLAN-tagged, and QinQ frames
+        assert!(frame_fits(1514, 1500));
+        assert!(frame_fits(1518, 1500));
+        assert!(frame_fits(1522, 1500));
+        assert!(!frame_fits(1600, 1500));
+        assert!(frame_fits(9716, 9702));
+    }
+}
@@ -1,5 +1,8 @@
+pub(crate) mod af_packet_core;
 pub(crate) mod monitor;
-// pub(crate) mod ring;
+pub(crate) mod offline_core;
+pub(crate) mod prometheus;
+pub(crate) mod ring;
 pub(crate) mod rx_core;
 
 use crate::dpdk;
@@ -1,8 +1,13 @@
+use super::prometheus::{Exporter, MetricType, Metrics};
+use super::rx_core::CoreStats;
+use super::CoreId;
 use crate::config::RuntimeConfig;
+use crate::conntrack::stats::ConnTrackCounters;
 use crate::dpdk;
 use crate::port::{statistics::PortStats, Port, PortId, RxQueue, RxQueueType};
+use crate::subscription::CallbackStats;
 
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::ffi::CString;
 use std::fmt;
 use std::fs;
@@ -31,7 +36,10 @@ pub(crate) struct Monitor {
     duration: Option<Duration>,
     display: Option<Display>,
     logger: Option<Logger>,
+    prometheus: Option<Prometheus>,
     ports: BTreeMap<PortId, Vec<RxQueue>>,
+    cores: BTreeMap<CoreId, Arc<CoreStats>>,
+    callbacks: Arc<CallbackStats>,
     is_running: Arc<AtomicBool>,
 }
 
@@ -39,6 +47,8 @@ impl Monitor {
     pub(crate) fn new(
         config: &RuntimeConfig,
         ports: &BTreeMap<PortId, Port>,
+        cores: BTreeMap<CoreId, Arc<CoreStats>>,
+        callbacks: Arc<CallbackStats>,
         is_running: Arc<AtomicBool>,
     ) -> Self {
         let date = Local::now();
@@ -55,6 +65,7 @@ impl Monitor {
                     return Some(Display {
                         ticker: tick(Duration::from_millis(1000)),
                         throughput: display_cfg.throughput,
+                        conntrack: display_cfg.conntrack,
                         keywords: display_cfg.port_stats.clone(),
                     });
                 }
@@ -81,10 +92,13 @@ impl Monitor {
                         let wtr = Writer::from_path(&fname).expect("create portstat log");
                         port_wtrs.insert(*port_id, wtr);
                     }
+                    let fname = path.join("conntrack.csv");
+                    let conntrack_wtr = Writer::from_path(&fname).expect("create conntrack log");
                     return Some(Logger {
                         ticker: tick(Duration::from_millis(log_cfg.interval)),
                         path,
                         port_wtrs,
+                        conntrack_wtr,
                         keywords: log_cfg.port_stats.clone(),
                     });
                 }
@@ -92,6 +106,20 @@ impl Monitor {
             None
         })();
 
+        let prometheus = (|| {
+            if let Some(monitor_cfg) = &online_cfg.monitor {
+                if let Some(prometheus_cfg) = &monitor_cfg.prometheus {
+                    let exporter =
+                        Exporter::start(&prometheus_cfg.address).expect("start metrics endpoint");
+                    return Some(Prometheus {
+                        ticker: tick(Duration::from_millis(prometheus_cfg.interval)),
+                        exporter,
+                    });
+                }
+            }
+            None
+        })();
+
         let mut monitor_ports: BTreeMap<PortId, Vec<RxQueue>> = BTreeMap::new();
         for (port_id, port) in ports.iter() {
             monitor_ports.insert(*port_id, port.queue_map.keys().cloned().collect());
@@ -101,7 +129,10 @@ impl Monitor {
             duration,
             display,
             logger,
+            prometheus,
             ports: monitor_ports,
+            cores,
+            callbacks,
             is_running,
         }
     }
@@ -148,6 +179,9 @@ impl Monitor {
                                 AggRxStats::display_rates(curr_rx, prev_rx, nms);
                                 AggRxStats::display_dropped(curr_rx, init_rx);
                             }
+                            if display.conntrack {
+                                display.conntrack_stats(&self.cores);
+                            }
                             prev_rx = curr_rx;
                             prev_ts = curr_ts;
                         }
@@ -164,6 +198,16 @@ impl Monitor {
                         Ok(_) => (),
                         Err(error) => log::error!("Monitor log error: {}", error),
                     }
+                    match logger.log_conntrack(init_ts.elapsed(), &self.cores) {
+                        Ok(_) => (),
+                        Err(error) => log::error!("Monitor log error: {}", error),
+                    }
+                }
+            }
+
+            if let Some(prometheus) = &self.prometheus {
+                if prometheus.ticker.try_recv().is_ok() {
+                    prometheus.export(&self.ports, &self.cores, &self.callbacks);
                 }
             }
         }
@@ -184,10 +228,22 @@ impl Monitor {
 struct Display {
     ticker: Receiver<Instant>,
     throughput: bool,
+    conntrack: bool,
     keywords: Vec<String>,
 }
 
 impl Display {
+    /// Display per-core and total connection tracking counters
+    fn conntrack_stats(&self, cores: &BTreeMap<CoreId, Arc<CoreStats>>) {
+        let mut total = ConnTrackCounters::default();
+        for (core_id, stats) in cores.iter() {
+            let counters = stats.conntrack.snapshot();
+            println!("Core {} conntrack: {}", core_id, counters);
+            total += counters;
+        }
+        println!("Total conntrack: {}", total);
+    }
+
     /// Display mempool usage
     fn mempool_usage(&self, ports: &BTreeMap<PortId, Vec<RxQueue>>) {
         for name in ports.keys().map(|id| format!("mempool_{}", id.socket_id())) {
@@ -212,6 +268,7 @@ struct Logger {
     ticker: Receiver<Instant>,
     path: PathBuf,
     port_wtrs: HashMap<PortId, Writer<std::fs::File>>,
+    conntrack_wtr: Writer<std::fs::File>,
     keywords: Vec<String>,
 }
 
@@ -231,6 +288,44 @@ impl Logger {
             wtr.write_record(None::<&[u8]>)?;
             wtr.flush()?;
         }
+        self.conntrack_wtr.write_record([
+            "ts",
+            "core",
+            "table_size",
+            "created",
+            "expired",
+            "terminated",
+            "rejected_full",
+            "evicted",
+            "ooo_overflow",
+            "parser_dropped",
+        ])?;
+        self.conntrack_wtr.flush()?;
+        Ok(())
+    }
+
+    /// Logs per-core connection tracking counters.
+    fn log_conntrack(
+        &mut self,
+        elapsed: Duration,
+        cores: &BTreeMap<CoreId, Arc<CoreStats>>,
+    ) -> Result<()> {
+        for (core_id, stats) in cores.iter() {
+            let c = stats.conntrack.snapshot();
+            self.conntrack_wtr.write_record(&[
+                elapsed.as_millis().to_string(),
+                core_id.to_string(),
+                c.table_size.to_string(),
+                c.created.to_string(),
+                c.expired.to_string(),
+                c.terminated.to_string(),
+                c.rejected_full.to_string(),
+                c.evicted.to_string(),
+                c.ooo_overflow.to_string(),
+                c.parser_dropped.to_string(),
+            ])?;
+        }
+        self.conntrack_wtr.flush()?;
         Ok(())
     }
 
@@ -269,6 +364,159 @@ impl Logger {
     }
 }
 
+#[derive(Debug)]
+struct Prometheus {
+    ticker: Receiver<Instant>,
+    exporter: Exporter,
+}
+
+impl Prometheus {
+    /// Collects current statistics and publishes them to the metrics endpoint.
+    fn export(
+        &self,
+        ports: &BTreeMap<PortId, Vec<RxQueue>>,
+        cores: &BTreeMap<CoreId, Arc<CoreStats>>,
+        callbacks: &CallbackStats,
+    ) {
+        let mut metrics = Metrics::default();
+
+        metrics.family(
+            "retina_port_xstat",
+            MetricType::Unknown,
+            "Port extended statistic.",
+        );
+        for port_id in ports.keys() {
+            match PortStats::collect(*port_id) {
+                Ok(port_stats) => {
+                    let port = port_id.to_string();
+                    for (name, value) in port_stats.stats.iter() {
+                        metrics.sample(&[("port", port.as_str()), ("name", name.as_str())], *value);
+                    }
+                }
+                Err(error) => log::error!("Monitor metrics error: {}", error),
+            }
+        }
+
+        let mempools: Vec<_> = ports
+            .keys()
+            .map(|id| format!("mempool_{}", id.socket_id()))
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .map(|name| {
+                let cname = CString::new(name.clone()).expect("Invalid CString conversion");
+                let mempool_raw = unsafe { dpdk::rte_mempool_lookup(cname.as_ptr()) };
+                let avail_cnt = unsafe { dpdk::rte_mempool_avail_count(mempool_raw) };
+                let inuse_cnt = unsafe { dpdk::rte_mempool_in_use_count(mempool_raw) };
+                (name, avail_cnt, inuse_cnt)
+            })
+            .collect();
+        metrics.family(
+            "retina_mempool_available",
+            MetricType::Gauge,
+            "Number of available mbufs in the mempool.",
+        );
+        for (name, avail_cnt, _) in mempools.iter() {
+            metrics.sample(&[("mempool", name.as_str())], *avail_cnt as u64);
+        }
+        metrics.family(
+            "retina_mempool_in_use",
+            MetricType::Gauge,
+            "Number of mbufs in use from the mempool.",
+        );
+        for (name, _, inuse_cnt) in mempools.iter() {
+            metrics.sample(&[("mempool", name.as_str())], *inuse_cnt as u64);
+        }
+
+        let cores: Vec<_> = cores
+            .iter()
+            .map(|(core_id, stats)| (core_id.to_string(), stats))
+            .collect();
+        metrics.family(
+            "retina_core_rx_packets",
+            MetricType::Counter,
+            "Packets received by the core.",
+        );
+        for (core, stats) in cores.iter() {
+            metrics.sample(
+                &[("core", core.as_str())],
+                stats.nb_pkts.load(Ordering::Relaxed),
+            );
+        }
+        metrics.family(
+            "retina_core_rx_bytes",
+            MetricType::Counter,
+            "Bytes received by the core.",
+        );
+        for (core, stats) in cores.iter() {
+            metrics.sample(
+                &[("core", core.as_str())],
+                stats.nb_bytes.load(Ordering::Relaxed),
+            );
+        }
+        metrics.family(
+            "retina_core_rx_discarded",
+            MetricType::Counter,
+            "Packets discarded by the core before processing.",
+        );
+        for (core, stats) in cores.iter() {
+            metrics.sample(
+                &[("core", core.as_str())],
+                stats.nb_discarded.load(Ordering::Relaxed),
+            );
+        }
+
+        let conntrack: Vec<_> = cores
+            .iter()
+            .map(|(core, stats)| (core, stats.conntrack.snapshot()))
+            .collect();
+        metrics.family(
+            "retina_conntrack_table_size",
+            MetricType::Gauge,
+            "Number of connections in the connection table.",
+        );
+        for (core, c) in conntrack.iter() {
+            metrics.sample(&[("core", core.as_str())], c.table_size);
+        }
+        metrics.family(
+            "retina_conntrack_connections",
+            MetricType::Counter,
+            "Connection table events.",
+        );
+        for (core, c) in conntrack.iter() {
+            for (event, value) in [
+                ("created", c.created),
+                ("expired", c.expired),
+                ("terminated", c.terminated),
+                ("rejected_full", c.rejected_full),
+                ("evicted", c.evicted),
+                ("ooo_overflow", c.ooo_overflow),
+                ("parser_dropped", c.parser_dropped),
+            ] {
+                metrics.sample(&[("core", core.as_str()), ("event", event)], value);
+            }
+        }
+
+        metrics.family(
+            "retina_subscription_callbacks",
+            MetricType::Counter,
+            "Number of callback invocations.",
+        );
+        metrics.sample(&[("subscription", callbacks.name)], callbacks.nb_invoked());
+
+        metrics.family(
+            "retina_subscription_dropped",
+            MetricType::Counter,
+            "Number of subscribable objects dropped under callback offload backpressure.",
+        );
+        metrics.sample(
+            &[("subscription", callbacks.name)],
+            callbacks.nb_dropped.load(Ordering::Relaxed),
+        );
+
+        self.exporter.update(metrics);
+    }
+}
+
 /// Aggregate RX port statistics at time of collection
 #[derive(Debug, Default, Clone, Copy)]
 struct AggRxStats {
@@ -0,0 +1,88 @@
+use super::ring::Ring;
+use super::rx_core::CoreStats;
+use super::CoreId;
+use crate::config::ConnTrackConfig;
+use crate::conntrack::clock;
+use crate::conntrack::{ConnTracker, TrackerConfig};
+use crate::filter::Filter;
+use crate::protocols::stream::ParserRegistry;
+use crate::subscription::*;
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// An OfflineCore processes packets distributed to it by the offline reader through `ring`, and
+/// reduces them into a stream of higher-level network events to be processed by the user.
+pub(crate) struct OfflineCore<'a, S>
+where
+    S: Subscribable,
+{
+    pub(crate) id: CoreId,
+    pub(crate) ring: Ring,
+    pub(crate) filter: Filter,
+    pub(crate) conntrack: ConnTrackConfig,
+    pub(crate) stats: Arc<CoreStats>,
+    pub(crate) subscription: Arc<Subscription<'a, S>>,
+    /// Set by the reader once the whole trace has been enqueued.
+    pub(crate) is_done: Arc<AtomicBool>,
+}
+
+impl<'a, S> OfflineCore<'a, S>
+where
+    S: Subscribable,
+{
+    pub(crate) fn rx_loop(&self) {
+        log::info!("Launched offline worker on core {}", self.id);
+
+        let mut nb_pkts = 0;
+        let mut nb_bytes = 0;
+
+        let config = TrackerConfig::from(&self.conntrack);
+        let registry = ParserRegistry::build::<S>(&self.filter).expect("Unable to build registry");
+        log::debug!("{:#?}", registry);
+        let mut conn_table =
+            ConnTracker::<S::Tracked>::new(config, registry, Arc::clone(&self.stats.conntrack));
+
+        loop {
+            match self.ring.sc_dequeue() {
+                Some(mbuf) => {
+                    if let Some(ts) = mbuf.timestamp() {
+                        clock::set_trace_time(ts);
+                    }
+                    conn_table.check_inactive(&self.subscription);
+                    nb_pkts += 1;
+                    nb_bytes += mbuf.data_len() as u64;
+                    S::process_packet(mbuf, &self.subscription, &mut conn_table);
+                }
+                None => {
+                    // The packet clock may advance between packets when replaying in real time
+                    conn_table.check_inactive(&self.subscription);
+                    // Check the ring again after observing `is_done`, as the reader may have
+                    // enqueued its last packets in between.
+                    if self.is_done.load(Ordering::Acquire) && self.ring.is_empty() {
+                        break;
+                    }
+                    std::hint::spin_loop();
+                }
+            }
+        }
+        self.stats.nb_pkts.store(nb_pkts, Ordering::Relaxed);
+        self.stats.nb_bytes.store(nb_bytes, Ordering::Relaxed);
+
+        // Deliver remaining data in table
+        conn_table.drain(&self.subscription);
+        self.subscription.invoke_core_exit(self.id.raw());
+
+        log::info!(
+            "Core {} total processed: {} pkts, {} bytes",
+            self.id,
+            nb_pkts,
+            nb_bytes
+        );
+        log::info!(
+            "Core {} conntrack: {}",
+            self.id,
+            self.stats.conntrack.snapshot()
+        );
+    }
+}
@@ -0,0 +1,197 @@
+//! Prometheus/OpenMetrics exporter.
+//!
+//! Serves the most recent metrics snapshot rendered by the monitor over HTTP. Metrics are rendered
+//! on the main core, so DPDK statistics are never queried from the HTTP thread.
+
+use std::fmt::Write as _;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, RwLock};
+use std::thread;
+use std::time::Duration;
+
+use anyhow::Result;
+
+/// OpenMetrics text format content type.
+const CONTENT_TYPE: &str = "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
+/// Serves metrics on `GET /metrics`.
+#[derive(Debug)]
+pub(crate) struct Exporter {
+    body: Arc<RwLock<String>>,
+}
+
+impl Exporter {
+    /// Binds to `address` and starts serving metrics in a background thread.
+    pub(crate) fn start(address: &str) -> Result<Self> {
+        let listener = TcpListener::bind(address)?;
+        log::info!(
+            "Serving metrics on http://{}/metrics",
+            listener.local_addr()?
+        );
+        let body = Arc::new(RwLock::new(String::from("# EOF\n")));
+        let shared = Arc::clone(&body);
+        thread::Builder::new()
+            .name("retina-metrics".to_owned())
+            .spawn(move || {
+                for stream in listener.incoming() {
+                    match stream {
+                        Ok(stream) => {
+                            if let Err(error) = serve(stream, &shared) {
+                                log::debug!("Metrics request failed: {}", error);
+                            }
+                        }
+                        Err(error) => log::warn!("Metrics connection failed: {}", error),
+                    }
+                }
+            })?;
+        Ok(Exporter { body })
+    }
+
+    /// Replaces the served metrics.
+    pub(crate) fn update(&self, metrics: Metrics) {
+        *self.body.write().unwrap() = metrics.finish();
+    }
+}
+
+/// Responds to a single HTTP request.
+fn serve(mut stream: TcpStream, body: &RwLock<String>) -> Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // Discard headers
+    let mut line = String::new();
+    while reader.read_line(&mut line)? > 2 {
+        line.clear();
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let (method, path) = (parts.next(), parts.next());
+    if method == Some("GET") && matches!(path, Some("/metrics") | Some("/")) {
+        let body = body.read().unwrap();
+        write!(
+            stream,
+            "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            CONTENT_TYPE,
+            body.len(),
+            body
+        )?;
+    } else {
+        stream.write_all(
+            b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+        )?;
+    }
+    stream.flush()?;
+    Ok(())
+}
+
+/// Builds a metrics exposition in OpenMetrics text format.
+#[derive(Debug, Default)]
+pub(crate) struct Metrics {
+    text: String,
+    /// Sample name of the current metric family.
+    sample_name: String,
+}
+
+impl Metrics {
+    /// Starts a new metric family. Counter samples are suffixed with `_total`.
+    pub(crate) fn family(&mut self, name: &str, ty: MetricType, help: &str) {
+        let _ = writeln!(self.text, "# TYPE {} {}", name, ty.as_str());
+        let _ = writeln!(self.text, "# HELP {} {}", name, escape(help, false));
+        self.sample_name = match ty {
+            MetricType::Counter => format!("{}_total", name),
+            _ => name.to_owned(),
+        };
+    }
+
+    /// Adds a sample to the current metric family.
+    pub(crate) fn sample(&mut self, labels: &[(&str, &str)], value: u64) {
+        self.text.push_str(&self.sample_name);
+        if !labels.is_empty() {
+            self.text.push('{');
+            for (i, (key, val)) in labels.iter().enumerate() {
+                if i > 0 {
+                    self.text.push(',');
+                }
+                let _ = write!(self.text, "{}=\"{}\"", key, escape(val, true));
+            }
+            self.text.push('}');
+        }
+        let _ = writeln!(self.text, " {}", value);
+    }
+
+    /// Terminates the exposition.
+    fn finish(mut self) -> String {
+        self.text.push_str("# EOF\n");
+        self.text
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum MetricType {
+    Counter,
+    Gauge,
+    Unknown,
+}
+
+impl MetricType {
+    fn as_str(&self) -> &'static str {
+        match self {
+            MetricType::Counter => "counter",
+            MetricType::Gauge => "gauge",
+            MetricType::Unknown => "unknown",
+        }
+    }
+}
+
+/// Escapes backslashes and newlines, and double quotes in label values.
+fn escape(s: &str, quotes: bool) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '"' if quotes => escaped.push_str("\\\""),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn core_metrics_rendering() {
+        let mut metrics = Metrics::default();
+        metrics.family(
+            "retina_rx_packets",
+            MetricType::Counter,
+            "Packets received.\nPer \"core\" \\ queue.",
+        );
+        metrics.sample(&[("core", "1")], 10);
+        metrics.sample(&[("core", "2"), ("queue", "a\"b\\c\nd")], 20);
+        metrics.family("retina_table_size", MetricType::Gauge, "Table size.");
+        metrics.sample(&[], 3);
+        metrics.family("retina_port", MetricType::Unknown, "Port statistic.");
+        metrics.sample(&[("name", "rx_good")], 4);
+        assert_eq!(
+            metrics.finish(),
+            concat!(
+                "# TYPE retina_rx_packets counter\n",
+                "# HELP retina_rx_packets Packets received.\\nPer \"core\" \\\\ queue.\n",
+                "retina_rx_packets_total{core=\"1\"} 10\n",
+                "retina_rx_packets_total{core=\"2\",queue=\"a\\\"b\\\\c\\nd\"} 20\n",
+                "# TYPE retina_table_size gauge\n",
+                "# HELP retina_table_size Table size.\n",
+                "retina_table_size 3\n",
+                "# TYPE retina_port unknown\n",
+                "# HELP retina_port Port statistic.\n",
+                "retina_port{name=\"rx_good\"} 4\n",
+                "# EOF\n",
+            )
+        );
+    }
+}
@@ -1,5 +1,6 @@
 use crate::dpdk;
 use crate::lcore::SocketId;
+use crate::memory::mbuf::Mbuf;
 
 use anyhow::{bail, Result};
 use std::ffi::{CStr, CString};
@@ -7,7 +8,7 @@ use std::fmt;
 use std::os::raw::{c_int, c_uint, c_void};
 use std::ptr::NonNull;
 
-/// A wrapper around a ring structure
+/// A wrapper around a ring of mbufs
 pub(crate) struct Ring {
     raw: NonNull<dpdk::rte_ring>,
 }
@@ -16,13 +17,12 @@ unsafe impl Send for Ring {}
 unsafe impl Sync for Ring {}
 
 impl Ring {
-    pub(crate) fn new(size: u32, socket_id: SocketId, flags: u32) -> Result<Self> {
-        if size == 0 || ((size & size - 1) != 0) {
+    pub(crate) fn new(name: &str, size: u32, socket_id: SocketId, flags: u32) -> Result<Self> {
+        if !size.is_power_of_two() {
             bail!("Ring size must be a power of 2");
         }
 
-        let name = format!("event_ring_{}", socket_id);
-        let cname = CString::new(name.clone()).unwrap();
+        let cname = CString::new(name).unwrap();
         log::debug!("Ring size: {}", size);
         let ring = unsafe {
             dpdk::rte_ring_create(
@@ -45,11 +45,6 @@ impl Ring {
         unsafe { self.raw.as_ref() }
     }
 
-    /// For DPDK functions
-    pub(crate) fn raw_mut(&mut self) -> &mut dpdk::rte_ring {
-        unsafe { self.raw.as_mut() }
-    }
-
     /// Returns the name of the Ring
     pub(crate) fn name(&self) -> &str {
         let cstr = unsafe { CStr::from_ptr(self.raw().name.as_ptr()) };
@@ -86,63 +81,35 @@ impl Ring {
         unsafe { dpdk::rte_ring_free_count(self.raw()) as u32 }
     }
 
-    /// Enqueue object of type `T` onto the ring (multi-producers safe)
-    pub(crate) fn mp_enqueue<T>(&mut self, obj: T) -> Result<()> {
-        let ret = unsafe {
-            dpdk::rte_ring_mp_enqueue(
-                self.raw_mut(),
-                Box::into_raw(Box::new(obj)) as *mut _ as *mut c_void,
-            )
-        };
+    /// Enqueues `mbuf` onto the ring, or returns it if the ring is full (NOT multi-producers
+    /// safe)
+    pub(crate) fn sp_enqueue(&self, mbuf: Mbuf) -> Result<(), Mbuf> {
+        let raw = mbuf.into_raw();
+        let ret = unsafe { dpdk::rte_ring_sp_enqueue(self.raw.as_ptr(), raw as *mut c_void) };
         if ret != 0 {
-            bail!("Failed to enqueue object");
+            return Err(Mbuf::new_unchecked(raw));
         }
         Ok(())
     }
 
-    /// Enqueue object of type `T` onto the ring (NOT multi-producers safe)
-    pub(crate) fn sp_enqueue<T>(&mut self, obj: T) -> Result<()> {
-        let ret = unsafe {
-            dpdk::rte_ring_sp_enqueue(
-                self.raw_mut(),
-                Box::into_raw(Box::new(obj)) as *mut _ as *mut c_void,
-            )
-        };
-        if ret != 0 {
-            bail!("Failed to enqueue object");
-        }
-        Ok(())
-    }
-
-    /// Dequeue one object from the ring and return it as `T` (multi-consumers safe)
-    pub(crate) fn mc_dequeue<T>(&mut self) -> Result<T> {
-        let mut ptr: *mut c_void = std::ptr::null_mut();
-        let tmp: *mut *mut c_void = &mut ptr;
-        let ret = unsafe { dpdk::rte_ring_mc_dequeue(self.raw_mut(), tmp) };
-        if ret != 0 {
-            bail!("Nothing to dequeue");
-        }
-        let obj = unsafe { Box::from_raw(ptr as *mut T) };
-        Ok(*obj)
-    }
-
-    /// Dequeue one object from the ring and return it as `T` (NOT multi-consumers safe)
-    pub(crate) fn sc_dequeue<T>(&mut self) -> Result<T> {
+    /// Dequeues one mbuf from the ring, or returns `None` if the ring is empty (NOT
+    /// multi-consumers safe)
+    pub(crate) fn sc_dequeue(&self) -> Option<Mbuf> {
         let mut ptr: *mut c_void = std::ptr::null_mut();
-        let tmp: *mut *mut c_void = &mut ptr;
-        let ret = unsafe { dpdk::rte_ring_sc_dequeue(self.raw_mut(), tmp) };
+        let ret = unsafe { dpdk::rte_ring_sc_dequeue(self.raw.as_ptr(), &mut ptr) };
         if ret != 0 {
-            bail!("Nothing to dequeue");
+            return None;
         }
-        let obj = unsafe { Box::from_raw(ptr as *mut T) };
-        Ok(*obj)
+        Some(Mbuf::new_unchecked(ptr as *mut dpdk::rte_mbuf))
     }
 }
 
 impl Drop for Ring {
     fn drop(&mut self) {
         log::info!("Dropping {}.", self.name());
-        unsafe { dpdk::rte_ring_free(self.raw_mut()) };
+        // Free mbufs still in the ring
+        while self.sc_dequeue().is_some() {}
+        unsafe { dpdk::rte_ring_free(self.raw.as_ptr()) };
     }
 }
 
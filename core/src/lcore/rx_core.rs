@@ -1,5 +1,6 @@
 use super::CoreId;
 use crate::config::ConnTrackConfig;
+use crate::conntrack::stats::ConnTrackStats;
 use crate::conntrack::{ConnTracker, TrackerConfig};
 use crate::dpdk;
 use crate::filter::Filter;
@@ -8,11 +9,24 @@ use crate::port::{RxQueue, RxQueueType};
 use crate::protocols::stream::ParserRegistry;
 use crate::subscription::*;
 
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
 
 use itertools::Itertools;
 
+/// Live counters of an RX core, shared with the monitor.
+#[derive(Debug, Default)]
+pub(crate) struct CoreStats {
+    /// Number of packets received.
+    pub(crate) nb_pkts: AtomicU64,
+    /// Number of bytes received.
+    pub(crate) nb_bytes: AtomicU64,
+    /// Number of packets discarded before processing (e.g., frames too large for an Mbuf).
+    pub(crate) nb_discarded: AtomicU64,
+    /// Connection tracking counters.
+    pub(crate) conntrack: Arc<ConnTrackStats>,
+}
+
 /// A RxCore polls from `rxqueues` and reduces the stream of packets into
 /// a stream of higher-level network events to be processed by the user.
 pub(crate) struct RxCore<'a, S>
@@ -23,6 +37,7 @@ where
     pub(crate) rxqueues: Vec<RxQueue>,
     pub(crate) filter: Filter,
     pub(crate) conntrack: ConnTrackConfig,
+    pub(crate) stats: Arc<CoreStats>,
     pub(crate) subscription: Arc<Subscription<'a, S>>,
     pub(crate) is_running: Arc<AtomicBool>,
 }
@@ -44,6 +59,7 @@ where
             rxqueues,
             filter,
             conntrack,
+            stats: Arc::new(CoreStats::default()),
             subscription,
             is_running,
         }
@@ -89,7 +105,8 @@ where
         let config = TrackerConfig::from(&self.conntrack);
         let registry = ParserRegistry::build::<S>(&self.filter).expect("Unable to build registry");
         log::debug!("{:#?}", registry);
-        let mut conn_table = ConnTracker::<S::Tracked>::new(config, registry);
+        let mut conn_table =
+            ConnTracker::<S::Tracked>::new(config, registry, Arc::clone(&self.stats.conntrack));
 
         while self.is_running.load(Ordering::Relaxed) {
             for rxqueue in self.rxqueues.iter() {
@@ -109,11 +126,14 @@ where
                     S::process_packet(mbuf, &self.subscription, &mut conn_table);
                 }
             }
+            self.stats.nb_pkts.store(nb_pkts, Ordering::Relaxed);
+            self.stats.nb_bytes.store(nb_bytes, Ordering::Relaxed);
             conn_table.check_inactive(&self.subscription);
         }
 
         // // Deliver remaining data in table from unfinished connections
         conn_table.drain(&self.subscription);
+        self.subscription.invoke_core_exit(self.id.raw());
 
         log::info!(
             "Core {} total recv from {}: {} pkts, {} bytes",
@@ -122,6 +142,11 @@ where
             nb_pkts,
             nb_bytes
         );
+        log::info!(
+            "Core {} conntrack: {}",
+            self.id,
+            self.stats.conntrack.snapshot()
+        );
     }
 
     fn rx_sink(&self) {
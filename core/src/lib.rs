@@ -53,17 +53,20 @@ mod dpdk;
 // However, module functions should be opaque to users, so documentation is hidden by default.
 #[doc(hidden)]
 pub mod filter;
+pub mod fingerprint;
 mod lcore;
 mod memory;
 mod port;
 pub mod protocols;
 mod runtime;
+#[cfg(feature = "sink")]
+pub mod sink;
 pub mod subscription;
 pub mod utils;
 
 pub use self::conntrack::conn_id::{ConnId, FiveTuple};
 pub use self::memory::mbuf::Mbuf;
-pub use self::runtime::Runtime;
+pub use self::runtime::{Runtime, StopHandle};
 
 pub use dpdk::rte_lcore_id;
 pub use dpdk::rte_rdtsc;
@@ -16,10 +16,14 @@ use crate::protocols::packet::{Packet, PacketHeader, PacketParseError};
 use std::fmt;
 use std::ptr::NonNull;
 use std::slice;
+use std::sync::OnceLock;
 
 use anyhow::{bail, Result};
 use thiserror::Error;
 
+/// Offset and `ol_flags` bit of the timestamp dynamic field, once registered.
+static TIMESTAMP_FIELD: OnceLock<(usize, u64)> = OnceLock::new();
+
 #[derive(Clone)]
 /// A packet buffer.
 ///
@@ -45,6 +49,20 @@ impl Mbuf {
         })
     }
 
+    /// Returns a new handle to the same packet buffer and increments its reference count. The
+    /// buffer is freed once every handle has been dropped. Handles must not modify the packet data.
+    pub(crate) fn share(&self) -> Mbuf {
+        unsafe { dpdk::rte_mbuf_refcnt_update(self.raw.as_ptr(), 1) };
+        Mbuf { raw: self.raw }
+    }
+
+    /// Consumes the Mbuf and returns the rte_mbuf raw pointer without freeing it.
+    pub(crate) fn into_raw(self) -> *mut dpdk::rte_mbuf {
+        let raw = self.raw.as_ptr();
+        std::mem::forget(self);
+        raw
+    }
+
     /// Creates a new Mbuf from a byte slice.
     pub(crate) fn from_bytes(data: &[u8], mp: *mut dpdk::rte_mempool) -> Result<Mbuf> {
         let mut mbuf = unsafe { Mbuf::new(dpdk::rte_pktmbuf_alloc(mp))? };
@@ -72,10 +90,44 @@ impl Mbuf {
         unsafe { self.raw.as_mut() }
     }
 
-    /// Returns the UNIX timestamp of the packet.
-    #[allow(dead_code)]
-    pub(crate) fn timestamp(&self) -> usize {
-        unimplemented!();
+    /// Registers the timestamp dynamic field. Must be called before timestamps can be set.
+    pub(crate) fn register_timestamp() -> Result<()> {
+        if TIMESTAMP_FIELD.get().is_some() {
+            return Ok(());
+        }
+        let mut offset = 0;
+        let mut flag = 0;
+        let ret = unsafe { dpdk::rte_mbuf_dyn_rx_timestamp_register(&mut offset, &mut flag) };
+        if ret < 0 {
+            bail!(MbufError::TimestampUnavailable);
+        }
+        TIMESTAMP_FIELD.get_or_init(|| (offset as usize, flag));
+        Ok(())
+    }
+
+    /// Returns the capture timestamp of the packet (in nanoseconds since the UNIX epoch), if it
+    /// was set by Retina.
+    pub(crate) fn timestamp(&self) -> Option<u64> {
+        let (offset, flag) = *TIMESTAMP_FIELD.get()?;
+        if self.raw().ol_flags & flag == 0 {
+            return None;
+        }
+        unsafe {
+            let field = (self.raw.as_ptr() as *const u8).add(offset) as *const u64;
+            Some(field.read_unaligned())
+        }
+    }
+
+    /// Sets the capture timestamp of the packet (in nanoseconds since the UNIX epoch). Does
+    /// nothing if the timestamp field is not registered.
+    pub(crate) fn set_timestamp(&mut self, ts: u64) {
+        if let Some(&(offset, flag)) = TIMESTAMP_FIELD.get() {
+            unsafe {
+                let field = (self.raw.as_ptr() as *mut u8).add(offset) as *mut u64;
+                field.write_unaligned(ts);
+            }
+            self.raw_mut().ol_flags |= flag;
+        }
     }
 
     /// Returns the length of the data in the Mbuf.
@@ -213,4 +265,7 @@ pub(crate) enum MbufError {
 
     #[error("Data write exceeds Mbuf segment buffer")]
     WritePastBuffer,
+
+    #[error("Unable to register Mbuf timestamp field")]
+    TimestampUnavailable,
 }
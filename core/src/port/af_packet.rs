@@ -0,0 +1,386 @@
+//! AF_PACKET sockets.
+//!
+//! Reads packets from a regular Linux network interface through a memory-mapped TPACKET_V3
+//! receive ring. Multiple sockets on the same interface join a common fanout group, so that the
+//! kernel load balances packets across them using a symmetric flow hash.
+
+use std::cmp;
+use std::ffi::CString;
+use std::io;
+use std::mem;
+use std::os::raw::{c_int, c_void};
+use std::ptr;
+use std::slice;
+use std::sync::atomic::{fence, Ordering};
+
+use anyhow::{bail, Result};
+use thiserror::Error;
+
+// Linux packet socket options and ring constants (linux/if_packet.h)
+const PACKET_ADD_MEMBERSHIP: c_int = 1;
+const PACKET_RX_RING: c_int = 5;
+const PACKET_STATISTICS: c_int = 6;
+const PACKET_VERSION: c_int = 10;
+const PACKET_FANOUT: c_int = 18;
+const PACKET_MR_PROMISC: u16 = 1;
+const PACKET_FANOUT_HASH: u32 = 0;
+const PACKET_FANOUT_FLAG_DEFRAG: u32 = 0x8000;
+const TPACKET_V3: c_int = 2;
+const TP_STATUS_KERNEL: u32 = 0;
+const TP_STATUS_USER: u32 = 1 << 0;
+const TP_STATUS_VLAN_VALID: u32 = 1 << 4;
+const TP_STATUS_VLAN_TPID_VALID: u32 = 1 << 6;
+
+/// Size of a ring block. Must be a multiple of the page size.
+const BLOCK_SIZE: usize = 1 << 20;
+/// Nominal frame size. Unused by TPACKET_V3 except for ring validation.
+const FRAME_SIZE: usize = 1 << 11;
+/// Timeout after which the kernel retires a partially filled block (in milliseconds).
+const BLOCK_TIMEOUT_MS: u32 = 10;
+
+const ETHER_ADDRS_LEN: usize = 12;
+const ETHERTYPE_VLAN: u16 = 0x8100;
+
+#[allow(dead_code)]
+#[repr(C)]
+struct TpacketReq3 {
+    tp_block_size: u32,
+    tp_block_nr: u32,
+    tp_frame_size: u32,
+    tp_frame_nr: u32,
+    tp_retire_blk_tov: u32,
+    tp_sizeof_priv: u32,
+    tp_feature_req_word: u32,
+}
+
+/// Block descriptor (`struct tpacket_block_desc` with a `struct tpacket_hdr_v1` header).
+#[allow(dead_code)]
+#[repr(C)]
+struct TpacketBlockDesc {
+    version: u32,
+    offset_to_priv: u32,
+    block_status: u32,
+    num_pkts: u32,
+    offset_to_first_pkt: u32,
+    blk_len: u32,
+    seq_num: u64,
+    ts_first_pkt: [u32; 2],
+    ts_last_pkt: [u32; 2],
+}
+
+#[allow(dead_code)]
+#[repr(C)]
+struct Tpacket3Hdr {
+    tp_next_offset: u32,
+    tp_sec: u32,
+    tp_nsec: u32,
+    tp_snaplen: u32,
+    tp_len: u32,
+    tp_status: u32,
+    tp_mac: u16,
+    tp_net: u16,
+    tp_rxhash: u32,
+    tp_vlan_tci: u32,
+    tp_vlan_tpid: u16,
+    tp_padding: u16,
+}
+
+#[allow(dead_code)]
+#[repr(C)]
+struct PacketMreq {
+    mr_ifindex: c_int,
+    mr_type: u16,
+    mr_alen: u16,
+    mr_address: [u8; 8],
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct TpacketStatsV3 {
+    tp_packets: u32,
+    tp_drops: u32,
+    tp_freeze_q_cnt: u32,
+}
+
+/// An AF_PACKET socket bound to a single interface.
+pub(crate) struct AfPacketSocket {
+    fd: c_int,
+    ring: *mut u8,
+    nb_blocks: usize,
+    next_block: usize,
+}
+
+impl AfPacketSocket {
+    /// Opens a socket on `interface` with a receive ring of approximately `ring_size` bytes, and
+    /// joins fanout group `fanout_group`.
+    pub(crate) fn open(
+        interface: &str,
+        fanout_group: u16,
+        promiscuous: bool,
+        ring_size: usize,
+    ) -> Result<Self> {
+        let ifname = CString::new(interface)?;
+        let ifindex = unsafe { libc::if_nametoindex(ifname.as_ptr()) };
+        if ifindex == 0 {
+            bail!(AfPacketError::Interface(
+                interface.to_owned(),
+                io::Error::last_os_error()
+            ));
+        }
+
+        let protocol = (libc::ETH_P_ALL as u16).to_be();
+        let fd = unsafe { libc::socket(libc::AF_PACKET, libc::SOCK_RAW, protocol as c_int) };
+        if fd < 0 {
+            bail!(AfPacketError::Syscall("socket", io::Error::last_os_error()));
+        }
+        // Closes the socket on error
+        let mut socket = AfPacketSocket {
+            fd,
+            ring: ptr::null_mut(),
+            nb_blocks: 0,
+            next_block: 0,
+        };
+
+        socket.set_option(PACKET_VERSION, &TPACKET_V3, "PACKET_VERSION")?;
+        let nb_blocks = cmp::max(ring_size / BLOCK_SIZE, 1);
+        let req = TpacketReq3 {
+            tp_block_size: BLOCK_SIZE as u32,
+            tp_block_nr: nb_blocks as u32,
+            tp_frame_size: FRAME_SIZE as u32,
+            tp_frame_nr: (BLOCK_SIZE / FRAME_SIZE * nb_blocks) as u32,
+            tp_retire_blk_tov: BLOCK_TIMEOUT_MS,
+            tp_sizeof_priv: 0,
+            tp_feature_req_word: 0,
+        };
+        socket.set_option(PACKET_RX_RING, &req, "PACKET_RX_RING")?;
+
+        let ring = unsafe {
+            libc::mmap(
+                ptr::null_mut(),
+                BLOCK_SIZE * nb_blocks,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_SHARED,
+                fd,
+                0,
+            )
+        };
+        if ring == libc::MAP_FAILED {
+            bail!(AfPacketError::Syscall("mmap", io::Error::last_os_error()));
+        }
+        socket.ring = ring as *mut u8;
+        socket.nb_blocks = nb_blocks;
+
+        let mut addr: libc::sockaddr_ll = unsafe { mem::zeroed() };
+        addr.sll_family = libc::AF_PACKET as u16;
+        addr.sll_protocol = protocol;
+        addr.sll_ifindex = ifindex as c_int;
+        let ret = unsafe {
+            libc::bind(
+                fd,
+                &addr as *const libc::sockaddr_ll as *const libc::sockaddr,
+                mem::size_of::<libc::sockaddr_ll>() as libc::socklen_t,
+            )
+        };
+        if ret < 0 {
+            bail!(AfPacketError::Syscall("bind", io::Error::last_os_error()));
+        }
+
+        if promiscuous {
+            let mreq = PacketMreq {
+                mr_ifindex: ifindex as c_int,
+                mr_type: PACKET_MR_PROMISC,
+                mr_alen: 0,
+                mr_address: [0; 8],
+            };
+            socket.set_option(PACKET_ADD_MEMBERSHIP, &mreq, "PACKET_ADD_MEMBERSHIP")?;
+        }
+
+        let fanout = fanout_group as u32 | ((PACKET_FANOUT_HASH | PACKET_FANOUT_FLAG_DEFRAG) << 16);
+        socket.set_option(PACKET_FANOUT, &fanout, "PACKET_FANOUT")?;
+        Ok(socket)
+    }
+
+    /// Invokes `f` on each frame of the next filled ring block, waiting up to `timeout`
+    /// milliseconds for one. Returns the number of frames read.
+    pub(crate) fn recv<F>(&mut self, timeout: c_int, mut f: F) -> Result<usize>
+    where
+        F: FnMut(Frame),
+    {
+        let block = unsafe { self.ring.add(self.next_block * BLOCK_SIZE) } as *mut TpacketBlockDesc;
+        if !Self::is_ready(block) {
+            let mut pfd = libc::pollfd {
+                fd: self.fd,
+                events: libc::POLLIN | libc::POLLERR,
+                revents: 0,
+            };
+            let ret = unsafe { libc::poll(&mut pfd, 1, timeout) };
+            if ret < 0 {
+                let error = io::Error::last_os_error();
+                if error.kind() == io::ErrorKind::Interrupted {
+                    return Ok(0);
+                }
+                bail!(AfPacketError::Syscall("poll", error));
+            }
+            if !Self::is_ready(block) {
+                return Ok(0);
+            }
+        }
+
+        let (nb_pkts, mut offset) = unsafe {
+            (
+                (*block).num_pkts as usize,
+                (*block).offset_to_first_pkt as usize,
+            )
+        };
+        for _ in 0..nb_pkts {
+            let hdr = unsafe { &*((block as *const u8).add(offset) as *const Tpacket3Hdr) };
+            let data = unsafe {
+                slice::from_raw_parts(
+                    (hdr as *const Tpacket3Hdr as *const u8).add(hdr.tp_mac as usize),
+                    hdr.tp_snaplen as usize,
+                )
+            };
+            let vlan = if hdr.tp_status & TP_STATUS_VLAN_VALID != 0 {
+                let tpid = if hdr.tp_status & TP_STATUS_VLAN_TPID_VALID != 0 {
+                    hdr.tp_vlan_tpid
+                } else {
+                    ETHERTYPE_VLAN
+                };
+                Some((tpid, hdr.tp_vlan_tci as u16))
+            } else {
+                None
+            };
+            f(Frame { data, vlan });
+            offset += hdr.tp_next_offset as usize;
+        }
+
+        // Return block to the kernel
+        fence(Ordering::Release);
+        unsafe { ptr::write_volatile(ptr::addr_of_mut!((*block).block_status), TP_STATUS_KERNEL) };
+        self.next_block = (self.next_block + 1) % self.nb_blocks;
+        Ok(nb_pkts)
+    }
+
+    /// Returns the number of packets received and dropped by the kernel since the last call.
+    pub(crate) fn stats(&self) -> Result<(u64, u64)> {
+        let mut stats = TpacketStatsV3::default();
+        let mut len = mem::size_of::<TpacketStatsV3>() as libc::socklen_t;
+        let ret = unsafe {
+            libc::getsockopt(
+                self.fd,
+                libc::SOL_PACKET,
+                PACKET_STATISTICS,
+                &mut stats as *mut TpacketStatsV3 as *mut c_void,
+                &mut len,
+            )
+        };
+        if ret < 0 {
+            bail!(AfPacketError::Syscall(
+                "PACKET_STATISTICS",
+                io::Error::last_os_error()
+            ));
+        }
+        Ok((stats.tp_packets as u64, stats.tp_drops as u64))
+    }
+
+    fn is_ready(block: *const TpacketBlockDesc) -> bool {
+        let status = unsafe { ptr::read_volatile(ptr::addr_of!((*block).block_status)) };
+        fence(Ordering::Acquire);
+        status & TP_STATUS_USER != 0
+    }
+
+    fn set_option<T>(&self, option: c_int, value: &T, name: &'static str) -> Result<()> {
+        let ret = unsafe {
+            libc::setsockopt(
+                self.fd,
+                libc::SOL_PACKET,
+                option,
+                value as *const T as *const c_void,
+                mem::size_of::<T>() as libc::socklen_t,
+            )
+        };
+        if ret < 0 {
+            bail!(AfPacketError::Syscall(name, io::Error::last_os_error()));
+        }
+        Ok(())
+    }
+}
+
+impl Drop for AfPacketSocket {
+    fn drop(&mut self) {
+        unsafe {
+            if !self.ring.is_null() {
+                libc::munmap(self.ring as *mut c_void, BLOCK_SIZE * self.nb_blocks);
+            }
+            libc::close(self.fd);
+        }
+    }
+}
+
+/// A frame read from the receive ring.
+#[derive(Debug)]
+pub(crate) struct Frame<'a> {
+    /// Frame data, starting at the Ethernet header.
+    data: &'a [u8],
+    /// VLAN tag (TPID, TCI) stripped by the kernel, if any.
+    vlan: Option<(u16, u16)>,
+}
+
+impl<'a> Frame<'a> {
+    /// Returns the frame as seen on the wire, re-inserting the VLAN tag into `buf` if the kernel
+    /// stripped it.
+    pub(crate) fn bytes<'b>(&self, buf: &'b mut Vec<u8>) -> &'b [u8]
+    where
+        'a: 'b,
+    {
+        match self.vlan {
+            Some((tpid, tci)) if self.data.len() >= ETHER_ADDRS_LEN => {
+                buf.clear();
+                buf.extend_from_slice(&self.data[..ETHER_ADDRS_LEN]);
+                buf.extend_from_slice(&tpid.to_be_bytes());
+                buf.extend_from_slice(&tci.to_be_bytes());
+                buf.extend_from_slice(&self.data[ETHER_ADDRS_LEN..]);
+                buf
+            }
+            _ => self.data,
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub(crate) enum AfPacketError {
+    #[error("Invalid interface {0}: {1}")]
+    Interface(String, io::Error),
+
+    #[error("AF_PACKET {0} failed: {1}")]
+    Syscall(&'static str, io::Error),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn core_af_packet_vlan_reinsert() {
+        let data = [
+            0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0x08, 0x00,
+        ];
+        let mut buf = vec![];
+
+        let frame = Frame {
+            data: &data,
+            vlan: None,
+        };
+        assert_eq!(frame.bytes(&mut buf), &data);
+
+        let frame = Frame {
+            data: &data,
+            vlan: Some((ETHERTYPE_VLAN, 0x0064)),
+        };
+        let bytes = frame.bytes(&mut buf);
+        assert_eq!(bytes.len(), data.len() + 4);
+        assert_eq!(&bytes[..12], &data[..12]);
+        assert_eq!(&bytes[12..16], &[0x81, 0x00, 0x00, 0x64]);
+        assert_eq!(&bytes[16..], &[0x08, 0x00]);
+    }
+}
@@ -1,5 +1,7 @@
+pub(crate) mod af_packet;
 #[allow(dead_code)]
 mod info;
+pub(crate) mod rss;
 pub(crate) mod statistics;
 
 use crate::config::PortMap;
@@ -0,0 +1,211 @@
+//! ARP packet.
+
+use crate::memory::mbuf::Mbuf;
+use crate::protocols::packet::{Packet, PacketHeader, PacketParseError};
+use crate::utils::types::*;
+
+use std::net::Ipv4Addr;
+
+use anyhow::{bail, Result};
+use pnet::datalink::MacAddr;
+
+/// ARP EtherType
+const ARP_PROTOCOL: usize = 0x0806;
+/// Hardware type: Ethernet
+const ARP_HTYPE_ETHERNET: u16 = 1;
+/// Protocol type: IPv4
+const ARP_PTYPE_IPV4: u16 = 0x0800;
+/// Header length of ARP for IPv4 over Ethernet
+const ARP_HEADER_LEN: usize = 28;
+
+/// Operation: request
+pub const ARP_OP_REQUEST: u16 = 1;
+/// Operation: reply
+pub const ARP_OP_REPLY: u16 = 2;
+
+/// An ARP packet.
+///
+/// Only ARP for IPv4 over Ethernet (hardware type 1, protocol type 0x0800) is supported. Packets
+/// with other hardware or protocol address formats fail to parse.
+#[derive(Debug)]
+pub struct Arp<'a> {
+    /// Fixed header.
+    header: ArpHeader,
+    /// Offset to `header` from the start of `mbuf`.
+    offset: usize,
+    /// Packet buffer.
+    mbuf: &'a Mbuf,
+}
+
+impl<'a> Arp<'a> {
+    /// Returns the hardware type.
+    #[inline]
+    pub fn hw_type(&self) -> u16 {
+        self.header.hw_type.into()
+    }
+
+    /// Returns the protocol type.
+    #[inline]
+    pub fn proto_type(&self) -> u16 {
+        self.header.proto_type.into()
+    }
+
+    /// Returns the hardware address length in bytes.
+    #[inline]
+    pub fn hw_len(&self) -> u8 {
+        self.header.hw_len
+    }
+
+    /// Returns the protocol address length in bytes.
+    #[inline]
+    pub fn proto_len(&self) -> u8 {
+        self.header.proto_len
+    }
+
+    /// Returns the operation (`1` for request, `2` for reply).
+    #[inline]
+    pub fn op(&self) -> u16 {
+        self.header.op.into()
+    }
+
+    /// Returns the sender hardware address.
+    #[inline]
+    pub fn sender_hw_addr(&self) -> MacAddr {
+        self.header.sender_hw_addr
+    }
+
+    /// Returns the sender protocol address.
+    #[inline]
+    pub fn sender_proto_addr(&self) -> Ipv4Addr {
+        self.header.sender_proto_addr
+    }
+
+    /// Returns the target hardware address.
+    #[inline]
+    pub fn target_hw_addr(&self) -> MacAddr {
+        self.header.target_hw_addr
+    }
+
+    /// Returns the target protocol address.
+    #[inline]
+    pub fn target_proto_addr(&self) -> Ipv4Addr {
+        self.header.target_proto_addr
+    }
+
+    /// Returns `true` if the packet is a gratuitous ARP (sender and target protocol addresses are
+    /// the same).
+    #[inline]
+    pub fn is_gratuitous(&self) -> bool {
+        self.sender_proto_addr() == self.target_proto_addr()
+    }
+}
+
+impl<'a> Packet<'a> for Arp<'a> {
+    fn mbuf(&self) -> &Mbuf {
+        self.mbuf
+    }
+
+    fn header_len(&self) -> usize {
+        self.header.length()
+    }
+
+    fn next_header_offset(&self) -> usize {
+        self.offset + self.header_len()
+    }
+
+    fn next_header(&self) -> Option<usize> {
+        None
+    }
+
+    fn parse_from(outer: &'a impl Packet<'a>) -> Result<Self>
+    where
+        Self: Sized,
+    {
+        let offset = outer.next_header_offset();
+        if let Ok(header) = outer.mbuf().get_data(offset) {
+            match outer.next_header() {
+                Some(ARP_PROTOCOL) => {
+                    let header: ArpHeader = unsafe { *header };
+                    if !header.is_ipv4_over_ethernet() {
+                        bail!(PacketParseError::InvalidProtocol);
+                    }
+                    Ok(Arp {
+                        header,
+                        offset,
+                        mbuf: outer.mbuf(),
+                    })
+                }
+                _ => bail!(PacketParseError::InvalidProtocol),
+            }
+        } else {
+            bail!(PacketParseError::InvalidRead)
+        }
+    }
+}
+
+/// Fixed portion of an ARP header for IPv4 over Ethernet.
+#[derive(Debug, Clone, Copy)]
+#[repr(C, packed)]
+struct ArpHeader {
+    hw_type: u16be,
+    proto_type: u16be,
+    hw_len: u8,
+    proto_len: u8,
+    op: u16be,
+    sender_hw_addr: MacAddr,
+    sender_proto_addr: Ipv4Addr,
+    target_hw_addr: MacAddr,
+    target_proto_addr: Ipv4Addr,
+}
+
+impl ArpHeader {
+    /// Returns `true` if the header describes ARP for IPv4 over Ethernet.
+    fn is_ipv4_over_ethernet(&self) -> bool {
+        u16::from(self.hw_type) == ARP_HTYPE_ETHERNET
+            && u16::from(self.proto_type) == ARP_PTYPE_IPV4
+            && self.hw_len == 6
+            && self.proto_len == 4
+    }
+}
+
+impl PacketHeader for ArpHeader {
+    /// Header length measured in bytes.
+    fn length(&self) -> usize {
+        ARP_HEADER_LEN
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(bytes: &[u8]) -> ArpHeader {
+        assert!(bytes.len() >= ARP_HEADER_LEN);
+        unsafe { std::ptr::read_unaligned(bytes.as_ptr() as *const ArpHeader) }
+    }
+
+    #[test]
+    fn core_arp_header() {
+        let mut packet = vec![0x00, 0x01, 0x08, 0x00, 6, 4, 0x00, 0x01];
+        packet.extend_from_slice(&[0x02, 0, 0, 0, 0, 1, 10, 0, 0, 1]);
+        packet.extend_from_slice(&[0, 0, 0, 0, 0, 0, 10, 0, 0, 2]);
+        // Ethernet padding
+        packet.extend_from_slice(&[0; 18]);
+
+        let arp = header(&packet);
+        assert!(arp.is_ipv4_over_ethernet());
+        assert_eq!(u16::from(arp.op), ARP_OP_REQUEST);
+        assert_eq!(arp.sender_hw_addr, MacAddr::new(0x02, 0, 0, 0, 0, 1));
+        assert_eq!({ arp.sender_proto_addr }, Ipv4Addr::new(10, 0, 0, 1));
+        assert_eq!({ arp.target_proto_addr }, Ipv4Addr::new(10, 0, 0, 2));
+        assert_eq!(arp.length(), ARP_HEADER_LEN);
+
+        // ARP for IPv6 addresses is not supported
+        packet[2..4].copy_from_slice(&[0x86, 0xdd]);
+        assert!(!header(&packet).is_ipv4_over_ethernet());
+        // Non-Ethernet hardware address length
+        packet[2..4].copy_from_slice(&[0x08, 0x00]);
+        packet[4] = 8;
+        assert!(!header(&packet).is_ipv4_over_ethernet());
+    }
+}
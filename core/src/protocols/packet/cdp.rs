@@ -0,0 +1,270 @@
+//! CDP packet.
+
+use crate::memory::mbuf::Mbuf;
+use crate::protocols::packet::{Packet, PacketHeader, PacketParseError};
+use crate::utils::types::*;
+
+use anyhow::{bail, Result};
+
+/// Largest value of the EtherType field interpreted as an IEEE 802.3 payload length
+const MAX_8023_LENGTH: usize = 1500;
+/// LLC SNAP DSAP/SSAP
+const LLC_SNAP_SAP: u8 = 0xAA;
+/// LLC unnumbered information control field
+const LLC_UI: u8 = 0x03;
+/// Cisco organizationally unique identifier
+const CISCO_OUI: [u8; 3] = [0x00, 0x00, 0x0C];
+/// CDP SNAP protocol ID
+const CDP_PROTOCOL: u16 = 0x2000;
+/// Size of the LLC/SNAP header and fixed CDP header
+const CDP_HEADER_LEN: usize = 12;
+/// Size of a TLV header (16-bit type, 16-bit length)
+const CDP_TLV_HEADER_LEN: usize = 4;
+
+// CDP TLV types.
+const TLV_DEVICE_ID: u16 = 0x0001;
+const TLV_PORT_ID: u16 = 0x0003;
+const TLV_SOFTWARE_VERSION: u16 = 0x0005;
+const TLV_PLATFORM: u16 = 0x0006;
+const TLV_NATIVE_VLAN: u16 = 0x000A;
+
+/// A Cisco Discovery Protocol packet.
+///
+/// CDP is carried in IEEE 802.3 frames with an LLC/SNAP header instead of an EtherType. The
+/// fixed header is followed by a sequence of type-length-value (TLV) structures, which are parsed
+/// on access.
+#[derive(Debug)]
+pub struct Cdp<'a> {
+    /// Fixed header, including LLC/SNAP.
+    header: CdpHeader,
+    /// Offset to `header` from the start of `mbuf`.
+    offset: usize,
+    /// IEEE 802.3 payload length, measured from `offset`.
+    length: usize,
+    /// Packet buffer.
+    mbuf: &'a Mbuf,
+}
+
+impl<'a> Cdp<'a> {
+    /// Returns the CDP version.
+    #[inline]
+    pub fn version(&self) -> u8 {
+        self.header.version
+    }
+
+    /// Returns the time to live in seconds.
+    #[inline]
+    pub fn ttl(&self) -> u8 {
+        self.header.ttl
+    }
+
+    /// Returns the checksum.
+    #[inline]
+    pub fn checksum(&self) -> u16 {
+        self.header.checksum.into()
+    }
+
+    /// Returns the device ID, or an empty string if not present.
+    pub fn device_id(&self) -> String {
+        self.text(TLV_DEVICE_ID)
+    }
+
+    /// Returns the port ID, or an empty string if not present.
+    pub fn port_id(&self) -> String {
+        self.text(TLV_PORT_ID)
+    }
+
+    /// Returns the software version, or an empty string if not present.
+    pub fn software_version(&self) -> String {
+        self.text(TLV_SOFTWARE_VERSION)
+    }
+
+    /// Returns the hardware platform, or an empty string if not present.
+    pub fn platform(&self) -> String {
+        self.text(TLV_PLATFORM)
+    }
+
+    /// Returns the native VLAN of the sending port, or `0` if not present.
+    pub fn native_vlan(&self) -> u16 {
+        match self.tlv(TLV_NATIVE_VLAN) {
+            Some(value) if value.len() >= 2 => u16::from_be_bytes([value[0], value[1]]),
+            _ => 0,
+        }
+    }
+
+    /// Returns the value of the first TLV of type `tlv_type`, if present.
+    pub fn tlv(&self, tlv_type: u16) -> Option<&[u8]> {
+        self.tlvs().find(|(t, _)| *t == tlv_type).map(|(_, v)| v)
+    }
+
+    /// Returns an iterator over the `(type, value)` pairs of each TLV. TLVs are bounded by the
+    /// IEEE 802.3 length, so Ethernet padding is not decoded.
+    pub fn tlvs(&self) -> CdpTlvs<'_> {
+        let offset = self.next_header_offset();
+        let end = std::cmp::min(self.offset + self.length, self.mbuf.data_len());
+        let data = self
+            .mbuf
+            .get_data_slice(offset, end.saturating_sub(offset))
+            .unwrap_or_default();
+        CdpTlvs { data }
+    }
+
+    fn text(&self, tlv_type: u16) -> String {
+        self.tlv(tlv_type)
+            .map(|value| String::from_utf8_lossy(value).into_owned())
+            .unwrap_or_default()
+    }
+}
+
+impl<'a> Packet<'a> for Cdp<'a> {
+    fn mbuf(&self) -> &Mbuf {
+        self.mbuf
+    }
+
+    fn header_len(&self) -> usize {
+        self.header.length()
+    }
+
+    fn next_header_offset(&self) -> usize {
+        self.offset + self.header_len()
+    }
+
+    fn next_header(&self) -> Option<usize> {
+        None
+    }
+
+    fn parse_from(outer: &'a impl Packet<'a>) -> Result<Self>
+    where
+        Self: Sized,
+    {
+        let offset = outer.next_header_offset();
+        if let Ok(header) = outer.mbuf().get_data(offset) {
+            match outer.next_header() {
+                Some(length) if length <= MAX_8023_LENGTH => {
+                    let header: CdpHeader = unsafe { *header };
+                    if !header.is_cdp() {
+                        bail!(PacketParseError::InvalidProtocol);
+                    }
+                    Ok(Cdp {
+                        header,
+                        offset,
+                        length,
+                        mbuf: outer.mbuf(),
+                    })
+                }
+                _ => bail!(PacketParseError::InvalidProtocol),
+            }
+        } else {
+            bail!(PacketParseError::InvalidRead)
+        }
+    }
+}
+
+/// Iterator over the TLVs of a CDP packet.
+#[derive(Debug)]
+pub struct CdpTlvs<'b> {
+    data: &'b [u8],
+}
+
+impl<'b> Iterator for CdpTlvs<'b> {
+    type Item = (u16, &'b [u8]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.data.len() < CDP_TLV_HEADER_LEN {
+            return None;
+        }
+        let tlv_type = u16::from_be_bytes([self.data[0], self.data[1]]);
+        // TLV length includes the type and length fields
+        let length = u16::from_be_bytes([self.data[2], self.data[3]]) as usize;
+        if length < CDP_TLV_HEADER_LEN || length > self.data.len() {
+            self.data = &[];
+            return None;
+        }
+        let value = &self.data[CDP_TLV_HEADER_LEN..length];
+        self.data = &self.data[length..];
+        Some((tlv_type, value))
+    }
+}
+
+/// LLC/SNAP header followed by the fixed portion of a CDP header.
+#[derive(Debug, Clone, Copy)]
+#[repr(C, packed)]
+struct CdpHeader {
+    dsap: u8,
+    ssap: u8,
+    control: u8,
+    oui: [u8; 3],
+    pid: u16be,
+    version: u8,
+    ttl: u8,
+    checksum: u16be,
+}
+
+impl CdpHeader {
+    /// Returns `true` if the LLC/SNAP header identifies a CDP packet.
+    fn is_cdp(&self) -> bool {
+        self.dsap == LLC_SNAP_SAP
+            && self.ssap == LLC_SNAP_SAP
+            && self.control == LLC_UI
+            && self.oui == CISCO_OUI
+            && u16::from(self.pid) == CDP_PROTOCOL
+    }
+}
+
+impl PacketHeader for CdpHeader {
+    /// Header length measured in bytes. Equivalent to the offset of the first TLV.
+    fn length(&self) -> usize {
+        CDP_HEADER_LEN
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn core_cdp_tlvs() {
+        let mut packet = vec![0xaa, 0xaa, 0x03, 0x00, 0x00, 0x0c, 0x20, 0x00];
+        // Version 2, TTL 180, checksum
+        packet.extend_from_slice(&[0x02, 0xb4, 0x12, 0x34]);
+        // Device ID
+        packet.extend_from_slice(&[0x00, 0x01, 0x00, 0x06, b's', b'w']);
+        // Native VLAN: 10
+        packet.extend_from_slice(&[0x00, 0x0a, 0x00, 0x06, 0x00, 0x0a]);
+        let length = packet.len();
+        // Ethernet padding
+        packet.extend_from_slice(&[0x00, 0x05, 0x00, 0x08, 0, 0, 0, 0]);
+
+        let header: CdpHeader = unsafe { std::ptr::read_unaligned(packet.as_ptr() as *const _) };
+        assert!(header.is_cdp());
+        assert_eq!(header.version, 2);
+        assert_eq!(header.ttl, 180);
+        assert_eq!(u16::from(header.checksum), 0x1234);
+
+        let tlvs = CdpTlvs {
+            data: &packet[CDP_HEADER_LEN..length],
+        };
+        assert_eq!(
+            tlvs.collect::<Vec<_>>(),
+            vec![
+                (TLV_DEVICE_ID, &b"sw"[..]),
+                (TLV_NATIVE_VLAN, &[0x00, 0x0a][..])
+            ]
+        );
+
+        // Malformed TLV lengths
+        let tlvs = CdpTlvs {
+            data: &[0x00, 0x01, 0x00, 0x02, 0x00, 0x03, 0x00, 0x04],
+        };
+        assert_eq!(tlvs.count(), 0);
+        let tlvs = CdpTlvs {
+            data: &[0x00, 0x01, 0x00, 0x06, b's'],
+        };
+        assert_eq!(tlvs.count(), 0);
+
+        // Other SNAP protocol
+        packet[7] = 0x01;
+        let header: CdpHeader = unsafe { std::ptr::read_unaligned(packet.as_ptr() as *const _) };
+        assert!(!header.is_cdp());
+    }
+}
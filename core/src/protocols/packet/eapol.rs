@@ -0,0 +1,249 @@
+//! 802.1X EAPOL packet.
+
+use crate::memory::mbuf::Mbuf;
+use crate::protocols::packet::{Packet, PacketHeader, PacketParseError};
+use crate::utils::types::*;
+
+use anyhow::{bail, Result};
+
+/// EAPOL EtherType
+const EAPOL_PROTOCOL: usize = 0x888E;
+/// Size of the EAPOL header
+const EAPOL_HEADER_LEN: usize = 4;
+
+// EAPOL packet types.
+pub const EAPOL_EAP_PACKET: u8 = 0;
+pub const EAPOL_START: u8 = 1;
+pub const EAPOL_LOGOFF: u8 = 2;
+pub const EAPOL_KEY: u8 = 3;
+
+// EAP codes that carry a Type field.
+const EAP_REQUEST: u8 = 1;
+const EAP_RESPONSE: u8 = 2;
+
+/// An 802.1X EAP over LAN (EAPOL) packet.
+///
+/// For EAP-Packet frames, the encapsulated EAP header fields are available through the `eap_*`
+/// accessors. For EAPOL-Key frames, the key descriptor type is available through
+/// `key_descriptor_type`. Accessors for fields that are not present in the packet return `0`.
+#[derive(Debug)]
+pub struct Eapol<'a> {
+    /// Fixed header.
+    header: EapolHeader,
+    /// Offset to `header` from the start of `mbuf`.
+    offset: usize,
+    /// Packet buffer.
+    mbuf: &'a Mbuf,
+}
+
+impl<'a> Eapol<'a> {
+    /// Returns the protocol version.
+    #[inline]
+    pub fn version(&self) -> u8 {
+        self.header.version
+    }
+
+    /// Returns the packet type.
+    #[inline]
+    pub fn packet_type(&self) -> u8 {
+        self.header.packet_type
+    }
+
+    /// Returns the length of the packet body in bytes.
+    #[inline]
+    pub fn body_length(&self) -> u16 {
+        self.header.body_length.into()
+    }
+
+    /// Returns the EAP code (`1` Request, `2` Response, `3` Success, `4` Failure).
+    pub fn eap_code(&self) -> u8 {
+        self.body().eap_code()
+    }
+
+    /// Returns the EAP identifier.
+    pub fn eap_identifier(&self) -> u8 {
+        self.body().eap_identifier()
+    }
+
+    /// Returns the EAP length, including the EAP header.
+    pub fn eap_length(&self) -> u16 {
+        self.body().eap_length()
+    }
+
+    /// Returns the EAP method type of a Request or Response.
+    pub fn eap_type(&self) -> u8 {
+        self.body().eap_type()
+    }
+
+    /// Returns the key descriptor type of an EAPOL-Key frame.
+    pub fn key_descriptor_type(&self) -> u8 {
+        self.body().key_descriptor_type()
+    }
+
+    /// Returns the packet body, bounded by the body length and the packet buffer.
+    fn body(&self) -> EapolBody<'_> {
+        let offset = self.next_header_offset();
+        let len = std::cmp::min(
+            self.body_length() as usize,
+            self.mbuf.data_len().saturating_sub(offset),
+        );
+        EapolBody {
+            packet_type: self.packet_type(),
+            data: self.mbuf.get_data_slice(offset, len).unwrap_or_default(),
+        }
+    }
+}
+
+impl<'a> Packet<'a> for Eapol<'a> {
+    fn mbuf(&self) -> &Mbuf {
+        self.mbuf
+    }
+
+    fn header_len(&self) -> usize {
+        self.header.length()
+    }
+
+    fn next_header_offset(&self) -> usize {
+        self.offset + self.header_len()
+    }
+
+    fn next_header(&self) -> Option<usize> {
+        None
+    }
+
+    fn parse_from(outer: &'a impl Packet<'a>) -> Result<Self>
+    where
+        Self: Sized,
+    {
+        let offset = outer.next_header_offset();
+        if let Ok(header) = outer.mbuf().get_data(offset) {
+            match outer.next_header() {
+                Some(EAPOL_PROTOCOL) => Ok(Eapol {
+                    header: unsafe { *header },
+                    offset,
+                    mbuf: outer.mbuf(),
+                }),
+                _ => bail!(PacketParseError::InvalidProtocol),
+            }
+        } else {
+            bail!(PacketParseError::InvalidRead)
+        }
+    }
+}
+
+/// Body of an EAPOL packet.
+#[derive(Debug)]
+struct EapolBody<'b> {
+    packet_type: u8,
+    data: &'b [u8],
+}
+
+impl<'b> EapolBody<'b> {
+    fn eap_code(&self) -> u8 {
+        self.eap_byte(0)
+    }
+
+    fn eap_identifier(&self) -> u8 {
+        self.eap_byte(1)
+    }
+
+    fn eap_length(&self) -> u16 {
+        match (self.packet_type, self.data) {
+            (EAPOL_EAP_PACKET, [_, _, hi, lo, ..]) => u16::from_be_bytes([*hi, *lo]),
+            _ => 0,
+        }
+    }
+
+    fn eap_type(&self) -> u8 {
+        match self.eap_code() {
+            EAP_REQUEST | EAP_RESPONSE => self.eap_byte(4),
+            _ => 0,
+        }
+    }
+
+    fn key_descriptor_type(&self) -> u8 {
+        if self.packet_type != EAPOL_KEY {
+            return 0;
+        }
+        self.byte(0)
+    }
+
+    fn eap_byte(&self, idx: usize) -> u8 {
+        if self.packet_type != EAPOL_EAP_PACKET {
+            return 0;
+        }
+        self.byte(idx)
+    }
+
+    fn byte(&self, idx: usize) -> u8 {
+        self.data.get(idx).copied().unwrap_or(0)
+    }
+}
+
+/// EAPOL header.
+#[derive(Debug, Clone, Copy)]
+#[repr(C, packed)]
+struct EapolHeader {
+    version: u8,
+    packet_type: u8,
+    body_length: u16be,
+}
+
+impl PacketHeader for EapolHeader {
+    /// Header length measured in bytes. Equivalent to the payload offset.
+    fn length(&self) -> usize {
+        EAPOL_HEADER_LEN
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn core_eapol_body() {
+        // EAP-Response/Identity "user"
+        let body = EapolBody {
+            packet_type: EAPOL_EAP_PACKET,
+            data: &[EAP_RESPONSE, 7, 0x00, 0x09, 1, b'u', b's', b'e', b'r'],
+        };
+        assert_eq!(body.eap_code(), EAP_RESPONSE);
+        assert_eq!(body.eap_identifier(), 7);
+        assert_eq!(body.eap_length(), 9);
+        assert_eq!(body.eap_type(), 1);
+        assert_eq!(body.key_descriptor_type(), 0);
+
+        // EAP-Success has no Type field
+        let body = EapolBody {
+            packet_type: EAPOL_EAP_PACKET,
+            data: &[3, 7, 0x00, 0x04],
+        };
+        assert_eq!(body.eap_length(), 4);
+        assert_eq!(body.eap_type(), 0);
+
+        // Truncated EAP header
+        let body = EapolBody {
+            packet_type: EAPOL_EAP_PACKET,
+            data: &[EAP_REQUEST, 7, 0x00],
+        };
+        assert_eq!(body.eap_identifier(), 7);
+        assert_eq!(body.eap_length(), 0);
+        assert_eq!(body.eap_type(), 0);
+
+        // EAPOL-Key with the RSN key descriptor
+        let body = EapolBody {
+            packet_type: EAPOL_KEY,
+            data: &[2, 0x01, 0x0a],
+        };
+        assert_eq!(body.key_descriptor_type(), 2);
+        assert_eq!(body.eap_code(), 0);
+
+        // EAPOL-Start has no body
+        let body = EapolBody {
+            packet_type: EAPOL_START,
+            data: &[],
+        };
+        assert_eq!(body.eap_code(), 0);
+        assert_eq!(body.key_descriptor_type(), 0);
+    }
+}
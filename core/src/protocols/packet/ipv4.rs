@@ -171,6 +171,15 @@ impl<'a> Packet<'a> for Ipv4<'a> {
         Some(self.protocol().into())
     }
 
+    fn payload_len(&self) -> usize {
+        let len = (self.total_length() as usize).saturating_sub(self.header_len());
+        let remaining = self
+            .mbuf
+            .data_len()
+            .saturating_sub(self.next_header_offset());
+        std::cmp::min(len, remaining)
+    }
+
     fn parse_from(outer: &'a impl Packet<'a>) -> Result<Self>
     where
         Self: Sized,
@@ -113,6 +113,14 @@ impl<'a> Packet<'a> for Ipv6<'a> {
         Some(self.next_header().into())
     }
 
+    fn payload_len(&self) -> usize {
+        let remaining = self
+            .mbuf
+            .data_len()
+            .saturating_sub(self.next_header_offset());
+        std::cmp::min(self.payload_length() as usize, remaining)
+    }
+
     fn parse_from(outer: &'a impl Packet<'a>) -> Result<Self>
     where
         Self: Sized,
@@ -0,0 +1,282 @@
+//! LLDP packet.
+
+use crate::memory::mbuf::Mbuf;
+use crate::protocols::packet::{Packet, PacketHeader, PacketParseError};
+use crate::utils::types::*;
+
+use anyhow::{bail, Result};
+use pnet::datalink::MacAddr;
+
+/// LLDP EtherType
+const LLDP_PROTOCOL: usize = 0x88CC;
+/// Size of a TLV header (7-bit type, 9-bit length)
+const LLDP_TLV_HEADER_LEN: usize = 2;
+
+// LLDP TLV types.
+const TLV_END: u8 = 0;
+const TLV_CHASSIS_ID: u8 = 1;
+const TLV_PORT_ID: u8 = 2;
+const TLV_TTL: u8 = 3;
+const TLV_PORT_DESCRIPTION: u8 = 4;
+const TLV_SYSTEM_NAME: u8 = 5;
+const TLV_SYSTEM_DESCRIPTION: u8 = 6;
+
+/// Chassis ID subtype: MAC address
+const CHASSIS_ID_MAC: u8 = 4;
+/// Port ID subtype: MAC address
+const PORT_ID_MAC: u8 = 3;
+
+/// An LLDP packet.
+///
+/// The LLDPDU is a sequence of type-length-value (TLV) structures, beginning with the mandatory
+/// Chassis ID, Port ID, and Time To Live TLVs. TLVs are parsed on access; optional TLVs that are
+/// not present return an empty value.
+#[derive(Debug)]
+pub struct Lldp<'a> {
+    /// Header of the first (Chassis ID) TLV.
+    header: LldpTlvHeader,
+    /// Offset to `header` from the start of `mbuf`.
+    offset: usize,
+    /// Packet buffer.
+    mbuf: &'a Mbuf,
+}
+
+impl<'a> Lldp<'a> {
+    /// Returns the chassis ID subtype.
+    #[inline]
+    pub fn chassis_id_subtype(&self) -> u8 {
+        self.subtype(TLV_CHASSIS_ID)
+    }
+
+    /// Returns the chassis ID. MAC addresses are formatted as colon-separated hex, other subtypes
+    /// are interpreted as text.
+    pub fn chassis_id(&self) -> String {
+        self.id(TLV_CHASSIS_ID, CHASSIS_ID_MAC)
+    }
+
+    /// Returns the port ID subtype.
+    #[inline]
+    pub fn port_id_subtype(&self) -> u8 {
+        self.subtype(TLV_PORT_ID)
+    }
+
+    /// Returns the port ID. MAC addresses are formatted as colon-separated hex, other subtypes are
+    /// interpreted as text.
+    pub fn port_id(&self) -> String {
+        self.id(TLV_PORT_ID, PORT_ID_MAC)
+    }
+
+    /// Returns the time to live in seconds.
+    pub fn ttl(&self) -> u16 {
+        match self.tlv(TLV_TTL) {
+            Some(value) if value.len() >= 2 => u16::from_be_bytes([value[0], value[1]]),
+            _ => 0,
+        }
+    }
+
+    /// Returns the port description, or an empty string if not present.
+    pub fn port_description(&self) -> String {
+        self.text(TLV_PORT_DESCRIPTION)
+    }
+
+    /// Returns the system name, or an empty string if not present.
+    pub fn system_name(&self) -> String {
+        self.text(TLV_SYSTEM_NAME)
+    }
+
+    /// Returns the system description, or an empty string if not present.
+    pub fn system_description(&self) -> String {
+        self.text(TLV_SYSTEM_DESCRIPTION)
+    }
+
+    /// Returns the value of the first TLV of type `tlv_type`, if present.
+    pub fn tlv(&self, tlv_type: u8) -> Option<&[u8]> {
+        self.tlvs().find(|(t, _)| *t == tlv_type).map(|(_, v)| v)
+    }
+
+    /// Returns an iterator over the `(type, value)` pairs of each TLV, up to but excluding the End
+    /// of LLDPDU TLV. Trailing bytes after the End of LLDPDU TLV, such as Ethernet padding, are
+    /// not decoded.
+    pub fn tlvs(&self) -> LldpTlvs<'_> {
+        let data = self
+            .mbuf
+            .get_data_slice(self.offset, self.mbuf.data_len() - self.offset)
+            .unwrap_or_default();
+        LldpTlvs::new(data)
+    }
+
+    fn subtype(&self, tlv_type: u8) -> u8 {
+        self.tlv(tlv_type)
+            .and_then(|value| value.first().copied())
+            .unwrap_or(0)
+    }
+
+    fn id(&self, tlv_type: u8, mac_subtype: u8) -> String {
+        match self.tlv(tlv_type) {
+            Some([subtype, id @ ..]) if *subtype == mac_subtype && id.len() == 6 => {
+                MacAddr::new(id[0], id[1], id[2], id[3], id[4], id[5]).to_string()
+            }
+            Some([_, id @ ..]) => String::from_utf8_lossy(id).into_owned(),
+            _ => String::new(),
+        }
+    }
+
+    fn text(&self, tlv_type: u8) -> String {
+        self.tlv(tlv_type)
+            .map(|value| String::from_utf8_lossy(value).into_owned())
+            .unwrap_or_default()
+    }
+}
+
+impl<'a> Packet<'a> for Lldp<'a> {
+    fn mbuf(&self) -> &Mbuf {
+        self.mbuf
+    }
+
+    /// Length of the LLDPDU, including the End of LLDPDU TLV.
+    fn header_len(&self) -> usize {
+        let mut tlvs = self.tlvs();
+        for _ in tlvs.by_ref() {}
+        tlvs.len.max(self.header.length())
+    }
+
+    fn next_header_offset(&self) -> usize {
+        self.offset + self.header_len()
+    }
+
+    fn next_header(&self) -> Option<usize> {
+        None
+    }
+
+    fn parse_from(outer: &'a impl Packet<'a>) -> Result<Self>
+    where
+        Self: Sized,
+    {
+        let offset = outer.next_header_offset();
+        if let Ok(header) = outer.mbuf().get_data(offset) {
+            match outer.next_header() {
+                Some(LLDP_PROTOCOL) => {
+                    let header: LldpTlvHeader = unsafe { *header };
+                    if header.tlv_type() != TLV_CHASSIS_ID {
+                        bail!(PacketParseError::InvalidProtocol);
+                    }
+                    Ok(Lldp {
+                        header,
+                        offset,
+                        mbuf: outer.mbuf(),
+                    })
+                }
+                _ => bail!(PacketParseError::InvalidProtocol),
+            }
+        } else {
+            bail!(PacketParseError::InvalidRead)
+        }
+    }
+}
+
+/// Iterator over the TLVs of an LLDPDU.
+#[derive(Debug)]
+pub struct LldpTlvs<'b> {
+    /// Remaining bytes of the LLDPDU.
+    data: &'b [u8],
+    /// Number of bytes consumed, including the End of LLDPDU TLV once reached.
+    len: usize,
+}
+
+impl<'b> LldpTlvs<'b> {
+    fn new(data: &'b [u8]) -> Self {
+        LldpTlvs { data, len: 0 }
+    }
+}
+
+impl<'b> Iterator for LldpTlvs<'b> {
+    type Item = (u8, &'b [u8]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.data.len() < LLDP_TLV_HEADER_LEN {
+            self.data = &[];
+            return None;
+        }
+        let type_length = u16::from_be_bytes([self.data[0], self.data[1]]);
+        let tlv_type = (type_length >> 9) as u8;
+        let length = (type_length & 0x01ff) as usize;
+        let end = LLDP_TLV_HEADER_LEN + length;
+        if end > self.data.len() {
+            self.data = &[];
+            return None;
+        }
+        self.len += end;
+        if tlv_type == TLV_END {
+            self.data = &[];
+            return None;
+        }
+        let value = &self.data[LLDP_TLV_HEADER_LEN..end];
+        self.data = &self.data[end..];
+        Some((tlv_type, value))
+    }
+}
+
+/// Type and length of an LLDP TLV.
+#[derive(Debug, Clone, Copy)]
+#[repr(C, packed)]
+struct LldpTlvHeader {
+    type_length: u16be,
+}
+
+impl LldpTlvHeader {
+    /// Upper 7 bits of the TLV header.
+    fn tlv_type(&self) -> u8 {
+        (u16::from(self.type_length) >> 9) as u8
+    }
+}
+
+impl PacketHeader for LldpTlvHeader {
+    /// Length of the TLV, including the 2-byte type/length field.
+    fn length(&self) -> usize {
+        LLDP_TLV_HEADER_LEN + (u16::from(self.type_length) & 0x01ff) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn core_lldp_tlvs() {
+        let mut lldpdu = vec![];
+        // Chassis ID: MAC address
+        lldpdu.extend_from_slice(&[0x02, 0x07, CHASSIS_ID_MAC, 0, 1, 2, 3, 4, 5]);
+        // Port ID: interface name
+        lldpdu.extend_from_slice(&[0x04, 0x05, 5, b'e', b't', b'h', b'0']);
+        // Time To Live: 120
+        lldpdu.extend_from_slice(&[0x06, 0x02, 0, 120]);
+        // System Name
+        lldpdu.extend_from_slice(&[0x0a, 0x02, b's', b'w']);
+        // End of LLDPDU
+        lldpdu.extend_from_slice(&[0x00, 0x00]);
+        let lldpdu_len = lldpdu.len();
+        // Ethernet trailer that is not zero padding
+        lldpdu.extend_from_slice(&[0x0c, 0x02, b'x', b'x', 0xff, 0xff]);
+
+        let mut tlvs = LldpTlvs::new(&lldpdu);
+        assert_eq!(
+            tlvs.by_ref().collect::<Vec<_>>(),
+            vec![
+                (TLV_CHASSIS_ID, &[CHASSIS_ID_MAC, 0, 1, 2, 3, 4, 5][..]),
+                (TLV_PORT_ID, &[5, b'e', b't', b'h', b'0'][..]),
+                (TLV_TTL, &[0, 120][..]),
+                (TLV_SYSTEM_NAME, &b"sw"[..]),
+            ]
+        );
+        assert_eq!(tlvs.len, lldpdu_len);
+
+        // Truncated TLV
+        let mut tlvs = LldpTlvs::new(&[0x02, 0x07, CHASSIS_ID_MAC, 0, 1]);
+        assert_eq!(tlvs.next(), None);
+        assert_eq!(tlvs.len, 0);
+
+        // Missing End of LLDPDU
+        let tlvs = LldpTlvs::new(&[0x06, 0x02, 0, 120, 0x00]);
+        assert_eq!(tlvs.collect::<Vec<_>>(), vec![(TLV_TTL, &[0, 120][..])]);
+    }
+}
@@ -5,9 +5,14 @@
 //! [pnet::packet](https://docs.rs/pnet/latest/pnet/packet/index.html). Every packet type represents
 //! a single frame on the wire.
 
+pub mod arp;
+pub mod cdp;
+pub mod eapol;
 pub mod ethernet;
 pub mod ipv4;
 pub mod ipv6;
+pub mod lldp;
+pub mod sctp;
 pub mod tcp;
 pub mod udp;
 use crate::memory::mbuf::Mbuf;
@@ -29,6 +34,14 @@ pub trait Packet<'a> {
     /// Next level IANA protocol number.
     fn next_header(&self) -> Option<usize>;
 
+    /// Length of the payload in bytes, excluding trailing bytes that are not part of the packet,
+    /// such as Ethernet padding. Defaults to the remainder of the packet buffer.
+    fn payload_len(&self) -> usize {
+        self.mbuf()
+            .data_len()
+            .saturating_sub(self.next_header_offset())
+    }
+
     /// Parses the `Packet`'s payload as a new `Packet` of type `T`.
     fn parse_to<T: Packet<'a>>(&'a self) -> Result<T>
     where
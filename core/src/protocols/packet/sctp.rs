@@ -0,0 +1,359 @@
+//! SCTP packet.
+
+use crate::memory::mbuf::Mbuf;
+use crate::protocols::packet::{Packet, PacketHeader, PacketParseError};
+use crate::utils::types::*;
+
+use anyhow::{bail, Result};
+
+/// SCTP assigned protocol number.
+pub const SCTP_PROTOCOL: usize = 132;
+/// Size of the SCTP common header
+const SCTP_HEADER_LEN: usize = 12;
+/// Size of a chunk header (type, flags, length)
+const CHUNK_HEADER_LEN: usize = 4;
+/// Size of the DATA chunk fields that precede the user data (TSN, stream ID, SSN, PPID)
+const DATA_HEADER_LEN: usize = 12;
+
+// SCTP chunk types.
+pub const DATA: u8 = 0;
+pub const INIT: u8 = 1;
+pub const INIT_ACK: u8 = 2;
+pub const SACK: u8 = 3;
+pub const HEARTBEAT: u8 = 4;
+pub const HEARTBEAT_ACK: u8 = 5;
+pub const ABORT: u8 = 6;
+pub const SHUTDOWN: u8 = 7;
+pub const SHUTDOWN_ACK: u8 = 8;
+pub const ERROR: u8 = 9;
+pub const COOKIE_ECHO: u8 = 10;
+pub const COOKIE_ACK: u8 = 11;
+pub const SHUTDOWN_COMPLETE: u8 = 14;
+
+// DATA chunk flags.
+pub(crate) const DATA_UNORDERED: u8 = 0b0000_0100;
+
+/// An SCTP packet.
+///
+/// The common header is followed by one or more chunks, which are parsed on access. Chunk values
+/// are exposed as raw byte slices, with helpers for DATA and INIT/INIT-ACK chunks.
+#[derive(Debug)]
+pub struct Sctp<'a> {
+    /// Common header.
+    header: SctpHeader,
+    /// Offset to `header` from the start of `mbuf`.
+    offset: usize,
+    /// Length of the SCTP packet, including the common header, as given by the IP header.
+    length: usize,
+    /// Packet buffer.
+    mbuf: &'a Mbuf,
+}
+
+impl<'a> Sctp<'a> {
+    /// Returns the sending port.
+    #[inline]
+    pub fn src_port(&self) -> u16 {
+        self.header.src_port.into()
+    }
+
+    /// Returns the receiving port.
+    #[inline]
+    pub fn dst_port(&self) -> u16 {
+        self.header.dst_port.into()
+    }
+
+    /// Returns the verification tag.
+    #[inline]
+    pub fn verification_tag(&self) -> u32 {
+        self.header.verification_tag.into()
+    }
+
+    /// Returns the CRC32c checksum.
+    #[inline]
+    pub fn checksum(&self) -> u32 {
+        self.header.checksum.into()
+    }
+
+    /// Returns the type of the first chunk in the packet.
+    pub fn chunk_type(&self) -> u8 {
+        self.chunks().next().map_or(0, |c| c.chunk_type)
+    }
+
+    /// Returns the number of chunks in the packet.
+    pub fn nb_chunks(&self) -> usize {
+        self.chunks().count()
+    }
+
+    /// Returns the payload protocol identifier of the first DATA chunk, or `0` if the packet does
+    /// not contain user data.
+    pub fn ppid(&self) -> u32 {
+        self.data_chunks().next().map_or(0, |d| d.ppid)
+    }
+
+    /// Returns `true` if the packet contains a chunk of type `chunk_type`.
+    pub fn has_chunk(&self, chunk_type: u8) -> bool {
+        self.chunks().any(|c| c.chunk_type == chunk_type)
+    }
+
+    /// Returns an iterator over the chunks in the packet. Chunks are bounded by the IP payload
+    /// length, so Ethernet padding is not decoded.
+    pub fn chunks(&self) -> SctpChunks<'_> {
+        let offset = self.next_header_offset();
+        let data = self
+            .mbuf
+            .get_data_slice(offset, self.payload_len())
+            .unwrap_or_default();
+        SctpChunks { data, offset }
+    }
+
+    /// Returns an iterator over the DATA chunks in the packet.
+    pub fn data_chunks(&self) -> impl Iterator<Item = SctpDataChunk> + '_ {
+        self.chunks().filter_map(|c| c.as_data())
+    }
+}
+
+impl<'a> Packet<'a> for Sctp<'a> {
+    fn mbuf(&self) -> &Mbuf {
+        self.mbuf
+    }
+
+    fn header_len(&self) -> usize {
+        self.header.length()
+    }
+
+    fn next_header_offset(&self) -> usize {
+        self.offset + self.header_len()
+    }
+
+    fn next_header(&self) -> Option<usize> {
+        None
+    }
+
+    fn payload_len(&self) -> usize {
+        self.length.saturating_sub(self.header_len())
+    }
+
+    fn parse_from(outer: &'a impl Packet<'a>) -> Result<Self>
+    where
+        Self: Sized,
+    {
+        let offset = outer.next_header_offset();
+        if let Ok(header) = outer.mbuf().get_data(offset) {
+            match outer.next_header() {
+                Some(SCTP_PROTOCOL) => Ok(Sctp {
+                    header: unsafe { *header },
+                    offset,
+                    length: outer.payload_len(),
+                    mbuf: outer.mbuf(),
+                }),
+                _ => bail!(PacketParseError::InvalidProtocol),
+            }
+        } else {
+            bail!(PacketParseError::InvalidRead)
+        }
+    }
+}
+
+/// A single SCTP chunk.
+#[derive(Debug, Clone, Copy)]
+pub struct SctpChunk<'b> {
+    /// Chunk type.
+    pub chunk_type: u8,
+    /// Chunk flags.
+    pub flags: u8,
+    /// Offset to the chunk value from the start of the packet buffer.
+    pub offset: usize,
+    /// Chunk value, excluding the chunk header and padding.
+    pub value: &'b [u8],
+}
+
+impl<'b> SctpChunk<'b> {
+    /// Parses the chunk as a DATA chunk.
+    pub fn as_data(&self) -> Option<SctpDataChunk> {
+        if self.chunk_type != DATA || self.value.len() < DATA_HEADER_LEN {
+            return None;
+        }
+        let v = self.value;
+        Some(SctpDataChunk {
+            tsn: u32::from_be_bytes([v[0], v[1], v[2], v[3]]),
+            stream_id: u16::from_be_bytes([v[4], v[5]]),
+            stream_seq: u16::from_be_bytes([v[6], v[7]]),
+            ppid: u32::from_be_bytes([v[8], v[9], v[10], v[11]]),
+            unordered: self.flags & DATA_UNORDERED != 0,
+            offset: self.offset + DATA_HEADER_LEN,
+            length: v.len() - DATA_HEADER_LEN,
+        })
+    }
+
+    /// Returns the Initiate Tag of an INIT or INIT-ACK chunk.
+    pub fn initiate_tag(&self) -> Option<u32> {
+        match self.chunk_type {
+            INIT | INIT_ACK if self.value.len() >= 4 => {
+                let v = self.value;
+                Some(u32::from_be_bytes([v[0], v[1], v[2], v[3]]))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Fields of a DATA chunk.
+#[derive(Debug, Clone, Copy)]
+pub struct SctpDataChunk {
+    /// Transmission sequence number.
+    pub tsn: u32,
+    /// Stream identifier.
+    pub stream_id: u16,
+    /// Stream sequence number.
+    pub stream_seq: u16,
+    /// Payload protocol identifier.
+    pub ppid: u32,
+    /// `true` if the U (unordered) bit is set.
+    pub unordered: bool,
+    /// Offset to the user data from the start of the packet buffer.
+    pub offset: usize,
+    /// Length of the user data in bytes.
+    pub length: usize,
+}
+
+/// Iterator over the chunks of an SCTP packet.
+#[derive(Debug)]
+pub struct SctpChunks<'b> {
+    data: &'b [u8],
+    offset: usize,
+}
+
+impl<'b> Iterator for SctpChunks<'b> {
+    type Item = SctpChunk<'b>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.data.len() < CHUNK_HEADER_LEN {
+            return None;
+        }
+        let chunk_type = self.data[0];
+        let flags = self.data[1];
+        // Chunk length includes the header but not the padding
+        let length = u16::from_be_bytes([self.data[2], self.data[3]]) as usize;
+        if length < CHUNK_HEADER_LEN || length > self.data.len() {
+            self.data = &[];
+            return None;
+        }
+        let chunk = SctpChunk {
+            chunk_type,
+            flags,
+            offset: self.offset + CHUNK_HEADER_LEN,
+            value: &self.data[CHUNK_HEADER_LEN..length],
+        };
+        let padded = std::cmp::min((length + 3) & !3, self.data.len());
+        self.data = &self.data[padded..];
+        self.offset += padded;
+        Some(chunk)
+    }
+}
+
+/// SCTP common header.
+#[derive(Debug, Clone, Copy)]
+#[repr(C, packed)]
+struct SctpHeader {
+    src_port: u16be,
+    dst_port: u16be,
+    verification_tag: u32be,
+    checksum: u32be,
+}
+
+impl PacketHeader for SctpHeader {
+    /// Header length measured in bytes. Equivalent to the offset of the first chunk.
+    fn length(&self) -> usize {
+        SCTP_HEADER_LEN
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn core_sctp_chunks() {
+        let mut data = vec![];
+        // DATA: TSN 1, stream 2, SSN 3, PPID 46, 5 bytes of user data padded to 4-byte boundary
+        data.extend_from_slice(&[DATA, 0x03, 0x00, 0x15]);
+        data.extend_from_slice(&[0, 0, 0, 1, 0, 2, 0, 3, 0, 0, 0, 46]);
+        data.extend_from_slice(&[b'h', b'e', b'l', b'l', b'o', 0, 0, 0]);
+        // Unordered DATA: TSN 2, stream 5, SSN 0, 4 bytes of user data
+        data.extend_from_slice(&[DATA, 0x07, 0x00, 0x14]);
+        data.extend_from_slice(&[0, 0, 0, 2, 0, 5, 0, 0, 0, 0, 0, 0]);
+        data.extend_from_slice(&[1, 2, 3, 4]);
+        // SACK without gap blocks
+        data.extend_from_slice(&[SACK, 0x00, 0x00, 0x10]);
+        data.extend_from_slice(&[0, 0, 0, 9, 0, 0, 0xff, 0xff, 0, 0, 0, 0]);
+
+        let chunks = SctpChunks {
+            data: &data,
+            offset: 46,
+        }
+        .collect::<Vec<_>>();
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(chunks[0].value.len(), 17);
+        assert_eq!(chunks[1].offset, 46 + 24 + CHUNK_HEADER_LEN);
+        assert_eq!(chunks[2].chunk_type, SACK);
+
+        let first = chunks[0].as_data().unwrap();
+        assert_eq!(
+            (first.tsn, first.stream_id, first.stream_seq, first.ppid),
+            (1, 2, 3, 46)
+        );
+        assert!(!first.unordered);
+        assert_eq!((first.offset, first.length), (46 + 16, 5));
+        let second = chunks[1].as_data().unwrap();
+        assert_eq!((second.stream_id, second.stream_seq), (5, 0));
+        assert!(second.unordered);
+        assert_eq!((second.offset, second.length), (46 + 24 + 16, 4));
+        assert!(chunks[2].as_data().is_none());
+
+        // Chunk lengths that exceed the packet end the iteration
+        let init = [INIT, 0, 0, 0x14, 0xde, 0xad, 0xbe, 0xef, 0, 0, 0xff, 0xff];
+        let chunk = SctpChunks {
+            data: &init,
+            offset: 0,
+        }
+        .next();
+        assert!(chunk.is_none());
+        let chunk = SctpChunks {
+            data: &init[..8],
+            offset: 0,
+        }
+        .next();
+        assert!(chunk.is_none());
+        // INIT Initiate Tag
+        let mut init = init.to_vec();
+        init[3] = 0x08;
+        let chunk = SctpChunks {
+            data: &init,
+            offset: 0,
+        }
+        .next()
+        .unwrap();
+        assert_eq!(chunk.initiate_tag(), Some(0xdeadbeef));
+
+        // Malformed chunk lengths end the iteration
+        let malformed = [COOKIE_ACK, 0, 0, 0x02, DATA, 0, 0, 0x04];
+        assert_eq!(
+            SctpChunks {
+                data: &malformed,
+                offset: 0
+            }
+            .count(),
+            0
+        );
+        // Zero padding after the last chunk is not a chunk
+        let padded = [COOKIE_ACK, 0, 0, 0x04, 0, 0, 0];
+        assert_eq!(
+            SctpChunks {
+                data: &padded,
+                offset: 0
+            }
+            .count(),
+            1
+        );
+    }
+}
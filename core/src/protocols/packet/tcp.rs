@@ -5,9 +5,12 @@ use crate::protocols::packet::{Packet, PacketHeader, PacketParseError};
 use crate::utils::types::*;
 
 use anyhow::{bail, Result};
+use serde::Serialize;
 
 /// TCP assigned protocol number.
 pub const TCP_PROTOCOL: usize = 6;
+/// Size of the fixed TCP header
+const TCP_HEADER_LEN: usize = 20;
 
 // TCP flags.
 pub(crate) const CWR: u8 = 0b1000_0000;
@@ -19,9 +22,23 @@ pub(crate) const RST: u8 = 0b0000_0100;
 pub(crate) const SYN: u8 = 0b0000_0010;
 pub(crate) const FIN: u8 = 0b0000_0001;
 
+// TCP option kinds.
+pub const OPT_EOL: u8 = 0;
+pub const OPT_NOP: u8 = 1;
+pub const OPT_MSS: u8 = 2;
+pub const OPT_WSCALE: u8 = 3;
+pub const OPT_SACK_PERMITTED: u8 = 4;
+pub const OPT_SACK: u8 = 5;
+pub const OPT_TIMESTAMPS: u8 = 8;
+pub const OPT_TFO: u8 = 34;
+/// Experimental option kind, used by TCP Fast Open before kind 34 was assigned
+pub const OPT_EXPERIMENTAL: u8 = 254;
+/// Experiment identifier for TCP Fast Open under kind 254
+const TFO_EXID: u16 = 0xF989;
+
 /// A TCP packet.
 ///
-/// TCP options are not parsed by default.
+/// TCP options are parsed on access. Use [`options`](Tcp::options) to parse all options at once.
 #[derive(Debug)]
 pub struct Tcp<'a> {
     /// Fixed header.
@@ -160,6 +177,214 @@ impl<'a> Tcp<'a> {
     pub fn synack(&self) -> u8 {
         ((self.flags() & (ACK | SYN)) != 0) as u8
     }
+
+    // ------------------------------------------------
+
+    /// Returns the raw bytes of the options field.
+    #[inline]
+    pub fn options_raw(&self) -> &[u8] {
+        let len = self.header_len().saturating_sub(TCP_HEADER_LEN);
+        self.mbuf
+            .get_data_slice(self.offset + TCP_HEADER_LEN, len)
+            .unwrap_or_default()
+    }
+
+    /// Returns the parsed TCP options.
+    pub fn options(&self) -> TcpOptions {
+        TcpOptions::parse(self.options_raw())
+    }
+
+    /// Returns an iterator over the TCP options as `(kind, value)` pairs.
+    #[inline]
+    pub fn options_iter(&self) -> TcpOptionsIter<'_> {
+        TcpOptionsIter::new(self.options_raw())
+    }
+
+    /// Returns the value of the first option of kind `kind`, excluding the kind and length bytes.
+    /// Unlike [`options`](Tcp::options), this does not allocate.
+    #[inline]
+    pub fn find_option(&self, kind: u8) -> Option<&[u8]> {
+        self.options_iter()
+            .find(|(k, _)| *k == kind)
+            .map(|(_, value)| value)
+    }
+
+    /// Returns the maximum segment size option, or `0` if not present.
+    pub fn mss(&self) -> u16 {
+        match self.find_option(OPT_MSS) {
+            Some(&[hi, lo]) => u16::from_be_bytes([hi, lo]),
+            _ => 0,
+        }
+    }
+
+    /// Returns the window scale shift count, or `0` if not present.
+    pub fn wscale(&self) -> u8 {
+        match self.find_option(OPT_WSCALE) {
+            Some(&[shift]) => shift,
+            _ => 0,
+        }
+    }
+
+    /// Returns `1` if the SACK-permitted option is present, and `0` otherwise.
+    pub fn has_sack_permitted(&self) -> u8 {
+        matches!(self.find_option(OPT_SACK_PERMITTED), Some([])) as u8
+    }
+
+    /// Returns `1` if the timestamps option is present, and `0` otherwise.
+    pub fn has_timestamps(&self) -> u8 {
+        self.timestamps().is_some() as u8
+    }
+
+    /// Returns the timestamp value (TSval) and timestamp echo reply (TSecr), if the timestamps
+    /// option is present.
+    pub fn timestamps(&self) -> Option<(u32, u32)> {
+        match self.find_option(OPT_TIMESTAMPS)? {
+            &[v0, v1, v2, v3, e0, e1, e2, e3] => Some((
+                u32::from_be_bytes([v0, v1, v2, v3]),
+                u32::from_be_bytes([e0, e1, e2, e3]),
+            )),
+            _ => None,
+        }
+    }
+
+    /// Returns `1` if a TCP Fast Open option (cookie or cookie request) is present, and `0`
+    /// otherwise.
+    pub fn has_tfo(&self) -> u8 {
+        self.options_iter()
+            .any(|(kind, value)| tfo_cookie(kind, value).is_some()) as u8
+    }
+}
+
+/// Parsed TCP options.
+///
+/// Options that are absent or malformed are `None` (or `false`/empty).
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+pub struct TcpOptions {
+    /// Maximum segment size.
+    pub mss: Option<u16>,
+    /// Window scale shift count.
+    pub wscale: Option<u8>,
+    /// SACK-permitted option is present.
+    pub sack_permitted: bool,
+    /// SACK blocks as (left edge, right edge) pairs.
+    pub sack_blocks: Vec<(u32, u32)>,
+    /// Timestamp value (TSval) and timestamp echo reply (TSecr).
+    pub timestamps: Option<(u32, u32)>,
+    /// TCP Fast Open cookie. An empty cookie is a cookie request.
+    pub tfo_cookie: Option<Vec<u8>>,
+    /// Option kinds in the order they appear, including NOP and EOL.
+    pub layout: Vec<u8>,
+    /// Number of bytes after the EOL option.
+    pub eol_padding: usize,
+    /// `true` if option parsing stopped early due to a malformed option.
+    pub malformed: bool,
+}
+
+impl TcpOptions {
+    /// Parses the options field of a TCP header.
+    pub fn parse(data: &[u8]) -> Self {
+        let mut opts = TcpOptions::default();
+        let mut iter = TcpOptionsIter::new(data);
+        for (kind, val) in iter.by_ref() {
+            opts.layout.push(kind);
+            match (kind, val.len()) {
+                (OPT_EOL, n) => opts.eol_padding = n,
+                (OPT_MSS, 2) => opts.mss = Some(u16::from_be_bytes([val[0], val[1]])),
+                (OPT_WSCALE, 1) => opts.wscale = Some(val[0]),
+                (OPT_SACK_PERMITTED, 0) => opts.sack_permitted = true,
+                (OPT_SACK, n) if n % 8 == 0 => {
+                    opts.sack_blocks = val
+                        .chunks_exact(8)
+                        .map(|b| {
+                            (
+                                u32::from_be_bytes([b[0], b[1], b[2], b[3]]),
+                                u32::from_be_bytes([b[4], b[5], b[6], b[7]]),
+                            )
+                        })
+                        .collect();
+                }
+                (OPT_TIMESTAMPS, 8) => {
+                    opts.timestamps = Some((
+                        u32::from_be_bytes([val[0], val[1], val[2], val[3]]),
+                        u32::from_be_bytes([val[4], val[5], val[6], val[7]]),
+                    ));
+                }
+                _ => {
+                    if let Some(cookie) = tfo_cookie(kind, val) {
+                        opts.tfo_cookie = Some(cookie.to_vec());
+                    }
+                }
+            }
+        }
+        if let Some(kind) = iter.malformed {
+            opts.layout.push(kind);
+            opts.malformed = true;
+        }
+        opts
+    }
+}
+
+/// Returns the TCP Fast Open cookie carried by an option, if the option is a Fast Open option.
+fn tfo_cookie(kind: u8, value: &[u8]) -> Option<&[u8]> {
+    match kind {
+        OPT_TFO => Some(value),
+        OPT_EXPERIMENTAL
+            if value.len() >= 2 && u16::from_be_bytes([value[0], value[1]]) == TFO_EXID =>
+        {
+            Some(&value[2..])
+        }
+        _ => None,
+    }
+}
+
+/// Iterator over the options of a TCP header.
+///
+/// Yields `(kind, value)` pairs, where the value excludes the kind and length bytes. NOP options
+/// have an empty value, and the value of the EOL option is the padding that follows it. Iteration
+/// ends after the EOL option or at the first malformed option.
+#[derive(Debug)]
+pub struct TcpOptionsIter<'b> {
+    /// Remaining bytes of the options field.
+    data: &'b [u8],
+    /// Kind of the malformed option that ended the iteration, if any.
+    malformed: Option<u8>,
+}
+
+impl<'b> TcpOptionsIter<'b> {
+    fn new(data: &'b [u8]) -> Self {
+        TcpOptionsIter {
+            data,
+            malformed: None,
+        }
+    }
+}
+
+impl<'b> Iterator for TcpOptionsIter<'b> {
+    type Item = (u8, &'b [u8]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let data = self.data;
+        let kind = *data.first()?;
+        match kind {
+            OPT_EOL => {
+                self.data = &[];
+                return Some((kind, &data[1..]));
+            }
+            OPT_NOP => {
+                self.data = &data[1..];
+                return Some((kind, &[]));
+            }
+            _ => (),
+        }
+        let len = data.get(1).map_or(0, |len| *len as usize);
+        if len < 2 || len > data.len() {
+            self.data = &[];
+            self.malformed = Some(kind);
+            return None;
+        }
+        self.data = &data[len..];
+        Some((kind, &data[2..len]))
+    }
 }
 
 impl<'a> Packet<'a> for Tcp<'a> {
@@ -223,3 +448,79 @@ impl PacketHeader for TcpHeader {
         ((self.data_offset_to_ns & 0xf0) >> 2).into()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn core_tcp_options() {
+        // Linux SYN: MSS 1460, SACK permitted, timestamps, NOP, window scale 7
+        let syn = [
+            2, 4, 0x05, 0xb4, 4, 2, 8, 10, 0, 0, 0, 1, 0, 0, 0, 0, 1, 3, 3, 7,
+        ];
+        let opts = TcpOptions::parse(&syn);
+        assert_eq!(opts.mss, Some(1460));
+        assert!(opts.sack_permitted);
+        assert_eq!(opts.timestamps, Some((1, 0)));
+        assert_eq!(opts.wscale, Some(7));
+        assert_eq!(
+            opts.layout,
+            vec![
+                OPT_MSS,
+                OPT_SACK_PERMITTED,
+                OPT_TIMESTAMPS,
+                OPT_NOP,
+                OPT_WSCALE
+            ]
+        );
+        assert!(!opts.malformed);
+        assert_eq!(
+            TcpOptionsIter::new(&syn).find(|(kind, _)| *kind == OPT_WSCALE),
+            Some((OPT_WSCALE, &[7][..]))
+        );
+
+        // NOPs, SACK blocks, EOL, and padding
+        let sack = [1, 1, 5, 10, 0, 0, 0, 1, 0, 0, 0, 2, 0, 0, 0];
+        let opts = TcpOptions::parse(&sack);
+        assert_eq!(opts.sack_blocks, vec![(1, 2)]);
+        assert_eq!(opts.layout, vec![OPT_NOP, OPT_NOP, OPT_SACK, OPT_EOL]);
+        assert_eq!(opts.eol_padding, 2);
+        // Options after EOL are not parsed
+        let opts = TcpOptions::parse(&[0, 2, 4, 0x05, 0xb4]);
+        assert_eq!(opts.mss, None);
+        assert_eq!(opts.eol_padding, 4);
+
+        // Truncated options
+        let opts = TcpOptions::parse(&[1, 2]);
+        assert_eq!(opts.layout, vec![OPT_NOP, OPT_MSS]);
+        assert!(opts.malformed);
+        let opts = TcpOptions::parse(&[3, 3, 7, 8, 10, 0, 0]);
+        assert_eq!(opts.wscale, Some(7));
+        assert_eq!(opts.timestamps, None);
+        assert!(opts.malformed);
+
+        // Malformed lengths
+        let opts = TcpOptions::parse(&[2, 0, 0x05, 0xb4]);
+        assert_eq!(opts.layout, vec![OPT_MSS]);
+        assert!(opts.malformed);
+        let opts = TcpOptions::parse(&[2, 1, 3, 3, 7]);
+        assert_eq!(opts.wscale, None);
+        assert!(opts.malformed);
+        // Unexpected value lengths are skipped
+        let opts = TcpOptions::parse(&[2, 3, 0x05, 3, 3, 7]);
+        assert_eq!(opts.mss, None);
+        assert_eq!(opts.wscale, Some(7));
+        assert!(!opts.malformed);
+
+        // TCP Fast Open cookie request and experimental cookie
+        let opts = TcpOptions::parse(&[34, 2]);
+        assert_eq!(opts.tfo_cookie, Some(vec![]));
+        let opts = TcpOptions::parse(&[254, 8, 0xf9, 0x89, 1, 2, 3, 4]);
+        assert_eq!(opts.tfo_cookie, Some(vec![1, 2, 3, 4]));
+        let opts = TcpOptions::parse(&[254, 4, 0x12, 0x34]);
+        assert_eq!(opts.tfo_cookie, None);
+
+        assert_eq!(TcpOptions::parse(&[]), TcpOptions::default());
+    }
+}
@@ -23,7 +23,7 @@ use std::str::FromStr;
 
 use anyhow::{bail, Result};
 use quic::QuicConn;
-use strum_macros::EnumString;
+use strum_macros::{EnumString, IntoStaticStr};
 
 /// Represents the result of parsing one packet as a protocol message.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -72,7 +72,12 @@ impl ParserRegistry {
         if !parsers.is_empty() {
             return Ok(ParserRegistry(parsers));
         }
+        ParserRegistry::from_filter(filter)
+    }
 
+    /// Builds a new `ParserRegistry` with the parsers required by the connection-level predicates
+    /// of `filter`.
+    pub(crate) fn from_filter(filter: &Filter) -> Result<ParserRegistry> {
         let mut stream_protocols = hashset! {};
         for pattern in filter.get_patterns_flat().iter() {
             for predicate in pattern.predicates.iter() {
@@ -93,6 +98,11 @@ impl ParserRegistry {
         Ok(ParserRegistry(parsers))
     }
 
+    /// Returns the names of the registered protocols.
+    pub(crate) fn protocols(&self) -> Vec<&'static str> {
+        self.0.iter().map(<&'static str>::from).collect()
+    }
+
     /// Probe the packet `pdu` with all registered protocol parsers.
     pub(crate) fn probe_all(&self, pdu: &L4Pdu) -> ProbeRegistryResult {
         if self.0.is_empty() {
@@ -232,7 +242,7 @@ impl Default for Session {
 /// a separate crate, so items that ought to be crate-private have their documentation hidden to
 /// avoid confusing users.
 #[doc(hidden)]
-#[derive(Debug, EnumString)]
+#[derive(Debug, EnumString, IntoStaticStr)]
 #[strum(serialize_all = "snake_case")]
 pub enum ConnParser {
     // TODO: refactor to use trait objects.
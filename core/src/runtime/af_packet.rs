@@ -0,0 +1,143 @@
+use crate::config::{AfPacketConfig, ConnTrackConfig};
+use crate::conntrack::stats::ConnTrackCounters;
+use crate::dpdk;
+use crate::filter::Filter;
+use crate::lcore::af_packet_core::AfPacketCore;
+use crate::lcore::rx_core::CoreStats;
+use crate::lcore::{CoreId, SocketId};
+use crate::memory::mempool::Mempool;
+use crate::subscription::*;
+
+use std::collections::BTreeMap;
+use std::os::raw::{c_uint, c_void};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+pub(crate) struct AfPacketRuntime<'a, S>
+where
+    S: Subscribable,
+{
+    rx_cores: BTreeMap<CoreId, AfPacketCore<'a, S>>,
+    options: AfPacketOptions,
+    is_running: Arc<AtomicBool>,
+}
+
+impl<'a, S> AfPacketRuntime<'a, S>
+where
+    S: Subscribable,
+{
+    pub(crate) fn new(
+        options: AfPacketOptions,
+        mempools: &BTreeMap<SocketId, Mempool>,
+        filter: Filter,
+        subscription: Arc<Subscription<'a, S>>,
+        is_running: Arc<AtomicBool>,
+    ) -> Self {
+        let fanout_group = options
+            .af_packet
+            .fanout_group
+            .unwrap_or(std::process::id() as u16);
+        let af_packet = Arc::new(options.af_packet.clone());
+
+        log::info!("Initializing AF_PACKET RX Cores...");
+        let mut rx_cores = BTreeMap::new();
+        for core_id in options.af_packet.cores.iter().map(|c| CoreId(*c)) {
+            let mempool_name = mempools
+                .get(&core_id.socket_id())
+                .expect("Get AF_PACKET mempool")
+                .name()
+                .to_string();
+            let rx_core = AfPacketCore {
+                id: core_id,
+                options: Arc::clone(&af_packet),
+                fanout_group,
+                mempool_name,
+                filter: filter.clone(),
+                conntrack: options.conntrack.clone(),
+                stats: Arc::new(CoreStats::default()),
+                subscription: Arc::clone(&subscription),
+                is_running: Arc::clone(&is_running),
+            };
+            rx_cores.insert(core_id, rx_core);
+        }
+
+        AfPacketRuntime {
+            rx_cores,
+            options,
+            is_running,
+        }
+    }
+
+    pub(crate) fn run(&mut self) {
+        log::info!("Launching AF_PACKET RX cores...");
+        for core_id in self.rx_cores.keys() {
+            let role = unsafe { dpdk::rte_eal_lcore_role(core_id.raw()) };
+            if role != dpdk::rte_lcore_role_t_ROLE_RTE {
+                log::error!("Attempted to launch non-DPDK core");
+                panic!();
+            }
+
+            let arg = &self.rx_cores as *const _ as *mut c_void;
+            let ret = unsafe {
+                dpdk::rte_eal_remote_launch(
+                    Some(launch_af_packet::<S>),
+                    arg,
+                    core_id.raw() as c_uint,
+                )
+            };
+            if ret != 0 {
+                log::error!("RX Core {} busy, launch failed.", core_id);
+                panic!();
+            }
+        }
+
+        // run main thread
+        let start = Instant::now();
+        let duration = self.options.af_packet.duration.map(Duration::from_secs);
+        while self.is_running.load(Ordering::Relaxed) {
+            if duration.is_some_and(|duration| start.elapsed() >= duration) {
+                self.is_running.store(false, Ordering::Relaxed);
+            }
+            std::thread::sleep(Duration::from_millis(100));
+        }
+        unsafe { dpdk::rte_eal_mp_wait_lcore() };
+
+        let mut nb_pkts = 0;
+        let mut nb_bytes = 0;
+        let mut nb_discarded = 0;
+        let mut conntrack = ConnTrackCounters::default();
+        for rx_core in self.rx_cores.values() {
+            nb_pkts += rx_core.stats.nb_pkts.load(Ordering::Relaxed);
+            nb_bytes += rx_core.stats.nb_bytes.load(Ordering::Relaxed);
+            nb_discarded += rx_core.stats.nb_discarded.load(Ordering::Relaxed);
+            conntrack += rx_core.stats.conntrack.snapshot();
+        }
+        println!(
+            "Processed: {} pkts, {} bytes ({} discarded)",
+            nb_pkts, nb_bytes, nb_discarded
+        );
+        println!("Conntrack: {}", conntrack);
+        println!("Main done. Ran for {:?}", start.elapsed());
+    }
+}
+
+/// Read-only runtime options for the AF_PACKET cores
+#[derive(Debug)]
+pub(crate) struct AfPacketOptions {
+    pub(crate) af_packet: AfPacketConfig,
+    pub(crate) conntrack: ConnTrackConfig,
+}
+
+extern "C" fn launch_af_packet<S>(arg: *mut c_void) -> i32
+where
+    S: Subscribable,
+{
+    let rx_cores = arg as *const BTreeMap<CoreId, AfPacketCore<S>>;
+    let rx_cores = unsafe { &*rx_cores };
+
+    let core_id = CoreId(unsafe { dpdk::rte_lcore_id() } as u32);
+    let rx_core = rx_cores.get(&core_id).expect("Invalid Core");
+    rx_core.rx_loop();
+    0
+}
@@ -3,20 +3,26 @@
 //! The runtime initializes the DPDK environment abstraction layer, creates memory pools, launches
 //! the packet processing cores, and manages logging and display output.
 
+mod af_packet;
 mod offline;
 mod online;
+pub(crate) mod trace;
+use self::af_packet::*;
 use self::offline::*;
 use self::online::*;
 
 use crate::config::*;
 use crate::dpdk;
 use crate::filter::{Filter, FilterFactory};
+use crate::fingerprint;
 use crate::lcore::SocketId;
 use crate::memory::mempool::Mempool;
+use crate::subscription::offload::Offload;
 use crate::subscription::*;
 
 use std::collections::BTreeMap;
 use std::ffi::CString;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 
 use anyhow::{bail, Result};
@@ -33,8 +39,11 @@ where
     mempools: BTreeMap<SocketId, Mempool>,
     online: Option<OnlineRuntime<'a, S>>,
     offline: Option<OfflineRuntime<'a, S>>,
-    #[cfg(feature = "timing")]
+    af_packet: Option<AfPacketRuntime<'a, S>>,
     subscription: Arc<Subscription<'a, S>>,
+    is_running: Arc<AtomicBool>,
+    shutdown_callback: Option<Box<dyn FnOnce() + 'a>>,
+    offload: Option<Offload<'a>>,
 }
 
 impl<'a, S> Runtime<'a, S>
@@ -58,11 +67,123 @@ where
         config: RuntimeConfig,
         factory: fn() -> FilterFactory,
         cb: impl Fn(S) + 'a,
+    ) -> Result<Self> {
+        if config.offload.is_some() {
+            bail!("Callback offload is configured, use Runtime::new_offloaded");
+        }
+        let stats = Arc::new(CallbackStats::new::<S>());
+        Self::init(config, factory, cb, stats, None)
+    }
+
+    /// Creates a new runtime from the `config` settings, filter, and callback, with the callback
+    /// offloaded to worker threads as configured in the `offload` section. Runs the callback
+    /// inline on the packet processing cores if offload is not configured.
+    ///
+    /// # Remarks
+    ///
+    /// Subscribable objects are delivered to the callback on the worker threads, so both must be
+    /// thread-safe. See [OffloadConfig](crate::config::OffloadConfig) for details.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut runtime = Runtime::new_offloaded(config, filter, callback)?;
+    /// ```
+    pub fn new_offloaded(
+        config: RuntimeConfig,
+        factory: fn() -> FilterFactory,
+        cb: impl Fn(S) + Send + Sync + 'a,
+    ) -> Result<Self>
+    where
+        S: Send + 'a,
+    {
+        let Some(offload_config) = config.offload.clone() else {
+            return Self::new(config, factory, cb);
+        };
+        let stats = Arc::new(CallbackStats::new::<S>());
+        let cores = config.processing_cores();
+        log::info!(
+            "Offloading callbacks from cores {:?} to {} worker(s)...",
+            cores,
+            offload_config.nb_workers.clamp(1, cores.len().max(1))
+        );
+        let (offload, dispatcher) = Offload::new(&offload_config, &cores, cb, Arc::clone(&stats));
+        let dispatcher = Arc::new(dispatcher);
+        let flush = {
+            let dispatcher = Arc::clone(&dispatcher);
+            move |core_id| dispatcher.flush(core_id)
+        };
+        let cb = move |obj| dispatcher.dispatch(unsafe { dpdk::rte_lcore_id() } as u32, obj);
+        let runtime = Self::init(config, factory, cb, stats, Some(offload))?;
+        runtime.subscription.set_core_flush(flush)?;
+        Ok(runtime)
+    }
+
+    /// Creates a new runtime from the `config` settings and filter that delivers subscribable
+    /// objects into a stream instead of invoking a callback. Requires the `stream` feature.
+    ///
+    /// # Remarks
+    ///
+    /// Up to `capacity` objects are buffered, and `capacity` must be greater than zero. While the
+    /// buffer is full, the packet processing cores block until the stream is polled, so the stream
+    /// should be consumed promptly to avoid packet loss. The stream ends once the runtime has been
+    /// dropped.
+    ///
+    /// The stream is meant to be consumed by an asynchronous runtime (e.g., `tokio`) on other
+    /// threads, while [run](Runtime::run) is called from the main thread, outside of any
+    /// asynchronous context. Callback offload is not supported.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let (mut runtime, mut stream) = Runtime::new_stream(config, filter, 1024)?;
+    /// let rt = tokio::runtime::Runtime::new()?;
+    /// let consumer = rt.spawn(async move {
+    ///     while let Some(conn) = stream.recv().await {
+    ///         // ...
+    ///     }
+    /// });
+    /// runtime.run();
+    /// drop(runtime);
+    /// rt.block_on(consumer)?;
+    /// ```
+    #[cfg(feature = "stream")]
+    pub fn new_stream(
+        config: RuntimeConfig,
+        factory: fn() -> FilterFactory,
+        capacity: usize,
+    ) -> Result<(Self, async_stream::SubscriptionStream<S>)>
+    where
+        S: Send + 'a,
+    {
+        if config.offload.is_some() {
+            bail!("Callback offload is not supported with subscription streams");
+        }
+        let stats = Arc::new(CallbackStats::new::<S>());
+        let (send, stream) = async_stream::channel(capacity, Arc::clone(&stats))?;
+        let runtime = Self::init(config, factory, send, stats, None)?;
+        Ok((runtime, stream))
+    }
+
+    fn init(
+        config: RuntimeConfig,
+        factory: fn() -> FilterFactory,
+        cb: impl Fn(S) + 'a,
+        stats: Arc<CallbackStats>,
+        offload: Option<Offload<'a>>,
     ) -> Result<Self> {
         let factory = factory();
         let filter =
             Filter::from_str(factory.filter_str.as_str(), true).expect("Failed to parse filter");
-        let subscription = Arc::new(Subscription::new(factory, cb));
+        let subscription = Arc::new(Subscription::new(factory, cb, stats));
+
+        // Set up signal handler
+        let is_running = Arc::new(AtomicBool::new(true));
+        let r = Arc::clone(&is_running);
+        ctrlc::set_handler(move || {
+            r.store(false, Ordering::Relaxed);
+        })
+        .expect("Error setting Ctrl-C handler");
 
         println!("Initializing Retina runtime...");
         log::info!("Initializing EAL...");
@@ -85,6 +206,11 @@ where
             }
         }
 
+        if let Some(fingerprint) = &config.fingerprint {
+            log::info!("Loading p0f signatures...");
+            fingerprint::p0f::load_database(&fingerprint.p0f_db)?;
+        }
+
         log::info!("Initializing Mempools...");
         let mut mempools = BTreeMap::new();
         let socket_ids = config.get_all_socket_ids();
@@ -92,6 +218,8 @@ where
             online.mtu
         } else if let Some(offline) = &config.offline {
             offline.mtu
+        } else if let Some(af_packet) = &config.af_packet {
+            af_packet.mtu
         } else {
             Mempool::default_mtu()
         };
@@ -113,6 +241,7 @@ where
                 &mut mempools,
                 filter.clone(),
                 Arc::clone(&subscription),
+                Arc::clone(&is_running),
             )
         });
 
@@ -127,6 +256,22 @@ where
                 &mempools,
                 filter.clone(),
                 Arc::clone(&subscription),
+                Arc::clone(&is_running),
+            )
+        });
+
+        let af_packet = config.af_packet.as_ref().map(|cfg| {
+            log::info!("Initializing AF_PACKET Runtime...");
+            let af_packet_opts = AfPacketOptions {
+                af_packet: cfg.clone(),
+                conntrack: config.conntrack.clone(),
+            };
+            AfPacketRuntime::new(
+                af_packet_opts,
+                &mempools,
+                filter.clone(),
+                Arc::clone(&subscription),
+                Arc::clone(&is_running),
             )
         });
 
@@ -135,12 +280,63 @@ where
             mempools,
             online,
             offline,
-            #[cfg(feature = "timing")]
+            af_packet,
             subscription,
+            is_running,
+            shutdown_callback: None,
+            offload,
         })
     }
 
-    /// Run Retina for the duration specified in the configuration or until `ctrl-c` to terminate.
+    /// Returns a handle that stops the runtime when triggered, in addition to `ctrl-c` and the
+    /// configured duration.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let stop = runtime.stop_handle();
+    /// std::thread::spawn(move || {
+    ///     std::thread::sleep(std::time::Duration::from_secs(60));
+    ///     stop.stop();
+    /// });
+    /// runtime.run();
+    /// ```
+    pub fn stop_handle(&self) -> StopHandle {
+        StopHandle {
+            is_running: Arc::clone(&self.is_running),
+        }
+    }
+
+    /// Sets a callback invoked on each packet processing core when it exits, after the remaining
+    /// connections in its table have been delivered. The callback takes the ID of the core, and
+    /// can be used to flush per-core state. Returns an error if it is already set.
+    ///
+    /// When callbacks are offloaded, the core first waits until the objects it queued have been
+    /// delivered to the callback.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// runtime.on_core_exit(|core_id| println!("Core {} done", core_id))?;
+    /// ```
+    pub fn on_core_exit(&mut self, cb: impl Fn(u32) + 'a) -> Result<()> {
+        self.subscription.set_core_exit_callback(cb)
+    }
+
+    /// Sets a callback invoked once on the main core at the end of the run, after all packet
+    /// processing cores have exited.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// runtime.on_shutdown(|| println!("Done"));
+    /// ```
+    pub fn on_shutdown(&mut self, cb: impl FnOnce() + 'a) {
+        self.shutdown_callback = Some(Box::new(cb));
+    }
+
+    /// Run Retina for the duration specified in the configuration, until `ctrl-c`, or until stopped
+    /// by a [StopHandle](StopHandle) to terminate.
     ///
     /// # Example
     ///
@@ -148,18 +344,65 @@ where
     /// runtime.run();
     /// ```
     pub fn run(&mut self) {
-        if let Some(online) = &mut self.online {
-            online.run();
-        } else if let Some(offline) = &self.offline {
-            offline.run();
-        } else {
-            log::error!("No runtime");
+        match self.offload.take() {
+            Some(offload) => {
+                std::thread::scope(|scope| {
+                    for worker in offload.workers {
+                        scope.spawn(worker);
+                    }
+                    self.run_cores();
+                    offload.is_done.store(true, Ordering::Release);
+                    log::info!("Waiting for offloaded callbacks...");
+                });
+                let nb_dropped = self.subscription.stats.nb_dropped.load(Ordering::Relaxed);
+                if nb_dropped > 0 {
+                    log::warn!("Dropped {} objects under offload backpressure", nb_dropped);
+                }
+            }
+            None => self.run_cores(),
         }
         #[cfg(feature = "timing")]
         {
             self.subscription.timers.display_stats();
             self.subscription.timers.dump_stats();
         }
+        if let Some(cb) = self.shutdown_callback.take() {
+            cb();
+        }
         log::info!("Done.");
     }
+
+    /// Runs the packet processing cores of the configured runtime mode until they exit.
+    fn run_cores(&mut self) {
+        if let Some(online) = &mut self.online {
+            online.run();
+        } else if let Some(offline) = &self.offline {
+            offline.run();
+        } else if let Some(af_packet) = &mut self.af_packet {
+            af_packet.run();
+        } else {
+            log::error!("No runtime");
+        }
+    }
+}
+
+/// A handle to stop a running [Runtime](Runtime) from another thread.
+///
+/// Stopping the runtime has the same effect as `ctrl-c`: packet processing cores stop receiving
+/// packets, deliver the remaining connections in their tables, and exit.
+#[derive(Debug, Clone)]
+pub struct StopHandle {
+    is_running: Arc<AtomicBool>,
+}
+
+impl StopHandle {
+    /// Stops the runtime.
+    pub fn stop(&self) {
+        self.is_running.store(false, Ordering::Relaxed);
+    }
+
+    /// Returns `true` if the runtime has been stopped.
+    pub fn is_stopped(&self) -> bool {
+        !self.is_running.load(Ordering::Relaxed)
+    }
 }
@@ -1,19 +1,40 @@
 use crate::config::{ConnTrackConfig, OfflineConfig};
+use crate::conntrack::clock;
+use crate::conntrack::stats::{ConnTrackCounters, ConnTrackStats};
 use crate::conntrack::{ConnTracker, TrackerConfig};
 use crate::dpdk;
 use crate::filter::Filter;
+use crate::lcore::offline_core::OfflineCore;
+use crate::lcore::ring::Ring;
+use crate::lcore::rx_core::CoreStats;
 use crate::lcore::{CoreId, SocketId};
 use crate::memory::mbuf::Mbuf;
 use crate::memory::mempool::Mempool;
+use crate::port::rss;
 use crate::protocols::stream::ParserRegistry;
+use crate::runtime::trace::{link, Frame, Trace};
 use crate::subscription::*;
 
 use std::collections::BTreeMap;
 use std::ffi::CString;
+use std::os::raw::{c_uint, c_void};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
 
 use cpu_time::ProcessTime;
-use pcap::Capture;
+
+/// Number of packets that can be queued to each offline worker core.
+const WORKER_RING_SIZE: u32 = 8192;
+
+/// Remaining time until a replayed packet is due below which the reader busy-waits instead of
+/// sleeping.
+const REPLAY_SPIN_THRESHOLD: Duration = Duration::from_micros(200);
+
+/// Maximum time the reader sleeps at once while waiting for a replayed packet, so that it remains
+/// responsive to stop requests.
+const REPLAY_MAX_SLEEP: Duration = Duration::from_millis(100);
 
 pub(crate) struct OfflineRuntime<'a, S>
 where
@@ -23,6 +44,9 @@ where
     pub(crate) filter: Filter,
     pub(crate) subscription: Arc<Subscription<'a, S>>,
     pub(crate) options: OfflineOptions,
+    workers: BTreeMap<CoreId, OfflineCore<'a, S>>,
+    is_done: Arc<AtomicBool>,
+    is_running: Arc<AtomicBool>,
 }
 
 impl<'a, S> OfflineRuntime<'a, S>
@@ -34,6 +58,7 @@ where
         mempools: &BTreeMap<SocketId, Mempool>,
         filter: Filter,
         subscription: Arc<Subscription<'a, S>>,
+        is_running: Arc<AtomicBool>,
     ) -> Self {
         let core_id = CoreId(unsafe { dpdk::rte_lcore_id() } as u32);
         let mempool_name = mempools
@@ -41,18 +66,66 @@ where
             .expect("Get offline mempool")
             .name()
             .to_string();
+
+        let is_done = Arc::new(AtomicBool::new(false));
+        let mut workers = BTreeMap::new();
+        let mut worker_ids: Vec<CoreId> =
+            options.offline.cores.iter().map(|c| CoreId(*c)).collect();
+        worker_ids.sort();
+        worker_ids.dedup();
+        if !worker_ids.is_empty() {
+            log::info!("Initializing Offline Worker Cores...");
+            // Capture timestamps are passed to the workers with the packets
+            Mbuf::register_timestamp().expect("Unable to register mbuf timestamp field");
+        }
+        if let Some(speed) = options.offline.replay_speed {
+            log::info!("Replaying trace at {}x speed", speed);
+            clock::set_replay_speed(speed);
+        }
+        for worker_id in worker_ids {
+            let ring = Ring::new(
+                &format!("offline_ring_{}", worker_id),
+                WORKER_RING_SIZE,
+                worker_id.socket_id(),
+                dpdk::RING_F_SP_ENQ | dpdk::RING_F_SC_DEQ,
+            )
+            .expect("Unable to create offline worker ring");
+            let worker = OfflineCore {
+                id: worker_id,
+                ring,
+                filter: filter.clone(),
+                conntrack: options.conntrack.clone(),
+                stats: Arc::new(CoreStats::default()),
+                subscription: Arc::clone(&subscription),
+                is_done: Arc::clone(&is_done),
+            };
+            workers.insert(worker_id, worker);
+        }
+
         OfflineRuntime {
             mempool_name,
             filter,
             subscription,
             options,
+            workers,
+            is_done,
+            is_running,
         }
     }
 
     pub(crate) fn run(&self) {
+        if self.workers.is_empty() {
+            self.run_single();
+        } else {
+            self.run_parallel();
+        }
+    }
+
+    /// Reads and processes the capture on the main core.
+    fn run_single(&self) {
         log::info!(
             "Launched offline analysis. Processing pcap: {}",
-            self.options.offline.pcap,
+            self.options.offline.pcap.join(", "),
         );
 
         let mut nb_pkts = 0;
@@ -61,18 +134,30 @@ where
         let config = TrackerConfig::from(&self.options.conntrack);
         let registry = ParserRegistry::build::<S>(&self.filter).expect("Unable to build registry");
         log::debug!("{:#?}", registry);
-        let mut stream_table = ConnTracker::<S::Tracked>::new(config, registry);
+        let stats = Arc::new(ConnTrackStats::default());
+        let mut stream_table = ConnTracker::<S::Tracked>::new(config, registry, Arc::clone(&stats));
 
         let mempool_raw = self.get_mempool_raw();
-        let pcap = self.options.offline.pcap.as_str();
-        let mut cap = Capture::from_file(pcap).expect("Error opening pcap. Aborting.");
+        let mut trace =
+            Trace::open(&self.options.offline.pcap).expect("Error opening pcap. Aborting.");
+        let mut nb_skipped = 0;
+        let mut buf = Vec::with_capacity(self.options.offline.mtu);
         let start = ProcessTime::try_now().expect("Getting process time failed");
-        while let Ok(frame) = cap.next() {
-            if frame.header.len as usize > self.options.offline.mtu {
-                continue;
+        while let Some(frame) = trace.next_frame() {
+            pace(frame.ts, &self.is_running, || {
+                stream_table.check_inactive(&self.subscription)
+            });
+            if !self.is_running.load(Ordering::Relaxed) {
+                log::info!("Stopping offline analysis early");
+                break;
             }
-            let mbuf = Mbuf::from_bytes(frame.data, mempool_raw)
-                .expect("Unable to allocate mbuf. Try increasing mempool size.");
+            clock::set_trace_time(frame.ts);
+            stream_table.check_inactive(&self.subscription);
+
+            let Some(mbuf) = self.to_mbuf(frame, &mut buf, mempool_raw) else {
+                nb_skipped += 1;
+                continue;
+            };
             nb_pkts += 1;
             nb_bytes += mbuf.data_len() as u64;
 
@@ -81,20 +166,153 @@ where
 
         // // Deliver remaining data in table
         stream_table.drain(&self.subscription);
+        self.subscription
+            .invoke_core_exit(unsafe { dpdk::rte_lcore_id() } as u32);
         let cpu_time = start.elapsed();
         println!("Processed: {} pkts, {} bytes", nb_pkts, nb_bytes);
+        println!("Skipped: {} pkts", nb_skipped);
+        println!("Conntrack: {}", stats.snapshot());
         println!("CPU time: {:?}ms", cpu_time.as_millis());
     }
 
+    /// Reads the capture on the main core and distributes packets to the worker cores.
+    fn run_parallel(&self) {
+        log::info!(
+            "Launched offline analysis on {} worker cores. Processing pcap: {}",
+            self.workers.len(),
+            self.options.offline.pcap.join(", "),
+        );
+
+        for worker_id in self.workers.keys() {
+            let role = unsafe { dpdk::rte_eal_lcore_role(worker_id.raw()) };
+            if role != dpdk::rte_lcore_role_t_ROLE_RTE {
+                log::error!("Attempted to launch non-DPDK core");
+                panic!();
+            }
+
+            let arg = &self.workers as *const _ as *mut c_void;
+            let ret = unsafe {
+                dpdk::rte_eal_remote_launch(
+                    Some(launch_offline::<S>),
+                    arg,
+                    worker_id.raw() as c_uint,
+                )
+            };
+            if ret != 0 {
+                log::error!("Worker Core {} busy, launch failed.", worker_id);
+                panic!();
+            }
+        }
+
+        let rings: Vec<&Ring> = self.workers.values().map(|w| &w.ring).collect();
+        let mempool_raw = self.get_mempool_raw();
+        let mut trace =
+            Trace::open(&self.options.offline.pcap).expect("Error opening pcap. Aborting.");
+        let mut nb_skipped = 0;
+        let mut buf = Vec::with_capacity(self.options.offline.mtu);
+        let start = ProcessTime::try_now().expect("Getting process time failed");
+        while let Some(frame) = trace.next_frame() {
+            pace(frame.ts, &self.is_running, || ());
+            if !self.is_running.load(Ordering::Relaxed) {
+                log::info!("Stopping offline analysis early");
+                break;
+            }
+            let Some(mut mbuf) = self.to_mbuf(frame, &mut buf, mempool_raw) else {
+                nb_skipped += 1;
+                continue;
+            };
+            let ring = rings[rss::queue_index(rss::rss_hash(&mbuf), rings.len())];
+            // Wait for the worker to catch up rather than dropping packets
+            while let Err(rejected) = ring.sp_enqueue(mbuf) {
+                mbuf = rejected;
+                std::hint::spin_loop();
+            }
+        }
+        self.is_done.store(true, Ordering::Release);
+        unsafe { dpdk::rte_eal_mp_wait_lcore() };
+        let cpu_time = start.elapsed();
+
+        let mut nb_pkts = 0;
+        let mut nb_bytes = 0;
+        let mut conntrack = ConnTrackCounters::default();
+        for worker in self.workers.values() {
+            nb_pkts += worker.stats.nb_pkts.load(Ordering::Relaxed);
+            nb_bytes += worker.stats.nb_bytes.load(Ordering::Relaxed);
+            conntrack += worker.stats.conntrack.snapshot();
+        }
+        println!("Processed: {} pkts, {} bytes", nb_pkts, nb_bytes);
+        println!("Skipped: {} pkts", nb_skipped);
+        println!("Conntrack: {}", conntrack);
+        println!("CPU time: {:?}ms", cpu_time.as_millis());
+    }
+
+    /// Copies `frame` into a new mbuf as an Ethernet frame, using `buf` as scratch space for
+    /// frames with other link-layer header types. Returns `None` if the frame exceeds the MTU or
+    /// cannot be converted to Ethernet.
+    fn to_mbuf(
+        &self,
+        frame: &Frame,
+        buf: &mut Vec<u8>,
+        mempool_raw: *mut dpdk::rte_mempool,
+    ) -> Option<Mbuf> {
+        let Some(data) = link::to_ethernet(frame.link_type, &frame.data, buf) else {
+            log::debug!("Skipping frame with link type {}", frame.link_type);
+            return None;
+        };
+        // Length on the wire after replacing the link-layer header
+        let len = frame.orig_len as usize + data.len() - frame.data.len();
+        if len > self.options.offline.mtu {
+            return None;
+        }
+        let mut mbuf = Mbuf::from_bytes(data, mempool_raw)
+            .expect("Unable to allocate mbuf. Try increasing mempool size.");
+        mbuf.set_timestamp(frame.ts);
+        Some(mbuf)
+    }
+
     fn get_mempool_raw(&self) -> *mut dpdk::rte_mempool {
         let cname = CString::new(self.mempool_name.clone()).expect("Invalid CString conversion");
         unsafe { dpdk::rte_mempool_lookup(cname.as_ptr()) }
     }
 }
 
+/// Waits until the packet captured at `ts` is due if the trace is replayed in real time, calling
+/// `idle` while waiting. Returns early if the runtime is stopped.
+fn pace(ts: u64, is_running: &AtomicBool, mut idle: impl FnMut()) {
+    let Some(due) = clock::replay_due(ts) else {
+        return;
+    };
+    while is_running.load(Ordering::Relaxed) {
+        let now = Instant::now();
+        if now >= due {
+            break;
+        }
+        idle();
+        let remaining = due - now;
+        if remaining > REPLAY_SPIN_THRESHOLD {
+            thread::sleep((remaining - REPLAY_SPIN_THRESHOLD).min(REPLAY_MAX_SLEEP));
+        } else {
+            std::hint::spin_loop();
+        }
+    }
+}
+
 /// Read-only runtime options for the offline core
 #[derive(Debug)]
 pub(crate) struct OfflineOptions {
     pub(crate) offline: OfflineConfig,
     pub(crate) conntrack: ConnTrackConfig,
 }
+
+extern "C" fn launch_offline<S>(arg: *mut c_void) -> i32
+where
+    S: Subscribable,
+{
+    let workers = arg as *const BTreeMap<CoreId, OfflineCore<S>>;
+    let workers = unsafe { &*workers };
+
+    let core_id = CoreId(unsafe { dpdk::rte_lcore_id() } as u32);
+    let worker = workers.get(&core_id).expect("Invalid Core");
+    worker.rx_loop();
+    0
+}
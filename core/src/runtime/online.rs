@@ -10,7 +10,7 @@ use crate::subscription::*;
 
 use std::collections::BTreeMap;
 use std::os::raw::{c_uint, c_void};
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
 use std::time::Instant;
 
@@ -35,15 +35,8 @@ where
         mempools: &mut BTreeMap<SocketId, Mempool>,
         filter: Filter,
         subscription: Arc<Subscription<'a, S>>,
+        is_running: Arc<AtomicBool>,
     ) -> Self {
-        // Set up signal handler
-        let is_running = Arc::new(AtomicBool::new(true));
-        let r = Arc::clone(&is_running);
-        ctrlc::set_handler(move || {
-            r.store(false, Ordering::Relaxed);
-        })
-        .expect("Error setting Ctrl-C handler");
-
         log::info!("Initializing Ports...");
         let mut ports: BTreeMap<PortId, Port> = BTreeMap::new();
         for port_map in options.online.ports.iter() {
@@ -90,7 +83,17 @@ where
             rx_cores.insert(core_id, rx_core);
         }
 
-        let monitor = Monitor::new(config, &ports, Arc::clone(&is_running));
+        let core_stats = rx_cores
+            .iter()
+            .map(|(core_id, rx_core)| (*core_id, Arc::clone(&rx_core.stats)))
+            .collect();
+        let monitor = Monitor::new(
+            config,
+            &ports,
+            core_stats,
+            Arc::clone(&subscription.stats),
+            Arc::clone(&is_running),
+        );
 
         OnlineRuntime {
             ports,
@@ -0,0 +1,320 @@
+//! Link-layer normalization.
+//!
+//! Retina's packet parsers expect frames to start with an Ethernet header. Frames captured with
+//! other link-layer header types are converted to Ethernet frames by replacing their link-layer
+//! header with a synthesized Ethernet header carrying the same EtherType (and MAC addresses, if
+//! known).
+
+use super::LINKTYPE_ETHERNET;
+
+/// BSD loopback encapsulation (host byte order address family).
+const LINKTYPE_NULL: u16 = 0;
+/// Raw IP, as written by some platforms.
+const DLT_RAW_12: u16 = 12;
+const DLT_RAW_14: u16 = 14;
+/// Raw IPv4 or IPv6.
+const LINKTYPE_RAW: u16 = 101;
+/// IEEE 802.11 without radio information.
+const LINKTYPE_IEEE802_11: u16 = 105;
+/// OpenBSD loopback encapsulation (network byte order address family).
+const LINKTYPE_LOOP: u16 = 108;
+/// Linux "cooked" capture encapsulation (e.g., `tcpdump -i any`).
+const LINKTYPE_LINUX_SLL: u16 = 113;
+/// IEEE 802.11 with a radiotap header.
+const LINKTYPE_IEEE802_11_RADIOTAP: u16 = 127;
+/// Raw IPv4.
+const LINKTYPE_IPV4: u16 = 228;
+/// Raw IPv6.
+const LINKTYPE_IPV6: u16 = 229;
+/// Linux "cooked" capture encapsulation v2.
+const LINKTYPE_LINUX_SLL2: u16 = 276;
+
+const ETHERTYPE_IPV4: u16 = 0x0800;
+const ETHERTYPE_IPV6: u16 = 0x86dd;
+
+/// A frame's Ethernet header fields and payload.
+struct Converted<'a> {
+    dst: [u8; 6],
+    src: [u8; 6],
+    ether_type: u16,
+    payload: &'a [u8],
+}
+
+const SLL_HEADER_LEN: usize = 16;
+const SLL2_HEADER_LEN: usize = 20;
+const NULL_HEADER_LEN: usize = 4;
+
+/// Returns `data` as an Ethernet frame, writing it into `buf` if it must be converted. Returns
+/// `None` if the frame cannot be converted (e.g., unsupported link type, truncated header, or
+/// encrypted or non-data 802.11 frame).
+pub(crate) fn to_ethernet<'a>(
+    link_type: u16,
+    data: &'a [u8],
+    buf: &'a mut Vec<u8>,
+) -> Option<&'a [u8]> {
+    let converted = match link_type {
+        LINKTYPE_ETHERNET => return Some(data),
+        DLT_RAW_12 | DLT_RAW_14 | LINKTYPE_RAW | LINKTYPE_IPV4 | LINKTYPE_IPV6 => Converted {
+            dst: [0; 6],
+            src: [0; 6],
+            ether_type: ip_ether_type(data)?,
+            payload: data,
+        },
+        LINKTYPE_NULL | LINKTYPE_LOOP => {
+            let header = data.get(..NULL_HEADER_LEN)?;
+            let family = if link_type == LINKTYPE_LOOP {
+                u32::from_be_bytes(header.try_into().ok()?)
+            } else {
+                // Host byte order of the capturing machine: the family is a small value, so the
+                // byte order can be inferred.
+                let le = u32::from_le_bytes(header.try_into().ok()?);
+                let be = u32::from_be_bytes(header.try_into().ok()?);
+                le.min(be)
+            };
+            let ether_type = match family {
+                2 => ETHERTYPE_IPV4,
+                // AF_INET6 on BSDs, Darwin, and Linux
+                10 | 24 | 28 | 30 => ETHERTYPE_IPV6,
+                _ => return None,
+            };
+            Converted {
+                dst: [0; 6],
+                src: [0; 6],
+                ether_type,
+                payload: &data[NULL_HEADER_LEN..],
+            }
+        }
+        LINKTYPE_LINUX_SLL => {
+            let header = data.get(..SLL_HEADER_LEN)?;
+            let addr_len = u16::from_be_bytes([header[4], header[5]]);
+            let src = link_addr(addr_len as usize, &header[6..14]);
+            let ether_type = u16::from_be_bytes([header[14], header[15]]);
+            Converted {
+                dst: [0; 6],
+                src,
+                ether_type,
+                payload: &data[SLL_HEADER_LEN..],
+            }
+        }
+        LINKTYPE_LINUX_SLL2 => {
+            let header = data.get(..SLL2_HEADER_LEN)?;
+            let ether_type = u16::from_be_bytes([header[0], header[1]]);
+            let src = link_addr(header[11] as usize, &header[12..20]);
+            Converted {
+                dst: [0; 6],
+                src,
+                ether_type,
+                payload: &data[SLL2_HEADER_LEN..],
+            }
+        }
+        LINKTYPE_IEEE802_11_RADIOTAP => {
+            let (len, fcs) = radiotap_header(data)?;
+            let mut frame = data.get(len..)?;
+            if fcs {
+                frame = frame.get(..frame.len().checked_sub(4)?)?;
+            }
+            ieee802_11(frame)?
+        }
+        LINKTYPE_IEEE802_11 => ieee802_11(data)?,
+        _ => return None,
+    };
+
+    buf.clear();
+    buf.extend_from_slice(&converted.dst);
+    buf.extend_from_slice(&converted.src);
+    buf.extend_from_slice(&converted.ether_type.to_be_bytes());
+    buf.extend_from_slice(converted.payload);
+    Some(&buf[..])
+}
+
+/// Returns the EtherType of a raw IP packet.
+fn ip_ether_type(data: &[u8]) -> Option<u16> {
+    match data.first()? >> 4 {
+        4 => Some(ETHERTYPE_IPV4),
+        6 => Some(ETHERTYPE_IPV6),
+        _ => None,
+    }
+}
+
+/// Returns a link-layer address as a MAC address, or zeros if it is not 6 bytes long.
+fn link_addr(addr_len: usize, addr: &[u8]) -> [u8; 6] {
+    let mut mac = [0; 6];
+    if addr_len == 6 {
+        mac.copy_from_slice(&addr[..6]);
+    }
+    mac
+}
+
+/// Returns the length of a radiotap header, and whether the 802.11 frame that follows it ends
+/// with a frame check sequence.
+fn radiotap_header(data: &[u8]) -> Option<(usize, bool)> {
+    const PRESENT_TSFT: u32 = 1 << 0;
+    const PRESENT_FLAGS: u32 = 1 << 1;
+    const PRESENT_EXT: u32 = 1 << 31;
+    const FLAGS_FCS: u8 = 0x10;
+
+    let len = u16::from_le_bytes([*data.get(2)?, *data.get(3)?]) as usize;
+    if len < 8 || len > data.len() {
+        return None;
+    }
+    let present = u32::from_le_bytes(data[4..8].try_into().ok()?);
+    // Skip extended presence bitmasks
+    let mut offset = 8;
+    let mut word = present;
+    while word & PRESENT_EXT != 0 {
+        word = u32::from_le_bytes(data.get(offset..offset + 4)?.try_into().ok()?);
+        offset += 4;
+    }
+    let mut fcs = false;
+    if present & PRESENT_FLAGS != 0 {
+        if present & PRESENT_TSFT != 0 {
+            // TSFT is 8-byte aligned
+            offset = (offset + 7) & !7;
+            offset += 8;
+        }
+        fcs = data.get(offset).is_some_and(|flags| flags & FLAGS_FCS != 0);
+    }
+    Some((len, fcs))
+}
+
+/// Converts an 802.11 data frame with an LLC/SNAP header.
+fn ieee802_11(frame: &[u8]) -> Option<Converted<'_>> {
+    const TYPE_DATA: u8 = 2;
+    const LLC_SNAP: [u8; 6] = [0xaa, 0xaa, 0x03, 0x00, 0x00, 0x00];
+
+    let fc = frame.get(..2)?;
+    let (ty, subtype) = ((fc[0] >> 2) & 0x3, fc[0] >> 4);
+    let (to_ds, from_ds) = (fc[1] & 0x01 != 0, fc[1] & 0x02 != 0);
+    let protected = fc[1] & 0x40 != 0;
+    let order = fc[1] & 0x80 != 0;
+    // Null data frames (subtype bit 2) carry no payload
+    if ty != TYPE_DATA || subtype & 0x4 != 0 || protected {
+        return None;
+    }
+
+    let qos = subtype & 0x8 != 0;
+    let mut header_len = 24;
+    if to_ds && from_ds {
+        header_len += 6;
+    }
+    if qos {
+        header_len += 2;
+        if order {
+            header_len += 4;
+        }
+    }
+    let header = frame.get(..header_len)?;
+    let addr = |i: usize| -> [u8; 6] { header[4 + 6 * i..10 + 6 * i].try_into().unwrap() };
+    let addr4 = || -> [u8; 6] { header[24..30].try_into().unwrap() };
+    let (dst, src) = match (to_ds, from_ds) {
+        (false, false) => (addr(0), addr(1)),
+        (true, false) => (addr(2), addr(1)),
+        (false, true) => (addr(0), addr(2)),
+        (true, true) => (addr(2), addr4()),
+    };
+
+    let llc = frame.get(header_len..header_len + 8)?;
+    if llc[..6] != LLC_SNAP {
+        return None;
+    }
+    let ether_type = u16::from_be_bytes([llc[6], llc[7]]);
+    Some(Converted {
+        dst,
+        src,
+        ether_type,
+        payload: &frame[header_len + 8..],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const IPV4: [u8; 4] = [0x45, 0x00, 0x00, 0x14];
+
+    fn ethernet(dst: [u8; 6], src: [u8; 6], ether_type: u16, payload: &[u8]) -> Vec<u8> {
+        let mut frame = vec![];
+        frame.extend_from_slice(&dst);
+        frame.extend_from_slice(&src);
+        frame.extend_from_slice(&ether_type.to_be_bytes());
+        frame.extend_from_slice(payload);
+        frame
+    }
+
+    #[test]
+    fn core_link_raw_and_null() {
+        let mut buf = vec![];
+        let expected = ethernet([0; 6], [0; 6], ETHERTYPE_IPV4, &IPV4);
+        assert_eq!(
+            to_ethernet(LINKTYPE_RAW, &IPV4, &mut buf).unwrap(),
+            &expected[..]
+        );
+        assert!(to_ethernet(LINKTYPE_RAW, &[0x10], &mut buf).is_none());
+
+        let data = [[2, 0, 0, 0].as_slice(), &IPV4].concat();
+        assert_eq!(
+            to_ethernet(LINKTYPE_NULL, &data, &mut buf).unwrap(),
+            &expected[..]
+        );
+        let data = [[0, 0, 0, 30].as_slice(), &[0x60]].concat();
+        let expected = ethernet([0; 6], [0; 6], ETHERTYPE_IPV6, &[0x60]);
+        assert_eq!(
+            to_ethernet(LINKTYPE_LOOP, &data, &mut buf).unwrap(),
+            &expected[..]
+        );
+    }
+
+    #[test]
+    fn core_link_sll() {
+        let mut buf = vec![];
+        let mac = [0x02, 0x42, 0xac, 0x11, 0x00, 0x02];
+
+        let mut sll = vec![0x00, 0x00, 0x00, 0x01, 0x00, 0x06];
+        sll.extend_from_slice(&mac);
+        sll.extend_from_slice(&[0, 0, 0x08, 0x00]);
+        sll.extend_from_slice(&IPV4);
+        let expected = ethernet([0; 6], mac, ETHERTYPE_IPV4, &IPV4);
+        assert_eq!(
+            to_ethernet(LINKTYPE_LINUX_SLL, &sll, &mut buf).unwrap(),
+            &expected[..]
+        );
+
+        let mut sll2 = vec![0x08, 0x00, 0, 0, 0, 0, 0, 3, 0x00, 0x01, 0x04, 0x06];
+        sll2.extend_from_slice(&mac);
+        sll2.extend_from_slice(&[0, 0]);
+        sll2.extend_from_slice(&IPV4);
+        assert_eq!(
+            to_ethernet(LINKTYPE_LINUX_SLL2, &sll2, &mut buf).unwrap(),
+            &expected[..]
+        );
+        assert!(to_ethernet(LINKTYPE_LINUX_SLL2, &sll2[..10], &mut buf).is_none());
+    }
+
+    #[test]
+    fn core_link_radiotap() {
+        let mut buf = vec![];
+        let (bssid, sta, dst) = ([1; 6], [2; 6], [3; 6]);
+
+        // Radiotap header with TSFT and Flags (FCS present)
+        let mut data = vec![0x00, 0x00, 0x12, 0x00, 0x03, 0x00, 0x00, 0x00];
+        data.extend_from_slice(&[0; 8]);
+        data.extend_from_slice(&[0x10, 0x00]);
+        // QoS data frame, ToDS
+        data.extend_from_slice(&[0x88, 0x01, 0x00, 0x00]);
+        data.extend_from_slice(&bssid);
+        data.extend_from_slice(&sta);
+        data.extend_from_slice(&dst);
+        data.extend_from_slice(&[0x00, 0x00, 0x00, 0x00]);
+        data.extend_from_slice(&[0xaa, 0xaa, 0x03, 0x00, 0x00, 0x00, 0x08, 0x00]);
+        data.extend_from_slice(&IPV4);
+        data.extend_from_slice(&[0xde, 0xad, 0xbe, 0xef]);
+
+        let expected = ethernet(dst, sta, ETHERTYPE_IPV4, &IPV4);
+        let converted = to_ethernet(LINKTYPE_IEEE802_11_RADIOTAP, &data, &mut buf).unwrap();
+        assert_eq!(converted, &expected[..]);
+
+        // Protected frames cannot be converted
+        data[19] |= 0x40;
+        assert!(to_ethernet(LINKTYPE_IEEE802_11_RADIOTAP, &data, &mut buf).is_none());
+    }
+}
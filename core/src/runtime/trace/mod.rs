@@ -0,0 +1,348 @@
+//! Packet trace input for offline mode.
+//!
+//! A trace is a set of pcap and pcapng files that are read as one continuous stream of frames in
+//! timestamp order. Files are opened lazily, once the merged stream reaches the timestamp of their
+//! first frame, so that long sequences of rotating capture files only keep a few files open at a
+//! time while overlapping files (e.g., captured on different interfaces) are merged packet by
+//! packet.
+
+pub(crate) mod link;
+mod pcap;
+mod pcapng;
+
+use self::pcap::PcapReader;
+use self::pcapng::PcapngReader;
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, VecDeque};
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader};
+use std::mem;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Result};
+use thiserror::Error;
+
+/// Ethernet link-layer header type.
+pub(crate) const LINKTYPE_ETHERNET: u16 = 1;
+
+const PCAP_MAGIC: [[u8; 4]; 4] = [
+    [0xd4, 0xc3, 0xb2, 0xa1],
+    [0xa1, 0xb2, 0xc3, 0xd4],
+    [0x4d, 0x3c, 0xb2, 0xa1],
+    [0xa1, 0xb2, 0x3c, 0x4d],
+];
+const PCAPNG_MAGIC: [u8; 4] = [0x0a, 0x0d, 0x0d, 0x0a];
+
+/// Largest captured frame or block accepted, to detect corrupt files.
+const MAX_CAPTURE_LEN: u32 = 1 << 20;
+
+/// A captured frame.
+#[derive(Debug, Default)]
+pub(crate) struct Frame {
+    /// Capture timestamp (nanoseconds since the UNIX epoch).
+    pub(crate) ts: u64,
+    /// Link-layer header type (`LINKTYPE_*` value).
+    pub(crate) link_type: u16,
+    /// Length of the frame on the wire. At least the length of `data`, even if the capture
+    /// records a shorter length.
+    pub(crate) orig_len: u32,
+    /// Captured frame data.
+    pub(crate) data: Vec<u8>,
+}
+
+/// A pcap or pcapng file reader.
+enum Reader {
+    Pcap(PcapReader<BufReader<File>>),
+    Pcapng(PcapngReader<BufReader<File>>),
+}
+
+impl Reader {
+    fn open(path: &Path) -> Result<Self> {
+        let mut file = BufReader::new(File::open(path)?);
+        let magic = file.fill_buf()?;
+        if magic.len() >= 4 && PCAP_MAGIC.iter().any(|m| magic.starts_with(m)) {
+            Ok(Reader::Pcap(PcapReader::new(file)?))
+        } else if magic.starts_with(&PCAPNG_MAGIC) {
+            Ok(Reader::Pcapng(PcapngReader::new(file)?))
+        } else {
+            bail!(TraceError::UnknownFormat(path.to_path_buf()))
+        }
+    }
+
+    /// Reads the next frame into `frame`. Returns `false` at the end of the file.
+    fn read_frame(&mut self, frame: &mut Frame) -> Result<bool> {
+        match self {
+            Reader::Pcap(reader) => reader.read_frame(frame),
+            Reader::Pcapng(reader) => reader.read_frame(frame),
+        }
+    }
+}
+
+/// A trace file that has been opened and has a frame pending.
+struct Active {
+    path: PathBuf,
+    reader: Reader,
+    frame: Frame,
+}
+
+/// A trace file that has not been opened yet.
+struct Pending {
+    path: PathBuf,
+    first_ts: u64,
+}
+
+/// A merged stream of frames from multiple trace files.
+pub(crate) struct Trace {
+    pending: VecDeque<Pending>,
+    active: Vec<Option<Active>>,
+    /// Min-heap of (timestamp of pending frame, index into `active`).
+    heap: BinaryHeap<Reverse<(u64, usize)>>,
+    current: Frame,
+}
+
+impl Trace {
+    /// Opens the trace made of `paths`. Each path may be a file, a directory (whose files are
+    /// all read), or a glob pattern.
+    pub(crate) fn open(paths: &[String]) -> Result<Self> {
+        let mut files = vec![];
+        for path in paths {
+            files.extend(expand(path)?);
+        }
+        files.sort();
+        files.dedup();
+
+        let mut pending = vec![];
+        for path in files {
+            let mut frame = Frame::default();
+            match Reader::open(&path).and_then(|mut reader| reader.read_frame(&mut frame)) {
+                Ok(true) => pending.push(Pending {
+                    path,
+                    first_ts: frame.ts,
+                }),
+                Ok(false) => log::warn!("Skipping empty trace file {:?}", path),
+                Err(error) => log::warn!("Skipping trace file {:?}: {}", path, error),
+            }
+        }
+        if pending.is_empty() {
+            bail!(TraceError::Empty(paths.to_vec()));
+        }
+        pending.sort_by_key(|p| p.first_ts);
+        log::info!("Reading {} trace files", pending.len());
+
+        Ok(Trace {
+            pending: pending.into(),
+            active: vec![],
+            heap: BinaryHeap::new(),
+            current: Frame::default(),
+        })
+    }
+
+    /// Returns the next frame in timestamp order, or `None` at the end of the trace.
+    pub(crate) fn next_frame(&mut self) -> Option<&Frame> {
+        // Open files that start before the earliest pending frame
+        while let Some(next) = self.pending.front() {
+            if self
+                .heap
+                .peek()
+                .is_some_and(|Reverse((ts, _))| *ts < next.first_ts)
+            {
+                break;
+            }
+            let next = self.pending.pop_front().unwrap();
+            if let Err(error) = self.activate(next.path.clone()) {
+                log::error!("Failed to read trace file {:?}: {}", next.path, error);
+            }
+        }
+
+        let Reverse((_, idx)) = self.heap.pop()?;
+        let active = self.active[idx].as_mut().unwrap();
+        mem::swap(&mut self.current, &mut active.frame);
+        match active.reader.read_frame(&mut active.frame) {
+            Ok(true) => self.heap.push(Reverse((active.frame.ts, idx))),
+            Ok(false) => {
+                log::info!("Finished reading {:?}", active.path);
+                self.active[idx] = None;
+            }
+            Err(error) => {
+                log::error!("Failed to read trace file {:?}: {}", active.path, error);
+                self.active[idx] = None;
+            }
+        }
+        Some(&self.current)
+    }
+
+    fn activate(&mut self, path: PathBuf) -> Result<()> {
+        log::info!("Opening {:?}", path);
+        let mut reader = Reader::open(&path)?;
+        let mut frame = Frame::default();
+        if reader.read_frame(&mut frame)? {
+            let idx = match self.active.iter().position(|a| a.is_none()) {
+                Some(idx) => idx,
+                None => {
+                    self.active.push(None);
+                    self.active.len() - 1
+                }
+            };
+            self.heap.push(Reverse((frame.ts, idx)));
+            self.active[idx] = Some(Active {
+                path,
+                reader,
+                frame,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Expands a file, directory, or glob pattern into a list of files.
+fn expand(path: &str) -> Result<Vec<PathBuf>> {
+    let mut files = vec![];
+    if path.contains(['*', '?', '[']) {
+        for entry in glob::glob(path)? {
+            let entry = entry?;
+            if entry.is_file() {
+                files.push(entry);
+            }
+        }
+    } else {
+        let path = Path::new(path);
+        if path.is_dir() {
+            for entry in fs::read_dir(path)? {
+                let entry = entry?.path();
+                if entry.is_file() {
+                    files.push(entry);
+                }
+            }
+        } else if path.is_file() {
+            files.push(path.to_path_buf());
+        } else {
+            bail!(TraceError::NotFound(path.to_path_buf()));
+        }
+    }
+    Ok(files)
+}
+
+/// Reads exactly `buf.len()` bytes. Returns `false` if the reader is at the end of the file, and
+/// fails if the file ends in the middle of `buf`.
+fn read_or_eof<R: io::Read>(reader: &mut R, buf: &mut [u8]) -> Result<bool> {
+    let mut nb_read = 0;
+    while nb_read < buf.len() {
+        match reader.read(&mut buf[nb_read..]) {
+            Ok(0) if nb_read == 0 => return Ok(false),
+            Ok(0) => bail!(TraceError::Truncated),
+            Ok(n) => nb_read += n,
+            Err(error) if error.kind() == io::ErrorKind::Interrupted => (),
+            Err(error) => bail!(error),
+        }
+    }
+    Ok(true)
+}
+
+/// Byte order of a capture file.
+#[derive(Debug, Clone, Copy)]
+struct Endian {
+    big: bool,
+}
+
+impl Endian {
+    fn u16(&self, b: &[u8]) -> u16 {
+        let b = [b[0], b[1]];
+        if self.big {
+            u16::from_be_bytes(b)
+        } else {
+            u16::from_le_bytes(b)
+        }
+    }
+
+    fn u32(&self, b: &[u8]) -> u32 {
+        let b = [b[0], b[1], b[2], b[3]];
+        if self.big {
+            u32::from_be_bytes(b)
+        } else {
+            u32::from_le_bytes(b)
+        }
+    }
+
+    fn i64(&self, b: &[u8]) -> i64 {
+        let b = [b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]];
+        if self.big {
+            i64::from_be_bytes(b)
+        } else {
+            i64::from_le_bytes(b)
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub(crate) enum TraceError {
+    #[error("No such file or directory: {0:?}")]
+    NotFound(PathBuf),
+
+    #[error("Not a pcap or pcapng file: {0:?}")]
+    UnknownFormat(PathBuf),
+
+    #[error("No packets in {0:?}")]
+    Empty(Vec<String>),
+
+    #[error("Truncated capture file")]
+    Truncated,
+
+    #[error("Malformed capture file: {0}")]
+    Malformed(&'static str),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn write_pcap(path: &Path, timestamps: &[u32]) {
+        let mut file = File::create(path).unwrap();
+        file.write_all(&[0xd4, 0xc3, 0xb2, 0xa1, 2, 0, 4, 0])
+            .unwrap();
+        file.write_all(&[0; 8]).unwrap();
+        file.write_all(&65535u32.to_le_bytes()).unwrap();
+        file.write_all(&1u32.to_le_bytes()).unwrap();
+        for ts in timestamps {
+            file.write_all(&ts.to_le_bytes()).unwrap();
+            file.write_all(&0u32.to_le_bytes()).unwrap();
+            file.write_all(&4u32.to_le_bytes()).unwrap();
+            file.write_all(&4u32.to_le_bytes()).unwrap();
+            file.write_all(&ts.to_be_bytes()).unwrap();
+        }
+    }
+
+    #[test]
+    fn core_trace_merge() {
+        let dir = std::env::temp_dir().join(format!("retina_trace_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        // Rotated files, and one file that overlaps both
+        write_pcap(&dir.join("b.pcap"), &[5, 6, 7]);
+        write_pcap(&dir.join("a.pcap"), &[1, 2, 3]);
+        write_pcap(&dir.join("c.pcap"), &[2, 6]);
+        fs::write(dir.join("notes.txt"), "not a capture").unwrap();
+
+        let mut trace = Trace::open(&[dir.to_string_lossy().to_string()]).unwrap();
+        let mut timestamps = vec![];
+        while let Some(frame) = trace.next_frame() {
+            assert_eq!(frame.link_type, LINKTYPE_ETHERNET);
+            assert_eq!(
+                frame.data,
+                ((frame.ts / 1_000_000_000) as u32).to_be_bytes()
+            );
+            timestamps.push(frame.ts / 1_000_000_000);
+        }
+        assert_eq!(timestamps, vec![1, 2, 2, 3, 5, 6, 6, 7]);
+
+        let pattern = dir.join("[ab].pcap").to_string_lossy().to_string();
+        let mut trace = Trace::open(&[pattern]).unwrap();
+        let mut nb_frames = 0;
+        while trace.next_frame().is_some() {
+            nb_frames += 1;
+        }
+        assert_eq!(nb_frames, 6);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
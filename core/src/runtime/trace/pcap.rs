@@ -0,0 +1,101 @@
+//! Classic pcap file reader.
+
+use super::{read_or_eof, Endian, Frame, TraceError, MAX_CAPTURE_LEN};
+
+use std::io::Read;
+
+use anyhow::{bail, Result};
+
+const GLOBAL_HEADER_LEN: usize = 24;
+const RECORD_HEADER_LEN: usize = 16;
+
+/// Reads frames from a classic pcap file with microsecond or nanosecond timestamps.
+pub(super) struct PcapReader<R> {
+    reader: R,
+    endian: Endian,
+    /// Whether timestamps have nanosecond (instead of microsecond) resolution.
+    nanos: bool,
+    link_type: u16,
+}
+
+impl<R: Read> PcapReader<R> {
+    pub(super) fn new(mut reader: R) -> Result<Self> {
+        let mut header = [0; GLOBAL_HEADER_LEN];
+        if !read_or_eof(&mut reader, &mut header)? {
+            bail!(TraceError::Truncated);
+        }
+        let (big, nanos) = match header[..4] {
+            [0xd4, 0xc3, 0xb2, 0xa1] => (false, false),
+            [0xa1, 0xb2, 0xc3, 0xd4] => (true, false),
+            [0x4d, 0x3c, 0xb2, 0xa1] => (false, true),
+            [0xa1, 0xb2, 0x3c, 0x4d] => (true, true),
+            _ => bail!(TraceError::Malformed("invalid pcap magic number")),
+        };
+        let endian = Endian { big };
+        Ok(PcapReader {
+            reader,
+            endian,
+            nanos,
+            // Upper bits of the link type field carry FCS information
+            link_type: endian.u32(&header[20..24]) as u16,
+        })
+    }
+
+    pub(super) fn read_frame(&mut self, frame: &mut Frame) -> Result<bool> {
+        let mut header = [0; RECORD_HEADER_LEN];
+        if !read_or_eof(&mut self.reader, &mut header)? {
+            return Ok(false);
+        }
+        let ts_sec = self.endian.u32(&header[0..4]) as u64;
+        let ts_frac = self.endian.u32(&header[4..8]) as u64;
+        let cap_len = self.endian.u32(&header[8..12]);
+        if cap_len > MAX_CAPTURE_LEN {
+            bail!(TraceError::Malformed("captured length too large"));
+        }
+
+        frame.ts = ts_sec * 1_000_000_000 + if self.nanos { ts_frac } else { ts_frac * 1000 };
+        frame.link_type = self.link_type;
+        frame.orig_len = self.endian.u32(&header[12..16]).max(cap_len);
+        frame.data.resize(cap_len as usize, 0);
+        if !read_or_eof(&mut self.reader, &mut frame.data)? {
+            bail!(TraceError::Truncated);
+        }
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn core_pcap_read() {
+        // Big-endian, nanosecond resolution, raw IP link type
+        let mut data = vec![0xa1, 0xb2, 0x3c, 0x4d, 0, 2, 0, 4];
+        data.extend_from_slice(&[0; 8]);
+        data.extend_from_slice(&65535u32.to_be_bytes());
+        data.extend_from_slice(&101u32.to_be_bytes());
+        data.extend_from_slice(&10u32.to_be_bytes());
+        data.extend_from_slice(&20u32.to_be_bytes());
+        data.extend_from_slice(&3u32.to_be_bytes());
+        data.extend_from_slice(&60u32.to_be_bytes());
+        data.extend_from_slice(&[0x45, 0x00, 0x00]);
+        // Original length shorter than the captured length
+        data.extend_from_slice(&10u32.to_be_bytes());
+        data.extend_from_slice(&30u32.to_be_bytes());
+        data.extend_from_slice(&2u32.to_be_bytes());
+        data.extend_from_slice(&0u32.to_be_bytes());
+        data.extend_from_slice(&[0x45, 0x00]);
+
+        let mut reader = PcapReader::new(&data[..]).unwrap();
+        let mut frame = Frame::default();
+        assert!(reader.read_frame(&mut frame).unwrap());
+        assert_eq!(frame.ts, 10_000_000_020);
+        assert_eq!(frame.link_type, 101);
+        assert_eq!(frame.orig_len, 60);
+        assert_eq!(frame.data, vec![0x45, 0x00, 0x00]);
+        assert!(reader.read_frame(&mut frame).unwrap());
+        assert_eq!(frame.orig_len, 2);
+        assert!(!reader.read_frame(&mut frame).unwrap());
+    }
+}
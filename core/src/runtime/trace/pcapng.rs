@@ -0,0 +1,309 @@
+//! pcapng file reader.
+//!
+//! Supports multiple sections and interfaces, with per-interface link types, timestamp
+//! resolutions (`if_tsresol`), and timestamp offsets (`if_tsoffset`).
+
+use super::{read_or_eof, Endian, Frame, TraceError, MAX_CAPTURE_LEN};
+
+use std::io::Read;
+
+use anyhow::{bail, Result};
+
+const SECTION_HEADER_BLOCK: u32 = 0x0a0d0d0a;
+const INTERFACE_DESCRIPTION_BLOCK: u32 = 0x00000001;
+const PACKET_BLOCK: u32 = 0x00000002;
+const SIMPLE_PACKET_BLOCK: u32 = 0x00000003;
+const ENHANCED_PACKET_BLOCK: u32 = 0x00000006;
+
+const BYTE_ORDER_MAGIC: u32 = 0x1a2b3c4d;
+
+const OPT_ENDOFOPT: u16 = 0;
+const OPT_IF_NAME: u16 = 2;
+const OPT_IF_TSRESOL: u16 = 9;
+const OPT_IF_TSOFFSET: u16 = 14;
+
+/// Interface described by an Interface Description Block.
+#[derive(Debug)]
+struct Interface {
+    link_type: u16,
+    snaplen: u32,
+    /// Timestamp resolution, as in `if_tsresol`: `10^-n` seconds if the most significant bit is
+    /// unset, `2^-n` seconds otherwise.
+    tsresol: u8,
+    /// Offset added to timestamps (seconds).
+    tsoffset: i64,
+}
+
+impl Interface {
+    /// Converts a timestamp in interface units to nanoseconds since the UNIX epoch.
+    fn nanos(&self, ts: u64) -> u64 {
+        let ts = ts as u128;
+        let nanos = if self.tsresol & 0x80 == 0 {
+            let exp = (self.tsresol & 0x7f) as u32;
+            if exp <= 9 {
+                ts * 10u128.pow(9 - exp)
+            } else {
+                ts / 10u128.pow((exp - 9).min(38))
+            }
+        } else {
+            (ts * 1_000_000_000) >> (self.tsresol & 0x7f).min(127)
+        };
+        let nanos = nanos as i128 + self.tsoffset as i128 * 1_000_000_000;
+        nanos.clamp(0, u64::MAX as i128) as u64
+    }
+}
+
+/// Reads frames from a pcapng file.
+pub(super) struct PcapngReader<R> {
+    reader: R,
+    endian: Endian,
+    /// Interfaces of the current section.
+    interfaces: Vec<Interface>,
+    /// Timestamp of the last frame read, used for Simple Packet Blocks.
+    last_ts: u64,
+    body: Vec<u8>,
+}
+
+impl<R: Read> PcapngReader<R> {
+    pub(super) fn new(reader: R) -> Result<Self> {
+        Ok(PcapngReader {
+            reader,
+            endian: Endian { big: false },
+            interfaces: vec![],
+            last_ts: 0,
+            body: vec![],
+        })
+    }
+
+    pub(super) fn read_frame(&mut self, frame: &mut Frame) -> Result<bool> {
+        loop {
+            let Some(block_type) = self.read_block()? else {
+                return Ok(false);
+            };
+            match block_type {
+                SECTION_HEADER_BLOCK => self.interfaces.clear(),
+                INTERFACE_DESCRIPTION_BLOCK => self.parse_interface()?,
+                ENHANCED_PACKET_BLOCK => return self.parse_packet(frame, false),
+                PACKET_BLOCK => return self.parse_packet(frame, true),
+                SIMPLE_PACKET_BLOCK => return self.parse_simple_packet(frame),
+                _ => (),
+            }
+        }
+    }
+
+    /// Reads the next block into `self.body`, and returns its type.
+    fn read_block(&mut self) -> Result<Option<u32>> {
+        let mut header = [0; 8];
+        if !read_or_eof(&mut self.reader, &mut header)? {
+            return Ok(None);
+        }
+        let mut body_offset = 0;
+        // The section header sets the byte order of the blocks that follow it
+        if header[..4] == SECTION_HEADER_BLOCK.to_be_bytes() {
+            let mut magic = [0; 4];
+            if !read_or_eof(&mut self.reader, &mut magic)? {
+                bail!(TraceError::Truncated);
+            }
+            self.endian = if u32::from_be_bytes(magic) == BYTE_ORDER_MAGIC {
+                Endian { big: true }
+            } else if u32::from_le_bytes(magic) == BYTE_ORDER_MAGIC {
+                Endian { big: false }
+            } else {
+                bail!(TraceError::Malformed("invalid pcapng byte-order magic"));
+            };
+            body_offset = 4;
+        }
+
+        let block_type = self.endian.u32(&header[0..4]);
+        let total_len = self.endian.u32(&header[4..8]);
+        if total_len < 12 + body_offset || total_len & 3 != 0 || total_len > MAX_CAPTURE_LEN {
+            bail!(TraceError::Malformed("invalid pcapng block length"));
+        }
+        // Body and trailing block length
+        self.body.resize((total_len - 8 - body_offset) as usize, 0);
+        if !read_or_eof(&mut self.reader, &mut self.body)? {
+            bail!(TraceError::Truncated);
+        }
+        self.body.truncate(self.body.len() - 4);
+        Ok(Some(block_type))
+    }
+
+    fn parse_interface(&mut self) -> Result<()> {
+        if self.body.len() < 8 {
+            bail!(TraceError::Malformed("short interface description block"));
+        }
+        let mut interface = Interface {
+            link_type: self.endian.u16(&self.body[0..2]),
+            snaplen: self.endian.u32(&self.body[4..8]),
+            tsresol: 6,
+            tsoffset: 0,
+        };
+        let mut name = None;
+        let mut options = &self.body[8..];
+        while options.len() >= 4 {
+            let code = self.endian.u16(&options[0..2]);
+            let len = self.endian.u16(&options[2..4]) as usize;
+            let value = match options.get(4..4 + len) {
+                Some(value) => value,
+                None => break,
+            };
+            match code {
+                OPT_ENDOFOPT => break,
+                OPT_IF_NAME => name = Some(String::from_utf8_lossy(value).to_string()),
+                OPT_IF_TSRESOL if len == 1 => interface.tsresol = value[0],
+                OPT_IF_TSOFFSET if len == 8 => interface.tsoffset = self.endian.i64(value),
+                _ => (),
+            }
+            options = options.get(4 + len.div_ceil(4) * 4..).unwrap_or_default();
+        }
+        log::info!(
+            "pcapng interface {} ({}): link type {}, snaplen {}, tsresol {:#x}, tsoffset {}s",
+            self.interfaces.len(),
+            name.as_deref().unwrap_or("unnamed"),
+            interface.link_type,
+            interface.snaplen,
+            interface.tsresol,
+            interface.tsoffset,
+        );
+        self.interfaces.push(interface);
+        Ok(())
+    }
+
+    /// Parses an Enhanced Packet Block, or an obsolete Packet Block if `obsolete` is set. Both
+    /// share the same layout except for the width of the interface ID.
+    fn parse_packet(&mut self, frame: &mut Frame, obsolete: bool) -> Result<bool> {
+        const HEADER_LEN: usize = 20;
+        if self.body.len() < HEADER_LEN {
+            bail!(TraceError::Malformed("short packet block"));
+        }
+        let if_id = if obsolete {
+            self.endian.u16(&self.body[0..2]) as usize
+        } else {
+            self.endian.u32(&self.body[0..4]) as usize
+        };
+        let Some(interface) = self.interfaces.get(if_id) else {
+            bail!(TraceError::Malformed("packet block for unknown interface"));
+        };
+        let ts_high = self.endian.u32(&self.body[4..8]) as u64;
+        let ts_low = self.endian.u32(&self.body[8..12]) as u64;
+        let cap_len = self.endian.u32(&self.body[12..16]) as usize;
+        let Some(data) = self.body.get(HEADER_LEN..HEADER_LEN + cap_len) else {
+            bail!(TraceError::Malformed("packet data exceeds block"));
+        };
+
+        frame.ts = interface.nanos(ts_high << 32 | ts_low);
+        frame.link_type = interface.link_type;
+        frame.orig_len = self.endian.u32(&self.body[16..20]).max(cap_len as u32);
+        frame.data.clear();
+        frame.data.extend_from_slice(data);
+        self.last_ts = frame.ts;
+        Ok(true)
+    }
+
+    fn parse_simple_packet(&mut self, frame: &mut Frame) -> Result<bool> {
+        if self.body.len() < 4 {
+            bail!(TraceError::Malformed("short simple packet block"));
+        }
+        let Some(interface) = self.interfaces.first() else {
+            bail!(TraceError::Malformed("packet block for unknown interface"));
+        };
+        let orig_len = self.endian.u32(&self.body[0..4]);
+        let mut cap_len = (self.body.len() - 4).min(orig_len as usize);
+        if interface.snaplen > 0 {
+            cap_len = cap_len.min(interface.snaplen as usize);
+        }
+
+        // Simple Packet Blocks carry no timestamp
+        frame.ts = self.last_ts;
+        frame.link_type = interface.link_type;
+        frame.orig_len = orig_len;
+        frame.data.clear();
+        frame.data.extend_from_slice(&self.body[4..4 + cap_len]);
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block(block_type: u32, body: &[u8]) -> Vec<u8> {
+        let mut body = body.to_vec();
+        while body.len() & 3 != 0 {
+            body.push(0);
+        }
+        let total_len = (body.len() + 12) as u32;
+        let mut block = vec![];
+        block.extend_from_slice(&block_type.to_le_bytes());
+        block.extend_from_slice(&total_len.to_le_bytes());
+        block.extend_from_slice(&body);
+        block.extend_from_slice(&total_len.to_le_bytes());
+        block
+    }
+
+    fn interface(link_type: u16, tsresol: Option<u8>) -> Vec<u8> {
+        let mut body = vec![];
+        body.extend_from_slice(&link_type.to_le_bytes());
+        body.extend_from_slice(&[0, 0]);
+        body.extend_from_slice(&0u32.to_le_bytes());
+        body.extend_from_slice(&OPT_IF_NAME.to_le_bytes());
+        body.extend_from_slice(&4u16.to_le_bytes());
+        body.extend_from_slice(b"eth0");
+        if let Some(tsresol) = tsresol {
+            body.extend_from_slice(&OPT_IF_TSRESOL.to_le_bytes());
+            body.extend_from_slice(&1u16.to_le_bytes());
+            body.extend_from_slice(&[tsresol, 0, 0, 0]);
+        }
+        body.extend_from_slice(&[0; 4]);
+        block(INTERFACE_DESCRIPTION_BLOCK, &body)
+    }
+
+    fn enhanced_packet(if_id: u32, ts: u64, data: &[u8]) -> Vec<u8> {
+        let mut body = vec![];
+        body.extend_from_slice(&if_id.to_le_bytes());
+        body.extend_from_slice(&((ts >> 32) as u32).to_le_bytes());
+        body.extend_from_slice(&(ts as u32).to_le_bytes());
+        body.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        body.extend_from_slice(&(data.len() as u32 + 10).to_le_bytes());
+        body.extend_from_slice(data);
+        block(ENHANCED_PACKET_BLOCK, &body)
+    }
+
+    #[test]
+    fn core_pcapng_read() {
+        let mut shb = vec![];
+        shb.extend_from_slice(&BYTE_ORDER_MAGIC.to_le_bytes());
+        shb.extend_from_slice(&[1, 0, 0, 0]);
+        shb.extend_from_slice(&(-1i64).to_le_bytes());
+
+        let mut data = block(SECTION_HEADER_BLOCK, &shb);
+        // Ethernet with default microsecond resolution, raw IP with nanosecond resolution
+        data.extend(interface(1, None));
+        data.extend(interface(101, Some(9)));
+        data.extend(enhanced_packet(0, 1_500_000, &[1, 2, 3, 4, 5]));
+        data.extend(enhanced_packet(1, 2_000_000_123, &[6, 7]));
+        data.extend(block(0x0000_0bad, &[0; 8]));
+        // A new section resets the interfaces
+        data.extend(block(SECTION_HEADER_BLOCK, &shb));
+        data.extend(interface(1, Some(0x80 | 10)));
+        data.extend(enhanced_packet(0, 3 << 10, &[8]));
+
+        let mut reader = PcapngReader::new(&data[..]).unwrap();
+        let mut frame = Frame::default();
+        assert!(reader.read_frame(&mut frame).unwrap());
+        assert_eq!(frame.ts, 1_500_000_000);
+        assert_eq!(frame.link_type, 1);
+        assert_eq!(frame.orig_len, 15);
+        assert_eq!(frame.data, vec![1, 2, 3, 4, 5]);
+
+        assert!(reader.read_frame(&mut frame).unwrap());
+        assert_eq!(frame.ts, 2_000_000_123);
+        assert_eq!(frame.link_type, 101);
+        assert_eq!(frame.data, vec![6, 7]);
+
+        assert!(reader.read_frame(&mut frame).unwrap());
+        assert_eq!(frame.ts, 3_000_000_000);
+        assert_eq!(frame.data, vec![8]);
+        assert!(!reader.read_frame(&mut frame).unwrap());
+    }
+}
@@ -0,0 +1,768 @@
+//! File encoders for each sink format.
+
+use super::{Compression, Format, SinkConfig};
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::mem;
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use arrow_array::RecordBatch;
+use arrow_ipc::writer::{FileWriter as IpcWriter, IpcWriteOptions};
+use arrow_json::reader::infer_json_schema_from_iterator;
+use arrow_json::ReaderBuilder;
+use arrow_schema::{DataType, Field, FieldRef, Fields, Schema, SchemaRef};
+use flate2::write::GzEncoder;
+use parquet::arrow::ArrowWriter;
+use parquet::basic::{GzipLevel, ZstdLevel};
+use parquet::file::properties::WriterProperties;
+use serde::ser;
+use serde::Serialize;
+use serde_json::value::Serializer as ValueSerializer;
+use serde_json::Value;
+
+/// Encodes records into a single file.
+pub(super) enum Encoder {
+    Jsonl(TextWriter),
+    Csv(Box<CsvWriter>),
+    Columnar(ColumnarWriter),
+}
+
+impl Encoder {
+    /// Creates the file at `path`.
+    pub(super) fn new(path: &Path, config: &SinkConfig) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let file = File::create(path)?;
+        Ok(match config.format {
+            Format::Jsonl => Encoder::Jsonl(TextWriter::new(file, config.compression)?),
+            Format::Csv => Encoder::Csv(Box::new(CsvWriter {
+                wtr: csv::Writer::from_writer(TextWriter::new(file, config.compression)?),
+                header: None,
+            })),
+            Format::Parquet | Format::ArrowIpc => Encoder::Columnar(ColumnarWriter {
+                file: Some(file),
+                format: config.format,
+                compression: config.compression,
+                batch_size: config.batch_size,
+                rows: Vec::with_capacity(config.batch_size),
+                writer: None,
+            }),
+        })
+    }
+
+    pub(super) fn write<T: Serialize>(&mut self, record: &T) -> Result<()> {
+        match self {
+            Encoder::Jsonl(wtr) => {
+                serde_json::to_writer(&mut *wtr, record)?;
+                wtr.write_all(b"\n")?;
+            }
+            Encoder::Csv(wtr) => wtr.write(record)?,
+            Encoder::Columnar(wtr) => {
+                wtr.rows.push(serde_json::to_value(record)?);
+                if wtr.rows.len() >= wtr.batch_size {
+                    wtr.write_batch()?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Buffers records that did not fit the schema of the previous file, to be written to this
+    /// file.
+    pub(super) fn requeue(&mut self, rows: Vec<Value>) {
+        if let Encoder::Columnar(wtr) = self {
+            wtr.rows = rows;
+        }
+    }
+
+    pub(super) fn flush(&mut self) -> Result<()> {
+        match self {
+            Encoder::Jsonl(wtr) => wtr.flush()?,
+            Encoder::Csv(wtr) => wtr.wtr.flush()?,
+            Encoder::Columnar(wtr) => {
+                wtr.write_batch()?;
+                match &mut wtr.writer {
+                    Some((_, BatchWriter::Parquet(writer))) => writer.flush()?,
+                    Some((_, BatchWriter::Ipc(writer))) => writer.flush()?,
+                    None => (),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes any buffered records and the file trailer, if any. The file is finished even if the
+    /// buffered records do not fit its schema.
+    pub(super) fn finish(self) -> Result<()> {
+        match self {
+            Encoder::Jsonl(wtr) => wtr.finish()?,
+            Encoder::Csv(wtr) => wtr
+                .wtr
+                .into_inner()
+                .map_err(|err| err.into_error())?
+                .finish()?,
+            Encoder::Columnar(mut wtr) => {
+                let written = wtr.write_batch();
+                match wtr.writer {
+                    Some((_, BatchWriter::Parquet(writer))) => {
+                        writer.close()?;
+                    }
+                    Some((_, BatchWriter::Ipc(mut writer))) => writer.finish()?,
+                    None => (),
+                }
+                written?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Encoder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Encoder::Jsonl(_) => write!(f, "Encoder::Jsonl"),
+            Encoder::Csv(_) => write!(f, "Encoder::Csv"),
+            Encoder::Columnar(wtr) => write!(f, "Encoder::Columnar({:?})", wtr.format),
+        }
+    }
+}
+
+/// A text file, optionally compressed as a whole.
+pub(super) enum TextWriter {
+    Plain(BufWriter<File>),
+    Gzip(GzEncoder<BufWriter<File>>),
+    Zstd(zstd::Encoder<'static, BufWriter<File>>),
+}
+
+impl TextWriter {
+    fn new(file: File, compression: Compression) -> io::Result<Self> {
+        let file = BufWriter::new(file);
+        Ok(match compression {
+            Compression::None => TextWriter::Plain(file),
+            Compression::Gzip => TextWriter::Gzip(GzEncoder::new(file, Default::default())),
+            Compression::Zstd => TextWriter::Zstd(zstd::Encoder::new(file, 0)?),
+        })
+    }
+
+    /// Writes the compression trailer, if any, and flushes the file.
+    fn finish(self) -> io::Result<()> {
+        let mut file = match self {
+            TextWriter::Plain(file) => file,
+            TextWriter::Gzip(wtr) => wtr.finish()?,
+            TextWriter::Zstd(wtr) => wtr.finish()?,
+        };
+        file.flush()
+    }
+}
+
+impl Write for TextWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            TextWriter::Plain(wtr) => wtr.write(buf),
+            TextWriter::Gzip(wtr) => wtr.write(buf),
+            TextWriter::Zstd(wtr) => wtr.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            TextWriter::Plain(wtr) => wtr.flush(),
+            TextWriter::Gzip(wtr) => wtr.flush(),
+            TextWriter::Zstd(wtr) => wtr.flush(),
+        }
+    }
+}
+
+/// A CSV file with columns derived from the record type.
+pub(super) struct CsvWriter {
+    wtr: csv::Writer<TextWriter>,
+    /// Column names, once the header has been written.
+    header: Option<Vec<String>>,
+}
+
+impl CsvWriter {
+    fn write<T: Serialize>(&mut self, record: &T) -> Result<()> {
+        let mut fields = vec![];
+        record.serialize(Columns {
+            name: String::new(),
+            fields: &mut fields,
+        })?;
+        let header = match self.header.take() {
+            Some(header) => header,
+            None => {
+                let header = fields.iter().map(|(name, _)| name.clone()).collect();
+                self.wtr.write_record(&header)?;
+                header
+            }
+        };
+        let header = self.header.insert(header);
+        if fields.iter().map(|(name, _)| name).eq(header.iter()) {
+            self.wtr
+                .write_record(fields.iter().map(|(_, value)| csv_value(value)))?;
+        } else {
+            // Fields missing from the header are dropped, missing fields are left empty
+            self.wtr.write_record(header.iter().map(|name| {
+                fields
+                    .iter()
+                    .find(|(field, _)| field == name)
+                    .map_or_else(String::new, |(_, value)| csv_value(value))
+            }))?;
+        }
+        Ok(())
+    }
+}
+
+fn csv_value(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        value => value.to_string(),
+    }
+}
+
+/// Serializes a record into CSV fields, in the order the record type declares them.
+///
+/// Fields of nested structs are flattened into columns named by their dot-separated path. Any
+/// other value, including optional structs, sequences, and maps, is a single column holding its
+/// JSON representation, so that the columns depend on the record type rather than on its values.
+struct Columns<'a> {
+    /// Column name of the value. Empty for the record itself.
+    name: String,
+    fields: &'a mut Vec<(String, Value)>,
+}
+
+impl Columns<'_> {
+    fn push(self, value: Value) -> Result<(), serde_json::Error> {
+        let name = if self.name.is_empty() {
+            "value".to_string()
+        } else {
+            self.name
+        };
+        self.fields.push((name, value));
+        Ok(())
+    }
+
+    fn nested(&mut self, key: &str) -> Columns<'_> {
+        let name = if self.name.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}.{}", self.name, key)
+        };
+        Columns {
+            name,
+            fields: self.fields,
+        }
+    }
+}
+
+macro_rules! serialize_value {
+    ($($method:ident($ty:ty)),* $(,)?) => {
+        $(
+            fn $method(self, v: $ty) -> Result<(), serde_json::Error> {
+                self.push(ValueSerializer.$method(v)?)
+            }
+        )*
+    };
+}
+
+impl<'a> ser::Serializer for Columns<'a> {
+    type Ok = ();
+    type Error = serde_json::Error;
+    type SerializeSeq = Column<'a, <ValueSerializer as ser::Serializer>::SerializeSeq>;
+    type SerializeTuple = Column<'a, <ValueSerializer as ser::Serializer>::SerializeTuple>;
+    type SerializeTupleStruct =
+        Column<'a, <ValueSerializer as ser::Serializer>::SerializeTupleStruct>;
+    type SerializeTupleVariant =
+        Column<'a, <ValueSerializer as ser::Serializer>::SerializeTupleVariant>;
+    type SerializeMap = Column<'a, <ValueSerializer as ser::Serializer>::SerializeMap>;
+    type SerializeStruct = Self;
+    type SerializeStructVariant =
+        Column<'a, <ValueSerializer as ser::Serializer>::SerializeStructVariant>;
+
+    serialize_value!(
+        serialize_bool(bool),
+        serialize_i8(i8),
+        serialize_i16(i16),
+        serialize_i32(i32),
+        serialize_i64(i64),
+        serialize_i128(i128),
+        serialize_u8(u8),
+        serialize_u16(u16),
+        serialize_u32(u32),
+        serialize_u64(u64),
+        serialize_u128(u128),
+        serialize_f32(f32),
+        serialize_f64(f64),
+        serialize_char(char),
+        serialize_str(&str),
+        serialize_bytes(&[u8]),
+    );
+
+    fn serialize_none(self) -> Result<(), serde_json::Error> {
+        self.push(Value::Null)
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<(), serde_json::Error> {
+        self.push(serde_json::to_value(value)?)
+    }
+
+    fn serialize_unit(self) -> Result<(), serde_json::Error> {
+        self.push(Value::Null)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), serde_json::Error> {
+        self.push(Value::Null)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        name: &'static str,
+        variant_index: u32,
+        variant: &'static str,
+    ) -> Result<(), serde_json::Error> {
+        self.push(ValueSerializer.serialize_unit_variant(name, variant_index, variant)?)
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<(), serde_json::Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        name: &'static str,
+        variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<(), serde_json::Error> {
+        self.push(ValueSerializer.serialize_newtype_variant(name, variant_index, variant, value)?)
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq, serde_json::Error> {
+        Ok(Column {
+            value: ValueSerializer.serialize_seq(len)?,
+            columns: self,
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, serde_json::Error> {
+        Ok(Column {
+            value: ValueSerializer.serialize_tuple(len)?,
+            columns: self,
+        })
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleStruct, serde_json::Error> {
+        Ok(Column {
+            value: ValueSerializer.serialize_tuple_struct(name, len)?,
+            columns: self,
+        })
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        name: &'static str,
+        variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleVariant, serde_json::Error> {
+        Ok(Column {
+            value: ValueSerializer.serialize_tuple_variant(name, variant_index, variant, len)?,
+            columns: self,
+        })
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap, serde_json::Error> {
+        Ok(Column {
+            value: ValueSerializer.serialize_map(len)?,
+            columns: self,
+        })
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, serde_json::Error> {
+        Ok(self)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        name: &'static str,
+        variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStructVariant, serde_json::Error> {
+        Ok(Column {
+            value: ValueSerializer.serialize_struct_variant(name, variant_index, variant, len)?,
+            columns: self,
+        })
+    }
+}
+
+impl ser::SerializeStruct for Columns<'_> {
+    type Ok = ();
+    type Error = serde_json::Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), serde_json::Error> {
+        value.serialize(self.nested(key))
+    }
+
+    fn end(self) -> Result<(), serde_json::Error> {
+        Ok(())
+    }
+}
+
+/// A compound value serialized into a single column.
+struct Column<'a, S> {
+    value: S,
+    columns: Columns<'a>,
+}
+
+impl<S> ser::SerializeSeq for Column<'_, S>
+where
+    S: ser::SerializeSeq<Ok = Value, Error = serde_json::Error>,
+{
+    type Ok = ();
+    type Error = serde_json::Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(
+        &mut self,
+        value: &T,
+    ) -> Result<(), serde_json::Error> {
+        self.value.serialize_element(value)
+    }
+
+    fn end(self) -> Result<(), serde_json::Error> {
+        self.columns.push(self.value.end()?)
+    }
+}
+
+impl<S> ser::SerializeTuple for Column<'_, S>
+where
+    S: ser::SerializeTuple<Ok = Value, Error = serde_json::Error>,
+{
+    type Ok = ();
+    type Error = serde_json::Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(
+        &mut self,
+        value: &T,
+    ) -> Result<(), serde_json::Error> {
+        self.value.serialize_element(value)
+    }
+
+    fn end(self) -> Result<(), serde_json::Error> {
+        self.columns.push(self.value.end()?)
+    }
+}
+
+impl<S> ser::SerializeTupleStruct for Column<'_, S>
+where
+    S: ser::SerializeTupleStruct<Ok = Value, Error = serde_json::Error>,
+{
+    type Ok = ();
+    type Error = serde_json::Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        value: &T,
+    ) -> Result<(), serde_json::Error> {
+        self.value.serialize_field(value)
+    }
+
+    fn end(self) -> Result<(), serde_json::Error> {
+        self.columns.push(self.value.end()?)
+    }
+}
+
+impl<S> ser::SerializeTupleVariant for Column<'_, S>
+where
+    S: ser::SerializeTupleVariant<Ok = Value, Error = serde_json::Error>,
+{
+    type Ok = ();
+    type Error = serde_json::Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        value: &T,
+    ) -> Result<(), serde_json::Error> {
+        self.value.serialize_field(value)
+    }
+
+    fn end(self) -> Result<(), serde_json::Error> {
+        self.columns.push(self.value.end()?)
+    }
+}
+
+impl<S> ser::SerializeMap for Column<'_, S>
+where
+    S: ser::SerializeMap<Ok = Value, Error = serde_json::Error>,
+{
+    type Ok = ();
+    type Error = serde_json::Error;
+
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<(), serde_json::Error> {
+        self.value.serialize_key(key)
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(
+        &mut self,
+        value: &T,
+    ) -> Result<(), serde_json::Error> {
+        self.value.serialize_value(value)
+    }
+
+    fn end(self) -> Result<(), serde_json::Error> {
+        self.columns.push(self.value.end()?)
+    }
+}
+
+impl<S> ser::SerializeStructVariant for Column<'_, S>
+where
+    S: ser::SerializeStructVariant<Ok = Value, Error = serde_json::Error>,
+{
+    type Ok = ();
+    type Error = serde_json::Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), serde_json::Error> {
+        self.value.serialize_field(key, value)
+    }
+
+    fn end(self) -> Result<(), serde_json::Error> {
+        self.columns.push(self.value.end()?)
+    }
+}
+
+/// A Parquet or Arrow IPC file, written in record batches.
+pub(super) struct ColumnarWriter {
+    /// File to write to, until the schema is known.
+    file: Option<File>,
+    format: Format,
+    compression: Compression,
+    batch_size: usize,
+    /// Buffered records.
+    rows: Vec<Value>,
+    /// Schema of the file and its writer, once the schema has been inferred.
+    writer: Option<(SchemaRef, BatchWriter)>,
+}
+
+enum BatchWriter {
+    Parquet(ArrowWriter<File>),
+    Ipc(IpcWriter<BufWriter<File>>),
+}
+
+impl ColumnarWriter {
+    /// Writes buffered records as a record batch. The schema of the file is inferred from the
+    /// first batch. Returns a [SchemaConflict] with the buffered records if they do not fit the
+    /// schema of the file.
+    fn write_batch(&mut self) -> Result<()> {
+        if self.rows.is_empty() {
+            return Ok(());
+        }
+        let (writer, inferred) = match self.writer.take() {
+            Some(writer) => (writer, false),
+            None => (self.create_writer()?, true),
+        };
+        let (schema, writer) = self.writer.insert(writer);
+
+        let rows = mem::take(&mut self.rows);
+        let batch = match decode(schema, &rows) {
+            Ok(batch) => batch,
+            Err(err) if !inferred => return Err(SchemaConflict { rows, source: err }.into()),
+            Err(err) => return Err(err),
+        };
+        if let Some(batch) = batch {
+            write_batch(writer, &batch)?;
+        }
+        Ok(())
+    }
+
+    /// Creates the writer, with the schema inferred from the buffered records.
+    fn create_writer(&mut self) -> Result<(SchemaRef, BatchWriter)> {
+        let schema = infer_json_schema_from_iterator(self.rows.iter().map(Ok))?;
+        let schema = Arc::new(Schema::new(
+            schema
+                .fields()
+                .iter()
+                .map(resolve_null_type)
+                .collect::<Fields>(),
+        ));
+        let file = self.file.take().ok_or_else(|| anyhow!("Missing file"))?;
+        let writer = match self.format {
+            Format::Parquet => {
+                let compression = match self.compression {
+                    Compression::None => parquet::basic::Compression::UNCOMPRESSED,
+                    Compression::Gzip => parquet::basic::Compression::GZIP(GzipLevel::default()),
+                    Compression::Zstd => parquet::basic::Compression::ZSTD(ZstdLevel::default()),
+                };
+                let props = WriterProperties::builder()
+                    .set_compression(compression)
+                    .build();
+                BatchWriter::Parquet(ArrowWriter::try_new(
+                    file,
+                    Arc::clone(&schema),
+                    Some(props),
+                )?)
+            }
+            _ => {
+                let compression = match self.compression {
+                    Compression::Zstd => Some(arrow_ipc::CompressionType::ZSTD),
+                    _ => None,
+                };
+                let options = IpcWriteOptions::default().try_with_compression(compression)?;
+                BatchWriter::Ipc(IpcWriter::try_new_with_options(
+                    BufWriter::new(file),
+                    &schema,
+                    options,
+                )?)
+            }
+        };
+        Ok((schema, writer))
+    }
+}
+
+/// Records that do not fit the schema of their Parquet or Arrow IPC file, e.g., because a field
+/// was only null in the records the schema was inferred from. The file is finished without them,
+/// and they are written to a new file instead.
+#[derive(Debug)]
+pub(super) struct SchemaConflict {
+    pub(super) rows: Vec<Value>,
+    source: anyhow::Error,
+}
+
+impl fmt::Display for SchemaConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Records do not match the file schema: {}", self.source)
+    }
+}
+
+impl std::error::Error for SchemaConflict {}
+
+/// Replaces the type of fields that were only null in the inferred records (and of their nested
+/// fields) with strings, so that later values of any primitive type can be written to them.
+fn resolve_null_type(field: &FieldRef) -> FieldRef {
+    let data_type = match field.data_type() {
+        DataType::Null => DataType::Utf8,
+        DataType::Struct(fields) => {
+            DataType::Struct(fields.iter().map(resolve_null_type).collect())
+        }
+        DataType::List(item) => DataType::List(resolve_null_type(item)),
+        _ => return Arc::clone(field),
+    };
+    Arc::new(Field::clone(field).with_data_type(data_type))
+}
+
+/// Decodes `rows` into a record batch with `schema`. Fields missing from the schema are ignored.
+fn decode(schema: &SchemaRef, rows: &[Value]) -> Result<Option<RecordBatch>> {
+    let mut decoder = ReaderBuilder::new(Arc::clone(schema))
+        .with_coerce_primitive(true)
+        .with_batch_size(rows.len())
+        .build_decoder()?;
+    decoder.serialize(rows)?;
+    Ok(decoder.flush()?)
+}
+
+fn write_batch(writer: &mut BatchWriter, batch: &RecordBatch) -> Result<()> {
+    match writer {
+        BatchWriter::Parquet(writer) => writer.write(batch)?,
+        BatchWriter::Ipc(writer) => writer.write(batch)?,
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[derive(Serialize)]
+    struct Os {
+        name: &'static str,
+    }
+
+    #[derive(Serialize)]
+    struct Flow {
+        pkts: u64,
+        os: Option<Os>,
+    }
+
+    #[derive(Serialize)]
+    struct Record {
+        z: u32,
+        flow: Flow,
+        ports: Vec<u16>,
+        os: Option<Os>,
+    }
+
+    fn columns<T: Serialize>(record: &T) -> Vec<(String, Value)> {
+        let mut fields = vec![];
+        record
+            .serialize(Columns {
+                name: String::new(),
+                fields: &mut fields,
+            })
+            .unwrap();
+        fields
+    }
+
+    #[test]
+    fn core_sink_csv_columns() {
+        let names = |fields: &[(String, Value)]| {
+            fields
+                .iter()
+                .map(|(name, _)| name.clone())
+                .collect::<Vec<_>>()
+        };
+        let empty = columns(&Record {
+            z: 1,
+            flow: Flow { pkts: 2, os: None },
+            ports: vec![],
+            os: None,
+        });
+        let full = columns(&Record {
+            z: 1,
+            flow: Flow {
+                pkts: 2,
+                os: Some(Os { name: "linux" }),
+            },
+            ports: vec![80, 443],
+            os: Some(Os { name: "windows" }),
+        });
+        // Columns follow the declaration order and do not depend on the values
+        assert_eq!(names(&empty), ["z", "flow.pkts", "flow.os", "ports", "os"]);
+        assert_eq!(names(&full), names(&empty));
+        assert_eq!(
+            full.into_iter().map(|(_, value)| value).collect::<Vec<_>>(),
+            [
+                json!(1),
+                json!(2),
+                json!({"name": "linux"}),
+                json!([80, 443]),
+                json!({"name": "windows"})
+            ]
+        );
+        assert_eq!(columns(&"x"), [("value".to_string(), json!("x"))]);
+    }
+}
@@ -0,0 +1,600 @@
+//! IPFIX flow records over UDP.
+//!
+//! Flow records are exported as bidirectional flows
+//! ([RFC 5103](https://www.rfc-editor.org/rfc/rfc5103)) in
+//! [IPFIX](https://www.rfc-editor.org/rfc/rfc7011) messages, with one template for IPv4 flows (ID
+//! 256) and one for IPv6 flows (ID 257). Each template has the following fields:
+//!
+//! | Information element               | ID              | Description                   |
+//! |-----------------------------------|-----------------|-------------------------------|
+//! | `sourceIPv4Address`               | 8               | Originator address (IPv4)     |
+//! | `destinationIPv4Address`          | 12              | Responder address (IPv4)      |
+//! | `sourceIPv6Address`               | 27              | Originator address (IPv6)     |
+//! | `destinationIPv6Address`          | 28              | Responder address (IPv6)      |
+//! | `sourceTransportPort`             | 7               | Originator port               |
+//! | `destinationTransportPort`        | 11              | Responder port                |
+//! | `protocolIdentifier`              | 4               | Transport protocol            |
+//! | `flowStartMilliseconds`           | 152             | First packet                  |
+//! | `flowEndMilliseconds`             | 153             | Last packet                   |
+//! | `packetDeltaCount`                | 2               | Originator packets            |
+//! | `transportOctetDeltaCount`        | 401             | Originator payload bytes      |
+//! | `reversePacketDeltaCount`         | 2 (PEN 29305)   | Responder packets             |
+//! | `reverseTransportOctetDeltaCount` | 401 (PEN 29305) | Responder payload bytes       |
+//! | `flowEndReason`                   | 136             | Why the flow was reported     |
+//! | `serverName`                      | 1 (enterprise)  | TLS or QUIC SNI               |
+//! | `ja3`                             | 2 (enterprise)  | JA3 fingerprint of the client |
+//! | `applicationProtocol`             | 3 (enterprise)  | Application-layer protocol    |
+//!
+//! The enterprise elements are variable-length strings, and are empty if unknown. Records
+//! converted from a [Connection] leave them empty, so they must be filled in by the caller. Their
+//! private enterprise number is set by [enterprise_id](IpfixConfig::enterprise_id).
+//!
+//! Long-lived connections are reported periodically if the
+//! [active_timeout](crate::config::ConnTrackConfig::active_timeout) is set: each interim
+//! connection record is exported with the counters since the previous record.
+
+use super::{ExportConfig, Exporter, Transport};
+use crate::conntrack::clock;
+use crate::conntrack::conn_id::FiveTuple;
+use crate::subscription::Connection;
+
+use std::net::{IpAddr, SocketAddr, ToSocketAddrs, UdpSocket};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, bail, Result};
+use serde::{Deserialize, Serialize};
+
+/// IPFIX protocol version.
+const VERSION: u16 = 10;
+
+/// Set ID of template sets.
+const TEMPLATE_SET_ID: u16 = 2;
+
+/// Template ID of IPv4 flows.
+const IPV4_TEMPLATE_ID: u16 = 256;
+
+/// Template ID of IPv6 flows.
+const IPV6_TEMPLATE_ID: u16 = 257;
+
+/// Length of the message header.
+const MESSAGE_HEADER_LEN: usize = 16;
+
+/// Length of a set header.
+const SET_HEADER_LEN: usize = 4;
+
+/// Private enterprise number of reverse information elements (RFC 5103).
+const REVERSE_PEN: u32 = 29305;
+
+/// Field length of variable-length information elements.
+const VARIABLE_LEN: u16 = 0xffff;
+
+/// IPFIX export options.
+///
+/// ## Example
+/// ```toml
+/// [ipfix]
+///     collector = "10.0.0.2:4739"
+///     observation_domain = 1
+///     template_refresh = 300
+/// ```
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct IpfixConfig {
+    /// Collector address (`<host>:<port>`).
+    pub collector: String,
+
+    /// Observation domain ID of the exported messages. Defaults to `0`.
+    #[serde(default = "default_observation_domain")]
+    pub observation_domain: u32,
+
+    /// Private enterprise number of Retina's information elements (SNI, JA3, and application
+    /// protocol). Defaults to `32473`, the number reserved for documentation.
+    ///
+    /// ## Remarks
+    /// Collectors only decode enterprise-specific elements they are configured for, so this should
+    /// be set to the enterprise number used in the collector's element definitions.
+    #[serde(default = "default_enterprise_id")]
+    pub enterprise_id: u32,
+
+    /// Interval (in seconds) at which templates are resent, so that collectors that start or
+    /// restart after the exporter can decode records. Defaults to `600`.
+    #[serde(default = "default_template_refresh")]
+    pub template_refresh: u64,
+
+    /// Maximum size (in bytes) of an IPFIX message. Should fit in the path MTU to the collector to
+    /// avoid IP fragmentation. Defaults to `1400`.
+    #[serde(default = "default_max_message_size")]
+    pub max_message_size: usize,
+
+    /// Maximum time (in milliseconds) a record waits to be sent with other records. Defaults to
+    /// `100`.
+    #[serde(default = "default_linger")]
+    pub linger: u64,
+
+    /// Maximum number of records waiting to be sent. Records exported while the queue is full
+    /// are dropped. Defaults to `65536`.
+    #[serde(default = "default_queue_size")]
+    pub queue_size: usize,
+}
+
+impl IpfixConfig {
+    /// Returns the default options for exporting to `collector`.
+    pub fn new(collector: impl Into<String>) -> Self {
+        IpfixConfig {
+            collector: collector.into(),
+            observation_domain: default_observation_domain(),
+            enterprise_id: default_enterprise_id(),
+            template_refresh: default_template_refresh(),
+            max_message_size: default_max_message_size(),
+            linger: default_linger(),
+            queue_size: default_queue_size(),
+        }
+    }
+}
+
+fn default_observation_domain() -> u32 {
+    0
+}
+
+fn default_enterprise_id() -> u32 {
+    32473
+}
+
+fn default_template_refresh() -> u64 {
+    600
+}
+
+fn default_max_message_size() -> usize {
+    1400
+}
+
+fn default_linger() -> u64 {
+    100
+}
+
+fn default_queue_size() -> usize {
+    65536
+}
+
+/// Reason a flow record was exported (`flowEndReason`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum EndReason {
+    /// No packets were observed for the inactivity timeout.
+    IdleTimeout = 1,
+    /// The flow is still active, and the record reports it up to now.
+    ActiveTimeout = 2,
+    /// The end of the flow was observed (e.g., a TCP FIN or RST).
+    EndOfFlow = 3,
+    /// The flow was terminated while still active (e.g., it was evicted from a full connection
+    /// table, or the runtime stopped).
+    ForcedEnd = 4,
+}
+
+/// A bidirectional flow record.
+#[derive(Debug, Clone)]
+pub struct FlowRecord {
+    /// Flow 5-tuple.
+    pub five_tuple: FiveTuple,
+    /// Time of the first packet.
+    pub start: SystemTime,
+    /// Time of the last packet.
+    pub end: SystemTime,
+    /// Number of packets from the originator.
+    pub orig_pkts: u64,
+    /// Number of payload bytes from the originator.
+    pub orig_bytes: u64,
+    /// Number of packets from the responder.
+    pub resp_pkts: u64,
+    /// Number of payload bytes from the responder.
+    pub resp_bytes: u64,
+    /// Reason the record was exported.
+    pub end_reason: EndReason,
+    /// Server name indication, if known.
+    pub sni: Option<String>,
+    /// JA3 fingerprint of the client, if known.
+    pub ja3: Option<String>,
+    /// Application-layer protocol (e.g., `tls`), if known.
+    pub app_proto: Option<String>,
+}
+
+impl From<&Connection> for FlowRecord {
+    /// Returns the flow record of a connection, with the counters since its previous record. The
+    /// end reason of an interim record is [ActiveTimeout](EndReason::ActiveTimeout). The end
+    /// reason of a final record is [ForcedEnd](EndReason::ForcedEnd) if the connection was evicted
+    /// or still active at the end of the run, [EndOfFlow](EndReason::EndOfFlow) if a FIN or RST
+    /// was observed, and [IdleTimeout](EndReason::IdleTimeout) otherwise.
+    ///
+    /// Connection records do not carry application-layer data, so `sni`, `ja3`, and `app_proto`
+    /// are left empty. Callers that also subscribe to application-layer data (e.g.,
+    /// [TlsHandshake](crate::subscription::TlsHandshake)) must fill them in.
+    fn from(conn: &Connection) -> Self {
+        let start = clock::system_time(conn.delta.ts);
+        let end_reason = if conn.interim {
+            EndReason::ActiveTimeout
+        } else if conn.forced_end {
+            EndReason::ForcedEnd
+        } else if conn.history.iter().any(|e| b"FfRr".contains(e)) {
+            EndReason::EndOfFlow
+        } else {
+            EndReason::IdleTimeout
+        };
+        FlowRecord {
+            five_tuple: conn.five_tuple,
+            start,
+            end: start + conn.delta.duration,
+            orig_pkts: conn.delta.orig_pkts,
+            orig_bytes: conn.delta.orig_bytes,
+            resp_pkts: conn.delta.resp_pkts,
+            resp_bytes: conn.delta.resp_bytes,
+            end_reason,
+            sni: None,
+            ja3: None,
+            app_proto: None,
+        }
+    }
+}
+
+impl FlowRecord {
+    /// Encodes the record as a data record, prefixed by its template ID.
+    fn encode(&self) -> Result<Vec<u8>> {
+        let mut buf = Vec::with_capacity(128);
+        let (orig, resp) = (self.five_tuple.orig, self.five_tuple.resp);
+        match (orig.ip(), resp.ip()) {
+            (IpAddr::V4(src), IpAddr::V4(dst)) => {
+                buf.extend_from_slice(&IPV4_TEMPLATE_ID.to_be_bytes());
+                buf.extend_from_slice(&src.octets());
+                buf.extend_from_slice(&dst.octets());
+            }
+            (IpAddr::V6(src), IpAddr::V6(dst)) => {
+                buf.extend_from_slice(&IPV6_TEMPLATE_ID.to_be_bytes());
+                buf.extend_from_slice(&src.octets());
+                buf.extend_from_slice(&dst.octets());
+            }
+            _ => bail!("Mismatched address families: {}", self.five_tuple),
+        }
+        buf.extend_from_slice(&orig.port().to_be_bytes());
+        buf.extend_from_slice(&resp.port().to_be_bytes());
+        buf.push(self.five_tuple.proto as u8);
+        buf.extend_from_slice(&millis(self.start).to_be_bytes());
+        buf.extend_from_slice(&millis(self.end).to_be_bytes());
+        for count in [
+            self.orig_pkts,
+            self.orig_bytes,
+            self.resp_pkts,
+            self.resp_bytes,
+        ] {
+            buf.extend_from_slice(&count.to_be_bytes());
+        }
+        buf.push(self.end_reason as u8);
+        for value in [&self.sni, &self.ja3, &self.app_proto] {
+            let value = value.as_deref().unwrap_or_default().as_bytes();
+            let value = &value[..value.len().min(u16::MAX as usize)];
+            if value.len() < 255 {
+                buf.push(value.len() as u8);
+            } else {
+                buf.push(255);
+                buf.extend_from_slice(&(value.len() as u16).to_be_bytes());
+            }
+            buf.extend_from_slice(value);
+        }
+        Ok(buf)
+    }
+}
+
+/// Milliseconds since the Unix epoch.
+fn millis(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+/// Exports flow records to an IPFIX collector over UDP.
+///
+/// Records are queued and sent from a background thread, as with [Exporter](Exporter).
+#[derive(Debug)]
+pub struct IpfixExporter {
+    exporter: Exporter,
+}
+
+impl IpfixExporter {
+    /// Creates a new exporter to the collector in `config`.
+    pub fn new(config: IpfixConfig) -> Result<Self> {
+        if config.max_message_size > u16::MAX as usize {
+            bail!("IPFIX messages are limited to {} bytes", u16::MAX);
+        }
+        let export_config = ExportConfig {
+            batch_size: 64,
+            linger: config.linger,
+            queue_size: config.queue_size,
+            ..ExportConfig::new(format!("udp://{}", config.collector))
+        };
+        let transport = IpfixTransport::new(config);
+        Ok(IpfixExporter {
+            exporter: Exporter::with_transport(export_config, transport)?,
+        })
+    }
+
+    /// Queues `record` for export. The record is dropped if the queue is full.
+    pub fn export(&self, record: &FlowRecord) -> Result<()> {
+        self.exporter.queue(record.encode()?)
+    }
+
+    /// Returns the number of records sent to the collector.
+    pub fn nb_exported(&self) -> u64 {
+        self.exporter.nb_exported()
+    }
+
+    /// Returns the number of records dropped.
+    pub fn nb_dropped(&self) -> u64 {
+        self.exporter.nb_dropped()
+    }
+
+    /// Sends the remaining queued records and stops the background thread.
+    pub fn finish(self) -> Result<()> {
+        self.exporter.finish()
+    }
+}
+
+/// Packs encoded data records into IPFIX messages.
+#[derive(Debug)]
+struct IpfixTransport {
+    config: IpfixConfig,
+    socket: Option<UdpSocket>,
+    /// Encoded template set.
+    templates: Vec<u8>,
+    /// Time the templates were last sent, if they were sent since the last reconnection.
+    templates_sent: Option<Instant>,
+    /// Number of data records sent.
+    sequence: u32,
+}
+
+impl IpfixTransport {
+    fn new(config: IpfixConfig) -> Self {
+        let templates = template_set(config.enterprise_id);
+        IpfixTransport {
+            config,
+            socket: None,
+            templates,
+            templates_sent: None,
+            sequence: 0,
+        }
+    }
+
+    fn connect(&self) -> Result<UdpSocket> {
+        let addr = self
+            .config
+            .collector
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| anyhow!("Unresolved IPFIX collector: {}", self.config.collector))?;
+        let local = match addr {
+            SocketAddr::V4(_) => "0.0.0.0:0",
+            SocketAddr::V6(_) => "[::]:0",
+        };
+        let socket = UdpSocket::bind(local)?;
+        socket.connect(addr)?;
+        Ok(socket)
+    }
+
+    /// Starts a new message, with the templates if they are due.
+    fn new_message(&mut self) -> Message {
+        let refresh = Duration::from_secs(self.config.template_refresh);
+        let mut buf = vec![0; MESSAGE_HEADER_LEN];
+        let templates_due = match self.templates_sent {
+            Some(sent) => sent.elapsed() >= refresh,
+            None => true,
+        };
+        if templates_due {
+            buf.extend_from_slice(&self.templates);
+            self.templates_sent = Some(Instant::now());
+        }
+        Message {
+            buf,
+            set: None,
+            nb_records: 0,
+        }
+    }
+
+    /// Finishes and sends `msg`.
+    fn send_message(&mut self, mut msg: Message) -> Result<()> {
+        msg.close_set();
+        let len = msg.buf.len() as u16;
+        let export_time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs() as u32;
+        let mut header = Vec::with_capacity(MESSAGE_HEADER_LEN);
+        header.extend_from_slice(&VERSION.to_be_bytes());
+        header.extend_from_slice(&len.to_be_bytes());
+        header.extend_from_slice(&export_time.to_be_bytes());
+        header.extend_from_slice(&self.sequence.to_be_bytes());
+        header.extend_from_slice(&self.config.observation_domain.to_be_bytes());
+        msg.buf[..MESSAGE_HEADER_LEN].copy_from_slice(&header);
+
+        let socket = match self.socket.take() {
+            Some(socket) => socket,
+            None => self.connect()?,
+        };
+        let socket = self.socket.insert(socket);
+        socket.send(&msg.buf)?;
+        self.sequence = self.sequence.wrapping_add(msg.nb_records);
+        Ok(())
+    }
+}
+
+impl Transport for IpfixTransport {
+    fn send(&mut self, records: &[Vec<u8>]) -> Result<()> {
+        let mut msg = self.new_message();
+        for record in records {
+            let (id, data) = record.split_at(2);
+            let id = u16::from_be_bytes([id[0], id[1]]);
+            let set_len = if msg.set.is_some_and(|(set_id, _)| set_id == id) {
+                0
+            } else {
+                SET_HEADER_LEN
+            };
+            if msg.nb_records > 0
+                && msg.buf.len() + set_len + data.len() > self.config.max_message_size
+            {
+                self.send_message(msg)?;
+                msg = self.new_message();
+            }
+            msg.push(id, data);
+        }
+        if msg.nb_records > 0 {
+            self.send_message(msg)?;
+        }
+        Ok(())
+    }
+
+    fn reconnect(&mut self) -> Result<()> {
+        self.socket = Some(self.connect()?);
+        // The collector may have restarted
+        self.templates_sent = None;
+        Ok(())
+    }
+}
+
+/// An IPFIX message being built.
+struct Message {
+    buf: Vec<u8>,
+    /// ID and offset of the current set.
+    set: Option<(u16, usize)>,
+    /// Number of data records in the message.
+    nb_records: u32,
+}
+
+impl Message {
+    /// Appends a data record of template `id`.
+    fn push(&mut self, id: u16, data: &[u8]) {
+        let new_set = match self.set {
+            Some((set_id, _)) => set_id != id,
+            None => true,
+        };
+        if new_set {
+            self.close_set();
+            self.set = Some((id, self.buf.len()));
+            self.buf.extend_from_slice(&id.to_be_bytes());
+            self.buf.extend_from_slice(&[0, 0]);
+        }
+        self.buf.extend_from_slice(data);
+        self.nb_records += 1;
+    }
+
+    /// Writes the length of the current set.
+    fn close_set(&mut self) {
+        if let Some((_, offset)) = self.set.take() {
+            let len = (self.buf.len() - offset) as u16;
+            self.buf[offset + 2..offset + 4].copy_from_slice(&len.to_be_bytes());
+        }
+    }
+}
+
+/// Returns the encoded template set, with enterprise elements numbered under `enterprise_id`.
+fn template_set(enterprise_id: u32) -> Vec<u8> {
+    // (element ID, length, enterprise number)
+    let fields = |src: u16, dst: u16, addr_len: u16| {
+        [
+            (src, addr_len, None),
+            (dst, addr_len, None),
+            (7, 2, None),
+            (11, 2, None),
+            (4, 1, None),
+            (152, 8, None),
+            (153, 8, None),
+            (2, 8, None),
+            (401, 8, None),
+            (2, 8, Some(REVERSE_PEN)),
+            (401, 8, Some(REVERSE_PEN)),
+            (136, 1, None),
+            (1, VARIABLE_LEN, Some(enterprise_id)),
+            (2, VARIABLE_LEN, Some(enterprise_id)),
+            (3, VARIABLE_LEN, Some(enterprise_id)),
+        ]
+    };
+    let templates = [
+        (IPV4_TEMPLATE_ID, fields(8, 12, 4)),
+        (IPV6_TEMPLATE_ID, fields(27, 28, 16)),
+    ];
+
+    let mut buf = vec![];
+    buf.extend_from_slice(&TEMPLATE_SET_ID.to_be_bytes());
+    buf.extend_from_slice(&[0, 0]);
+    for (id, fields) in templates {
+        buf.extend_from_slice(&id.to_be_bytes());
+        buf.extend_from_slice(&(fields.len() as u16).to_be_bytes());
+        for (element, len, enterprise) in fields {
+            match enterprise {
+                Some(enterprise) => {
+                    buf.extend_from_slice(&(element | 0x8000).to_be_bytes());
+                    buf.extend_from_slice(&len.to_be_bytes());
+                    buf.extend_from_slice(&enterprise.to_be_bytes());
+                }
+                None => {
+                    buf.extend_from_slice(&element.to_be_bytes());
+                    buf.extend_from_slice(&len.to_be_bytes());
+                }
+            }
+        }
+    }
+    let len = buf.len() as u16;
+    buf[2..4].copy_from_slice(&len.to_be_bytes());
+    buf
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn core_ipfix_messages() {
+        let collector = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let config = IpfixConfig {
+            max_message_size: 200,
+            ..IpfixConfig::new(collector.local_addr().unwrap().to_string())
+        };
+        let mut transport = IpfixTransport::new(config);
+        let record = FlowRecord {
+            five_tuple: FiveTuple {
+                orig: "10.0.0.1:50000".parse().unwrap(),
+                resp: "10.0.0.2:443".parse().unwrap(),
+                proto: 6,
+            },
+            start: UNIX_EPOCH + Duration::from_millis(1_000),
+            end: UNIX_EPOCH + Duration::from_millis(3_500),
+            orig_pkts: 10,
+            orig_bytes: 1_000,
+            resp_pkts: 8,
+            resp_bytes: 20_000,
+            end_reason: EndReason::EndOfFlow,
+            sni: Some("example.com".to_string()),
+            ja3: None,
+            app_proto: Some("tls".to_string()),
+        };
+        let data = record.encode().unwrap();
+        assert_eq!(
+            data.len(),
+            2 + 4 + 4 + 2 + 2 + 1 + 8 * 6 + 1 + (1 + 11) + 1 + (1 + 3)
+        );
+        transport.send(&[data.clone(), data.clone(), data]).unwrap();
+
+        let mut buf = [0; 1500];
+        let field = |buf: &[u8], offset: usize| u16::from_be_bytes([buf[offset], buf[offset + 1]]);
+        // The first message starts with the templates
+        let len = collector.recv(&mut buf).unwrap();
+        assert_eq!(field(&buf, 0), VERSION);
+        assert_eq!(field(&buf, 2) as usize, len);
+        assert_eq!(&buf[8..12], &0u32.to_be_bytes());
+        assert_eq!(field(&buf, 16), TEMPLATE_SET_ID);
+        let data_set = 16 + field(&buf, 18) as usize;
+        assert_eq!(field(&buf, data_set), IPV4_TEMPLATE_ID);
+        let nb_records = (len - data_set - SET_HEADER_LEN) / 79;
+        assert_eq!(nb_records, 1);
+        // The other records do not fit and are sent in a second message
+        let len = collector.recv(&mut buf).unwrap();
+        assert_eq!(&buf[8..12], &1u32.to_be_bytes());
+        assert_eq!(field(&buf, 16), IPV4_TEMPLATE_ID);
+        assert_eq!(field(&buf, 18) as usize, len - MESSAGE_HEADER_LEN);
+        assert_eq!(len, MESSAGE_HEADER_LEN + SET_HEADER_LEN + 2 * 79);
+    }
+}
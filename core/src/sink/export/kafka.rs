@@ -0,0 +1,157 @@
+//! Kafka producer.
+
+use super::{Deliveries, Transport};
+
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use anyhow::Result;
+use rdkafka::config::ClientConfig;
+use rdkafka::error::{KafkaError, RDKafkaErrorCode};
+use rdkafka::producer::{BaseProducer, BaseRecord, DeliveryResult, Producer, ProducerContext};
+use rdkafka::ClientContext;
+
+/// How long to wait for the producer queue to drain when it is full.
+const QUEUE_FULL_POLL: Duration = Duration::from_millis(100);
+
+/// How long to wait for in-flight messages to be delivered on flush.
+const FLUSH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Produces each record as a message to a Kafka topic.
+///
+/// The producer connects to the brokers and retries failed deliveries in the background, so
+/// records are delivered at least once. Records are counted as exported once the brokers
+/// acknowledge them, and as dropped if their delivery fails (e.g., after `message.timeout.ms`).
+pub struct KafkaTransport {
+    producer: BaseProducer<DeliveryCounter>,
+    topic: String,
+}
+
+impl KafkaTransport {
+    /// Creates a producer to `topic` on the comma-separated list of `brokers`.
+    pub fn new(brokers: &str, topic: &str) -> Result<Self> {
+        Self::with_config(ClientConfig::new().set("bootstrap.servers", brokers), topic)
+    }
+
+    /// Creates a producer to `topic` with the librdkafka client `config` (e.g., to set up
+    /// authentication or compression).
+    pub fn with_config(config: &ClientConfig, topic: &str) -> Result<Self> {
+        Ok(KafkaTransport {
+            producer: config.create_with_context(DeliveryCounter::default())?,
+            topic: topic.to_string(),
+        })
+    }
+}
+
+impl Transport for KafkaTransport {
+    fn send(&mut self, records: &[Vec<u8>]) -> Result<()> {
+        for record in records {
+            loop {
+                let message = BaseRecord::<(), [u8]>::to(&self.topic).payload(record);
+                match self.producer.send(message) {
+                    Ok(()) => break,
+                    Err((KafkaError::MessageProduction(RDKafkaErrorCode::QueueFull), _)) => {
+                        self.producer.poll(QUEUE_FULL_POLL);
+                    }
+                    Err((err, _)) => return Err(err.into()),
+                }
+            }
+        }
+        self.producer.poll(Duration::ZERO);
+        Ok(())
+    }
+
+    fn reconnect(&mut self) -> Result<()> {
+        // The producer reconnects to the brokers by itself
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.producer.flush(FLUSH_TIMEOUT)?;
+        Ok(())
+    }
+
+    fn deliveries(&mut self) -> Option<Deliveries> {
+        let counter = self.producer.context();
+        Some(Deliveries {
+            delivered: counter.delivered.swap(0, Ordering::Relaxed),
+            failed: counter.failed.swap(0, Ordering::Relaxed),
+        })
+    }
+}
+
+/// Counts the outcome of each message, as reported by the producer's delivery callback.
+#[derive(Debug, Default)]
+struct DeliveryCounter {
+    delivered: AtomicU64,
+    failed: AtomicU64,
+}
+
+impl ClientContext for DeliveryCounter {}
+
+impl ProducerContext for DeliveryCounter {
+    type DeliveryOpaque = ();
+
+    fn delivery(&self, result: &DeliveryResult<'_>, _opaque: ()) {
+        match result {
+            Ok(_) => self.delivered.fetch_add(1, Ordering::Relaxed),
+            Err((err, _)) => {
+                log::debug!("Kafka delivery failed: {}", err);
+                self.failed.fetch_add(1, Ordering::Relaxed)
+            }
+        };
+    }
+}
+
+impl fmt::Debug for KafkaTransport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KafkaTransport")
+            .field("topic", &self.topic)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{ExportConfig, Exporter};
+    use super::*;
+
+    fn export(transport: KafkaTransport, nb_records: u64) -> Exporter {
+        let config = ExportConfig {
+            batch_size: 2,
+            linger: 1,
+            ..ExportConfig::new("test")
+        };
+        let mut exporter = Exporter::with_transport(config, transport).unwrap();
+        for i in 0..nb_records {
+            exporter.export(&i).unwrap();
+        }
+        exporter.close().unwrap();
+        exporter
+    }
+
+    #[test]
+    fn core_kafka_delivery_failure() {
+        // No broker is listening, so every message times out
+        let transport = KafkaTransport::with_config(
+            ClientConfig::new()
+                .set("bootstrap.servers", "127.0.0.1:1")
+                .set("message.timeout.ms", "100"),
+            "retina",
+        )
+        .unwrap();
+        let exporter = export(transport, 5);
+        assert_eq!(exporter.nb_exported(), 0);
+        assert_eq!(exporter.nb_dropped(), 5);
+    }
+
+    #[test]
+    #[ignore = "requires a Kafka broker on localhost:9092"]
+    fn core_kafka_delivery() {
+        let transport = KafkaTransport::new("localhost:9092", "retina-test").unwrap();
+        let exporter = export(transport, 5);
+        assert_eq!(exporter.nb_exported(), 5);
+        assert_eq!(exporter.nb_dropped(), 0);
+    }
+}
@@ -0,0 +1,130 @@
+//! Newline-delimited JSON over a stream socket.
+
+use super::Transport;
+
+use std::io::{BufWriter, Write};
+use std::net::TcpStream;
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+
+use anyhow::Result;
+
+/// Sends each record as a line over a Unix domain socket or TCP connection.
+///
+/// The connection is established on the first send.
+#[derive(Debug)]
+pub struct LineTransport {
+    addr: Addr,
+    stream: Option<BufWriter<Stream>>,
+}
+
+#[derive(Debug)]
+enum Addr {
+    Unix(PathBuf),
+    Tcp(String),
+}
+
+#[derive(Debug)]
+enum Stream {
+    Unix(UnixStream),
+    Tcp(TcpStream),
+}
+
+impl LineTransport {
+    /// Creates a transport to the Unix domain socket at `path`.
+    pub fn unix(path: impl Into<PathBuf>) -> Self {
+        LineTransport {
+            addr: Addr::Unix(path.into()),
+            stream: None,
+        }
+    }
+
+    /// Creates a transport to the TCP server at `addr` (`<host>:<port>`).
+    pub fn tcp(addr: impl Into<String>) -> Self {
+        LineTransport {
+            addr: Addr::Tcp(addr.into()),
+            stream: None,
+        }
+    }
+
+    fn connect(&self) -> Result<BufWriter<Stream>> {
+        let stream = match &self.addr {
+            Addr::Unix(path) => Stream::Unix(UnixStream::connect(path)?),
+            Addr::Tcp(addr) => {
+                let stream = TcpStream::connect(addr)?;
+                stream.set_nodelay(true)?;
+                Stream::Tcp(stream)
+            }
+        };
+        Ok(BufWriter::new(stream))
+    }
+}
+
+impl Transport for LineTransport {
+    fn send(&mut self, records: &[Vec<u8>]) -> Result<()> {
+        let stream = match self.stream.take() {
+            Some(stream) => stream,
+            None => self.connect()?,
+        };
+        let stream = self.stream.insert(stream);
+        for record in records {
+            stream.write_all(record)?;
+            stream.write_all(b"\n")?;
+        }
+        stream.flush()?;
+        Ok(())
+    }
+
+    fn reconnect(&mut self) -> Result<()> {
+        self.stream = None;
+        self.stream = Some(self.connect()?);
+        Ok(())
+    }
+}
+
+impl Write for Stream {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            Stream::Unix(stream) => stream.write(buf),
+            Stream::Tcp(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            Stream::Unix(stream) => stream.flush(),
+            Stream::Tcp(stream) => stream.flush(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader};
+    use std::os::unix::net::UnixListener;
+
+    #[test]
+    fn core_export_unix_reconnect() {
+        let path = std::env::temp_dir().join(format!("retina-export-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let listener = UnixListener::bind(&path).unwrap();
+        let mut transport = LineTransport::unix(&path);
+
+        let read_line = |stream: UnixStream| {
+            let mut line = String::new();
+            BufReader::new(stream).read_line(&mut line).unwrap();
+            line
+        };
+        transport.send(&[b"{\"a\":1}".to_vec()]).unwrap();
+        let (stream, _) = listener.accept().unwrap();
+        assert_eq!(read_line(stream), "{\"a\":1}\n");
+
+        // The collector closed the connection
+        transport.reconnect().unwrap();
+        transport.send(&[b"{\"a\":2}".to_vec()]).unwrap();
+        let (stream, _) = listener.accept().unwrap();
+        assert_eq!(read_line(stream), "{\"a\":2}\n");
+        std::fs::remove_file(&path).unwrap();
+    }
+}
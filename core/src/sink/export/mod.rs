@@ -0,0 +1,436 @@
+//! Record export.
+//!
+//! An [Exporter](Exporter) streams serialized records (e.g., subscribable objects) to an external
+//! collector as JSON. Records are queued by the packet processing cores and sent in batches by a
+//! background thread, which reconnects and retries when the collector is unavailable, so the
+//! cores never wait on the network.
+//!
+//! Records can be sent as newline-delimited JSON over a Unix domain socket or TCP connection, or
+//! produced to a Kafka topic (requires the `kafka` feature). Other destinations can be added by
+//! implementing [Transport](Transport). Connection records can also be exported as IPFIX flow
+//! records, see [IpfixExporter](IpfixExporter).
+//!
+//! ## Example
+//! Exports TLS handshakes to a local collector:
+//! ```
+//! #[filter("tls")]
+//! fn main() {
+//!     let config = default_config();
+//!     let exporter = Exporter::new(ExportConfig::new("unix:///run/collector.sock")).unwrap();
+//!     let cb = |tls: TlsHandshake| {
+//!         exporter.export(&tls).unwrap();
+//!     };
+//!     let mut runtime = Runtime::new(config, filter, cb).unwrap();
+//!     runtime.run();
+//!     drop(runtime);
+//!     exporter.finish().unwrap();
+//! }
+//! ```
+
+mod ipfix;
+#[cfg(feature = "kafka")]
+mod kafka;
+mod line;
+
+pub use self::ipfix::{EndReason, FlowRecord, IpfixConfig, IpfixExporter};
+#[cfg(feature = "kafka")]
+pub use self::kafka::KafkaTransport;
+pub use self::line::LineTransport;
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, bail, Result};
+use crossbeam_channel::{bounded, Receiver, RecvTimeoutError, Sender, TrySendError};
+use serde::{Deserialize, Serialize};
+
+/// Maximum delay between reconnection attempts.
+const MAX_RETRY_BACKOFF: Duration = Duration::from_secs(10);
+
+/// Export options.
+///
+/// ## Example
+/// ```toml
+/// [export]
+///     endpoint = "kafka://localhost:9092/retina"
+///     batch_size = 1024
+///     linger = 50
+/// ```
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct ExportConfig {
+    /// Collector to export to, as one of:
+    /// - `unix://<path>`: newline-delimited JSON over a Unix domain socket.
+    /// - `tcp://<host>:<port>`: newline-delimited JSON over TCP.
+    /// - `kafka://<broker>[,<broker>...]/<topic>`: one Kafka message per record (requires the
+    ///   `kafka` feature).
+    pub endpoint: String,
+
+    /// Maximum number of records sent together. Defaults to `512`.
+    #[serde(default = "default_batch_size")]
+    pub batch_size: usize,
+
+    /// Maximum time (in milliseconds) a record waits for its batch to fill up before the batch is
+    /// sent. Defaults to `100`.
+    #[serde(default = "default_linger")]
+    pub linger: u64,
+
+    /// Maximum number of records waiting to be sent. Records exported while the queue is full
+    /// are dropped. Defaults to `65536`.
+    #[serde(default = "default_queue_size")]
+    pub queue_size: usize,
+
+    /// Number of times a batch is retried after a failed send, reconnecting before each retry.
+    /// The batch is dropped if all retries fail. Defaults to `5`.
+    #[serde(default = "default_max_retries")]
+    pub max_retries: u32,
+
+    /// Delay (in milliseconds) before the first retry, doubled for each subsequent retry up to 10
+    /// seconds. Defaults to `100`.
+    #[serde(default = "default_retry_backoff")]
+    pub retry_backoff: u64,
+}
+
+impl ExportConfig {
+    /// Returns the default options for exporting to `endpoint`.
+    pub fn new(endpoint: impl Into<String>) -> Self {
+        ExportConfig {
+            endpoint: endpoint.into(),
+            batch_size: default_batch_size(),
+            linger: default_linger(),
+            queue_size: default_queue_size(),
+            max_retries: default_max_retries(),
+            retry_backoff: default_retry_backoff(),
+        }
+    }
+}
+
+fn default_batch_size() -> usize {
+    512
+}
+
+fn default_linger() -> u64 {
+    100
+}
+
+fn default_queue_size() -> usize {
+    65536
+}
+
+fn default_max_retries() -> u32 {
+    5
+}
+
+fn default_retry_backoff() -> u64 {
+    100
+}
+
+/// A connection to a collector.
+pub trait Transport: Send {
+    /// Sends a batch of serialized records. If sending fails part-way, the whole batch is sent
+    /// again on retry.
+    fn send(&mut self, records: &[Vec<u8>]) -> Result<()>;
+
+    /// Re-establishes the connection after a failed send.
+    fn reconnect(&mut self) -> Result<()>;
+
+    /// Flushes records that have been sent but may still be buffered.
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Returns the outcome of the records whose delivery completed since the last call, for
+    /// transports that deliver records in the background after `send` returns (e.g., Kafka).
+    /// Returns `None` by default, in which case records are counted as exported once `send`
+    /// returns.
+    fn deliveries(&mut self) -> Option<Deliveries> {
+        None
+    }
+}
+
+/// Number of records whose delivery completed, as reported by a [Transport].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Deliveries {
+    /// Records acknowledged by the collector.
+    pub delivered: u64,
+    /// Records that could not be delivered.
+    pub failed: u64,
+}
+
+/// Exports serialized records to a collector from a background thread.
+///
+/// Records are delivered at least once: a batch that fails part-way is resent in full, so the
+/// collector may receive duplicates of the records sent before the failure.
+#[derive(Debug)]
+pub struct Exporter {
+    /// Queue of serialized records, closed on finish.
+    tx: Option<Sender<Vec<u8>>>,
+    handle: Option<JoinHandle<()>>,
+    stats: Arc<ExportStats>,
+}
+
+impl Exporter {
+    /// Creates a new exporter to the endpoint in `config`.
+    pub fn new(config: ExportConfig) -> Result<Self> {
+        let transport: Box<dyn Transport> = match config.endpoint.split_once("://") {
+            Some(("unix", path)) => Box::new(LineTransport::unix(path)),
+            Some(("tcp", addr)) => Box::new(LineTransport::tcp(addr)),
+            #[cfg(feature = "kafka")]
+            Some(("kafka", url)) => {
+                let (brokers, topic) = url
+                    .split_once('/')
+                    .ok_or_else(|| anyhow!("Missing Kafka topic: {}", config.endpoint))?;
+                Box::new(KafkaTransport::new(brokers, topic)?)
+            }
+            _ => bail!("Unsupported export endpoint: {}", config.endpoint),
+        };
+        Self::with_transport(config, transport)
+    }
+
+    /// Creates a new exporter that sends records with `transport`. The endpoint in `config` is
+    /// ignored.
+    pub fn with_transport(
+        config: ExportConfig,
+        transport: impl Transport + 'static,
+    ) -> Result<Self> {
+        if config.batch_size == 0 {
+            bail!("Export batch size must be positive");
+        }
+        let (tx, rx) = bounded(config.queue_size);
+        let stats = Arc::new(ExportStats::default());
+        let mut worker = Worker {
+            config,
+            transport,
+            stats: Arc::clone(&stats),
+        };
+        let handle = thread::Builder::new()
+            .name("retina-export".to_string())
+            .spawn(move || worker.run(rx))?;
+        Ok(Exporter {
+            tx: Some(tx),
+            handle: Some(handle),
+            stats,
+        })
+    }
+
+    /// Queues `record` for export. The record is dropped if the queue is full.
+    pub fn export<T: Serialize>(&self, record: &T) -> Result<()> {
+        self.queue(serde_json::to_vec(record)?)
+    }
+
+    /// Queues an already encoded record for export.
+    pub(crate) fn queue(&self, record: Vec<u8>) -> Result<()> {
+        let tx = self
+            .tx
+            .as_ref()
+            .ok_or_else(|| anyhow!("Exporter finished"))?;
+        match tx.try_send(record) {
+            Ok(()) => (),
+            Err(TrySendError::Full(_)) => {
+                self.stats.nb_dropped.fetch_add(1, Ordering::Relaxed);
+            }
+            Err(TrySendError::Disconnected(_)) => bail!("Export thread exited"),
+        }
+        Ok(())
+    }
+
+    /// Returns the number of records delivered to the collector.
+    pub fn nb_exported(&self) -> u64 {
+        self.stats.nb_exported.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of records dropped, either because the queue was full, because their
+    /// batch could not be sent, or because their delivery failed.
+    pub fn nb_dropped(&self) -> u64 {
+        self.stats.nb_dropped.load(Ordering::Relaxed)
+    }
+
+    /// Sends the remaining queued records and stops the background thread.
+    pub fn finish(mut self) -> Result<()> {
+        self.close()
+    }
+
+    fn close(&mut self) -> Result<()> {
+        drop(self.tx.take());
+        if let Some(handle) = self.handle.take() {
+            handle
+                .join()
+                .map_err(|_| anyhow!("Export thread panicked"))?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Exporter {
+    fn drop(&mut self) {
+        if let Err(err) = self.close() {
+            log::error!("Failed to finish export: {:?}", err);
+        }
+    }
+}
+
+impl<T: Transport + ?Sized> Transport for Box<T> {
+    fn send(&mut self, records: &[Vec<u8>]) -> Result<()> {
+        (**self).send(records)
+    }
+
+    fn reconnect(&mut self) -> Result<()> {
+        (**self).reconnect()
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        (**self).flush()
+    }
+
+    fn deliveries(&mut self) -> Option<Deliveries> {
+        (**self).deliveries()
+    }
+}
+
+/// Export counters.
+#[derive(Debug, Default)]
+struct ExportStats {
+    nb_exported: AtomicU64,
+    nb_dropped: AtomicU64,
+}
+
+/// Sends batches of queued records on the background thread.
+struct Worker<T: Transport> {
+    config: ExportConfig,
+    transport: T,
+    stats: Arc<ExportStats>,
+}
+
+impl<T: Transport> Worker<T> {
+    /// Sends batches until the queue is closed and empty.
+    fn run(&mut self, rx: Receiver<Vec<u8>>) {
+        let linger = Duration::from_millis(self.config.linger);
+        let mut batch = Vec::with_capacity(self.config.batch_size);
+        let mut is_open = true;
+        while is_open {
+            // Wait for the first record of the batch, then for the batch to fill up
+            match rx.recv() {
+                Ok(record) => batch.push(record),
+                Err(_) => break,
+            }
+            let deadline = Instant::now() + linger;
+            while batch.len() < self.config.batch_size {
+                match rx.recv_deadline(deadline) {
+                    Ok(record) => batch.push(record),
+                    Err(RecvTimeoutError::Timeout) => break,
+                    Err(RecvTimeoutError::Disconnected) => {
+                        is_open = false;
+                        break;
+                    }
+                }
+            }
+            self.send(&batch);
+            batch.clear();
+        }
+        if let Err(err) = self.transport.flush() {
+            log::error!("Failed to flush exported records: {:?}", err);
+        }
+        self.count_deliveries(0);
+    }
+
+    /// Sends `batch`, reconnecting and retrying on failure.
+    fn send(&mut self, batch: &[Vec<u8>]) {
+        let mut backoff = Duration::from_millis(self.config.retry_backoff);
+        let mut result = self.transport.send(batch);
+        for _ in 0..self.config.max_retries {
+            let Err(err) = &result else {
+                break;
+            };
+            log::warn!("Export failed, retrying in {:?}: {:?}", backoff, err);
+            thread::sleep(backoff);
+            backoff = (backoff * 2).min(MAX_RETRY_BACKOFF);
+            result = self
+                .transport
+                .reconnect()
+                .and_then(|()| self.transport.send(batch));
+        }
+        match result {
+            Ok(()) => self.count_deliveries(batch.len()),
+            Err(err) => {
+                log::error!("Dropping {} exported records: {:?}", batch.len(), err);
+                self.stats
+                    .nb_dropped
+                    .fetch_add(batch.len() as u64, Ordering::Relaxed);
+                self.count_deliveries(0);
+            }
+        }
+    }
+
+    /// Counts the records whose delivery completed, after `nb_sent` records were sent.
+    fn count_deliveries(&mut self, nb_sent: usize) {
+        let deliveries = self.transport.deliveries().unwrap_or(Deliveries {
+            delivered: nb_sent as u64,
+            failed: 0,
+        });
+        self.stats
+            .nb_exported
+            .fetch_add(deliveries.delivered, Ordering::Relaxed);
+        if deliveries.failed > 0 {
+            log::error!("Failed to deliver {} exported records", deliveries.failed);
+            self.stats
+                .nb_dropped
+                .fetch_add(deliveries.failed, Ordering::Relaxed);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// Fails every other send.
+    struct Flaky {
+        sent: Arc<Mutex<Vec<Vec<Vec<u8>>>>>,
+        fail: bool,
+    }
+
+    impl Transport for Flaky {
+        fn send(&mut self, records: &[Vec<u8>]) -> Result<()> {
+            self.fail = !self.fail;
+            if self.fail {
+                bail!("Disconnected");
+            }
+            self.sent.lock().unwrap().push(records.to_vec());
+            Ok(())
+        }
+
+        fn reconnect(&mut self) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn core_export_retry() {
+        let sent = Arc::new(Mutex::new(vec![]));
+        let config = ExportConfig {
+            batch_size: 2,
+            linger: 1000,
+            retry_backoff: 1,
+            ..ExportConfig::new("test")
+        };
+        let transport = Flaky {
+            sent: Arc::clone(&sent),
+            fail: false,
+        };
+        let exporter = Exporter::with_transport(config, transport).unwrap();
+        for i in 0..3 {
+            exporter.export(&i).unwrap();
+        }
+        // Wait for the full batch, the last record is sent on finish
+        while sent.lock().unwrap().is_empty() {
+            thread::sleep(Duration::from_millis(1));
+        }
+        exporter.finish().unwrap();
+        assert_eq!(
+            *sent.lock().unwrap(),
+            vec![vec![b"0".to_vec(), b"1".to_vec()], vec![b"2".to_vec()]]
+        );
+    }
+}
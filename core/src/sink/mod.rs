@@ -0,0 +1,435 @@
+//! Structured output sinks.
+//!
+//! A [Sink](Sink) writes subscribable objects (or any other `Serialize` record) to files in JSON
+//! Lines, CSV, Parquet, or Arrow IPC format. Each packet processing core writes to its own files,
+//! so cores do not contend for a shared writer. Files can be rotated by number of records or by
+//! age, and compressed. Records can also be exported to an external collector, see
+//! [export](export). Requires the `sink` feature.
+//!
+//! Columns of CSV, Parquet, and Arrow IPC files are derived from the `Serialize` implementation
+//! of the records. CSV columns follow the fields of the record type in order: fields of nested
+//! structs are flattened into dot-separated columns, and other values (including optional
+//! structs) are written as JSON. Parquet and Arrow IPC files store nested fields as struct columns,
+//! with the schema of each file inferred from its first batch of records. If later records do not
+//! fit the schema (e.g., a struct in a field that was only null in the first batch), the file is
+//! rotated and the records are written to a new file with a schema inferred from them.
+//!
+//! ## Example
+//! Logs connection records to Parquet files, e.g., `conn-core3-0.parquet`:
+//! ```
+//! #[filter("tcp")]
+//! fn main() {
+//!     let config = default_config();
+//!     let sink = Sink::new(SinkConfig {
+//!         format: Format::Parquet,
+//!         ..SinkConfig::new("conn")
+//!     })
+//!     .unwrap();
+//!     let cb = |conn: Connection| {
+//!         sink.write(&conn).unwrap();
+//!     };
+//!     let mut runtime = Runtime::new(config, filter, cb).unwrap();
+//!     runtime.run();
+//!     drop(runtime);
+//!     sink.finish().unwrap();
+//! }
+//! ```
+
+mod encoder;
+pub mod export;
+pub mod zeek;
+
+use self::encoder::{Encoder, SchemaConflict};
+use crate::dpdk;
+use crate::subscription::MAX_CORES;
+
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+
+/// Sink options.
+///
+/// ## Example
+/// ```toml
+/// [sink]
+///     path = "./logs/conn"
+///     format = "jsonl"
+///     compression = "zstd"
+///     rotate_records = 1000000
+///     rotate_interval = 3600
+/// ```
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct SinkConfig {
+    /// Output path, without extension. Each core writes to files named
+    /// `<path>-core<id>-<seq>.<ext>`, where `seq` counts the files rotated by the core.
+    pub path: PathBuf,
+
+    /// Output format. Defaults to `jsonl`.
+    #[serde(default = "default_format")]
+    pub format: Format,
+
+    /// Output compression. Defaults to `none`.
+    ///
+    /// ## Remarks
+    /// JSON Lines and CSV files are compressed as a whole. Parquet and Arrow IPC files compress
+    /// their column data instead, and Arrow IPC files support only `zstd` compression.
+    #[serde(default = "default_compression")]
+    pub compression: Compression,
+
+    /// Number of records after which a file is rotated. Defaults to `None` (no rotation by number
+    /// of records).
+    #[serde(default = "default_rotate_records")]
+    pub rotate_records: Option<u64>,
+
+    /// Age (in seconds) after which a file is rotated. Defaults to `None` (no rotation by age).
+    ///
+    /// ## Remarks
+    /// The age of a file is only checked when a record is written to it, so idle files are not
+    /// rotated until the next record.
+    #[serde(default = "default_rotate_interval")]
+    pub rotate_interval: Option<u64>,
+
+    /// Number of records buffered and written together as a record batch in Parquet and Arrow IPC
+    /// files. Defaults to `8192`.
+    #[serde(default = "default_batch_size")]
+    pub batch_size: usize,
+}
+
+impl SinkConfig {
+    /// Returns the default options for writing to `path`.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        SinkConfig {
+            path: path.into(),
+            format: default_format(),
+            compression: default_compression(),
+            rotate_records: default_rotate_records(),
+            rotate_interval: default_rotate_interval(),
+            batch_size: default_batch_size(),
+        }
+    }
+}
+
+fn default_format() -> Format {
+    Format::Jsonl
+}
+
+fn default_compression() -> Compression {
+    Compression::None
+}
+
+fn default_rotate_records() -> Option<u64> {
+    None
+}
+
+fn default_rotate_interval() -> Option<u64> {
+    None
+}
+
+fn default_batch_size() -> usize {
+    8192
+}
+
+/// Sink output format.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Format {
+    /// One JSON object per line.
+    Jsonl,
+    /// Comma-separated values with a header row.
+    Csv,
+    /// Apache Parquet.
+    Parquet,
+    /// Arrow IPC file format.
+    ArrowIpc,
+}
+
+impl Format {
+    fn extension(self) -> &'static str {
+        match self {
+            Format::Jsonl => "jsonl",
+            Format::Csv => "csv",
+            Format::Parquet => "parquet",
+            Format::ArrowIpc => "arrow",
+        }
+    }
+}
+
+/// Sink output compression.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Compression {
+    /// No compression.
+    None,
+    /// Gzip (DEFLATE) compression.
+    Gzip,
+    /// Zstandard compression.
+    Zstd,
+}
+
+/// Writes serializable records to per-core files.
+///
+/// Files are finished (e.g., Parquet footers are written) by [finish](Sink::finish), or when the
+/// sink is dropped.
+#[derive(Debug)]
+pub struct Sink {
+    config: SinkConfig,
+    /// Files of each core, indexed by core ID. The last entry is shared by other threads.
+    writers: Box<[Mutex<CoreWriter>]>,
+}
+
+impl Sink {
+    /// Creates a new sink. Files are created when the first record is written to them.
+    pub fn new(config: SinkConfig) -> Result<Self> {
+        if config.format == Format::ArrowIpc && config.compression == Compression::Gzip {
+            bail!("Arrow IPC files do not support gzip compression");
+        }
+        if config.batch_size == 0 {
+            bail!("Sink batch size must be positive");
+        }
+        if config.path.file_name().is_none() {
+            bail!("Invalid sink path: {:?}", config.path);
+        }
+        let writers = (0..=MAX_CORES)
+            .map(|core| {
+                let label = if core < MAX_CORES {
+                    format!("core{}", core)
+                } else {
+                    "shared".to_string()
+                };
+                Mutex::new(CoreWriter {
+                    label,
+                    seq: 0,
+                    file: None,
+                })
+            })
+            .collect();
+        Ok(Sink { config, writers })
+    }
+
+    /// Writes `record` to the current file of the calling core.
+    pub fn write<T: Serialize>(&self, record: &T) -> Result<()> {
+        let core = unsafe { dpdk::rte_lcore_id() } as usize;
+        self.write_on(core.min(MAX_CORES), record)
+    }
+
+    /// Writes `record` to the current file of core `core`.
+    fn write_on<T: Serialize>(&self, core: usize, record: &T) -> Result<()> {
+        let mut writer = self.writers[core].lock().unwrap();
+        writer.write(&self.config, record)
+    }
+
+    /// Flushes buffered records of all cores to their files.
+    pub fn flush(&self) -> Result<()> {
+        for writer in self.writers.iter() {
+            writer.lock().unwrap().flush(&self.config)?;
+        }
+        Ok(())
+    }
+
+    /// Finishes the current files of all cores. Records written afterwards are written to new
+    /// files.
+    pub fn finish(&self) -> Result<()> {
+        for writer in self.writers.iter() {
+            writer.lock().unwrap().finish(&self.config)?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Sink {
+    fn drop(&mut self) {
+        if let Err(err) = self.finish() {
+            log::error!("Failed to finish sink files: {:?}", err);
+        }
+    }
+}
+
+/// Files of a single core.
+#[derive(Debug)]
+struct CoreWriter {
+    /// Identifies the core in file names.
+    label: String,
+    /// Sequence number of the next file.
+    seq: u64,
+    /// Current file, if any.
+    file: Option<FileWriter>,
+}
+
+impl CoreWriter {
+    fn write<T: Serialize>(&mut self, config: &SinkConfig, record: &T) -> Result<()> {
+        if self.file.as_ref().is_some_and(|file| file.expired(config)) {
+            self.finish(config)?;
+        }
+        let file = match self.file.take() {
+            Some(file) => file,
+            None => self.open(config)?,
+        };
+        let file = self.file.insert(file);
+        file.nb_records += 1;
+        let written = file.encoder.write(record);
+        self.rotate_on_conflict(config, written)?;
+        Ok(())
+    }
+
+    fn flush(&mut self, config: &SinkConfig) -> Result<()> {
+        if let Some(file) = &mut self.file {
+            let flushed = file.encoder.flush();
+            if self.rotate_on_conflict(config, flushed)? {
+                if let Some(file) = &mut self.file {
+                    file.encoder.flush()?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self, config: &SinkConfig) -> Result<()> {
+        if let Some(file) = self.file.take() {
+            let finished = file.encoder.finish();
+            if self.rotate_on_conflict(config, finished)? {
+                if let Some(file) = self.file.take() {
+                    file.encoder.finish()?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Moves records that do not fit the schema of the current file, if `result` is a
+    /// [SchemaConflict], to a new file. Returns `true` if the file was rotated.
+    fn rotate_on_conflict(&mut self, config: &SinkConfig, result: Result<()>) -> Result<bool> {
+        let conflict = match result {
+            Ok(()) => return Ok(false),
+            Err(err) => err.downcast::<SchemaConflict>()?,
+        };
+        log::debug!("{}, rotating file", conflict);
+        if let Some(file) = self.file.take() {
+            file.encoder.finish()?;
+        }
+        let mut file = self.open(config)?;
+        file.nb_records = conflict.rows.len() as u64;
+        file.encoder.requeue(conflict.rows);
+        self.file = Some(file);
+        Ok(true)
+    }
+
+    /// Creates the next file.
+    fn open(&mut self, config: &SinkConfig) -> Result<FileWriter> {
+        let path = file_path(config, &self.label, self.seq);
+        self.seq += 1;
+        Ok(FileWriter {
+            encoder: Encoder::new(&path, config)?,
+            nb_records: 0,
+            opened: Instant::now(),
+        })
+    }
+}
+
+/// An open file.
+#[derive(Debug)]
+struct FileWriter {
+    encoder: Encoder,
+    /// Number of records written to the file.
+    nb_records: u64,
+    /// Time the file was created.
+    opened: Instant,
+}
+
+impl FileWriter {
+    /// Returns `true` if the file should be rotated before the next record.
+    fn expired(&self, config: &SinkConfig) -> bool {
+        config
+            .rotate_records
+            .is_some_and(|max| self.nb_records >= max)
+            || config
+                .rotate_interval
+                .is_some_and(|secs| self.opened.elapsed() >= Duration::from_secs(secs))
+    }
+}
+
+/// Returns the path of file `seq` of the core labeled `label`.
+fn file_path(config: &SinkConfig, label: &str, seq: u64) -> PathBuf {
+    let mut name = config.path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!("-{}-{}.{}", label, seq, config.format.extension()));
+    if matches!(config.format, Format::Jsonl | Format::Csv) {
+        match config.compression {
+            Compression::None => (),
+            Compression::Gzip => name.push(".gz"),
+            Compression::Zstd => name.push(".zst"),
+        }
+    }
+    config.path.parent().unwrap_or(Path::new("")).join(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+    use std::fs;
+
+    #[test]
+    fn core_sink_rotation() {
+        let dir = std::env::temp_dir().join(format!("retina-sink-{}", std::process::id()));
+        let sink = Sink::new(SinkConfig {
+            rotate_records: Some(2),
+            ..SinkConfig::new(dir.join("conn"))
+        })
+        .unwrap();
+        for i in 0..3 {
+            sink.write_on(3, &json!({"id": i})).unwrap();
+        }
+        sink.write_on(MAX_CORES, &json!({"id": 3})).unwrap();
+        sink.finish().unwrap();
+
+        let read = |name: &str| {
+            fs::read_to_string(dir.join(name))
+                .unwrap()
+                .lines()
+                .map(|line| serde_json::from_str::<Value>(line).unwrap()["id"].clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(read("conn-core3-0.jsonl"), vec![json!(0), json!(1)]);
+        assert_eq!(read("conn-core3-1.jsonl"), vec![json!(2)]);
+        assert_eq!(read("conn-shared-0.jsonl"), vec![json!(3)]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn core_sink_schema_conflict() {
+        use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+        let dir = std::env::temp_dir().join(format!("retina-sink-schema-{}", std::process::id()));
+        let sink = Sink::new(SinkConfig {
+            format: Format::Parquet,
+            batch_size: 2,
+            ..SinkConfig::new(dir.join("conn"))
+        })
+        .unwrap();
+        // The first batch has only null values for the `os` struct
+        for (id, os) in [
+            (0, Value::Null),
+            (1, Value::Null),
+            (2, json!({"name": "linux"})),
+            (3, Value::Null),
+            (4, json!({"name": "windows"})),
+        ] {
+            sink.write_on(3, &json!({"id": id, "os": os})).unwrap();
+        }
+        sink.finish().unwrap();
+
+        let read = |name: &str| {
+            let file = fs::File::open(dir.join(name)).unwrap();
+            let reader = ParquetRecordBatchReaderBuilder::try_new(file)
+                .unwrap()
+                .build()
+                .unwrap();
+            reader.map(|batch| batch.unwrap().num_rows()).sum::<usize>()
+        };
+        assert_eq!(read("conn-core3-0.parquet"), 2);
+        assert_eq!(read("conn-core3-1.parquet"), 3);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
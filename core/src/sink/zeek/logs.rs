@@ -0,0 +1,373 @@
+//! Zeek log records of subscribable types.
+
+use super::{uid, ZeekRecord, ZeekValue};
+use crate::conntrack::clock;
+use crate::conntrack::conn_id::FiveTuple;
+use crate::protocols::packet::tcp::TCP_PROTOCOL;
+use crate::protocols::packet::udp::UDP_PROTOCOL;
+use crate::protocols::stream::dns::Data;
+use crate::protocols::stream::tls::{Certificate, ServerKeyExchange};
+use crate::subscription::{Connection, DnsTransaction, HttpTransaction, TlsHandshake};
+
+use std::time::{Duration, Instant};
+
+use dns_parser::ResponseCode;
+use ring::digest;
+
+/// Names and types of the fields identifying the connection of a record.
+macro_rules! conn_id_fields {
+    ($($field:expr),* $(,)?) => {
+        &[
+            ("ts", "time"),
+            ("uid", "string"),
+            ("id.orig_h", "addr"),
+            ("id.orig_p", "port"),
+            ("id.resp_h", "addr"),
+            ("id.resp_p", "port"),
+            $($field),*
+        ]
+    };
+}
+
+/// Returns the values of the fields identifying the connection of a record.
+fn conn_id_values(five_tuple: &FiveTuple, ts: Instant) -> Vec<ZeekValue> {
+    vec![
+        ZeekValue::Time(clock::system_time(ts)),
+        ZeekValue::String(uid(five_tuple, ts)),
+        ZeekValue::Addr(five_tuple.orig.ip()),
+        ZeekValue::Port(five_tuple.orig.port()),
+        ZeekValue::Addr(five_tuple.resp.ip()),
+        ZeekValue::Port(five_tuple.resp.port()),
+    ]
+}
+
+/// Returns the Zeek name of transport protocol `proto`.
+fn proto_name(proto: usize) -> &'static str {
+    match proto {
+        TCP_PROTOCOL => "tcp",
+        UDP_PROTOCOL => "udp",
+        _ => "unknown_transport",
+    }
+}
+
+/// Returns the Zeek connection state of `conn` (e.g., `SF` for a normally established and
+/// terminated connection), derived from its history.
+///
+/// See the `conn_state` field of [Zeek's
+/// conn.log](https://docs.zeek.org/en/master/scripts/base/protocols/conn/main.zeek.html) for the
+/// list of states.
+pub fn conn_state(conn: &Connection) -> &'static str {
+    state(
+        conn.five_tuple.proto,
+        &conn.history,
+        conn.orig.nb_pkts,
+        conn.resp.nb_pkts,
+    )
+}
+
+fn state(proto: usize, history: &[u8], orig_pkts: u64, resp_pkts: u64) -> &'static str {
+    if proto != TCP_PROTOCOL {
+        return match (orig_pkts > 0, resp_pkts > 0) {
+            (true, false) => "S0",
+            (true, true) => "SF",
+            (false, true) => "SHR",
+            (false, false) => "OTH",
+        };
+    }
+    let seen = |event: u8| history.contains(&event);
+    match (seen(b'S'), seen(b'h')) {
+        (true, false) if seen(b'R') => "RSTOS0",
+        (true, false) if seen(b'r') => "REJ",
+        (true, false) if seen(b'F') => "SH",
+        (true, false) => "S0",
+        (false, true) if seen(b'r') => "RSTRH",
+        (false, true) if seen(b'f') => "SHR",
+        (false, true) => "OTH",
+        (true, true) if seen(b'R') => "RSTO",
+        (true, true) if seen(b'r') => "RSTR",
+        (true, true) if seen(b'F') && seen(b'f') => "SF",
+        (true, true) if seen(b'F') => "S2",
+        (true, true) if seen(b'f') => "S3",
+        (true, true) => "S1",
+        (false, false) => "OTH",
+    }
+}
+
+impl ZeekRecord for Connection {
+    const PATH: &'static str = "conn";
+
+    const FIELDS: &'static [(&'static str, &'static str)] = conn_id_fields![
+        ("proto", "enum"),
+        ("service", "string"),
+        ("duration", "interval"),
+        ("orig_bytes", "count"),
+        ("resp_bytes", "count"),
+        ("conn_state", "string"),
+        ("local_orig", "bool"),
+        ("local_resp", "bool"),
+        ("missed_bytes", "count"),
+        ("history", "string"),
+        ("orig_pkts", "count"),
+        ("orig_ip_bytes", "count"),
+        ("resp_pkts", "count"),
+        ("resp_ip_bytes", "count"),
+        ("tunnel_parents", "set[string]"),
+    ];
+
+    fn values(&self) -> Vec<ZeekValue> {
+        let mut values = conn_id_values(&self.five_tuple, self.ts);
+        values.extend([
+            ZeekValue::String(proto_name(self.five_tuple.proto).to_string()),
+            ZeekValue::Unset,
+            ZeekValue::Interval(self.duration),
+            ZeekValue::Count(self.orig.nb_bytes),
+            ZeekValue::Count(self.resp.nb_bytes),
+            ZeekValue::String(conn_state(self).to_string()),
+            ZeekValue::Unset,
+            ZeekValue::Unset,
+            ZeekValue::Count(self.orig.missed_bytes() + self.resp.missed_bytes()),
+            ZeekValue::String(self.history()),
+            ZeekValue::Count(self.orig.nb_pkts),
+            ZeekValue::Unset,
+            ZeekValue::Count(self.resp.nb_pkts),
+            ZeekValue::Unset,
+            ZeekValue::Unset,
+        ]);
+        values
+    }
+}
+
+impl ZeekRecord for DnsTransaction {
+    const PATH: &'static str = "dns";
+
+    const FIELDS: &'static [(&'static str, &'static str)] = conn_id_fields![
+        ("proto", "enum"),
+        ("trans_id", "count"),
+        ("rtt", "interval"),
+        ("query", "string"),
+        ("qclass", "count"),
+        ("qclass_name", "string"),
+        ("qtype", "count"),
+        ("qtype_name", "string"),
+        ("rcode", "count"),
+        ("rcode_name", "string"),
+        ("AA", "bool"),
+        ("TC", "bool"),
+        ("RD", "bool"),
+        ("RA", "bool"),
+        ("Z", "count"),
+        ("answers", "vector[string]"),
+        ("TTLs", "vector[interval]"),
+        ("rejected", "bool"),
+    ];
+
+    fn values(&self) -> Vec<ZeekValue> {
+        let query = self.data.query.as_ref();
+        let response = self.data.response.as_ref();
+        let rcode = response.map(|response| match response.response_code {
+            ResponseCode::NoError => (0, "NOERROR".to_string()),
+            ResponseCode::FormatError => (1, "FORMERR".to_string()),
+            ResponseCode::ServerFailure => (2, "SERVFAIL".to_string()),
+            ResponseCode::NameError => (3, "NXDOMAIN".to_string()),
+            ResponseCode::NotImplemented => (4, "NOTIMP".to_string()),
+            ResponseCode::Refused => (5, "REFUSED".to_string()),
+            ResponseCode::Reserved(code) => (code as u64, format!("unknown-{}", code)),
+        });
+        let answers = response.map(|response| {
+            response
+                .answers
+                .iter()
+                .filter_map(|answer| {
+                    let data = match &answer.data {
+                        Data::A(a) => a.0.to_string(),
+                        Data::Aaaa(aaaa) => aaaa.0.to_string(),
+                        Data::Cname(name) | Data::Ns(name) | Data::Ptr(name) => name.clone(),
+                        Data::Txt(txt) => format!("TXT {} {}", txt.len(), txt),
+                        Data::Mx(mx) => mx.exchange.clone(),
+                        Data::Soa(soa) => soa.primary_ns.clone(),
+                        Data::Srv(srv) => srv.target.clone(),
+                        Data::Unknown => return None,
+                    };
+                    Some((data, Duration::from_secs(answer.ttl as u64)))
+                })
+                .unzip::<_, _, Vec<_>, Vec<_>>()
+        });
+
+        let mut values = conn_id_values(&self.five_tuple, self.conn_ts);
+        values.extend([
+            ZeekValue::String(proto_name(self.five_tuple.proto).to_string()),
+            ZeekValue::Count(self.data.transaction_id as u64),
+            ZeekValue::Unset,
+            query
+                .and_then(|query| query.queries.first().cloned())
+                .into(),
+            ZeekValue::Unset,
+            ZeekValue::Unset,
+            ZeekValue::Unset,
+            ZeekValue::Unset,
+            rcode
+                .as_ref()
+                .map_or(ZeekValue::Unset, |(code, _)| ZeekValue::Count(*code)),
+            rcode.map(|(_, name)| name).into(),
+            response.map_or(ZeekValue::Unset, |r| ZeekValue::Bool(r.authoritative)),
+            ZeekValue::Unset,
+            query.map_or(ZeekValue::Unset, |q| ZeekValue::Bool(q.recursion_desired)),
+            response.map_or(ZeekValue::Unset, |r| ZeekValue::Bool(r.recursion_available)),
+            ZeekValue::Unset,
+        ]);
+        match answers {
+            Some((answers, ttls)) => {
+                values.push(ZeekValue::Strings(answers));
+                values.push(ZeekValue::Intervals(ttls));
+            }
+            None => values.extend([ZeekValue::Unset, ZeekValue::Unset]),
+        }
+        values.push(ZeekValue::Unset);
+        values
+    }
+}
+
+impl ZeekRecord for HttpTransaction {
+    const PATH: &'static str = "http";
+
+    const FIELDS: &'static [(&'static str, &'static str)] = conn_id_fields![
+        ("trans_depth", "count"),
+        ("method", "string"),
+        ("host", "string"),
+        ("uri", "string"),
+        ("referrer", "string"),
+        ("version", "string"),
+        ("user_agent", "string"),
+        ("request_body_len", "count"),
+        ("response_body_len", "count"),
+        ("status_code", "count"),
+        ("status_msg", "string"),
+    ];
+
+    fn values(&self) -> Vec<ZeekValue> {
+        let request = &self.data.request;
+        let response = &self.data.response;
+        let version = request.version.as_ref().or(response.version.as_ref());
+        let count =
+            |len: Option<usize>| len.map_or(ZeekValue::Unset, |len| ZeekValue::Count(len as u64));
+
+        let mut values = conn_id_values(&self.five_tuple, self.conn_ts);
+        values.extend([
+            ZeekValue::Count(self.data.trans_depth as u64),
+            request.method.clone().into(),
+            request.host.clone().into(),
+            request.uri.clone().into(),
+            ZeekValue::Unset,
+            version
+                .map(|version| version.trim_start_matches("HTTP/").to_string())
+                .into(),
+            request.user_agent.clone().into(),
+            count(request.content_length),
+            count(response.content_length),
+            count(response.status_code.map(usize::from)),
+            response.status_msg.clone().into(),
+        ]);
+        values
+    }
+}
+
+/// Returns the Zeek name of the TLS named group with identifier `group`.
+fn curve_name(group: u16) -> String {
+    match group {
+        0x0017 => "secp256r1".to_string(),
+        0x0018 => "secp384r1".to_string(),
+        0x0019 => "secp521r1".to_string(),
+        0x001d => "x25519".to_string(),
+        0x001e => "x448".to_string(),
+        0x0100 => "ffdhe2048".to_string(),
+        0x0101 => "ffdhe3072".to_string(),
+        0x0102 => "ffdhe4096".to_string(),
+        0x0103 => "ffdhe6144".to_string(),
+        0x0104 => "ffdhe8192".to_string(),
+        0x11ec => "X25519MLKEM768".to_string(),
+        0x6399 => "X25519Kyber768Draft00".to_string(),
+        group => format!("unknown-{}", group),
+    }
+}
+
+impl ZeekRecord for TlsHandshake {
+    const PATH: &'static str = "ssl";
+
+    const FIELDS: &'static [(&'static str, &'static str)] = conn_id_fields![
+        ("version", "string"),
+        ("cipher", "string"),
+        ("curve", "string"),
+        ("server_name", "string"),
+        ("resumed", "bool"),
+        ("last_alert", "string"),
+        ("next_protocol", "string"),
+        ("established", "bool"),
+        ("cert_chain_fps", "vector[string]"),
+        ("client_cert_chain_fps", "vector[string]"),
+        ("ja3", "string"),
+        ("ja3s", "string"),
+    ];
+
+    fn values(&self) -> Vec<ZeekValue> {
+        let tls = &self.data;
+        let server_hello = tls.server_hello.as_ref();
+        let version = match tls.version() {
+            0 => None,
+            0x0300 => Some("SSLv3".to_string()),
+            0x0301 => Some("TLSv10".to_string()),
+            0x0302 => Some("TLSv11".to_string()),
+            0x0303 => Some("TLSv12".to_string()),
+            0x0304 => Some("TLSv13".to_string()),
+            version => Some(format!("unknown-{}", version)),
+        };
+        let curve = match &tls.server_key_exchange {
+            Some(ServerKeyExchange::Ecdh(params)) => Some(params.curve),
+            _ => server_hello.and_then(|sh| sh.key_share.as_ref().map(|share| share.group)),
+        };
+        let fingerprints = |certs: &[Certificate]| {
+            ZeekValue::Strings(
+                certs
+                    .iter()
+                    .map(|cert| hex::encode(digest::digest(&digest::SHA256, &cert.raw)))
+                    .collect(),
+            )
+        };
+
+        let mut values = conn_id_values(&self.five_tuple, self.conn_ts);
+        values.extend([
+            version.into(),
+            server_hello.map(|_| tls.cipher()).into(),
+            curve.map(|curve| curve_name(curve.0)).into(),
+            tls.client_hello
+                .as_ref()
+                .and_then(|ch| ch.server_name.clone())
+                .into(),
+            ZeekValue::Unset,
+            ZeekValue::Unset,
+            server_hello.and_then(|sh| sh.alpn_protocol.clone()).into(),
+            ZeekValue::Bool(server_hello.is_some()),
+            fingerprints(&tls.server_certificates),
+            fingerprints(&tls.client_certificates),
+            tls.client_hello.as_ref().map(|_| tls.ja3_hash()).into(),
+            server_hello.map(|_| tls.ja3s_hash()).into(),
+        ]);
+        values
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn core_zeek_conn_state() {
+        assert_eq!(state(TCP_PROTOCOL, b"ShADadFf", 5, 4), "SF");
+        assert_eq!(state(TCP_PROTOCOL, b"ShADadF", 4, 3), "S2");
+        assert_eq!(state(TCP_PROTOCOL, b"ShADadR", 4, 3), "RSTO");
+        assert_eq!(state(TCP_PROTOCOL, b"Sr", 1, 1), "REJ");
+        assert_eq!(state(TCP_PROTOCOL, b"S", 2, 0), "S0");
+        assert_eq!(state(TCP_PROTOCOL, b"Dd", 2, 2), "OTH");
+        assert_eq!(state(UDP_PROTOCOL, b"Dd", 1, 1), "SF");
+        assert_eq!(state(UDP_PROTOCOL, b"D", 1, 0), "S0");
+    }
+}
@@ -0,0 +1,343 @@
+//! Zeek-compatible logs.
+//!
+//! A [ZeekWriter](ZeekWriter) writes subscribable objects as [Zeek](https://zeek.org) logs, in
+//! Zeek's tab-separated (TSV) or JSON format, so that they can be ingested by tools built for
+//! Zeek. The following logs are supported:
+//! - `conn.log` from [Connection](crate::subscription::Connection) records
+//! - `dns.log` from [DnsTransaction](crate::subscription::DnsTransaction)s
+//! - `http.log` from [HttpTransaction](crate::subscription::HttpTransaction)s
+//! - `ssl.log` from [TlsHandshake](crate::subscription::TlsHandshake)s
+//!
+//! Each log record carries the [uid](uid) of its connection, derived from the connection 5-tuple
+//! and the timestamp of its first packet. In offline mode, the uid is stable across runs over the
+//! same trace, so records of different logs can be joined.
+//!
+//! Logs only contain the fields that Retina observes. Zeek fields that Retina does not track
+//! (e.g., `local_orig` in `conn.log`) are left unset.
+//!
+//! ## Example
+//! Writes a `conn.log`:
+//! ```
+//! #[filter("tcp")]
+//! fn main() {
+//!     let config = default_config();
+//!     let file = BufWriter::new(File::create("conn.log").unwrap());
+//!     let wtr = Mutex::new(ZeekWriter::new(file, ZeekFormat::Tsv));
+//!     let cb = |conn: Connection| {
+//!         wtr.lock().unwrap().write(&conn).unwrap();
+//!     };
+//!     let mut runtime = Runtime::new(config, filter, cb).unwrap();
+//!     runtime.run();
+//!     drop(runtime);
+//!     wtr.into_inner().unwrap().finish().unwrap();
+//! }
+//! ```
+
+mod logs;
+
+pub use self::logs::conn_state;
+
+use crate::conntrack::clock;
+use crate::conntrack::conn_id::FiveTuple;
+
+use std::fmt::Write as _;
+use std::io::Write;
+use std::marker::PhantomData;
+use std::net::IpAddr;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use anyhow::Result;
+use chrono::Local;
+use serde_json::{Map, Value};
+
+/// Zeek log format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ZeekFormat {
+    /// Tab-separated values, with Zeek's header and footer.
+    Tsv,
+    /// One JSON object per line, as written by Zeek with `LogAscii::use_json`.
+    Json,
+}
+
+/// A record that can be written to a Zeek log.
+pub trait ZeekRecord {
+    /// Name of the log (e.g., `conn`).
+    const PATH: &'static str;
+
+    /// Names and Zeek types of the fields of the log.
+    const FIELDS: &'static [(&'static str, &'static str)];
+
+    /// Returns the values of the fields, in the order of [FIELDS](ZeekRecord::FIELDS).
+    fn values(&self) -> Vec<ZeekValue>;
+}
+
+/// Value of a Zeek log field.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ZeekValue {
+    /// Field is not set.
+    Unset,
+    /// `time`
+    Time(SystemTime),
+    /// `interval`
+    Interval(Duration),
+    /// `string` or `enum`
+    String(String),
+    /// `addr`
+    Addr(IpAddr),
+    /// `port`
+    Port(u16),
+    /// `count`
+    Count(u64),
+    /// `bool`
+    Bool(bool),
+    /// `set` or `vector` of strings.
+    Strings(Vec<String>),
+    /// `vector[interval]`
+    Intervals(Vec<Duration>),
+}
+
+impl From<Option<String>> for ZeekValue {
+    fn from(value: Option<String>) -> Self {
+        value.map_or(ZeekValue::Unset, ZeekValue::String)
+    }
+}
+
+/// Writes records to a Zeek log.
+#[derive(Debug)]
+pub struct ZeekWriter<R, W>
+where
+    R: ZeekRecord,
+    W: Write,
+{
+    wtr: W,
+    format: ZeekFormat,
+    /// Whether the TSV header has been written.
+    opened: bool,
+    record: PhantomData<fn(&R)>,
+}
+
+impl<R, W> ZeekWriter<R, W>
+where
+    R: ZeekRecord,
+    W: Write,
+{
+    /// Creates a new log writer to `wtr`. The TSV header is written with the first record.
+    pub fn new(wtr: W, format: ZeekFormat) -> Self {
+        ZeekWriter {
+            wtr,
+            format,
+            opened: false,
+            record: PhantomData,
+        }
+    }
+
+    /// Writes `record` to the log.
+    pub fn write(&mut self, record: &R) -> Result<()> {
+        let values = record.values();
+        match self.format {
+            ZeekFormat::Tsv => {
+                if !self.opened {
+                    self.write_header()?;
+                    self.opened = true;
+                }
+                let line = values.iter().map(tsv_value).collect::<Vec<_>>().join("\t");
+                writeln!(self.wtr, "{}", line)?;
+            }
+            ZeekFormat::Json => {
+                let mut fields = Map::new();
+                for ((name, _), value) in R::FIELDS.iter().zip(values) {
+                    if let Some(value) = json_value(value) {
+                        fields.insert(name.to_string(), value);
+                    }
+                }
+                serde_json::to_writer(&mut self.wtr, &fields)?;
+                self.wtr.write_all(b"\n")?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes the TSV footer, if any, and returns the underlying writer.
+    pub fn finish(mut self) -> Result<W> {
+        if self.opened {
+            writeln!(
+                self.wtr,
+                "#close\t{}",
+                Local::now().format("%Y-%m-%d-%H-%M-%S")
+            )?;
+        }
+        self.wtr.flush()?;
+        Ok(self.wtr)
+    }
+
+    fn write_header(&mut self) -> Result<()> {
+        let names = R::FIELDS.iter().map(|(name, _)| *name);
+        let types = R::FIELDS.iter().map(|(_, ty)| *ty);
+        writeln!(self.wtr, "#separator \\x09")?;
+        writeln!(self.wtr, "#set_separator\t,")?;
+        writeln!(self.wtr, "#empty_field\t(empty)")?;
+        writeln!(self.wtr, "#unset_field\t-")?;
+        writeln!(self.wtr, "#path\t{}", R::PATH)?;
+        writeln!(
+            self.wtr,
+            "#open\t{}",
+            Local::now().format("%Y-%m-%d-%H-%M-%S")
+        )?;
+        writeln!(
+            self.wtr,
+            "#fields\t{}",
+            names.collect::<Vec<_>>().join("\t")
+        )?;
+        writeln!(self.wtr, "#types\t{}", types.collect::<Vec<_>>().join("\t"))?;
+        Ok(())
+    }
+}
+
+/// Returns the Zeek-style unique identifier of the connection with 5-tuple `five_tuple` whose
+/// first packet was observed at `ts` (e.g., `CHhAvVGS1DHFjwGM9`).
+pub fn uid(five_tuple: &FiveTuple, ts: Instant) -> String {
+    let nanos = clock::system_time(ts)
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    let mut bytes = Vec::with_capacity(64);
+    for addr in [five_tuple.orig, five_tuple.resp] {
+        match addr.ip() {
+            IpAddr::V4(ip) => bytes.extend_from_slice(&ip.octets()),
+            IpAddr::V6(ip) => bytes.extend_from_slice(&ip.octets()),
+        }
+        bytes.extend_from_slice(&addr.port().to_be_bytes());
+    }
+    bytes.extend_from_slice(&(five_tuple.proto as u64).to_be_bytes());
+    bytes.extend_from_slice(&nanos.to_be_bytes());
+
+    // 96 bits from two FNV-1a hashes with different offsets
+    let hash = (fnv1a(0xcbf2_9ce4_8422_2325, &bytes) as u128) << 32
+        | fnv1a(0x6c62_272e_07bb_0142, &bytes) as u128 & 0xffff_ffff;
+    let mut uid = String::from("C");
+    uid.push_str(&base62(hash));
+    uid
+}
+
+fn fnv1a(offset: u64, bytes: &[u8]) -> u64 {
+    bytes.iter().fold(offset, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+fn base62(mut value: u128) -> String {
+    const DIGITS: &[u8] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+    let mut digits = vec![];
+    loop {
+        digits.push(DIGITS[(value % 62) as usize]);
+        value /= 62;
+        if value == 0 {
+            break;
+        }
+    }
+    digits.iter().rev().map(|digit| *digit as char).collect()
+}
+
+/// Formats `value` as a TSV field.
+fn tsv_value(value: &ZeekValue) -> String {
+    match value {
+        ZeekValue::Unset => "-".to_string(),
+        ZeekValue::Time(time) => format_secs(time.duration_since(UNIX_EPOCH).unwrap_or_default()),
+        ZeekValue::Interval(interval) => format_secs(*interval),
+        ZeekValue::String(s) => escape(s, false),
+        ZeekValue::Addr(addr) => addr.to_string(),
+        ZeekValue::Port(port) => port.to_string(),
+        ZeekValue::Count(count) => count.to_string(),
+        ZeekValue::Bool(b) => if *b { "T" } else { "F" }.to_string(),
+        ZeekValue::Strings(values) => join(values.iter().map(|s| escape(s, true))),
+        ZeekValue::Intervals(values) => join(values.iter().map(|value| format_secs(*value))),
+    }
+}
+
+/// Converts `value` to a JSON field, or `None` if it is unset.
+fn json_value(value: ZeekValue) -> Option<Value> {
+    let secs = |duration: Duration| Value::from(duration.as_secs_f64());
+    Some(match value {
+        ZeekValue::Unset => return None,
+        ZeekValue::Time(time) => secs(time.duration_since(UNIX_EPOCH).unwrap_or_default()),
+        ZeekValue::Interval(interval) => secs(interval),
+        ZeekValue::String(s) => Value::from(s),
+        ZeekValue::Addr(addr) => Value::from(addr.to_string()),
+        ZeekValue::Port(port) => Value::from(port),
+        ZeekValue::Count(count) => Value::from(count),
+        ZeekValue::Bool(b) => Value::from(b),
+        ZeekValue::Strings(values) => Value::from(values),
+        ZeekValue::Intervals(values) => Value::from_iter(values.into_iter().map(secs)),
+    })
+}
+
+/// Formats a duration as seconds with microsecond precision.
+fn format_secs(duration: Duration) -> String {
+    format!("{}.{:06}", duration.as_secs(), duration.subsec_micros())
+}
+
+/// Joins container elements, or returns the empty field marker.
+fn join(values: impl Iterator<Item = String>) -> String {
+    let joined = values.collect::<Vec<_>>().join(",");
+    if joined.is_empty() {
+        "(empty)".to_string()
+    } else {
+        joined
+    }
+}
+
+/// Escapes separators, non-printable characters, and values that would be read as markers.
+fn escape(s: &str, in_set: bool) -> String {
+    if s.is_empty() {
+        return if in_set {
+            String::new()
+        } else {
+            "(empty)".to_string()
+        };
+    }
+    if s == "-" || s == "(empty)" {
+        return s.bytes().fold(String::new(), |mut escaped, byte| {
+            let _ = write!(escaped, "\\x{:02x}", byte);
+            escaped
+        });
+    }
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            ',' if in_set => escaped.push_str("\\x2c"),
+            c if c.is_control() => {
+                let mut buf = [0; 4];
+                for byte in c.encode_utf8(&mut buf).bytes() {
+                    let _ = write!(escaped, "\\x{:02x}", byte);
+                }
+            }
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn core_zeek_tsv_escape() {
+        assert_eq!(escape("a\tb\\c", false), "a\\x09b\\\\c");
+        assert_eq!(escape("-", false), "\\x2d");
+        assert_eq!(escape("", false), "(empty)");
+        assert_eq!(
+            tsv_value(&ZeekValue::Strings(vec![
+                "a,b".to_string(),
+                "c".to_string()
+            ])),
+            "a\\x2cb,c"
+        );
+        assert_eq!(tsv_value(&ZeekValue::Strings(vec![])), "(empty)");
+        assert_eq!(
+            tsv_value(&ZeekValue::Interval(Duration::from_micros(1_500_001))),
+            "1.500001"
+        );
+    }
+}
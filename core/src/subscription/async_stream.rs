@@ -0,0 +1,101 @@
+//! Asynchronous subscriptions.
+//!
+//! Instead of invoking a callback, the packet processing cores can deliver subscribable objects
+//! into a bounded channel, consumed as a [Stream](futures_core::Stream) by an asynchronous
+//! application (e.g., one that exports records with an async client). Requires the `stream`
+//! feature. See [Runtime::new_stream](crate::Runtime::new_stream) for details.
+
+use super::CallbackStats;
+
+use std::pin::Pin;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use anyhow::{bail, Result};
+use futures_core::Stream;
+use tokio::sync::mpsc;
+
+/// A stream of subscribable objects delivered by the packet processing cores.
+///
+/// The stream ends once the [Runtime](crate::Runtime) that produces it has been dropped.
+#[derive(Debug)]
+pub struct SubscriptionStream<S> {
+    rx: mpsc::Receiver<S>,
+}
+
+impl<S> SubscriptionStream<S> {
+    /// Receives the next subscribable object, or `None` if the stream has ended.
+    pub async fn recv(&mut self) -> Option<S> {
+        self.rx.recv().await
+    }
+
+    /// Returns the underlying channel receiver.
+    pub fn into_inner(self) -> mpsc::Receiver<S> {
+        self.rx
+    }
+}
+
+impl<S> Stream for SubscriptionStream<S> {
+    type Item = S;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<S>> {
+        self.rx.poll_recv(cx)
+    }
+}
+
+/// Creates a channel that buffers up to `capacity` subscribable objects. Returns the callback
+/// that sends objects into the channel, blocking while it is full, and the stream that receives
+/// them. Objects sent after the stream is dropped are counted as dropped in `stats`. Returns an
+/// error if `capacity` is zero.
+pub(crate) fn channel<S>(
+    capacity: usize,
+    stats: Arc<CallbackStats>,
+) -> Result<(impl Fn(S), SubscriptionStream<S>)> {
+    if capacity == 0 {
+        bail!("Subscription stream capacity must be greater than zero");
+    }
+    let (tx, rx) = mpsc::channel(capacity);
+    let send = move |obj| {
+        if tx.blocking_send(obj).is_err() {
+            stats.nb_dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    };
+    Ok((send, SubscriptionStream { rx }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn core_stream_delivery() {
+        let stats = Arc::new(CallbackStats::named("test"));
+        let (send, mut stream) = channel(2, Arc::clone(&stats)).unwrap();
+        let producer = std::thread::spawn(move || {
+            for obj in 0..10 {
+                send(obj);
+            }
+        });
+
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let received = rt.block_on(async {
+            let mut received = vec![];
+            while let Some(obj) = stream.recv().await {
+                received.push(obj);
+            }
+            received
+        });
+        producer.join().unwrap();
+        assert_eq!(received, (0..10).collect::<Vec<_>>());
+
+        let (send, stream) = channel(2, Arc::clone(&stats)).unwrap();
+        drop(stream);
+        send(0);
+        assert_eq!(stats.nb_dropped.load(Ordering::Relaxed), 1);
+
+        assert!(channel::<u32>(0, stats).is_err());
+    }
+}
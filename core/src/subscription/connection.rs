@@ -1,6 +1,6 @@
 //! Connection records.
 //!
-//! This is a connection-level subscription that provides TCP and/or UDP connection information,
+//! This is a connection-level subscription that provides TCP, UDP, and SCTP connection information,
 //! statistics, and state history. It does not deliver payload data.
 //!
 //!
@@ -20,13 +20,15 @@
 //! }
 //! ```
 
+use crate::conntrack::clock;
 use crate::conntrack::conn::tcp_conn::reassembly::wrapping_lt;
 use crate::conntrack::conn_id::FiveTuple;
 use crate::conntrack::pdu::{L4Context, L4Pdu};
 use crate::conntrack::ConnTracker;
 use crate::filter::FilterResult;
+use crate::fingerprint::p0f::{self, OsMatch, TcpFingerprint};
 use crate::memory::mbuf::Mbuf;
-use crate::protocols::packet::tcp::{ACK, FIN, RST, SYN};
+use crate::protocols::packet::tcp::{TcpOptions, ACK, FIN, RST, SYN, TCP_PROTOCOL};
 use crate::protocols::stream::{ConnParser, Session};
 use crate::subscription::{Level, Subscribable, Subscription, Trackable};
 
@@ -53,9 +55,9 @@ const HIST_RST: u8 = b'R';
 
 /// A connection record.
 ///
-/// This subscribable type returns general information regarding TCP and UDP connections but does
-/// does not track payload data. If applicable, Retina internally manages stream reassembly. All
-/// connections are interpreted using flow semantics.
+/// This subscribable type returns general information regarding TCP and UDP connections and SCTP
+/// associations, but does not track payload data. If applicable, Retina internally manages stream
+/// reassembly. All connections are interpreted using flow semantics.
 #[derive(Debug)]
 pub struct Connection {
     /// The connection 5-tuple.
@@ -78,6 +80,14 @@ pub struct Connection {
     pub max_inactivity: Duration,
     /// The duration between the first and second packets.
     pub time_to_second_packet: Duration,
+    /// Time between the originator's SYN and the responder's SYN/ACK. This approximates the
+    /// round-trip time between the vantage point and the responder. Only applies to TCP
+    /// connections where the handshake was observed.
+    pub syn_rtt: Option<Duration>,
+    /// Time between the responder's SYN/ACK and the originator's ACK that completes the handshake.
+    /// This approximates the round-trip time between the vantage point and the originator. Only
+    /// applies to TCP connections where the handshake was observed.
+    pub ack_rtt: Option<Duration>,
     /// Connection history.
     ///
     /// This represents a summary of the connection history in the order the packets were observed,
@@ -98,6 +108,25 @@ pub struct Connection {
     pub orig: Flow,
     /// Responder flow.
     pub resp: Flow,
+    /// Best-guess operating system of the originator, inferred from its SYN. `None` if no
+    /// signature matched or fingerprinting is disabled.
+    pub client_os: Option<OsMatch>,
+    /// Best-guess operating system of the responder, inferred from its SYN/ACK. `None` if no
+    /// signature matched or fingerprinting is disabled.
+    pub server_os: Option<OsMatch>,
+    /// Whether this is an interim record of a connection that is still active.
+    ///
+    /// Interim records are delivered every
+    /// [active_timeout](crate::config::ConnTrackConfig::active_timeout) while the connection is
+    /// active, followed by a final record when it terminates. Counters of all records are
+    /// cumulative since the start of the connection, and `delta` holds the counters since the
+    /// previous record.
+    pub interim: bool,
+    /// Counters since the previous record of the connection.
+    pub delta: Delta,
+    /// Whether the connection was still active when its final record was delivered, because it
+    /// was evicted from a full connection table or the run ended.
+    pub forced_end: bool,
 }
 
 impl Connection {
@@ -130,6 +159,58 @@ impl Connection {
     pub fn history(&self) -> String {
         String::from_utf8_lossy(&self.history).into_owned()
     }
+
+    /// Returns the end-to-end handshake round-trip time (the sum of `syn_rtt` and `ack_rtt`).
+    #[inline]
+    pub fn handshake_rtt(&self) -> Option<Duration> {
+        Some(self.syn_rtt? + self.ack_rtt?)
+    }
+
+    /// Returns the window scale shift counts of the originator and responder, if window scaling
+    /// was negotiated (both the SYN and SYN/ACK carry the option).
+    pub fn window_scale(&self) -> Option<(u8, u8)> {
+        let orig = self.orig.syn_options.as_ref()?.wscale?;
+        let resp = self.resp.syn_options.as_ref()?.wscale?;
+        Some((orig, resp))
+    }
+
+    /// Returns `true` if selective acknowledgments were negotiated.
+    pub fn sack_permitted(&self) -> bool {
+        self.negotiated(|opts| opts.sack_permitted)
+    }
+
+    /// Returns `true` if TCP timestamps were negotiated.
+    pub fn timestamps(&self) -> bool {
+        self.negotiated(|opts| opts.timestamps.is_some())
+    }
+
+    /// Returns `true` if both the SYN and SYN/ACK satisfy `offered`.
+    fn negotiated(&self, offered: impl Fn(&TcpOptions) -> bool) -> bool {
+        match (&self.orig.syn_options, &self.resp.syn_options) {
+            (Some(orig), Some(resp)) => offered(orig) && offered(resp),
+            _ => false,
+        }
+    }
+}
+
+/// Counters of a connection since its previous record.
+///
+/// For the first record of a connection, these are counted since the first packet.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Delta {
+    /// Time of the previous record, or of the first packet for the first record.
+    #[serde(skip)]
+    pub ts: Instant,
+    /// Time elapsed since `ts`. For the final record, this is measured up to the last packet.
+    pub duration: Duration,
+    /// Number of packets from the originator.
+    pub orig_pkts: u64,
+    /// Number of payload bytes from the originator.
+    pub orig_bytes: u64,
+    /// Number of packets from the responder.
+    pub resp_pkts: u64,
+    /// Number of payload bytes from the responder.
+    pub resp_bytes: u64,
 }
 
 impl Serialize for Connection {
@@ -137,13 +218,20 @@ impl Serialize for Connection {
     where
         S: Serializer,
     {
-        let mut state = serializer.serialize_struct("Connection", 6)?;
+        let mut state = serializer.serialize_struct("Connection", 13)?;
         state.serialize_field("five_tuple", &self.five_tuple)?;
         state.serialize_field("duration", &self.duration)?;
         state.serialize_field("max_inactivity", &self.max_inactivity)?;
+        state.serialize_field("syn_rtt", &self.syn_rtt)?;
+        state.serialize_field("ack_rtt", &self.ack_rtt)?;
         state.serialize_field("history", &self.history())?;
         state.serialize_field("orig", &self.orig)?;
         state.serialize_field("resp", &self.resp)?;
+        state.serialize_field("client_os", &self.client_os)?;
+        state.serialize_field("server_os", &self.server_os)?;
+        state.serialize_field("interim", &self.interim)?;
+        state.serialize_field("delta", &self.delta)?;
+        state.serialize_field("forced_end", &self.forced_end)?;
         state.end()
     }
 }
@@ -195,27 +283,43 @@ pub struct TrackedConnection {
     second_seen_ts: Instant,
     last_seen_ts: Instant,
     max_inactivity: Duration,
+    syn_ts: Option<Instant>,
+    synack_ts: Option<Instant>,
+    ack_ts: Option<Instant>,
     history: Vec<u8>,
     ctos: Flow,
     stoc: Flow,
+    client_fp: Option<TcpFingerprint>,
+    server_fp: Option<TcpFingerprint>,
+    /// Time and cumulative counters of the last interim record.
+    reported: Delta,
+    /// Set if the connection is terminated while still active.
+    forced_end: bool,
 }
 
 impl TrackedConnection {
     #[inline]
     fn update(&mut self, segment: L4Pdu) {
-        let now = Instant::now();
+        let now = clock::now();
         let inactivity = now - self.last_seen_ts;
         if inactivity > self.max_inactivity {
             self.max_inactivity = inactivity;
         }
         self.last_seen_ts = now;
 
+        self.update_fingerprint(&segment);
+        if segment.ctxt.proto == TCP_PROTOCOL {
+            self.update_handshake(&segment, now);
+            self.update_rtt(&segment, now);
+        }
         if segment.dir {
             self.update_history(&segment, 0x0);
-            self.ctos.insert_segment(segment);
+            let peer_ack = self.stoc.last_ack;
+            self.ctos.insert_segment(segment, peer_ack);
         } else {
             self.update_history(&segment, 0x20);
-            self.stoc.insert_segment(segment);
+            let peer_ack = self.ctos.last_ack;
+            self.stoc.insert_segment(segment, peer_ack);
         }
 
         if self.ctos.nb_pkts + self.stoc.nb_pkts == 2 {
@@ -223,6 +327,57 @@ impl TrackedConnection {
         }
     }
 
+    /// Records the arrival times of the handshake packets. Uses the last SYN observed before the
+    /// SYN/ACK, since the SYN/ACK most likely responds to a retransmitted SYN.
+    #[inline]
+    fn update_handshake(&mut self, segment: &L4Pdu, now: Instant) {
+        if self.ack_ts.is_some() {
+            return;
+        }
+        match (segment.dir, segment.flags() & (SYN | ACK)) {
+            (true, SYN) if self.synack_ts.is_none() => self.syn_ts = Some(now),
+            (false, f) if f == SYN | ACK && self.syn_ts.is_some() => {
+                self.synack_ts.get_or_insert(now);
+            }
+            (true, ACK) if self.synack_ts.is_some() => self.ack_ts = Some(now),
+            _ => (),
+        }
+    }
+
+    /// Takes RTT samples from the segment as an acknowledgment of the opposite flow, then times
+    /// the segment itself. Timestamp-based samples are only taken if both endpoints negotiated the
+    /// TCP timestamps option.
+    #[inline]
+    fn update_rtt(&mut self, segment: &L4Pdu, now: Instant) {
+        let timestamps = match (&self.ctos.syn_options, &self.stoc.syn_options) {
+            (Some(orig), Some(resp)) if orig.timestamps.is_some() && resp.timestamps.is_some() => {
+                segment.timestamps()
+            }
+            _ => None,
+        };
+        let (sender, receiver) = if segment.dir {
+            (&mut self.ctos, &mut self.stoc)
+        } else {
+            (&mut self.stoc, &mut self.ctos)
+        };
+        receiver.ack_rtt_sample(segment, timestamps.map(|(_, tsecr)| tsecr), now);
+        sender.start_rtt_sample(segment, timestamps.map(|(tsval, _)| tsval), now);
+    }
+
+    /// Records the fingerprint of the first SYN from the originator and the first SYN/ACK from the
+    /// responder.
+    #[inline]
+    fn update_fingerprint(&mut self, segment: &L4Pdu) {
+        let fp = match (segment.dir, segment.flags() & (SYN | ACK)) {
+            (true, SYN) => &mut self.client_fp,
+            (false, f) if f == SYN | ACK => &mut self.server_fp,
+            _ => return,
+        };
+        if fp.is_none() && p0f::enabled() {
+            *fp = TcpFingerprint::from_mbuf(&segment.mbuf);
+        }
+    }
+
     #[inline]
     fn update_history(&mut self, segment: &L4Pdu, mask: u8) {
         fn insert(history: &mut Vec<u8>, event: u8) {
@@ -248,22 +403,98 @@ impl TrackedConnection {
             insert(&mut self.history, HIST_DATA ^ mask);
         }
     }
+
+    /// Returns the record of the connection, ending at `end` (the time of the last packet for the
+    /// final record).
+    fn record(&self, interim: bool, end: Instant) -> Connection {
+        let (duration, max_inactivity, time_to_second_packet) =
+            if self.ctos.nb_pkts + self.stoc.nb_pkts == 1 {
+                (
+                    Duration::default(),
+                    Duration::default(),
+                    Duration::default(),
+                )
+            } else {
+                (
+                    self.last_seen_ts - self.first_seen_ts,
+                    self.max_inactivity,
+                    self.second_seen_ts - self.first_seen_ts,
+                )
+            };
+
+        let (client_os, server_os) = match p0f::database() {
+            Some(db) => (
+                self.client_fp.as_ref().and_then(|fp| db.match_request(fp)),
+                self.server_fp.as_ref().and_then(|fp| db.match_response(fp)),
+            ),
+            None => (None, None),
+        };
+
+        let syn_rtt = match (self.syn_ts, self.synack_ts) {
+            (Some(syn), Some(synack)) => Some(synack - syn),
+            _ => None,
+        };
+        let ack_rtt = match (self.synack_ts, self.ack_ts) {
+            (Some(synack), Some(ack)) => Some(ack - synack),
+            _ => None,
+        };
+
+        Connection {
+            five_tuple: self.five_tuple,
+            ts: self.first_seen_ts,
+            duration,
+            max_inactivity,
+            time_to_second_packet,
+            syn_rtt,
+            ack_rtt,
+            history: self.history.clone(),
+            orig: self.ctos.clone(),
+            resp: self.stoc.clone(),
+            client_os,
+            server_os,
+            interim,
+            forced_end: self.forced_end,
+            delta: Delta {
+                ts: self.reported.ts,
+                duration: end.saturating_duration_since(self.reported.ts),
+                orig_pkts: self.ctos.nb_pkts - self.reported.orig_pkts,
+                orig_bytes: self.ctos.nb_bytes - self.reported.orig_bytes,
+                resp_pkts: self.stoc.nb_pkts - self.reported.resp_pkts,
+                resp_bytes: self.stoc.nb_bytes - self.reported.resp_bytes,
+            },
+        }
+    }
 }
 
 impl Trackable for TrackedConnection {
     type Subscribed = Connection;
+    const INTERIM_RECORDS: bool = true;
 
     fn new(five_tuple: FiveTuple) -> Self {
-        let now = Instant::now();
+        let now = clock::now();
         TrackedConnection {
             five_tuple,
             first_seen_ts: now,
             second_seen_ts: now,
             last_seen_ts: now,
             max_inactivity: Duration::default(),
+            syn_ts: None,
+            synack_ts: None,
+            ack_ts: None,
             history: Vec::with_capacity(16),
             ctos: Flow::new(),
             stoc: Flow::new(),
+            client_fp: None,
+            server_fp: None,
+            reported: Delta {
+                ts: now,
+                duration: Duration::default(),
+                orig_pkts: 0,
+                orig_bytes: 0,
+                resp_pkts: 0,
+                resp_bytes: 0,
+            },
+            forced_end: false,
         }
     }
 
@@ -280,30 +511,28 @@ impl Trackable for TrackedConnection {
     }
 
     fn on_terminate(&mut self, subscription: &Subscription<Self::Subscribed>) {
-        let (duration, max_inactivity, time_to_second_packet) =
-            if self.ctos.nb_pkts + self.stoc.nb_pkts == 1 {
-                (
-                    Duration::default(),
-                    Duration::default(),
-                    Duration::default(),
-                )
-            } else {
-                (
-                    self.last_seen_ts - self.first_seen_ts,
-                    self.max_inactivity,
-                    self.second_seen_ts - self.first_seen_ts,
-                )
-            };
+        let conn = self.record(false, self.last_seen_ts);
+        subscription.invoke(conn);
+    }
 
-        let conn = Connection {
-            five_tuple: self.five_tuple,
-            ts: self.first_seen_ts,
-            duration,
-            max_inactivity,
-            time_to_second_packet,
-            history: self.history.clone(),
-            orig: self.ctos.clone(),
-            resp: self.stoc.clone(),
+    fn on_force_end(&mut self) {
+        self.forced_end = true;
+    }
+
+    fn on_active_timeout(&mut self, subscription: &Subscription<Self::Subscribed>) {
+        if self.last_seen_ts <= self.reported.ts {
+            // No packets since the last record
+            return;
+        }
+        let now = clock::now();
+        let conn = self.record(true, now);
+        self.reported = Delta {
+            ts: now,
+            duration: Duration::default(),
+            orig_pkts: self.ctos.nb_pkts,
+            orig_bytes: self.ctos.nb_bytes,
+            resp_pkts: self.stoc.nb_pkts,
+            resp_bytes: self.stoc.nb_bytes,
         };
         subscription.invoke(conn);
     }
@@ -343,6 +572,47 @@ pub struct Flow {
     /// Maps relative sequence number of a content gap to the number of packets observed before it
     /// is filled. Only applies to TCP flows.
     pub gaps: HashMap<u32, u64>,
+    /// TCP options sent on the SYN (originator) or SYN/ACK (responder) in this direction. Only
+    /// applies to TCP flows.
+    pub syn_options: Option<TcpOptions>,
+    /// Number of retransmitted packets. A retransmission is a segment whose payload was entirely
+    /// observed before in this direction, or a repeated SYN. Only applies to TCP flows.
+    pub nb_retrans_pkts: u64,
+    /// Number of spurious retransmissions (retransmitted segments whose payload had already been
+    /// acknowledged by the peer). These are also counted in `nb_retrans_pkts`. Only applies to
+    /// TCP flows.
+    pub nb_spurious_retrans_pkts: u64,
+    /// Number of out-of-order packets. An out-of-order segment carries payload that has not been
+    /// observed before, but starts before the highest sequence number observed in this direction.
+    /// Only applies to TCP flows.
+    pub nb_ooo_pkts: u64,
+    /// Number of duplicate ACKs (pure ACKs that repeat the previous acknowledgment number and
+    /// window). Only applies to TCP flows.
+    pub nb_dup_acks: u64,
+    /// Number of packets advertising a zero receive window. Only applies to TCP flows.
+    pub nb_zero_window_pkts: u64,
+    /// Highest acknowledgment number sent in this direction.
+    #[serde(skip)]
+    last_ack: Option<u32>,
+    /// Receive window of the last acknowledgment sent in this direction.
+    #[serde(skip)]
+    last_window: u16,
+    /// Round-trip times between data segments in this direction and the acknowledgments that
+    /// cover them, as measured at the vantage point. Retransmitted segments are not sampled
+    /// (Karn's algorithm). Only applies to TCP flows.
+    pub data_rtt: RttStats,
+    /// Round-trip times between TCP timestamp values in this direction and the first segment from
+    /// the peer that echoes them. Only applies to TCP flows that negotiated timestamps.
+    pub ts_rtt: RttStats,
+    /// Sequence number that acknowledges the timed segment, and the time it was observed.
+    #[serde(skip)]
+    rtt_pending: Option<(u32, Instant)>,
+    /// Timestamp value being timed, and the time it was first observed.
+    #[serde(skip)]
+    ts_pending: Option<(u32, Instant)>,
+    /// Last timestamp value that was timed.
+    #[serde(skip)]
+    ts_last: Option<u32>,
 }
 
 impl Flow {
@@ -357,12 +627,83 @@ impl Flow {
             capacity: 100, // temp hardcode for now
             chunks: Vec::with_capacity(100),
             gaps: HashMap::new(),
+            syn_options: None,
+            nb_retrans_pkts: 0,
+            nb_spurious_retrans_pkts: 0,
+            nb_ooo_pkts: 0,
+            nb_dup_acks: 0,
+            nb_zero_window_pkts: 0,
+            last_ack: None,
+            last_window: 0,
+            data_rtt: RttStats::default(),
+            ts_rtt: RttStats::default(),
+            rtt_pending: None,
+            ts_pending: None,
+            ts_last: None,
         }
     }
 
+    /// Starts timing `segment` if no other segment is being timed, and invalidates the pending
+    /// sample if `segment` retransmits the timed data.
     #[inline]
-    fn insert_segment(&mut self, segment: L4Pdu) {
-        self.nb_pkts += 1;
+    fn start_rtt_sample(&mut self, segment: &L4Pdu, tsval: Option<u32>, now: Instant) {
+        if segment.length() > 0 {
+            let seq_end = segment.seq_no().wrapping_add(segment.length() as u32);
+            match self.rtt_pending {
+                Some((ack, _)) if wrapping_lt(segment.seq_no(), ack) => self.rtt_pending = None,
+                Some(_) => (),
+                None => self.rtt_pending = Some((seq_end, now)),
+            }
+        }
+        if let Some(tsval) = tsval {
+            let is_new = match self.ts_last {
+                Some(last) => wrapping_lt(last, tsval),
+                None => true,
+            };
+            if self.ts_pending.is_none() && is_new {
+                self.ts_pending = Some((tsval, now));
+                self.ts_last = Some(tsval);
+            }
+        }
+    }
+
+    /// Completes pending samples that are acknowledged or echoed by `segment` from the peer.
+    #[inline]
+    fn ack_rtt_sample(&mut self, segment: &L4Pdu, tsecr: Option<u32>, now: Instant) {
+        if let Some((ack, ts)) = self.rtt_pending {
+            if segment.flags() & ACK != 0 && !wrapping_lt(segment.ack_no(), ack) {
+                self.data_rtt.insert(now - ts);
+                self.rtt_pending = None;
+            }
+        }
+        if let (Some((tsval, ts)), Some(tsecr)) = (self.ts_pending, tsecr) {
+            if !wrapping_lt(tsecr, tsval) {
+                self.ts_rtt.insert(now - ts);
+                self.ts_pending = None;
+            }
+        }
+    }
+
+    /// Updates the flow with `segment`. `peer_ack` is the highest acknowledgment number sent by
+    /// the peer, used to detect spurious retransmissions.
+    #[inline]
+    fn insert_segment(&mut self, segment: L4Pdu, peer_ack: Option<u32>) {
+        if !self.count_packet(&segment.ctxt) {
+            return;
+        }
+
+        if segment.ctxt.proto == TCP_PROTOCOL {
+            if segment.flags() & SYN != 0 {
+                if self.syn_options.is_none() {
+                    self.syn_options = segment.tcp_options();
+                } else {
+                    self.nb_retrans_pkts += 1;
+                }
+            }
+            if segment.flags() & ACK != 0 {
+                self.update_ack(&segment);
+            }
+        }
 
         if segment.offset() > segment.mbuf.data_len()
             || (segment.offset() + segment.length()) > segment.mbuf.data_len()
@@ -370,12 +711,30 @@ impl Flow {
             self.nb_malformed_pkts += 1;
             return;
         }
-        self.nb_bytes += segment.length() as u64;
+        self.insert_payload(&segment.ctxt, peer_ack);
+    }
+
+    /// Counts the packet of a segment. Returns `false` for the DATA chunks after the first of a
+    /// split SCTP packet, since the packet is counted with its first chunk.
+    #[inline]
+    fn count_packet(&mut self, ctxt: &L4Context) -> bool {
+        if ctxt.sctp.is_some_and(|sctp| sctp.trailing) {
+            return false;
+        }
+        self.nb_pkts += 1;
+        true
+    }
+
+    /// Tracks the sequence space covered by the payload of a well-formed segment. Retransmissions
+    /// and out-of-order arrivals are only classified for TCP flows.
+    #[inline]
+    fn insert_payload(&mut self, ctxt: &L4Context, peer_ack: Option<u32>) {
+        self.nb_bytes += ctxt.packet_len() as u64;
 
-        let seq_no = if segment.flags() & SYN != 0 {
-            segment.seq_no().wrapping_add(1)
+        let seq_no = if ctxt.flags & SYN != 0 {
+            ctxt.seq_no.wrapping_add(1)
         } else {
-            segment.seq_no()
+            ctxt.seq_no
         };
 
         if self.chunks.is_empty() {
@@ -387,14 +746,63 @@ impl Flow {
             return;
         }
 
-        if self.chunks.len() < self.capacity {
-            let seg_start = seq_no.wrapping_sub(self.data_start);
-            let seg_end = seg_start + segment.length() as u32;
+        let seg_start = seq_no.wrapping_sub(self.data_start);
+        let seg_end = seg_start + ctxt.length as u32;
+        if ctxt.proto == TCP_PROTOCOL && ctxt.length > 0 {
+            let seq_end = seq_no.wrapping_add(ctxt.length as u32);
+            self.classify_segment(Chunk(seg_start, seg_end), seq_end, peer_ack);
+        }
 
+        if self.chunks.len() < self.capacity {
             self.merge_chunk(Chunk(seg_start, seg_end));
         }
     }
 
+    /// Counts duplicate ACKs and zero-window advertisements, and records the highest
+    /// acknowledgment number.
+    #[inline]
+    fn update_ack(&mut self, segment: &L4Pdu) {
+        let ack_no = segment.ack_no();
+        let window = segment.window();
+        let pure_ack = segment.length() == 0 && segment.flags() & (SYN | FIN | RST) == 0;
+        if pure_ack && self.last_ack == Some(ack_no) && self.last_window == window {
+            self.nb_dup_acks += 1;
+        }
+        if window == 0 && segment.flags() & RST == 0 {
+            self.nb_zero_window_pkts += 1;
+        }
+        let is_highest = match self.last_ack {
+            Some(last) => !wrapping_lt(ack_no, last),
+            None => true,
+        };
+        if is_highest {
+            self.last_ack = Some(ack_no);
+        }
+        self.last_window = window;
+    }
+
+    /// Classifies a payload-carrying segment that starts before the highest observed sequence
+    /// number as either a retransmission or an out-of-order arrival. `seq_end` is the raw sequence
+    /// number following the segment.
+    #[inline]
+    fn classify_segment(&mut self, chunk: Chunk, seq_end: u32, peer_ack: Option<u32>) {
+        let highest = match self.chunks.last() {
+            Some(last) => last.1,
+            None => return,
+        };
+        if chunk.0 >= highest {
+            return;
+        }
+        if self.chunks.iter().any(|c| c.0 <= chunk.0 && chunk.1 <= c.1) {
+            self.nb_retrans_pkts += 1;
+            if peer_ack.is_some_and(|ack| !wrapping_lt(ack, seq_end)) {
+                self.nb_spurious_retrans_pkts += 1;
+            }
+        } else {
+            self.nb_ooo_pkts += 1;
+        }
+    }
+
     /// Insert `chunk` into flow, merging intervals as necessary. Flow `chunks` are a sorted set of
     /// non-overlapping intervals.
     #[inline]
@@ -476,6 +884,42 @@ impl Flow {
     }
 }
 
+/// Summary statistics of round-trip time samples.
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct RttStats {
+    /// Number of samples.
+    pub nb_samples: u64,
+    /// Minimum sample.
+    pub min: Duration,
+    /// Maximum sample.
+    pub max: Duration,
+    /// Sum of all samples.
+    pub total: Duration,
+}
+
+impl RttStats {
+    /// Returns the mean round-trip time, or `None` if there were no samples.
+    #[inline]
+    pub fn mean(&self) -> Option<Duration> {
+        if self.nb_samples == 0 {
+            return None;
+        }
+        Some(self.total / self.nb_samples as u32)
+    }
+
+    #[inline]
+    fn insert(&mut self, rtt: Duration) {
+        if self.nb_samples == 0 || rtt < self.min {
+            self.min = rtt;
+        }
+        if rtt > self.max {
+            self.max = rtt;
+        }
+        self.total += rtt;
+        self.nb_samples += 1;
+    }
+}
+
 /// Start (inclusive) and end (exclusive) interval of contiguous TCP payload bytes.
 #[derive(Debug, Default, Clone, Copy, Eq, PartialEq, Serialize)]
 pub struct Chunk(u32, u32);
@@ -483,6 +927,151 @@ pub struct Chunk(u32, u32);
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::conntrack::pdu::SctpContext;
+    use crate::protocols::packet::sctp::SCTP_PROTOCOL;
+    use crate::protocols::packet::udp::UDP_PROTOCOL;
+
+    #[test]
+    fn core_rtt_stats() {
+        let mut stats = RttStats::default();
+        assert_eq!(stats.mean(), None);
+        stats.insert(Duration::from_millis(30));
+        stats.insert(Duration::from_millis(10));
+        stats.insert(Duration::from_millis(20));
+        assert_eq!(stats.nb_samples, 3);
+        assert_eq!(stats.min, Duration::from_millis(10));
+        assert_eq!(stats.max, Duration::from_millis(30));
+        assert_eq!(stats.mean(), Some(Duration::from_millis(20)));
+    }
+
+    #[test]
+    fn core_connection_serialize() {
+        let ts = Instant::now();
+        let conn = Connection {
+            five_tuple: FiveTuple {
+                orig: "10.0.0.1:1234".parse().unwrap(),
+                resp: "10.0.0.2:80".parse().unwrap(),
+                proto: TCP_PROTOCOL,
+            },
+            ts,
+            duration: Duration::from_secs(1),
+            max_inactivity: Duration::from_millis(500),
+            time_to_second_packet: Duration::from_millis(10),
+            syn_rtt: None,
+            ack_rtt: None,
+            history: b"ShAD".to_vec(),
+            orig: Flow::new(),
+            resp: Flow::new(),
+            client_os: None,
+            server_os: None,
+            interim: false,
+            delta: Delta {
+                ts,
+                duration: Duration::from_secs(1),
+                orig_pkts: 0,
+                orig_bytes: 0,
+                resp_pkts: 0,
+                resp_bytes: 0,
+            },
+            forced_end: true,
+        };
+        let value = serde_json::to_value(&conn).unwrap();
+        let fields = value.as_object().unwrap();
+        assert_eq!(fields.len(), 13);
+        assert_eq!(fields["history"], "ShAD");
+        assert_eq!(fields["forced_end"], true);
+    }
+
+    #[test]
+    fn core_classify_segment() {
+        let mut flow = Flow::new();
+        flow.chunks = vec![Chunk(0, 100), Chunk(200, 300)];
+        flow.classify_segment(Chunk(300, 400), 1400, None);
+        flow.classify_segment(Chunk(100, 200), 1200, Some(1100));
+        flow.classify_segment(Chunk(0, 100), 1100, Some(1100));
+        flow.classify_segment(Chunk(200, 300), 1300, Some(1100));
+        assert_eq!(flow.nb_ooo_pkts, 1);
+        assert_eq!(flow.nb_retrans_pkts, 2);
+        assert_eq!(flow.nb_spurious_retrans_pkts, 1);
+    }
+
+    #[test]
+    fn core_udp_not_classified() {
+        let mut flow = Flow::new();
+        let ctxt = L4Context {
+            src: "10.0.0.1:1234".parse().unwrap(),
+            dst: "10.0.0.2:53".parse().unwrap(),
+            proto: UDP_PROTOCOL,
+            idx: 0,
+            offset: 42,
+            length: 0,
+            seq_no: 0,
+            ack_no: 0,
+            window: 0,
+            flags: 0,
+            timestamps: None,
+            sctp: None,
+        };
+        for length in [100, 50, 200, 100] {
+            flow.insert_payload(&L4Context { length, ..ctxt }, None);
+        }
+        assert_eq!(flow.nb_bytes, 450);
+        assert_eq!(flow.nb_retrans_pkts, 0);
+        assert_eq!(flow.nb_ooo_pkts, 0);
+    }
+
+    #[test]
+    fn core_sctp_bundled_data() {
+        let sctp = SctpContext {
+            nb_data: 3,
+            data_len: 300,
+            ..Default::default()
+        };
+        let packet = L4Context {
+            src: "10.0.0.1:2905".parse().unwrap(),
+            dst: "10.0.0.2:2905".parse().unwrap(),
+            proto: SCTP_PROTOCOL,
+            idx: 0,
+            offset: 62,
+            length: 100,
+            seq_no: 0,
+            ack_no: 0,
+            window: 0,
+            flags: 0,
+            timestamps: None,
+            sctp: Some(sctp),
+        };
+        let insert = |flow: &mut Flow, ctxt: L4Context| {
+            if flow.count_packet(&ctxt) {
+                flow.insert_payload(&ctxt, None);
+            }
+        };
+
+        // Before the connection matches, the packet is split into one PDU per DATA chunk
+        let mut split = Flow::new();
+        for (i, length) in [100, 120, 80].into_iter().enumerate() {
+            let sctp = SctpContext {
+                trailing: i > 0,
+                ..sctp
+            };
+            insert(
+                &mut split,
+                L4Context {
+                    length,
+                    sctp: Some(sctp),
+                    ..packet
+                },
+            );
+        }
+        // Afterwards, it is delivered whole
+        let mut whole = Flow::new();
+        insert(&mut whole, packet);
+
+        for flow in [split, whole] {
+            assert_eq!(flow.nb_pkts, 1);
+            assert_eq!(flow.nb_bytes, 300);
+        }
+    }
 
     #[test]
     fn core_merge_chunk_fill_single() {
@@ -34,7 +34,7 @@ use crate::subscription::{Level, Subscribable, Subscription, Trackable};
 
 use std::net::SocketAddr;
 
-/// Ethernet frames in a TCP or UDP connection.
+/// Ethernet frames in a TCP or UDP connection or an SCTP association.
 #[derive(Debug, Clone)]
 pub struct ConnectionFrame {
     pub five_tuple: FiveTuple,
@@ -16,6 +16,7 @@
 //!     runtime.run();
 //! }
 
+use crate::conntrack::clock;
 use crate::conntrack::conn_id::FiveTuple;
 use crate::conntrack::pdu::{L4Context, L4Pdu};
 use crate::conntrack::ConnTracker;
@@ -28,11 +29,15 @@ use crate::subscription::{Level, Subscribable, Subscription, Trackable};
 use serde::Serialize;
 
 use std::net::SocketAddr;
+use std::time::Instant;
 
 /// A parsed DNS transaction and connection metadata.
 #[derive(Debug, Serialize)]
 pub struct DnsTransaction {
     pub five_tuple: FiveTuple,
+    /// Timestamp of the first packet in the connection.
+    #[serde(skip)]
+    pub conn_ts: Instant,
     pub data: Dns,
 }
 
@@ -91,6 +96,7 @@ impl Subscribable for DnsTransaction {
 #[doc(hidden)]
 pub struct TrackedDns {
     five_tuple: FiveTuple,
+    first_seen_ts: Instant,
 }
 
 impl TrackedDns {}
@@ -99,7 +105,10 @@ impl Trackable for TrackedDns {
     type Subscribed = DnsTransaction;
 
     fn new(five_tuple: FiveTuple) -> Self {
-        TrackedDns { five_tuple }
+        TrackedDns {
+            five_tuple,
+            first_seen_ts: clock::now(),
+        }
     }
 
     fn pre_match(&mut self, _pdu: L4Pdu, _session_id: Option<usize>) {}
@@ -108,6 +117,7 @@ impl Trackable for TrackedDns {
         if let SessionData::Dns(dns) = session.data {
             subscription.invoke(DnsTransaction {
                 five_tuple: self.five_tuple,
+                conn_ts: self.first_seen_ts,
                 data: *dns,
             });
         }
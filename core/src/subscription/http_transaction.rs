@@ -18,6 +18,7 @@
 //!     println!("Count: {:?}", cnt);
 //! }
 
+use crate::conntrack::clock;
 use crate::conntrack::conn_id::FiveTuple;
 use crate::conntrack::pdu::{L4Context, L4Pdu};
 use crate::conntrack::ConnTracker;
@@ -30,11 +31,15 @@ use crate::subscription::{Level, Subscribable, Subscription, Trackable};
 use serde::Serialize;
 
 use std::net::SocketAddr;
+use std::time::Instant;
 
 /// A parsed HTTP transaction and connection metadata.
 #[derive(Debug, Serialize)]
 pub struct HttpTransaction {
     pub five_tuple: FiveTuple,
+    /// Timestamp of the first packet in the connection.
+    #[serde(skip)]
+    pub conn_ts: Instant,
     pub data: Http,
 }
 
@@ -94,13 +99,17 @@ impl Subscribable for HttpTransaction {
 #[doc(hidden)]
 pub struct TrackedHttp {
     five_tuple: FiveTuple,
+    first_seen_ts: Instant,
 }
 
 impl Trackable for TrackedHttp {
     type Subscribed = HttpTransaction;
 
     fn new(five_tuple: FiveTuple) -> Self {
-        TrackedHttp { five_tuple }
+        TrackedHttp {
+            five_tuple,
+            first_seen_ts: clock::now(),
+        }
     }
 
     fn pre_match(&mut self, _pdu: L4Pdu, _session_id: Option<usize>) {}
@@ -109,6 +118,7 @@ impl Trackable for TrackedHttp {
         if let SessionData::Http(http) = session.data {
             subscription.invoke(HttpTransaction {
                 five_tuple: self.five_tuple,
+                conn_ts: self.first_seen_ts,
                 data: *http,
             });
         }
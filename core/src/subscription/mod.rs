@@ -5,6 +5,8 @@
 //! parameter and immutably borrows values from the environment. Built-in subscribable types can
 //! be customized within the framework to provide additional data to the callback if needed.
 
+#[cfg(feature = "stream")]
+pub mod async_stream;
 pub mod connection;
 pub mod connection_frame;
 pub mod dns_transaction;
@@ -14,6 +16,8 @@ pub mod quic_stream;
 pub mod tls_handshake;
 pub mod zc_frame;
 
+pub(crate) mod offload;
+
 // Re-export subscribable types for more convenient usage.
 pub use self::connection::Connection;
 pub use self::connection_frame::ConnectionFrame;
@@ -27,6 +31,7 @@ pub use self::zc_frame::ZcFrame;
 use crate::conntrack::conn_id::FiveTuple;
 use crate::conntrack::pdu::L4Pdu;
 use crate::conntrack::ConnTracker;
+use crate::dpdk;
 use crate::filter::{ConnFilterFn, PacketFilterFn, SessionFilterFn};
 use crate::filter::{FilterFactory, FilterResult};
 use crate::memory::mbuf::Mbuf;
@@ -35,6 +40,16 @@ use crate::protocols::stream::{ConnData, ConnParser, Session};
 #[cfg(feature = "timing")]
 use crate::timing::timer::Timers;
 
+use std::cell::OnceCell;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use anyhow::{bail, Result};
+
+/// Number of cores with their own per-core state, such as callback counters and sink files (DPDK's
+/// default `RTE_MAX_LCORE`). Other threads share a single slot.
+pub(crate) const MAX_CORES: usize = 128;
+
 /// The abstraction level of the subscribable type.
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub enum Level {
@@ -71,6 +86,10 @@ pub trait Subscribable {
 pub trait Trackable {
     type Subscribed: Subscribable<Tracked = Self>;
 
+    /// Whether the type delivers interim records of long-lived connections. If `false` (the
+    /// default), no active timeout is scheduled and `on_active_timeout` is never invoked.
+    const INTERIM_RECORDS: bool = false;
+
     /// Create a new Trackable type to manage subscription data for the duration of the connection
     /// represented by `five_tuple`.
     fn new(five_tuple: FiveTuple) -> Self;
@@ -86,6 +105,15 @@ pub trait Trackable {
 
     /// Update tracked subscription data on connection termination.
     fn on_terminate(&mut self, subscription: &Subscription<Self::Subscribed>);
+
+    /// Update tracked subscription data before a connection that is still active is terminated
+    /// (e.g., when it is evicted from a full table or drained at the end of the run). Does nothing
+    /// by default.
+    fn on_force_end(&mut self) {}
+
+    /// Update tracked subscription data when the active timeout of a long-lived connection
+    /// expires. Does nothing by default.
+    fn on_active_timeout(&mut self, _subscription: &Subscription<Self::Subscribed>) {}
 }
 
 /// A request for a callback on a subset of traffic specified by the filter.
@@ -98,6 +126,12 @@ where
     conn_filter: ConnFilterFn,
     session_filter: SessionFilterFn,
     callback: Box<dyn Fn(S) + 'a>,
+    /// Invoked on each packet processing core when it exits. Set before the runtime is run.
+    core_exit_callback: OnceCell<Box<dyn Fn(u32) + 'a>>,
+    /// Waits for the callbacks offloaded from a core to complete, if callbacks are offloaded.
+    core_flush: OnceCell<Box<dyn Fn(u32) + 'a>>,
+    /// Callback counters, shared with the monitor.
+    pub(crate) stats: Arc<CallbackStats>,
     #[cfg(feature = "timing")]
     pub(crate) timers: Timers,
 }
@@ -106,13 +140,21 @@ impl<'a, S> Subscription<'a, S>
 where
     S: Subscribable,
 {
-    /// Creates a new subscription from a filter and a callback.
-    pub(crate) fn new(factory: FilterFactory, cb: impl Fn(S) + 'a) -> Self {
+    /// Creates a new subscription from a filter and a callback that records its invocations in
+    /// `stats`.
+    pub(crate) fn new(
+        factory: FilterFactory,
+        cb: impl Fn(S) + 'a,
+        stats: Arc<CallbackStats>,
+    ) -> Self {
         Subscription {
             packet_filter: factory.packet_filter,
             conn_filter: factory.conn_filter,
             session_filter: factory.session_filter,
             callback: Box::new(cb),
+            core_exit_callback: OnceCell::new(),
+            core_flush: OnceCell::new(),
+            stats,
             #[cfg(feature = "timing")]
             timers: Timers::new(),
         }
@@ -139,5 +181,103 @@ where
         tsc_start!(t0);
         (self.callback)(obj);
         tsc_record!(self.timers, "callback", t0);
+        self.stats
+            .incr_invoked(unsafe { dpdk::rte_lcore_id() } as usize);
+    }
+
+    /// Sets the callback invoked on each packet processing core when it exits. Returns an error if
+    /// it is already set.
+    pub(crate) fn set_core_exit_callback(&self, cb: impl Fn(u32) + 'a) -> Result<()> {
+        if self.core_exit_callback.set(Box::new(cb)).is_err() {
+            bail!("Core exit callback already set");
+        }
+        Ok(())
+    }
+
+    /// Sets the function that waits for the callbacks offloaded from a core to complete. Returns
+    /// an error if it is already set.
+    pub(crate) fn set_core_flush(&self, flush: impl Fn(u32) + 'a) -> Result<()> {
+        if self.core_flush.set(Box::new(flush)).is_err() {
+            bail!("Core flush already set");
+        }
+        Ok(())
+    }
+
+    /// Invokes the core exit callback, if any, on core `core_id`. If callbacks are offloaded, waits
+    /// until the objects queued by the core have been delivered first.
+    pub(crate) fn invoke_core_exit(&self, core_id: u32) {
+        if let Some(cb) = self.core_exit_callback.get() {
+            if let Some(flush) = self.core_flush.get() {
+                flush(core_id);
+            }
+            cb(core_id);
+        }
+    }
+}
+
+/// Number of callback invocations of a subscription.
+#[derive(Debug)]
+pub(crate) struct CallbackStats {
+    /// Name of the subscribable type.
+    pub(crate) name: &'static str,
+    /// Number of times the callback has been invoked on each core, indexed by core ID. When
+    /// callbacks are offloaded, counts the objects queued for the callback instead.
+    nb_invoked: Box<[CoreCounter]>,
+    /// Number of objects dropped under offload backpressure, across all cores.
+    pub(crate) nb_dropped: AtomicU64,
+}
+
+impl CallbackStats {
+    pub(crate) fn new<S: Subscribable>() -> Self {
+        let type_name = std::any::type_name::<S>();
+        CallbackStats::named(type_name.rsplit("::").next().unwrap_or(type_name))
+    }
+
+    /// Creates counters for a subscription labelled `name`.
+    pub(crate) fn named(name: &'static str) -> Self {
+        CallbackStats {
+            name,
+            nb_invoked: (0..=MAX_CORES).map(|_| CoreCounter::default()).collect(),
+            nb_dropped: AtomicU64::new(0),
+        }
+    }
+
+    /// Records a callback invocation on core `core_id`.
+    #[inline]
+    fn incr_invoked(&self, core_id: usize) {
+        self.nb_invoked[core_id.min(MAX_CORES)]
+            .0
+            .fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Returns the number of times the callback has been invoked, across all cores.
+    pub(crate) fn nb_invoked(&self) -> u64 {
+        self.nb_invoked
+            .iter()
+            .map(|counter| counter.0.load(Ordering::Relaxed))
+            .sum()
+    }
+}
+
+/// Counter updated by a single core, aligned to a cache line to avoid false sharing.
+#[derive(Debug, Default)]
+#[repr(align(64))]
+struct CoreCounter(AtomicU64);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn core_callback_stats() {
+        let stats = CallbackStats::named("test");
+        stats.incr_invoked(0);
+        stats.incr_invoked(3);
+        stats.incr_invoked(3);
+        // Non-DPDK threads (LCORE_ID_ANY)
+        stats.incr_invoked(u32::MAX as usize);
+        assert_eq!(stats.nb_invoked(), 4);
+        assert_eq!(stats.nb_invoked[3].0.load(Ordering::Relaxed), 2);
+        assert_eq!(stats.nb_invoked[MAX_CORES].0.load(Ordering::Relaxed), 1);
     }
 }
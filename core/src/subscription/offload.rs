@@ -0,0 +1,316 @@
+//! Callback offload.
+//!
+//! Subscribable objects are pushed onto a bounded queue per packet processing core and delivered
+//! to the callback by a pool of worker threads. Each queue is served by a single worker, so that
+//! objects from the same core are delivered in order. See
+//! [OffloadConfig](crate::config::OffloadConfig) for details.
+
+use super::CallbackStats;
+use crate::config::{Backpressure, OffloadConfig};
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crossbeam_channel::{bounded, select, Receiver, Select, Sender, TrySendError};
+
+/// How long workers wait for objects before checking whether to exit.
+const WORKER_POLL_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Offload worker threads, to be run for the duration of the run.
+pub(crate) struct Offload<'a> {
+    /// Worker thread bodies.
+    pub(crate) workers: Vec<Box<dyn FnOnce() + Send + 'a>>,
+    /// Set once the packet processing cores have exited, after which workers exit once their
+    /// queues are empty.
+    pub(crate) is_done: Arc<AtomicBool>,
+}
+
+impl<'a> Offload<'a> {
+    /// Creates a queue for each of the packet processing `cores` and the workers that deliver
+    /// queued objects to `callback`. Returns the workers, and the dispatcher that queues objects
+    /// on the packet processing cores.
+    pub(crate) fn new<S>(
+        config: &OffloadConfig,
+        cores: &[u32],
+        callback: impl Fn(S) + Send + Sync + 'a,
+        stats: Arc<CallbackStats>,
+    ) -> (Self, Dispatcher<'a, S>)
+    where
+        S: Send + 'a,
+    {
+        let callback: Arc<dyn Fn(S) + Send + Sync + 'a> = Arc::new(callback);
+        let nb_queues = cores.iter().max().map_or(0, |max| *max as usize + 1);
+        let mut queues = (0..nb_queues).map(|_| None).collect::<Vec<_>>();
+        let nb_workers = config.nb_workers.clamp(1, cores.len().max(1));
+        // Each worker holds the sending end of a channel that is disconnected when it exits
+        let (alive, exited): (Vec<_>, Vec<_>) = (0..nb_workers).map(|_| bounded(0)).unzip();
+        let mut worker_queues = (0..nb_workers).map(|_| vec![]).collect::<Vec<_>>();
+        for (i, core) in cores.iter().enumerate() {
+            let (tx, rx) = bounded(config.queue_size);
+            let drain = match config.backpressure {
+                Backpressure::DropOldest => Some(rx.clone()),
+                _ => None,
+            };
+            worker_queues[i % nb_workers].push(rx);
+            queues[*core as usize] = Some(Queue {
+                tx,
+                drain,
+                worker: exited[i % nb_workers].clone(),
+            });
+        }
+
+        let is_done = Arc::new(AtomicBool::new(false));
+        let workers = worker_queues
+            .into_iter()
+            .zip(alive)
+            .filter(|(queues, _)| !queues.is_empty())
+            .map(|(queues, alive): (_, Sender<()>)| {
+                let callback = Arc::clone(&callback);
+                let is_done = Arc::clone(&is_done);
+                Box::new(move || {
+                    let _alive = alive;
+                    work(&queues, &*callback, &is_done)
+                }) as Box<dyn FnOnce() + Send + 'a>
+            })
+            .collect();
+
+        let dispatcher = Dispatcher {
+            queues,
+            backpressure: config.backpressure,
+            callback,
+            stats,
+        };
+        (Offload { workers, is_done }, dispatcher)
+    }
+}
+
+/// An item on an offload queue.
+enum Task<S> {
+    /// Object to deliver to the callback.
+    Deliver(S),
+    /// Acknowledged once all objects queued before it have been delivered.
+    Flush(Sender<()>),
+}
+
+/// Queue of a packet processing core.
+struct Queue<S> {
+    tx: Sender<Task<S>>,
+    /// Receiving end of the queue, used to drop the oldest objects under `DropOldest`.
+    drain: Option<Receiver<Task<S>>>,
+    /// Disconnected once the worker serving the queue exits.
+    worker: Receiver<()>,
+}
+
+/// Queues subscribable objects for the offload workers.
+pub(crate) struct Dispatcher<'a, S> {
+    /// Queue of each packet processing core, indexed by core ID.
+    queues: Vec<Option<Queue<S>>>,
+    backpressure: Backpressure,
+    callback: Arc<dyn Fn(S) + Send + Sync + 'a>,
+    stats: Arc<CallbackStats>,
+}
+
+impl<S> Dispatcher<'_, S> {
+    /// Queues `obj` on the queue of core `core_id`, applying the backpressure policy if the queue
+    /// is full. Invokes the callback inline if the core has no queue.
+    pub(crate) fn dispatch(&self, core_id: u32, obj: S) {
+        let Some(Some(queue)) = self.queues.get(core_id as usize) else {
+            (self.callback)(obj);
+            return;
+        };
+        let task = Task::Deliver(obj);
+        match (self.backpressure, &queue.drain) {
+            (Backpressure::DropOldest, Some(drain)) => {
+                let mut task = task;
+                while let Err(TrySendError::Full(rejected)) = queue.tx.try_send(task) {
+                    task = rejected;
+                    if let Ok(Task::Deliver(_)) = drain.try_recv() {
+                        self.stats.nb_dropped.fetch_add(1, Ordering::Relaxed);
+                    }
+                }
+            }
+            (Backpressure::Block, _) => {
+                // Fails only if the worker has exited
+                if queue.tx.send(task).is_err() {
+                    self.stats.nb_dropped.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+            _ => {
+                if queue.tx.try_send(task).is_err() {
+                    self.stats.nb_dropped.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        }
+    }
+
+    /// Waits until the objects queued by core `core_id` have been delivered, or the worker serving
+    /// its queue has exited. Queued objects are not dropped, regardless of the backpressure policy.
+    pub(crate) fn flush(&self, core_id: u32) {
+        let Some(Some(queue)) = self.queues.get(core_id as usize) else {
+            return;
+        };
+        let (ack_tx, ack_rx) = bounded(1);
+        select! {
+            send(queue.tx, Task::Flush(ack_tx)) -> res => {
+                if res.is_err() {
+                    return;
+                }
+            }
+            recv(queue.worker) -> _ => return,
+        }
+        select! {
+            recv(ack_rx) -> _ => (),
+            recv(queue.worker) -> _ => (),
+        }
+    }
+}
+
+/// Delivers objects from `queues` to `callback` until `is_done` is set and the queues are empty.
+fn work<S>(queues: &[Receiver<Task<S>>], callback: &dyn Fn(S), is_done: &AtomicBool) {
+    let mut select = Select::new();
+    for queue in queues {
+        select.recv(queue);
+    }
+    loop {
+        match select.select_timeout(WORKER_POLL_TIMEOUT) {
+            Ok(oper) => {
+                let index = oper.index();
+                match oper.recv(&queues[index]) {
+                    Ok(Task::Deliver(obj)) => callback(obj),
+                    Ok(Task::Flush(ack)) => {
+                        let _ = ack.send(());
+                    }
+                    Err(_) => (),
+                }
+            }
+            Err(_) => {
+                if is_done.load(Ordering::Acquire) && queues.iter().all(|queue| queue.is_empty()) {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    fn run(backpressure: Backpressure) -> (Vec<u32>, u64) {
+        let config = OffloadConfig {
+            nb_workers: 4,
+            queue_size: 2,
+            backpressure,
+        };
+        let stats = Arc::new(CallbackStats::named("test"));
+        let delivered = Mutex::new(vec![]);
+        let (Offload { workers, is_done }, dispatcher) = Offload::new(
+            &config,
+            &[3],
+            |obj: u32| delivered.lock().unwrap().push(obj),
+            Arc::clone(&stats),
+        );
+        assert_eq!(workers.len(), 1);
+
+        std::thread::scope(|scope| {
+            let producer = scope.spawn(|| {
+                for obj in 0..5 {
+                    dispatcher.dispatch(3, obj);
+                }
+                // Core without a queue
+                dispatcher.dispatch(7, 100);
+            });
+            // Fill the queue before starting the workers, unless the producer waits for them
+            let producer = match backpressure {
+                Backpressure::Block => Some(producer),
+                _ => {
+                    producer.join().unwrap();
+                    None
+                }
+            };
+            for worker in workers {
+                scope.spawn(worker);
+            }
+            if let Some(producer) = producer {
+                producer.join().unwrap();
+            }
+            is_done.store(true, Ordering::Release);
+        });
+        drop(dispatcher);
+        let mut delivered = delivered.into_inner().unwrap();
+        delivered.sort();
+        (delivered, stats.nb_dropped.load(Ordering::Relaxed))
+    }
+
+    #[test]
+    fn core_offload_backpressure() {
+        assert_eq!(run(Backpressure::Block), (vec![0, 1, 2, 3, 4, 100], 0));
+        assert_eq!(run(Backpressure::DropNewest), (vec![0, 1, 100], 3));
+        assert_eq!(run(Backpressure::DropOldest), (vec![3, 4, 100], 3));
+    }
+
+    #[test]
+    fn core_offload_flush() {
+        for backpressure in [Backpressure::Block, Backpressure::DropOldest] {
+            let config = OffloadConfig {
+                nb_workers: 1,
+                queue_size: 2,
+                backpressure,
+            };
+            let stats = Arc::new(CallbackStats::named("test"));
+            let delivered = Mutex::new(vec![]);
+            let (Offload { workers, is_done }, dispatcher) = Offload::new(
+                &config,
+                &[1, 3],
+                |obj: u32| {
+                    std::thread::sleep(Duration::from_millis(1));
+                    delivered.lock().unwrap().push(obj);
+                },
+                Arc::clone(&stats),
+            );
+            std::thread::scope(|scope| {
+                for worker in workers {
+                    scope.spawn(worker);
+                }
+                dispatcher.dispatch(3, 0);
+                dispatcher.dispatch(3, 1);
+                dispatcher.flush(3);
+                assert_eq!(*delivered.lock().unwrap(), vec![0, 1]);
+                // Empty queue and core without a queue
+                dispatcher.flush(1);
+                dispatcher.flush(7);
+                is_done.store(true, Ordering::Release);
+            });
+            assert_eq!(stats.nb_dropped.load(Ordering::Relaxed), 0);
+        }
+    }
+
+    #[test]
+    fn core_offload_worker_exit() {
+        let config = OffloadConfig {
+            nb_workers: 1,
+            queue_size: 1,
+            backpressure: Backpressure::Block,
+        };
+        let stats = Arc::new(CallbackStats::named("test"));
+        let (Offload { mut workers, .. }, dispatcher) = Offload::new(
+            &config,
+            &[3],
+            |obj: u32| assert_ne!(obj, 0, "callback failed"),
+            Arc::clone(&stats),
+        );
+        std::thread::scope(|scope| {
+            let worker = scope.spawn(workers.pop().unwrap());
+            dispatcher.dispatch(3, 0);
+            assert!(worker.join().is_err());
+            // Neither blocks once the worker has exited
+            for obj in 1..5 {
+                dispatcher.dispatch(3, obj);
+            }
+            dispatcher.flush(3);
+        });
+        assert_eq!(stats.nb_dropped.load(Ordering::Relaxed), 4);
+    }
+}
@@ -17,6 +17,7 @@
 //!     runtime.run();
 //! }
 
+use crate::conntrack::clock;
 use crate::conntrack::conn_id::FiveTuple;
 use crate::conntrack::pdu::{L4Context, L4Pdu};
 use crate::conntrack::ConnTracker;
@@ -29,12 +30,16 @@ use crate::subscription::{Level, Subscribable, Subscription, Trackable};
 use serde::Serialize;
 
 use std::net::SocketAddr;
+use std::time::Instant;
 
 /// A parsed TLS handshake and connection metadata.
 #[derive(Debug, Serialize)]
 pub struct TlsHandshake {
     /// Connection 5-tuple.
     pub five_tuple: FiveTuple,
+    /// Timestamp of the first packet in the connection.
+    #[serde(skip)]
+    pub conn_ts: Instant,
     /// Parsed TLS handshake data.
     pub data: Tls,
 }
@@ -94,13 +99,17 @@ impl Subscribable for TlsHandshake {
 #[doc(hidden)]
 pub struct TrackedTls {
     five_tuple: FiveTuple,
+    first_seen_ts: Instant,
 }
 
 impl Trackable for TrackedTls {
     type Subscribed = TlsHandshake;
 
     fn new(five_tuple: FiveTuple) -> Self {
-        TrackedTls { five_tuple }
+        TrackedTls {
+            five_tuple,
+            first_seen_ts: clock::now(),
+        }
     }
 
     fn pre_match(&mut self, _pdu: L4Pdu, _session_id: Option<usize>) {}
@@ -109,6 +118,7 @@ impl Trackable for TrackedTls {
         if let SessionData::Tls(tls) = session.data {
             subscription.invoke(TlsHandshake {
                 five_tuple: self.five_tuple,
+                conn_ts: self.first_seen_ts,
                 data: *tls,
             });
         }
@@ -1,15 +1,13 @@
 use retina_core::config::load_config;
+use retina_core::sink::{Format, Sink, SinkConfig};
 use retina_core::subscription::Connection;
 use retina_core::Runtime;
 use retina_filtergen::filter;
 
-use std::fs::File;
-use std::io::{BufWriter, Write};
 use std::path::PathBuf;
 use std::sync::atomic::{AtomicUsize, Ordering};
-use std::sync::Mutex;
 
-use anyhow::Result;
+use anyhow::{bail, Result};
 use clap::Parser;
 
 // Define command-line arguments.
@@ -21,10 +19,18 @@ struct Args {
         short,
         long,
         parse(from_os_str),
-        value_name = "FILE",
-        default_value = "conn.jsonl"
+        value_name = "PATH",
+        default_value = "conn"
     )]
     outfile: PathBuf,
+    #[clap(
+        short,
+        long,
+        value_name = "FORMAT",
+        default_value = "jsonl",
+        possible_values = &["jsonl", "csv", "parquet", "arrow"]
+    )]
+    format: String,
 }
 
 #[filter("")]
@@ -33,23 +39,34 @@ fn main() -> Result<()> {
     let args = Args::parse();
     let config = load_config(&args.config);
 
-    // Use `BufWriter` to improve the speed of repeated write calls to the same file.
-    let file = Mutex::new(BufWriter::new(File::create(&args.outfile)?));
+    let format = match args.format.as_str() {
+        "jsonl" => Format::Jsonl,
+        "csv" => Format::Csv,
+        "parquet" => Format::Parquet,
+        "arrow" => Format::ArrowIpc,
+        format => bail!("Unknown format: {}", format),
+    };
+    // Each core writes to its own files, e.g., `conn-core1-0.jsonl`.
+    let sink = Sink::new(SinkConfig {
+        format,
+        ..SinkConfig::new(&args.outfile)
+    })?;
     let cnt = AtomicUsize::new(0);
 
-    let callback = |conn: Connection| {
-        if let Ok(serialized) = serde_json::to_string(&conn) {
-            let mut wtr = file.lock().unwrap();
-            wtr.write_all(serialized.as_bytes()).unwrap();
-            wtr.write_all(b"\n").unwrap();
+    let callback = |conn: Connection| match sink.write(&conn) {
+        Ok(()) => {
             cnt.fetch_add(1, Ordering::Relaxed);
         }
+        Err(err) => log::error!("Failed to log connection: {:?}", err),
     };
     let mut runtime = Runtime::new(config, filter, callback)?;
     runtime.run();
+    drop(runtime);
 
-    let mut wtr = file.lock().unwrap();
-    wtr.flush()?;
-    println!("Done. Logged {:?} connections to {:?}", cnt, &args.outfile);
+    sink.finish()?;
+    println!(
+        "Done. Logged {:?} connections to {:?}-*",
+        cnt, &args.outfile
+    );
     Ok(())
 }
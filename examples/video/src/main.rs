@@ -4,6 +4,8 @@ use retina_core::Runtime;
 use retina_filtergen::filter;
 
 use std::collections::BinaryHeap;
+use std::fs::File;
+use std::net::IpAddr;
 use std::path::PathBuf;
 use std::sync::Mutex;
 use std::time::{Duration, Instant};
@@ -66,52 +68,63 @@ fn main() -> Result<()> {
                 vacant.insert(client, Session::new(now, conn));
             }
         }
-        if let Some((client, session)) = sessions.front() {
+        if let Some((_, session)) = sessions.front() {
             if now.duration_since(session.last_updated) > Duration::from_millis(500) {
-                let conns = &session.connections;
-                let intervals = conns
-                    .iter()
-                    .map(|c| (c.ts, c.ts + c.duration))
-                    .collect::<Vec<_>>();
-                let parallel_flows = max_overlap(intervals);
-                let mut bytes_up = 0;
-                let mut bytes_dn = 0;
-                let mut ooo_pkts_up = 0.0;
-                let mut ooo_pkts_dn = 0.0;
-                let mut tput_dn = 0.0;
-                for c in conns {
-                    bytes_up += c.orig.nb_bytes;
-                    bytes_dn += c.resp.nb_bytes;
-                    ooo_pkts_up += c.orig.gaps.len() as f64;
-                    ooo_pkts_dn += c.resp.gaps.len() as f64;
-                    tput_dn += 8.0 * c.resp.nb_bytes as f64 / c.duration.as_micros() as f64;
-                }
-                let last = conns[conns.len() - 1].ts + conns[conns.len() - 1].duration;
-                let duration = last.saturating_duration_since(conns[0].ts);
-
-                let mut wtr = wtr.lock().unwrap();
-                wtr.serialize((
-                    client,
-                    parallel_flows,
-                    bytes_up,
-                    bytes_dn,
-                    ooo_pkts_up / conns.len() as f64,
-                    ooo_pkts_dn / conns.len() as f64,
-                    tput_dn / conns.len() as f64 * parallel_flows as f64,
-                    duration.as_millis(),
-                ))
-                .unwrap();
-                sessions.pop_front();
+                let (client, session) = sessions.pop_front().unwrap();
+                write_session(&mut wtr.lock().unwrap(), client, &session).unwrap();
             }
         }
     };
     let mut runtime = Runtime::new(config, filter, callback)?;
+    // Write out sessions that were still active at the end of the run
+    runtime.on_shutdown(|| {
+        let mut wtr = wtr.lock().unwrap();
+        for (client, session) in sessions.lock().unwrap().drain() {
+            write_session(&mut wtr, client, &session).unwrap();
+        }
+    });
     runtime.run();
 
     wtr.lock().unwrap().flush()?;
     Ok(())
 }
 
+/// Writes the summary of a video `session` of `client` as a CSV record.
+fn write_session(wtr: &mut Writer<File>, client: IpAddr, session: &Session) -> Result<()> {
+    let conns = &session.connections;
+    let intervals = conns
+        .iter()
+        .map(|c| (c.ts, c.ts + c.duration))
+        .collect::<Vec<_>>();
+    let parallel_flows = max_overlap(intervals);
+    let mut bytes_up = 0;
+    let mut bytes_dn = 0;
+    let mut ooo_pkts_up = 0.0;
+    let mut ooo_pkts_dn = 0.0;
+    let mut tput_dn = 0.0;
+    for c in conns {
+        bytes_up += c.orig.nb_bytes;
+        bytes_dn += c.resp.nb_bytes;
+        ooo_pkts_up += c.orig.gaps.len() as f64;
+        ooo_pkts_dn += c.resp.gaps.len() as f64;
+        tput_dn += 8.0 * c.resp.nb_bytes as f64 / c.duration.as_micros() as f64;
+    }
+    let last = conns[conns.len() - 1].ts + conns[conns.len() - 1].duration;
+    let duration = last.saturating_duration_since(conns[0].ts);
+
+    wtr.serialize((
+        client,
+        parallel_flows,
+        bytes_up,
+        bytes_dn,
+        ooo_pkts_up / conns.len() as f64,
+        ooo_pkts_dn / conns.len() as f64,
+        tput_dn / conns.len() as f64 * parallel_flows as f64,
+        duration.as_millis(),
+    ))?;
+    Ok(())
+}
+
 /// A video session that contains multiple network flows.
 #[derive(Debug)]
 struct Session {
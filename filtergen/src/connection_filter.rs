@@ -1,10 +1,11 @@
-use heck::CamelCase;
-use proc_macro2::{Ident, Span};
+use proc_macro2::Span;
 use quote::quote;
 
 use retina_core::filter::ast::*;
 use retina_core::filter::ptree::{PNode, PTree, Terminate};
 
+use crate::util::protocol_ident;
+
 pub(crate) fn gen_connection_filter(
     ptree: &PTree,
     statics: &mut Vec<proc_macro2::TokenStream>,
@@ -86,7 +87,7 @@ fn add_service_pred(
     protocol: &ProtocolName,
 ) {
     if matches!(node.terminates, Terminate::Connection) {
-        let service_ident = Ident::new(&protocol.name().to_camel_case(), Span::call_site());
+        let service_ident = protocol_ident(protocol);
         // let service_lit = syn::LitStr::new(&protocol.name().to_string(), Span::call_site());
         ct_nodes.push(node.id);
         let idx_lit = syn::LitInt::new(&node.id.to_string(), Span::call_site());
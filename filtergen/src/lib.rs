@@ -36,9 +36,11 @@
 //!
 //! ## Protocols
 //! All protocol identifiers are valid as long as Retina contains an appropriate [protocol
-//! module](../retina_core/protocols) of the same name. The [`filter`](macro@self::filter) macro
-//! automatically generates filtering code using structs defined in the protocol's corresponding
-//! parser module. The exception to this is `ethernet`, which Retina filters for by default.
+//! module](../retina_core/protocols) of the same name, declared in the [field
+//! registry](../retina_core/filter/fields/constant.PROTOCOLS.html). The
+//! [`filter`](macro@self::filter) macro automatically generates filtering code using structs
+//! defined in the protocol's corresponding parser module. The exception to this is `ethernet`,
+//! which Retina filters for by default.
 //!
 //! For example, [`ipv4`](../retina_core/protocols/packet/ipv4) and
 //! [`tls`](../retina_core/protocols/stream/tls) are filterable protocols because they are both
@@ -47,10 +49,15 @@
 //! Retina will also automatically expand filter expressions to their fully-qualified form. For
 //! example, the filter `tcp` is equivalent to `(ipv4 and tcp) or (ipv6 and tcp)`.
 //!
+//! Link-layer protocols that are not carried over IP (`arp`, `lldp`, `cdp`, and `eapol`) can only be
+//! matched at the packet layer. They are best used with packet-level subscriptions such as
+//! [`Frame`](../retina_core/subscription/frame/struct.Frame.html), since connection tracking only
+//! applies to TCP, UDP, and SCTP traffic.
+//!
 //! ## Fields
-//! All field identifiers are valid as long as Retina exposes a public accessor method for the
-//! corresponding protocol struct of the same name, and the method returns a supported RHS field
-//! type.
+//! All field identifiers are valid as long as they are declared in the field registry, which maps
+//! each field to its type, the public accessor method of the corresponding protocol struct, the
+//! comparison operators it supports, and whether it can be filtered in hardware.
 //!
 //! For example,
 //! [`ipv4.src_addr`](../retina_core/protocols/packet/ipv4/struct.Ipv4.html#method.src_addr) and
@@ -59,6 +66,12 @@
 //! `Ipv4Addr`, and `sni()` is a public method associated with the `Tls` struct that returns a
 //! `String`.
 //!
+//! Filters are checked against the fields and value types of each protocol when they are
+//! compiled. Unknown protocols or fields, values of the wrong type, and unsupported comparison
+//! operators are reported as compile errors that show the location of the error in the filter
+//! expression and, for likely typos, the intended name. To inspect how a filter is compiled,
+//! including its predicate trie, run `retina explain '<filter>'`.
+//!
 //! Retina also supports two combined fields: `addr` and `port`. Logically, these are equivalent to
 //! `src_addr or dst_addr` and `src_port or dst_port`, respectively, except in predicates that use
 //! the `!=` comparison operator (details below).
@@ -126,7 +139,7 @@ use proc_macro::TokenStream;
 use quote::quote;
 use syn::parse_macro_input;
 
-use retina_core::filter::Filter;
+use retina_core::filter::{Filter, SpannedFilterError};
 
 use crate::connection_filter::gen_connection_filter;
 use crate::packet_filter::gen_packet_filter;
@@ -156,16 +169,29 @@ use crate::session_filter::gen_session_filter;
 /// ```
 #[proc_macro_attribute]
 pub fn filter(args: TokenStream, input: TokenStream) -> TokenStream {
-    let filter_str = parse_macro_input!(args as syn::LitStr).value();
+    let filter_lit = parse_macro_input!(args as syn::LitStr);
+    let filter_str = filter_lit.value();
     let input = parse_macro_input!(input as syn::ItemFn);
     // let input_sig = &input.sig.ident;
 
-    let filter = Filter::from_str(&filter_str, false).unwrap();
+    let filter = match Filter::from_str(&filter_str, false) {
+        Ok(filter) => filter,
+        Err(error) => {
+            let error = filter_error(&filter_lit, error);
+            // Define a placeholder `filter` so the error is not followed by unresolved names.
+            return quote! {
+                #error
+                fn filter() -> retina_core::filter::FilterFactory {
+                    unreachable!()
+                }
+                #input
+            }
+            .into();
+        }
+    };
 
     let mut ptree = filter.to_ptree();
     ptree.prune_branches();
-    // Displays the predicate trie during compilation.
-    println!("{}", ptree);
 
     // store lazily evaluated statics like pre-compiled Regex
     let mut statics: Vec<proc_macro2::TokenStream> = vec![];
@@ -219,3 +245,29 @@ pub fn filter(args: TokenStream, input: TokenStream) -> TokenStream {
     };
     filtergen.into()
 }
+
+/// Converts a filter error into a `compile_error!` that points at the filter literal. The message
+/// shows the location of the error in the filter expression, since the span of a sub-range of a
+/// string literal is only available on nightly compilers.
+fn filter_error(filter_lit: &syn::LitStr, error: anyhow::Error) -> proc_macro2::TokenStream {
+    let filter_str = filter_lit.value();
+    let (message, span) = match error.downcast_ref::<SpannedFilterError>() {
+        Some(error) => {
+            // Byte offsets into the literal value are only valid in the source token when the
+            // literal contains no escape sequences.
+            let span = if filter_lit.token().to_string() == format!("\"{}\"", filter_str) {
+                let start = error.span.start + 1;
+                let end = std::cmp::max(error.span.end, error.span.start + 1) + 1;
+                filter_lit.token().subspan(start..end)
+            } else {
+                None
+            };
+            (
+                error.render(&filter_str),
+                span.unwrap_or_else(|| filter_lit.span()),
+            )
+        }
+        None => (format!("{:#}", error), filter_lit.span()),
+    };
+    syn::Error::new(span, message).to_compile_error()
+}
@@ -1,4 +1,3 @@
-use heck::CamelCase;
 use proc_macro2::{Ident, Span};
 use quote::quote;
 
@@ -6,7 +5,7 @@ use retina_core::filter::ast::*;
 use retina_core::filter::ptree::{PNode, PTree, Terminate};
 use retina_core::protocol;
 
-use crate::util::binary_to_tokens;
+use crate::util::{binary_to_tokens, protocol_ident};
 
 // TODO: lots of opportunities to optimize further. But need to be careful about correctness
 // example: collapse if statements at each header?
@@ -26,7 +25,7 @@ pub(crate) fn gen_packet_filter(
 
     let name = "ethernet";
     let outer = Ident::new(name, Span::call_site());
-    let outer_type = Ident::new(&outer.to_string().to_camel_case(), Span::call_site());
+    let outer_type = protocol_ident(&protocol!(name));
 
     let mut body: Vec<proc_macro2::TokenStream> = vec![];
     let mut pt_nodes = vec![];
@@ -103,7 +102,7 @@ fn add_unary_pred(
 ) {
     let outer = Ident::new(outer_protocol.name(), Span::call_site());
     let ident = Ident::new(protocol.name(), Span::call_site());
-    let ident_type = Ident::new(&ident.to_string().to_camel_case(), Span::call_site());
+    let ident_type = protocol_ident(protocol);
 
     let mut body: Vec<proc_macro2::TokenStream> = vec![];
     gen_packet_filter_util(pt_nodes, &mut body, statics, node, outer_protocol);
@@ -1,11 +1,10 @@
-use heck::CamelCase;
 use proc_macro2::{Ident, Span};
 use quote::quote;
 
 use retina_core::filter::ast::*;
 use retina_core::filter::ptree::{PNode, PTree, Terminate};
 
-use crate::util::binary_to_tokens;
+use crate::util::{binary_to_tokens, protocol_ident};
 
 pub(crate) fn gen_session_filter(
     ptree: &PTree,
@@ -52,9 +51,9 @@ fn add_node_match_arm(
         let mut body: Vec<proc_macro2::TokenStream> = vec![];
         gen_session_filter_util(&mut body, statics, node);
 
-        let service = node.pred.get_protocol().name();
-        let proto_name = Ident::new(service, Span::call_site());
-        let proto_variant = Ident::new(&service.to_camel_case(), Span::call_site());
+        let service = node.pred.get_protocol();
+        let proto_name = Ident::new(service.name(), Span::call_site());
+        let proto_variant = protocol_ident(service);
 
         code.push(quote! {
             #idx_lit => {
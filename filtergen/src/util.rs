@@ -1,11 +1,30 @@
 use retina_core::filter::ast::{BinOp, FieldName, ProtocolName, Value};
+use retina_core::filter::fields::{self, ProtocolDef};
 
 use heck::CamelCase;
 use proc_macro2::{Ident, Span};
 use quote::quote;
 use regex::Regex;
 
-// TODO: give better compiler errors
+/// Returns the registry declaration of `protocol`. Filters are validated against the registry
+/// before code generation, so a missing protocol is a bug.
+pub(crate) fn protocol_def(protocol: &ProtocolName) -> &'static ProtocolDef {
+    fields::protocol(protocol.name()).unwrap_or_else(|| panic!("Unknown protocol: `{}`.", protocol))
+}
+
+/// Returns the identifier of the protocol struct (and of its `ConnParser` and `SessionData`
+/// variants) of `protocol`.
+pub(crate) fn protocol_ident(protocol: &ProtocolName) -> Ident {
+    Ident::new(protocol_def(protocol).ident, Span::call_site())
+}
+
+/// Returns the identifier of the accessor method of `protocol.field`.
+fn accessor_ident(protocol: &ProtocolName, field: &str) -> Ident {
+    let field_def = protocol_def(protocol)
+        .field(field)
+        .unwrap_or_else(|| panic!("Unknown field: `{}.{}`.", protocol, field));
+    Ident::new(field_def.accessor, Span::call_site())
+}
 
 pub(crate) fn binary_to_tokens(
     protocol: &ProtocolName,
@@ -32,6 +51,8 @@ fn combined_field(
     value: &Value,
 ) -> proc_macro2::TokenStream {
     let proto = Ident::new(protocol.name(), Span::call_site());
+    let src = accessor_ident(protocol, &format!("src_{}", field.name()));
+    let dst = accessor_ident(protocol, &format!("dst_{}", field.name()));
     match field.name() {
         "addr" => match value {
             Value::Ipv4(ipv4net) => {
@@ -47,23 +68,23 @@ fn combined_field(
                 match *op {
                     BinOp::Eq => {
                         if ipv4net.prefix_len() == 32 {
-                            quote! { u32::from(#proto.src_addr()) == #addr_lit || u32::from(#proto.dst_addr()) == #addr_lit }
+                            quote! { u32::from(#proto.#src()) == #addr_lit || u32::from(#proto.#dst()) == #addr_lit }
                         } else {
-                            quote! { u32::from(#proto.src_addr()) & #netmask_lit == #net_lit || u32::from(#proto.dst_addr()) & #netmask_lit == #net_lit }
+                            quote! { u32::from(#proto.#src()) & #netmask_lit == #net_lit || u32::from(#proto.#dst()) & #netmask_lit == #net_lit }
                         }
                     }
                     BinOp::Ne => {
                         if ipv4net.prefix_len() == 32 {
-                            quote! { u32::from(#proto.src_addr()) != #addr_lit && u32::from(#proto.dst_addr()) != #addr_lit }
+                            quote! { u32::from(#proto.#src()) != #addr_lit && u32::from(#proto.#dst()) != #addr_lit }
                         } else {
-                            quote! { u32::from(#proto.src_addr()) & #netmask_lit != #net_lit && u32::from(#proto.dst_addr()) & #netmask_lit != #net_lit }
+                            quote! { u32::from(#proto.#src()) & #netmask_lit != #net_lit && u32::from(#proto.#dst()) & #netmask_lit != #net_lit }
                         }
                     }
                     BinOp::In => {
                         if ipv4net.prefix_len() == 32 {
-                            quote! { u32::from(#proto.src_addr()) == #addr_lit || u32::from(#proto.dst_addr()) == #addr_lit }
+                            quote! { u32::from(#proto.#src()) == #addr_lit || u32::from(#proto.#dst()) == #addr_lit }
                         } else {
-                            quote! { u32::from(#proto.src_addr()) & #netmask_lit == #net_lit || u32::from(#proto.dst_addr()) & #netmask_lit == #net_lit }
+                            quote! { u32::from(#proto.#src()) & #netmask_lit == #net_lit || u32::from(#proto.#dst()) & #netmask_lit == #net_lit }
                         }
                     }
                     _ => panic!("Invalid binary operation `{}` for value: `{}`.", op, value),
@@ -82,23 +103,23 @@ fn combined_field(
                 match *op {
                     BinOp::Eq => {
                         if ipv6net.prefix_len() == 128 {
-                            quote! { u128::from(#proto.src_addr()) == #addr_lit || u128::from(#proto.dst_addr()) == #addr_lit }
+                            quote! { u128::from(#proto.#src()) == #addr_lit || u128::from(#proto.#dst()) == #addr_lit }
                         } else {
-                            quote! { u128::from(#proto.src_addr()) & #netmask_lit == #net_lit || u128::from(#proto.dst_addr()) & #netmask_lit == #net_lit }
+                            quote! { u128::from(#proto.#src()) & #netmask_lit == #net_lit || u128::from(#proto.#dst()) & #netmask_lit == #net_lit }
                         }
                     }
                     BinOp::Ne => {
                         if ipv6net.prefix_len() == 128 {
-                            quote! { u128::from(#proto.src_addr()) != #addr_lit && u128::from(#proto.dst_addr()) != #addr_lit }
+                            quote! { u128::from(#proto.#src()) != #addr_lit && u128::from(#proto.#dst()) != #addr_lit }
                         } else {
-                            quote! { u128::from(#proto.src_addr()) & #netmask_lit != #net_lit && u128::from(#proto.dst_addr()) & #netmask_lit != #net_lit }
+                            quote! { u128::from(#proto.#src()) & #netmask_lit != #net_lit && u128::from(#proto.#dst()) & #netmask_lit != #net_lit }
                         }
                     }
                     BinOp::In => {
                         if ipv6net.prefix_len() == 128 {
-                            quote! { u128::from(#proto.src_addr()) == #addr_lit || u128::from(#proto.dst_addr()) == #addr_lit }
+                            quote! { u128::from(#proto.#src()) == #addr_lit || u128::from(#proto.#dst()) == #addr_lit }
                         } else {
-                            quote! { u128::from(#proto.src_addr()) & #netmask_lit == #net_lit || u128::from(#proto.dst_addr()) & #netmask_lit == #net_lit }
+                            quote! { u128::from(#proto.#src()) & #netmask_lit == #net_lit || u128::from(#proto.#dst()) & #netmask_lit == #net_lit }
                         }
                     }
                     _ => panic!("Invalid binary operation `{}` for value: `{}`.", op, value),
@@ -111,22 +132,22 @@ fn combined_field(
                 let val_lit = syn::LitInt::new(&val.to_string(), Span::call_site());
                 match *op {
                     BinOp::Eq => {
-                        quote! { #proto.src_port() == #val_lit || #proto.dst_port() == #val_lit }
+                        quote! { #proto.#src() == #val_lit || #proto.#dst() == #val_lit }
                     }
                     BinOp::Ne => {
-                        quote! { #proto.src_port() != #val_lit && #proto.dst_port() != #val_lit }
+                        quote! { #proto.#src() != #val_lit && #proto.#dst() != #val_lit }
                     }
                     BinOp::Ge => {
-                        quote! { #proto.src_port() >= #val_lit || #proto.dst_port() >= #val_lit }
+                        quote! { #proto.#src() >= #val_lit || #proto.#dst() >= #val_lit }
                     }
                     BinOp::Le => {
-                        quote! { #proto.src_port() <= #val_lit || #proto.dst_port() <= #val_lit }
+                        quote! { #proto.#src() <= #val_lit || #proto.#dst() <= #val_lit }
                     }
                     BinOp::Gt => {
-                        quote! { #proto.src_port() > #val_lit || #proto.dst_port() > #val_lit }
+                        quote! { #proto.#src() > #val_lit || #proto.#dst() > #val_lit }
                     }
                     BinOp::Lt => {
-                        quote! { #proto.src_port() < #val_lit || #proto.dst_port() < #val_lit }
+                        quote! { #proto.#src() < #val_lit || #proto.#dst() < #val_lit }
                     }
                     _ => panic!("Invalid binary operation `{}` for value: `{}`.", op, value),
                 }
@@ -145,7 +166,7 @@ fn standard_field(
     statics: &mut Vec<proc_macro2::TokenStream>,
 ) -> proc_macro2::TokenStream {
     let proto = Ident::new(protocol.name(), Span::call_site());
-    let field = Ident::new(field.name(), Span::call_site());
+    let field = accessor_ident(protocol, field.name());
 
     match value {
         Value::Int(val) => {